@@ -0,0 +1,115 @@
+// 颜色调整，所有调整在一次 dispatch 中完成，顺序为:
+// 白平衡 -> 曝光 -> 亮度/对比度 -> 色相/饱和度(HSV 或 HSL) -> gamma
+// alpha 保持不变
+
+struct Params {
+    // 白平衡各通道的增益，由 Rust 端根据色温和色调计算
+    white_balance : vec4<f32>,
+    // 2^EV
+    exposure_scale : f32,
+    brightness : f32,
+    contrast : f32,
+    saturation : f32,
+    // 色相旋转的角度
+    hue : f32,
+    inverse_gamma : f32,
+    // 0: HSV, 1: HSL
+    hue_space : u32,
+    _padding : f32,
+}
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<STORAGE_FORMAT, write>;
+@group(0) @binding(2) var<uniform> params : Params;
+
+// 色相，范围 0~1
+fn rgb_to_hue(c : vec3<f32>, max_value : f32, delta : f32) -> f32 {
+    if delta <= 0.0 {
+        return 0.0;
+    }
+    var h = 0.0;
+    if max_value == c.r {
+        h = (c.g - c.b) / delta;
+    } else if max_value == c.g {
+        h = (c.b - c.r) / delta + 2.0;
+    } else {
+        h = (c.r - c.g) / delta + 4.0;
+    }
+    return fract(h / 6.0);
+}
+
+// 饱和度为 1、亮度为 1 时色相 h 对应的颜色
+fn hue_to_rgb(h : f32) -> vec3<f32> {
+    let k = vec3<f32>(0.0, 4.0, 2.0);
+    let p = abs(fract(h + k / 6.0) * 6.0 - 3.0);
+    return clamp(p - 1.0, vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+fn rgb_to_hsv(c : vec3<f32>) -> vec3<f32> {
+    let max_value = max(c.r, max(c.g, c.b));
+    let min_value = min(c.r, min(c.g, c.b));
+    let delta = max_value - min_value;
+    var s = 0.0;
+    if max_value > 0.0 {
+        s = delta / max_value;
+    }
+    return vec3<f32>(rgb_to_hue(c, max_value, delta), s, max_value);
+}
+
+fn hsv_to_rgb(c : vec3<f32>) -> vec3<f32> {
+    return c.z * mix(vec3<f32>(1.0), hue_to_rgb(c.x), c.y);
+}
+
+// 亮度 l 为最大值和最小值的平均，c 需要在 0~1 范围内
+fn rgb_to_hsl(c : vec3<f32>) -> vec3<f32> {
+    let max_value = max(c.r, max(c.g, c.b));
+    let min_value = min(c.r, min(c.g, c.b));
+    let delta = max_value - min_value;
+    let l = (max_value + min_value) * 0.5;
+    let chroma_range = 1.0 - abs(2.0 * l - 1.0);
+    var s = 0.0;
+    if chroma_range > 0.0 {
+        s = delta / chroma_range;
+    }
+    return vec3<f32>(rgb_to_hue(c, max_value, delta), s, l);
+}
+
+fn hsl_to_rgb(c : vec3<f32>) -> vec3<f32> {
+    let chroma = c.y * (1.0 - abs(2.0 * c.z - 1.0));
+    return c.z + chroma * (hue_to_rgb(c.x) - 0.5);
+}
+
+@compute @workgroup_size(16, 16)
+fn adjust(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = vec2<i32>(textureDimensions(input_texture));
+    let coords = vec2<i32>(global_id.xy);
+    if coords.x >= dimensions.x || coords.y >= dimensions.y {
+        return;
+    }
+
+    let color = textureLoad(input_texture, coords, 0);
+    var rgb = color.rgb * params.white_balance.rgb * params.exposure_scale;
+    rgb = (rgb - 0.5) * params.contrast + 0.5 + params.brightness;
+    rgb = max(rgb, vec3<f32>(0.0));
+
+    if params.hue != 0.0 || params.saturation != 1.0 {
+        if params.hue_space == 0u {
+            var hsv = rgb_to_hsv(rgb);
+            hsv.x = fract(hsv.x + params.hue / 360.0);
+            hsv.y = clamp(hsv.y * params.saturation, 0.0, 1.0);
+            rgb = hsv_to_rgb(hsv);
+        } else {
+            // HSL 只在 0~1 范围内有定义，超出的部分(如曝光增加后)先截断
+            var hsl = rgb_to_hsl(min(rgb, vec3<f32>(1.0)));
+            hsl.x = fract(hsl.x + params.hue / 360.0);
+            hsl.y = clamp(hsl.y * params.saturation, 0.0, 1.0);
+            // 舍入误差可能带来很小的负数，pow 之后会变成 NaN
+            rgb = max(hsl_to_rgb(hsl), vec3<f32>(0.0));
+        }
+    }
+
+    if params.inverse_gamma != 1.0 {
+        rgb = pow(rgb, vec3<f32>(params.inverse_gamma));
+    }
+    textureStore(output_texture, coords, vec4<f32>(rgb, color.a));
+}
@@ -0,0 +1,95 @@
+// 可分离模糊，每次沿一个方向处理
+// blur_pass: 每个工作组把一段 256 个像素和两侧 radius 个像素读入共享内存，再做一维卷积
+// box_running_sum: 每个线程处理一整行(列)，用滑动窗口求和实现任意半径的盒式模糊
+// border.wgsl 由 Rust 端拼接在本文件前面
+
+struct Params {
+    // 0: 水平, 1: 垂直
+    direction : u32,
+    radius : u32,
+    edge_mode : u32,
+    _padding : u32,
+}
+
+const TILE_SIZE : u32 = 256u;
+const MAX_RADIUS : u32 = 64u;
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<STORAGE_FORMAT, write>;
+@group(0) @binding(2) var<storage, read> weights : array<f32>;
+@group(0) @binding(3) var<uniform> params : Params;
+
+var<workgroup> tile : array<vec4<f32>, 384>; // TILE_SIZE + 2 * MAX_RADIUS
+
+// 模糊方向上的第 position 个像素，所在的行(列)为 line
+fn line_coords(position : i32, line : i32) -> vec2<i32> {
+    if params.direction == 0u {
+        return vec2<i32>(position, line);
+    }
+    return vec2<i32>(line, position);
+}
+
+fn line_length() -> i32 {
+    let dimensions = vec2<i32>(textureDimensions(input_texture));
+    if params.direction == 0u {
+        return dimensions.x;
+    }
+    return dimensions.y;
+}
+
+fn line_count() -> i32 {
+    let dimensions = vec2<i32>(textureDimensions(input_texture));
+    if params.direction == 0u {
+        return dimensions.y;
+    }
+    return dimensions.x;
+}
+
+fn load(position : i32, line : i32, length : i32) -> vec4<f32> {
+    let index = border_index(position, length, params.edge_mode);
+    return textureLoad(input_texture, line_coords(index, line), 0);
+}
+
+@compute @workgroup_size(256)
+fn blur_pass(@builtin(local_invocation_id) local_id : vec3<u32>, @builtin(workgroup_id) group_id : vec3<u32>) {
+    let length = line_length();
+    let line = i32(group_id.y);
+    let start = i32(group_id.x * TILE_SIZE) - i32(params.radius);
+
+    for (var i = local_id.x; i < TILE_SIZE + 2u * params.radius; i = i + TILE_SIZE) {
+        tile[i] = load(start + i32(i), line, length);
+    }
+    workgroupBarrier();
+
+    let position = i32(group_id.x * TILE_SIZE + local_id.x);
+    if position >= length {
+        return;
+    }
+
+    var sum = vec4<f32>(0.0);
+    for (var k = 0u; k <= 2u * params.radius; k = k + 1u) {
+        sum = sum + weights[k] * tile[local_id.x + k];
+    }
+    textureStore(output_texture, line_coords(position, line), sum);
+}
+
+@compute @workgroup_size(64)
+fn box_running_sum(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let line = i32(global_id.x);
+    if line >= line_count() {
+        return;
+    }
+
+    let length = line_length();
+    let radius = i32(params.radius);
+    let scale = 1.0 / f32(2 * radius + 1);
+
+    var sum = vec4<f32>(0.0);
+    for (var k = -radius; k <= radius; k = k + 1) {
+        sum = sum + load(k, line, length);
+    }
+    for (var position = 0; position < length; position = position + 1) {
+        textureStore(output_texture, line_coords(position, line), sum * scale);
+        sum = sum + load(position + radius + 1, line, length) - load(position - radius, line, length);
+    }
+}
@@ -0,0 +1,31 @@
+// 边缘处理: 把越界的下标映射回 [0, length)
+// 由 Rust 端拼接在需要它的着色器前面
+
+const EDGE_CLAMP : u32 = 0u;
+const EDGE_MIRROR : u32 = 1u;
+const EDGE_WRAP : u32 = 2u;
+
+fn border_index(index : i32, length : i32, mode : u32) -> i32 {
+    if mode == EDGE_WRAP {
+        // 只对非负数取余，负数取余在部分后端(如 GL)的结果与 WGSL 不一致
+        let r = abs(index) % length;
+        return select(r, (length - r) % length, index < 0);
+    }
+    if mode == EDGE_MIRROR {
+        // 镜像时不重复边缘像素: ...c b | a b c | b a...
+        if length == 1 {
+            return 0;
+        }
+        let period = 2 * (length - 1);
+        var i = abs(index) % period;
+        if i >= length {
+            i = period - i;
+        }
+        return i;
+    }
+    return clamp(index, 0, length - 1);
+}
+
+fn border_coords(coords : vec2<i32>, dimensions : vec2<i32>, mode : u32) -> vec2<i32> {
+    return vec2<i32>(border_index(coords.x, dimensions.x, mode), border_index(coords.y, dimensions.y, mode));
+}
@@ -0,0 +1,58 @@
+// 色键抠像: 在 YCbCr 空间中按像素色度与键色色度的距离计算 alpha，并去除溢色
+// ycbcr.wgsl 由 Rust 端拼接在本文件前面
+
+struct Params {
+    // 键色的 (Cb, Cr)
+    key_chroma : vec2<f32>,
+    // 距离小于 similarity 的像素完全透明
+    similarity : f32,
+    // 从透明过渡到不透明的距离范围
+    smoothness : f32,
+    // 距离小于 similarity + spill 的像素按比例去饱和，去掉边缘的键色反光
+    spill : f32,
+    _padding0 : f32,
+    _padding1 : vec2<f32>,
+}
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<STORAGE_FORMAT, write>;
+@group(0) @binding(2) var<uniform> params : Params;
+@group(0) @binding(3) var y_texture : texture_2d<f32>;
+// NV21 的色度平面，r 通道为 V(Cr)，g 通道为 U(Cb)
+@group(0) @binding(4) var vu_texture : texture_2d<f32>;
+
+fn key(rgb : vec3<f32>, alpha : f32, ycbcr : vec3<f32>) -> vec4<f32> {
+    let chroma_distance = distance(ycbcr.yz, params.key_chroma);
+    let base = chroma_distance - params.similarity;
+    let mask = pow(clamp(base / max(params.smoothness, 1e-4), 0.0, 1.0), 1.5);
+    let spill = pow(clamp(base / max(params.spill, 1e-4), 0.0, 1.0), 1.5);
+
+    // 去溢色: 键色附近的颜色向同亮度的灰色靠拢
+    let gray = vec3<f32>(dot(rgb, vec3<f32>(0.2126, 0.7152, 0.0722)));
+    let color = clamp(mix(gray, rgb, spill), vec3<f32>(0.0), vec3<f32>(1.0));
+    return vec4<f32>(color, alpha * mask);
+}
+
+@compute @workgroup_size(16, 16)
+fn chroma_key(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let size = textureDimensions(input_texture);
+    if global_id.x >= size.x || global_id.y >= size.y {
+        return;
+    }
+    let color = textureLoad(input_texture, global_id.xy, 0);
+    textureStore(output_texture, global_id.xy, key(color.rgb, color.a, rgb_to_ycbcr(color.rgb)));
+}
+
+// 直接处理相机的 NV21 帧，省去单独的 YUV 转 RGB
+@compute @workgroup_size(16, 16)
+fn chroma_key_nv21(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let size = textureDimensions(y_texture);
+    if global_id.x >= size.x || global_id.y >= size.y {
+        return;
+    }
+    let y = textureLoad(y_texture, global_id.xy, 0).r;
+    let vu = textureLoad(vu_texture, global_id.xy / 2u, 0).rg;
+    let ycbcr = vec3<f32>(y, vu.g, vu.r);
+    let rgb = clamp(ycbcr_to_rgb(ycbcr), vec3<f32>(0.0), vec3<f32>(1.0));
+    textureStore(output_texture, global_id.xy, key(rgb, 1.0, ycbcr));
+}
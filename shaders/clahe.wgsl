@@ -0,0 +1,139 @@
+// CLAHE(限制对比度的自适应直方图均衡化)
+// tile_histograms: 每个工作组统计一个 tile 的亮度直方图
+// build_tile_luts: 每个工作组对一个 tile 的直方图做裁剪、重新分配，并生成映射表
+// apply: 在相邻 4 个 tile 的映射表之间双线性插值
+
+struct Params {
+    tiles : vec2<u32>,
+    // 裁剪上限，是平均每个桶像素数的倍数，<= 0 表示不裁剪
+    clip_limit : f32,
+    _padding : u32,
+}
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<STORAGE_FORMAT, write>;
+@group(0) @binding(2) var<storage, read_write> tile_bins : array<u32>;
+@group(0) @binding(3) var<storage, read_write> tile_luts : array<f32>;
+@group(0) @binding(4) var<uniform> params : Params;
+
+var<workgroup> local_bins : array<atomic<u32>, 256>;
+var<workgroup> excess : atomic<u32>;
+var<workgroup> cdf : array<u32, 256>;
+
+fn to_bin(value : f32) -> u32 {
+    return u32(clamp(value, 0.0, 1.0) * 255.0 + 0.5);
+}
+
+fn luma(color : vec4<f32>) -> f32 {
+    return dot(vec3<f32>(0.299, 0.587, 0.114), color.rgb);
+}
+
+fn replace_luma(rgb : vec3<f32>, luma : f32, new_luma : f32) -> vec3<f32> {
+    return clamp(rgb + (new_luma - luma), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+fn tile_start(tile : vec2<u32>) -> vec2<u32> {
+    return tile * textureDimensions(input_texture) / params.tiles;
+}
+
+@compute @workgroup_size(16, 16)
+fn tile_histograms(
+    @builtin(workgroup_id) group_id : vec3<u32>,
+    @builtin(local_invocation_id) local_id : vec3<u32>,
+    @builtin(local_invocation_index) local_index : u32,
+) {
+    atomicStore(&local_bins[local_index], 0u);
+    workgroupBarrier();
+
+    let start = tile_start(group_id.xy);
+    let end = tile_start(group_id.xy + 1u);
+    for (var y = start.y + local_id.y; y < end.y; y = y + 16u) {
+        for (var x = start.x + local_id.x; x < end.x; x = x + 16u) {
+            let color = textureLoad(input_texture, vec2<i32>(i32(x), i32(y)), 0);
+            atomicAdd(&local_bins[to_bin(luma(color))], 1u);
+        }
+    }
+    workgroupBarrier();
+
+    let tile_index = group_id.x + group_id.y * params.tiles.x;
+    tile_bins[tile_index * 256u + local_index] = atomicLoad(&local_bins[local_index]);
+}
+
+@compute @workgroup_size(256)
+fn build_tile_luts(@builtin(workgroup_id) group_id : vec3<u32>, @builtin(local_invocation_index) i : u32) {
+    if i == 0u {
+        atomicStore(&excess, 0u);
+    }
+    workgroupBarrier();
+
+    let start = tile_start(group_id.xy);
+    let size = tile_start(group_id.xy + 1u) - start;
+    let pixels = max(size.x * size.y, 1u);
+    let tile_index = group_id.x + group_id.y * params.tiles.x;
+
+    var limit = 0xffffffffu;
+    if params.clip_limit > 0.0 {
+        limit = max(1u, u32(params.clip_limit * f32(pixels) / 256.0));
+    }
+
+    // 超出上限的部分平均分配给所有桶
+    let count = tile_bins[tile_index * 256u + i];
+    if count > limit {
+        atomicAdd(&excess, count - limit);
+    }
+    workgroupBarrier();
+
+    let total_excess = atomicLoad(&excess);
+    var clipped = min(count, limit) + total_excess / 256u;
+    if i < total_excess % 256u {
+        clipped = clipped + 1u;
+    }
+    cdf[i] = clipped;
+
+    for (var offset = 1u; offset < 256u; offset = offset << 1u) {
+        workgroupBarrier();
+        var value = cdf[i];
+        if i >= offset {
+            value = value + cdf[i - offset];
+        }
+        workgroupBarrier();
+        cdf[i] = value;
+    }
+    workgroupBarrier();
+
+    tile_luts[tile_index * 256u + i] = f32(cdf[i]) / f32(pixels);
+}
+
+fn lut_value(tile : vec2<i32>, bin : u32) -> f32 {
+    let tile_index = u32(tile.x) + u32(tile.y) * params.tiles.x;
+    return tile_luts[tile_index * 256u + bin];
+}
+
+@compute @workgroup_size(16, 16)
+fn apply(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = textureDimensions(input_texture);
+    let coords = vec2<i32>(global_id.xy);
+
+    if(coords.x >= i32(dimensions.x) || coords.y >= i32(dimensions.y)) {
+        return;
+    }
+
+    let color = textureLoad(input_texture, coords.xy, 0);
+    let gray = luma(color);
+    let bin = to_bin(gray);
+
+    // 像素相对 tile 中心的位置
+    let tile_size = vec2<f32>(dimensions) / vec2<f32>(params.tiles);
+    let position = (vec2<f32>(global_id.xy) + 0.5) / tile_size - 0.5;
+    let base = floor(position);
+    let f = position - base;
+    let max_tile = vec2<f32>(params.tiles - 1u);
+    let t0 = vec2<i32>(clamp(base, vec2<f32>(0.0), max_tile));
+    let t1 = vec2<i32>(clamp(base + 1.0, vec2<f32>(0.0), max_tile));
+
+    let top = mix(lut_value(t0, bin), lut_value(vec2<i32>(t1.x, t0.y), bin), f.x);
+    let bottom = mix(lut_value(vec2<i32>(t0.x, t1.y), bin), lut_value(t1, bin), f.x);
+    let rgb = replace_luma(color.rgb, gray, mix(top, bottom, f.y));
+
+    textureStore(output_texture, coords.xy, vec4<f32>(rgb, color.a));
+}
@@ -0,0 +1,56 @@
+// 流压缩: 标记 -> 扫描 -> 分散写入
+// Elem 由 Rust 端拼接在本文件前面。
+
+struct Params {
+    len: u32,
+    // 0: ==, 1: !=, 2: <, 3: <=, 4: >, 5: >=
+    comparison: u32,
+    operand: Elem,
+    _padding: u32,
+}
+
+const WORKGROUP_SIZE: u32 = 256u;
+
+@group(0) @binding(0) var<storage, read> input : array<Elem>;
+@group(0) @binding(1) var<storage, read_write> flags : array<u32>;
+@group(0) @binding(2) var<storage, read> positions : array<u32>;
+@group(0) @binding(3) var<storage, read_write> output : array<Elem>;
+@group(0) @binding(4) var<storage, read_write> count : array<u32>;
+@group(0) @binding(5) var<uniform> params : Params;
+
+// x 与 operand 比较，NaN 只满足 !=
+fn keep(x: Elem) -> bool {
+    switch params.comparison {
+        case 0u: { return x == params.operand; }
+        case 1u: { return x != params.operand; }
+        case 2u: { return x < params.operand; }
+        case 3u: { return x <= params.operand; }
+        case 4u: { return x > params.operand; }
+        default: { return x >= params.operand; }
+    }
+}
+
+// 满足条件的元素标记为 1
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn mark(@builtin(global_invocation_id) global_id : vec3u, @builtin(num_workgroups) num_workgroups : vec3u) {
+    let i = global_id.x + global_id.y * num_workgroups.x * WORKGROUP_SIZE;
+    if i >= params.len {
+        return;
+    }
+    flags[i] = select(0u, 1u, keep(input[i]));
+}
+
+// positions 是 flags 的 exclusive 前缀和，即元素在输出中的位置
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn scatter(@builtin(global_invocation_id) global_id : vec3u, @builtin(num_workgroups) num_workgroups : vec3u) {
+    let i = global_id.x + global_id.y * num_workgroups.x * WORKGROUP_SIZE;
+    if i >= params.len {
+        return;
+    }
+    if flags[i] != 0u {
+        output[positions[i]] = input[i];
+    }
+    if i == params.len - 1u {
+        count[0] = positions[i] + flags[i];
+    }
+}
@@ -0,0 +1,124 @@
+// 把前景(source)叠加到背景(backdrop)上，按 W3C Compositing and Blending 的公式:
+// 先用混合模式得到 Cs' = (1 - αb) Cs + αb B(Cb, Cs)，再按 Porter-Duff 的 Fa、Fb 合成:
+// co = αs Fa Cs' + αb Fb Cb，αo = αs Fa + αb Fb
+// 前景范围以外当作完全透明
+
+struct Params {
+    // 前景左上角在背景中的位置
+    offset : vec2<i32>,
+    // 0: over, 1: in, 2: out, 3: atop, 4: xor
+    porter_duff : u32,
+    // 0: normal, 1: multiply, 2: screen, 3: overlay, 4: soft light, 5: difference
+    blend : u32,
+    // 前景的不透明度
+    opacity : f32,
+    // 1 表示输入和输出都是预乘 alpha 的颜色
+    premultiplied : u32,
+    _padding : vec2<u32>,
+}
+
+@group(0) @binding(0) var backdrop_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<STORAGE_FORMAT, write>;
+@group(0) @binding(2) var<uniform> params : Params;
+@group(0) @binding(3) var source_texture : texture_2d<f32>;
+
+fn hard_light(cb : vec3<f32>, cs : vec3<f32>) -> vec3<f32> {
+    let multiply = cb * 2.0 * cs;
+    let screen = 1.0 - (1.0 - cb) * (1.0 - (2.0 * cs - 1.0));
+    return select(screen, multiply, cs <= vec3<f32>(0.5));
+}
+
+fn soft_light(cb : vec3<f32>, cs : vec3<f32>) -> vec3<f32> {
+    let d = select(sqrt(cb), ((16.0 * cb - 12.0) * cb + 4.0) * cb, cb <= vec3<f32>(0.25));
+    let darken = cb - (1.0 - 2.0 * cs) * cb * (1.0 - cb);
+    let lighten = cb + (2.0 * cs - 1.0) * (d - cb);
+    return select(lighten, darken, cs <= vec3<f32>(0.5));
+}
+
+fn blend(cb : vec3<f32>, cs : vec3<f32>) -> vec3<f32> {
+    switch params.blend {
+        case 1u: {
+            return cb * cs;
+        }
+        case 2u: {
+            return cb + cs - cb * cs;
+        }
+        case 3u: {
+            return hard_light(cs, cb);
+        }
+        case 4u: {
+            return soft_light(cb, cs);
+        }
+        case 5u: {
+            return abs(cb - cs);
+        }
+        default: {
+            return cs;
+        }
+    }
+}
+
+// Porter-Duff 的 (Fa, Fb)
+fn porter_duff_factors(alpha_s : f32, alpha_b : f32) -> vec2<f32> {
+    switch params.porter_duff {
+        case 1u: {
+            return vec2<f32>(alpha_b, 0.0);
+        }
+        case 2u: {
+            return vec2<f32>(1.0 - alpha_b, 0.0);
+        }
+        case 3u: {
+            return vec2<f32>(alpha_b, 1.0 - alpha_s);
+        }
+        case 4u: {
+            return vec2<f32>(1.0 - alpha_b, 1.0 - alpha_s);
+        }
+        default: {
+            return vec2<f32>(1.0, 1.0 - alpha_s);
+        }
+    }
+}
+
+// 转为非预乘的颜色
+fn unpremultiply(color : vec4<f32>) -> vec4<f32> {
+    if params.premultiplied == 0u {
+        return color;
+    }
+    if color.a <= 0.0 {
+        return vec4<f32>(0.0);
+    }
+    return vec4<f32>(color.rgb / color.a, color.a);
+}
+
+@compute @workgroup_size(16, 16)
+fn composite(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = vec2<i32>(textureDimensions(backdrop_texture));
+    let coords = vec2<i32>(global_id.xy);
+    if coords.x >= dimensions.x || coords.y >= dimensions.y {
+        return;
+    }
+
+    let backdrop = clamp(unpremultiply(textureLoad(backdrop_texture, coords, 0)), vec4<f32>(0.0), vec4<f32>(1.0));
+    var source = vec4<f32>(0.0);
+    let source_coords = coords - params.offset;
+    if all(source_coords >= vec2<i32>(0)) && all(source_coords < vec2<i32>(textureDimensions(source_texture))) {
+        source = clamp(unpremultiply(textureLoad(source_texture, source_coords, 0)), vec4<f32>(0.0), vec4<f32>(1.0));
+    }
+
+    let alpha_s = source.a * params.opacity;
+    let alpha_b = backdrop.a;
+    let blended = (1.0 - alpha_b) * source.rgb + alpha_b * blend(backdrop.rgb, source.rgb);
+    let factors = porter_duff_factors(alpha_s, alpha_b);
+
+    // 预乘的结果
+    let alpha = alpha_s * factors.x + alpha_b * factors.y;
+    let color = alpha_s * factors.x * blended + alpha_b * factors.y * backdrop.rgb;
+
+    if params.premultiplied == 1u {
+        textureStore(output_texture, coords, vec4<f32>(color, alpha));
+    } else if alpha > 0.0 {
+        textureStore(output_texture, coords, vec4<f32>(color / alpha, alpha));
+    } else {
+        textureStore(output_texture, coords, vec4<f32>(0.0));
+    }
+}
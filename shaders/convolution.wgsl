@@ -0,0 +1,42 @@
+// 通用 K×K 卷积，weights 按行优先存放，已在 Rust 端完成归一化
+// border.wgsl 由 Rust 端拼接在本文件前面
+
+struct Params {
+    // K = 2 * radius + 1
+    radius : u32,
+    edge_mode : u32,
+    // 非 0 时 alpha 直接取原像素
+    skip_alpha : u32,
+    bias : f32,
+}
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<STORAGE_FORMAT, write>;
+@group(0) @binding(2) var<storage, read> weights : array<f32>;
+@group(0) @binding(3) var<uniform> params : Params;
+
+@compute @workgroup_size(16, 16)
+fn convolve(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = vec2<i32>(textureDimensions(input_texture));
+    let coords = vec2<i32>(global_id.xy);
+    if coords.x >= dimensions.x || coords.y >= dimensions.y {
+        return;
+    }
+
+    let radius = i32(params.radius);
+    let size = 2 * radius + 1;
+    var sum = vec4<f32>(0.0);
+    for (var ky = 0; ky < size; ky = ky + 1) {
+        for (var kx = 0; kx < size; kx = kx + 1) {
+            let offset = vec2<i32>(kx - radius, ky - radius);
+            let sample_coords = border_coords(coords + offset, dimensions, params.edge_mode);
+            sum = sum + weights[ky * size + kx] * textureLoad(input_texture, sample_coords, 0);
+        }
+    }
+
+    var result = sum + vec4<f32>(params.bias);
+    if params.skip_alpha != 0u {
+        result.a = textureLoad(input_texture, coords, 0).a;
+    }
+    textureStore(output_texture, coords, result);
+}
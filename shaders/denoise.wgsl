@@ -0,0 +1,148 @@
+// 保边去噪: 双边滤波、导向滤波(He et al.)和非局部均值
+// border.wgsl 由 Rust 端拼接在本文件前面，越界时重复边缘像素
+// 单通道(R32Float)输入时 g、b 恒为 0，按颜色计算的距离和结果同样适用
+
+struct Params {
+    // 双边滤波的窗口半径
+    radius : i32,
+    // 非局部均值的搜索半径和块半径
+    search_radius : i32,
+    patch_radius : i32,
+    // 1 表示导向图是单通道的，直接取 r 作为引导值，否则取亮度
+    single_channel : u32,
+    spatial_sigma : f32,
+    range_sigma : f32,
+    // 导向滤波的正则项
+    epsilon : f32,
+    // 非局部均值的滤波强度
+    h : f32,
+}
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<STORAGE_FORMAT, write>;
+@group(0) @binding(2) var<uniform> params : Params;
+@group(0) @binding(3) var guide_texture : texture_2d<f32>;
+// 导向滤波的中间结果
+@group(0) @binding(4) var first_target : texture_storage_2d<rgba32float, write>;
+@group(0) @binding(5) var second_target : texture_storage_2d<rgba32float, write>;
+@group(0) @binding(6) var first_source : texture_2d<f32>;
+@group(0) @binding(7) var second_source : texture_2d<f32>;
+
+fn load(texture : texture_2d<f32>, coords : vec2<i32>) -> vec4<f32> {
+    let dimensions = vec2<i32>(textureDimensions(texture));
+    return textureLoad(texture, border_coords(coords, dimensions, EDGE_CLAMP), 0);
+}
+
+@compute @workgroup_size(16, 16)
+fn bilateral(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = vec2<i32>(textureDimensions(input_texture));
+    let coords = vec2<i32>(global_id.xy);
+    if coords.x >= dimensions.x || coords.y >= dimensions.y {
+        return;
+    }
+
+    let center = textureLoad(input_texture, coords, 0);
+    let spatial_scale = 1.0 / (2.0 * params.spatial_sigma * params.spatial_sigma);
+    let range_scale = 1.0 / (2.0 * params.range_sigma * params.range_sigma);
+    var sum = vec3<f32>(0.0);
+    var weight_sum = 0.0;
+    for (var dy = -params.radius; dy <= params.radius; dy++) {
+        for (var dx = -params.radius; dx <= params.radius; dx++) {
+            let sample = load(input_texture, coords + vec2<i32>(dx, dy)).rgb;
+            let difference = sample - center.rgb;
+            let weight = exp(-f32(dx * dx + dy * dy) * spatial_scale - dot(difference, difference) * range_scale);
+            sum += weight * sample;
+            weight_sum += weight;
+        }
+    }
+    textureStore(output_texture, coords, vec4<f32>(sum / weight_sum, center.a));
+}
+
+fn guide_value(coords : vec2<i32>) -> f32 {
+    let color = textureLoad(guide_texture, coords, 0);
+    if params.single_channel == 1u {
+        return color.r;
+    }
+    return dot(color.rgb, vec3<f32>(0.299, 0.587, 0.114));
+}
+
+// 第一步: 输出 (p, I) 和 (I p, I²)，随后分别做盒式滤波
+@compute @workgroup_size(16, 16)
+fn guided_products(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = vec2<i32>(textureDimensions(input_texture));
+    let coords = vec2<i32>(global_id.xy);
+    if coords.x >= dimensions.x || coords.y >= dimensions.y {
+        return;
+    }
+    let p = textureLoad(input_texture, coords, 0).rgb;
+    let i = guide_value(coords);
+    textureStore(first_target, coords, vec4<f32>(p, i));
+    textureStore(second_target, coords, vec4<f32>(i * p, i * i));
+}
+
+// 第二步: 由均值求每个窗口的线性系数 a = cov(I, p) / (var(I) + ε)，b = mean(p) - a mean(I)
+@compute @workgroup_size(16, 16)
+fn guided_coefficients(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = vec2<i32>(textureDimensions(first_source));
+    let coords = vec2<i32>(global_id.xy);
+    if coords.x >= dimensions.x || coords.y >= dimensions.y {
+        return;
+    }
+    let means = textureLoad(first_source, coords, 0);
+    let correlations = textureLoad(second_source, coords, 0);
+    let mean_p = means.rgb;
+    let mean_i = means.a;
+    let variance = correlations.a - mean_i * mean_i;
+    let covariance = correlations.rgb - mean_i * mean_p;
+    let a = covariance / (variance + params.epsilon);
+    let b = mean_p - a * mean_i;
+    textureStore(first_target, coords, vec4<f32>(a, 0.0));
+    textureStore(second_target, coords, vec4<f32>(b, 0.0));
+}
+
+// 第三步: 系数再做一次盒式滤波后，q = mean(a) I + mean(b)
+@compute @workgroup_size(16, 16)
+fn guided_output(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = vec2<i32>(textureDimensions(input_texture));
+    let coords = vec2<i32>(global_id.xy);
+    if coords.x >= dimensions.x || coords.y >= dimensions.y {
+        return;
+    }
+    let alpha = textureLoad(input_texture, coords, 0).a;
+    let mean_a = textureLoad(first_source, coords, 0).rgb;
+    let mean_b = textureLoad(second_source, coords, 0).rgb;
+    textureStore(output_texture, coords, vec4<f32>(mean_a * guide_value(coords) + mean_b, alpha));
+}
+
+// 非局部均值: 按以像素为中心的小块之间的距离加权平均搜索窗口内的像素
+@compute @workgroup_size(16, 16)
+fn nl_means(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = vec2<i32>(textureDimensions(input_texture));
+    let coords = vec2<i32>(global_id.xy);
+    if coords.x >= dimensions.x || coords.y >= dimensions.y {
+        return;
+    }
+
+    let patch_size = f32((2 * params.patch_radius + 1) * (2 * params.patch_radius + 1));
+    let scale = 1.0 / (params.h * params.h);
+    var sum = vec3<f32>(0.0);
+    var weight_sum = 0.0;
+    for (var sy = -params.search_radius; sy <= params.search_radius; sy++) {
+        for (var sx = -params.search_radius; sx <= params.search_radius; sx++) {
+            let candidate = coords + vec2<i32>(sx, sy);
+            var distance2 = 0.0;
+            for (var py = -params.patch_radius; py <= params.patch_radius; py++) {
+                for (var px = -params.patch_radius; px <= params.patch_radius; px++) {
+                    let offset = vec2<i32>(px, py);
+                    let difference = load(input_texture, coords + offset).rgb - load(input_texture, candidate + offset).rgb;
+                    distance2 += dot(difference, difference);
+                }
+            }
+            let weight = exp(-distance2 / patch_size * scale);
+            sum += weight * load(input_texture, candidate).rgb;
+            weight_sum += weight;
+        }
+    }
+    let alpha = textureLoad(input_texture, coords, 0).a;
+    textureStore(output_texture, coords, vec4<f32>(sum / weight_sum, alpha));
+}
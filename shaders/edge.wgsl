@@ -0,0 +1,167 @@
+// Sobel / Scharr 梯度和 Canny 边缘检测
+// compute_gradient: 对亮度求梯度，输出 (gx, gy, 幅值, 方向)，核已归一化，单位阶跃边缘的幅值为 1
+// threshold_magnitude: 幅值超过 high_threshold 的像素输出为白色
+// suppress: 非极大值抑制和双阈值，状态 0: 非边缘, 1: 弱边缘, 2: 强边缘
+// hysteresis: 与强边缘相邻的弱边缘变为强边缘，由 Rust 端反复调用直到 changed 为 0
+// finalize: 把强边缘写成二值图
+// border.wgsl 由 Rust 端拼接在本文件前面
+
+struct Params {
+    width : u32,
+    height : u32,
+    // 0: Sobel, 1: Scharr
+    kernel : u32,
+    edge_mode : u32,
+    low_threshold : f32,
+    high_threshold : f32,
+    _padding : vec2<u32>,
+}
+
+const NONE : u32 = 0u;
+const WEAK : u32 = 1u;
+const STRONG : u32 = 2u;
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var gradient_output : texture_storage_2d<rgba32float, write>;
+@group(0) @binding(2) var gradient_texture : texture_2d<f32>;
+@group(0) @binding(3) var mask_output : texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(4) var<uniform> params : Params;
+@group(0) @binding(5) var<storage, read_write> states : array<atomic<u32>>;
+@group(0) @binding(6) var<storage, read_write> changed : atomic<u32>;
+
+fn luminance(coords : vec2<i32>) -> f32 {
+    let dimensions = vec2<i32>(i32(params.width), i32(params.height));
+    let color = textureLoad(input_texture, border_coords(coords, dimensions, params.edge_mode), 0);
+    return dot(color.rgb, vec3<f32>(0.299, 0.587, 0.114));
+}
+
+fn in_bounds(coords : vec2<i32>) -> bool {
+    return coords.x >= 0 && coords.y >= 0 && coords.x < i32(params.width) && coords.y < i32(params.height);
+}
+
+fn state_index(coords : vec2<i32>) -> u32 {
+    return u32(coords.y) * params.width + u32(coords.x);
+}
+
+@compute @workgroup_size(16, 16)
+fn compute_gradient(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let coords = vec2<i32>(global_id.xy);
+    if !in_bounds(coords) {
+        return;
+    }
+
+    // Sobel: [1 2 1] / 4, Scharr: [3 10 3] / 16
+    var side = 1.0 / 4.0;
+    var center = 2.0 / 4.0;
+    if params.kernel == 1u {
+        side = 3.0 / 16.0;
+        center = 10.0 / 16.0;
+    }
+
+    let top_left = luminance(coords + vec2<i32>(-1, -1));
+    let top = luminance(coords + vec2<i32>(0, -1));
+    let top_right = luminance(coords + vec2<i32>(1, -1));
+    let left = luminance(coords + vec2<i32>(-1, 0));
+    let right = luminance(coords + vec2<i32>(1, 0));
+    let bottom_left = luminance(coords + vec2<i32>(-1, 1));
+    let bottom = luminance(coords + vec2<i32>(0, 1));
+    let bottom_right = luminance(coords + vec2<i32>(1, 1));
+
+    let gx = side * (top_right - top_left) + center * (right - left) + side * (bottom_right - bottom_left);
+    let gy = side * (bottom_left - top_left) + center * (bottom - top) + side * (bottom_right - top_right);
+    textureStore(gradient_output, coords, vec4<f32>(gx, gy, length(vec2<f32>(gx, gy)), atan2(gy, gx)));
+}
+
+@compute @workgroup_size(16, 16)
+fn threshold_magnitude(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let coords = vec2<i32>(global_id.xy);
+    if !in_bounds(coords) {
+        return;
+    }
+
+    var value = 0.0;
+    if textureLoad(gradient_texture, coords, 0).z >= params.high_threshold {
+        value = 1.0;
+    }
+    textureStore(mask_output, coords, vec4<f32>(value, value, value, 1.0));
+}
+
+fn magnitude_at(coords : vec2<i32>) -> f32 {
+    if !in_bounds(coords) {
+        return 0.0;
+    }
+    return textureLoad(gradient_texture, coords, 0).z;
+}
+
+@compute @workgroup_size(16, 16)
+fn suppress(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let coords = vec2<i32>(global_id.xy);
+    if !in_bounds(coords) {
+        return;
+    }
+
+    let gradient = textureLoad(gradient_texture, coords, 0);
+    let magnitude = gradient.z;
+
+    // 把梯度方向量化为 0°、45°、90°、135°，沿该方向比较两侧的幅值
+    var angle = degrees(gradient.w);
+    if angle < 0.0 {
+        angle = angle + 180.0;
+    }
+    var offset = vec2<i32>(1, 0);
+    if angle >= 22.5 && angle < 67.5 {
+        offset = vec2<i32>(1, 1);
+    } else if angle >= 67.5 && angle < 112.5 {
+        offset = vec2<i32>(0, 1);
+    } else if angle >= 112.5 && angle < 157.5 {
+        offset = vec2<i32>(-1, 1);
+    }
+
+    var state = NONE;
+    if magnitude >= magnitude_at(coords + offset) && magnitude > magnitude_at(coords - offset) {
+        if magnitude >= params.high_threshold {
+            state = STRONG;
+        } else if magnitude >= params.low_threshold {
+            state = WEAK;
+        }
+    }
+    atomicStore(&states[state_index(coords)], state);
+}
+
+@compute @workgroup_size(16, 16)
+fn hysteresis(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let coords = vec2<i32>(global_id.xy);
+    if !in_bounds(coords) {
+        return;
+    }
+
+    let index = state_index(coords);
+    if atomicLoad(&states[index]) != WEAK {
+        return;
+    }
+
+    for (var dy = -1; dy <= 1; dy = dy + 1) {
+        for (var dx = -1; dx <= 1; dx = dx + 1) {
+            let neighbor = coords + vec2<i32>(dx, dy);
+            if in_bounds(neighbor) && atomicLoad(&states[state_index(neighbor)]) == STRONG {
+                atomicStore(&states[index], STRONG);
+                atomicStore(&changed, 1u);
+                return;
+            }
+        }
+    }
+}
+
+@compute @workgroup_size(16, 16)
+fn finalize(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let coords = vec2<i32>(global_id.xy);
+    if !in_bounds(coords) {
+        return;
+    }
+
+    var value = 0.0;
+    if atomicLoad(&states[state_index(coords)]) == STRONG {
+        value = 1.0;
+    }
+    textureStore(mask_output, coords, vec4<f32>(value, value, value, 1.0));
+}
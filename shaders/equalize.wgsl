@@ -0,0 +1,67 @@
+// 直方图均衡化: build_lut 根据亮度直方图生成映射表，apply 替换每个像素的亮度(保持色度不变)
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<STORAGE_FORMAT, write>;
+// histogram.wgsl 输出的直方图，亮度在 768..1023
+@group(0) @binding(2) var<storage, read> bins : array<u32>;
+@group(0) @binding(3) var<storage, read_write> lut : array<f32, 256>;
+
+var<workgroup> cdf : array<u32, 256>;
+
+fn to_bin(value : f32) -> u32 {
+    return u32(clamp(value, 0.0, 1.0) * 255.0 + 0.5);
+}
+
+// 保持 YCbCr 中的色度，只替换亮度
+fn replace_luma(rgb : vec3<f32>, luma : f32, new_luma : f32) -> vec3<f32> {
+    return clamp(rgb + (new_luma - luma), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+@compute @workgroup_size(256)
+fn build_lut(@builtin(local_invocation_index) i : u32) {
+    cdf[i] = bins[3u * 256u + i];
+
+    // 累积分布(Hillis-Steele 扫描)
+    for (var offset = 1u; offset < 256u; offset = offset << 1u) {
+        workgroupBarrier();
+        var value = cdf[i];
+        if i >= offset {
+            value = value + cdf[i - offset];
+        }
+        workgroupBarrier();
+        cdf[i] = value;
+    }
+    workgroupBarrier();
+
+    // 第一个非零的累积值
+    var cdf_min = 0u;
+    for (var j = 0u; j < 256u; j = j + 1u) {
+        if cdf[j] != 0u {
+            cdf_min = cdf[j];
+            break;
+        }
+    }
+
+    let total = cdf[255];
+    if cdf[i] < cdf_min || total <= cdf_min {
+        lut[i] = 0.0;
+    } else {
+        lut[i] = f32(cdf[i] - cdf_min) / f32(total - cdf_min);
+    }
+}
+
+@compute @workgroup_size(16, 16)
+fn apply(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = textureDimensions(input_texture);
+    let coords = vec2<i32>(global_id.xy);
+
+    if(coords.x >= i32(dimensions.x) || coords.y >= i32(dimensions.y)) {
+        return;
+    }
+
+    let color = textureLoad(input_texture, coords.xy, 0);
+    let gray = dot(vec3<f32>(0.299, 0.587, 0.114), color.rgb);
+    let rgb = replace_luma(color.rgb, gray, lut[to_bin(gray)]);
+
+    textureStore(output_texture, coords.xy, vec4<f32>(rgb, color.a));
+}
@@ -0,0 +1,55 @@
+// 多种灰度算法，输入为 sRGB 编码的值
+// 0: BT.601 加权(与 grayscale.wgsl 相同，直接作用于编码值)
+// 1: BT.709 相对亮度，在线性光中计算后重新编码为 sRGB
+// 2: CIE L*，L* / 100 作为灰度
+// 3: 各通道平均
+// srgb.wgsl 由 Rust 端拼接在本文件前面
+
+struct Params {
+    mode : u32,
+    _padding0 : u32,
+    _padding1 : u32,
+    _padding2 : u32,
+}
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<STORAGE_FORMAT, write>;
+@group(0) @binding(2) var<uniform> params : Params;
+
+// 由相对亮度 Y(0~1) 计算 L*(0~100)
+fn cie_lightness(y : f32) -> f32 {
+    let epsilon = 216.0 / 24389.0;
+    let kappa = 24389.0 / 27.0;
+    if y <= epsilon {
+        return y * kappa;
+    }
+    return 116.0 * pow(y, 1.0 / 3.0) - 16.0;
+}
+
+@compute @workgroup_size(16, 16)
+fn grayscale(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = vec2<i32>(textureDimensions(input_texture));
+    let coords = vec2<i32>(global_id.xy);
+    if coords.x >= dimensions.x || coords.y >= dimensions.y {
+        return;
+    }
+
+    let color = textureLoad(input_texture, coords, 0);
+    let luminance = dot(srgb_to_linear(color.rgb), vec3<f32>(0.2126, 0.7152, 0.0722));
+    var gray = 0.0;
+    switch params.mode {
+        case 1u: {
+            gray = linear_to_srgb(vec3<f32>(luminance)).r;
+        }
+        case 2u: {
+            gray = cie_lightness(luminance) / 100.0;
+        }
+        case 3u: {
+            gray = (color.r + color.g + color.b) / 3.0;
+        }
+        default: {
+            gray = dot(color.rgb, vec3<f32>(0.299, 0.587, 0.114));
+        }
+    }
+    textureStore(output_texture, coords, vec4<f32>(gray, gray, gray, color.a));
+}
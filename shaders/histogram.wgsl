@@ -0,0 +1,40 @@
+// 直方图: 每个工作组先在共享内存中统计，再合并到全局
+// bins 布局: [R 0..255][G 0..255][B 0..255][亮度 0..255]
+
+const BINS: u32 = 256u;
+const CHANNELS: u32 = 4u;
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var<storage, read_write> bins : array<atomic<u32>, 1024>;
+
+var<workgroup> local_bins : array<atomic<u32>, 1024>;
+
+fn to_bin(value : f32) -> u32 {
+    return u32(clamp(value, 0.0, 1.0) * 255.0 + 0.5);
+}
+
+@compute @workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) global_id : vec3<u32>, @builtin(local_invocation_index) local_index : u32) {
+    for (var i = local_index; i < BINS * CHANNELS; i = i + 256u) {
+        atomicStore(&local_bins[i], 0u);
+    }
+    workgroupBarrier();
+
+    let dimensions = textureDimensions(input_texture);
+    if global_id.x < dimensions.x && global_id.y < dimensions.y {
+        let color = textureLoad(input_texture, vec2<i32>(global_id.xy), 0);
+        let gray = dot(vec3<f32>(0.299, 0.587, 0.114), color.rgb);
+        atomicAdd(&local_bins[to_bin(color.r)], 1u);
+        atomicAdd(&local_bins[BINS + to_bin(color.g)], 1u);
+        atomicAdd(&local_bins[2u * BINS + to_bin(color.b)], 1u);
+        atomicAdd(&local_bins[3u * BINS + to_bin(gray)], 1u);
+    }
+    workgroupBarrier();
+
+    for (var i = local_index; i < BINS * CHANNELS; i = i + 256u) {
+        let count = atomicLoad(&local_bins[i]);
+        if count != 0u {
+            atomicAdd(&bins[i], count);
+        }
+    }
+}
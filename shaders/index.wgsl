@@ -1,11 +1,23 @@
+// WORKGROUP_SIZE 由 Rust 端根据设备限制拼接在本文件前面
+
 struct ArrayData {
     data: array<f32>,
 }
 
+struct Params {
+    len: u32,
+}
+
 @group(0) @binding(0) var<storage, read> input_array : ArrayData;
 @group(0) @binding(1) var<storage, read_write> output_array : ArrayData;
+@group(0) @binding(2) var<uniform> params : Params;
 
-@compute @workgroup_size(8)
-fn main(@builtin(global_invocation_id) global_id : vec3u) {
-    output_array.data[global_id.x] = input_array.data[global_id.x] +1.;
+@compute @workgroup_size(WORKGROUP_SIZE)
+fn main(@builtin(global_invocation_id) global_id : vec3u, @builtin(num_workgroups) num_workgroups : vec3u) {
+    // 工作组数量超过单维上限时会分摊到 y 维
+    let index = global_id.x + global_id.y * num_workgroups.x * WORKGROUP_SIZE;
+    if index >= params.len {
+        return;
+    }
+    output_array.data[index] = input_array.data[index] +1.;
 }
@@ -0,0 +1,56 @@
+// 带边界处理的插值，warp.wgsl 和 remap.wgsl 共用
+// 使用者需要声明 input_texture，以及带 interpolation、border、constant 字段的 params
+// border.wgsl 需要拼接在本文件前面
+
+const BORDER_CONSTANT : u32 = 3u;
+
+fn fetch(coords : vec2<i32>) -> vec4<f32> {
+    let dimensions = vec2<i32>(textureDimensions(input_texture));
+    if params.border == BORDER_CONSTANT {
+        if any(coords < vec2<i32>(0)) || any(coords >= dimensions) {
+            return params.constant;
+        }
+        return textureLoad(input_texture, coords, 0);
+    }
+    return textureLoad(input_texture, border_coords(coords, dimensions, params.border), 0);
+}
+
+// Catmull-Rom 权重
+fn cubic_weights(t : f32) -> vec4<f32> {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    return vec4<f32>(
+        -0.5 * t3 + t2 - 0.5 * t,
+        1.5 * t3 - 2.5 * t2 + 1.0,
+        -1.5 * t3 + 2.0 * t2 + 0.5 * t,
+        0.5 * t3 - 0.5 * t2,
+    );
+}
+
+// 在像素下标坐标 position 处插值
+fn sample_at(position : vec2<f32>) -> vec4<f32> {
+    if params.interpolation == 0u {
+        return fetch(vec2<i32>(floor(position + 0.5)));
+    }
+
+    let base = floor(position);
+    let t = position - base;
+    let origin = vec2<i32>(base);
+    if params.interpolation == 1u {
+        let top = mix(fetch(origin), fetch(origin + vec2<i32>(1, 0)), t.x);
+        let bottom = mix(fetch(origin + vec2<i32>(0, 1)), fetch(origin + vec2<i32>(1, 1)), t.x);
+        return mix(top, bottom, t.y);
+    }
+
+    let wx = cubic_weights(t.x);
+    let wy = cubic_weights(t.y);
+    var sum = vec4<f32>(0.0);
+    for (var j = 0; j < 4; j = j + 1) {
+        var row = vec4<f32>(0.0);
+        for (var i = 0; i < 4; i = i + 1) {
+            row = row + wx[i] * fetch(origin + vec2<i32>(i - 1, j - 1));
+        }
+        sum = sum + wy[j] * row;
+    }
+    return sum;
+}
@@ -0,0 +1,150 @@
+// 连通域标记
+// init_labels: 亮度不小于阈值的像素为前景，标签初始化为 自身下标 + 1，背景为 0
+// propagate: 取邻域内最小的标签并做指针跳跃，由 Rust 端反复调用直到 changed 为 0，
+//            收敛后每个像素的标签都等于所在连通域中第一个像素(按行扫描顺序)的 下标 + 1
+// mark_roots: 标签等于 自身下标 + 1 的像素是连通域的根
+// finalize: roots 做 inclusive 前缀和之后就是从 1 开始的连续编号，写入标签纹理并统计面积、包围盒和质心
+
+struct Params {
+    width : u32,
+    height : u32,
+    // 4 或 8
+    connectivity : u32,
+    threshold : f32,
+}
+
+// 坐标和可能超过 u32，拆成低 32 位和高 32 位
+struct ComponentStats {
+    area : atomic<u32>,
+    min_x : atomic<u32>,
+    min_y : atomic<u32>,
+    max_x : atomic<u32>,
+    max_y : atomic<u32>,
+    sum_x_low : atomic<u32>,
+    sum_x_high : atomic<u32>,
+    sum_y_low : atomic<u32>,
+    sum_y_high : atomic<u32>,
+}
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var label_texture : texture_storage_2d<r32uint, write>;
+@group(0) @binding(2) var<storage, read_write> labels : array<atomic<u32>>;
+@group(0) @binding(3) var<uniform> params : Params;
+@group(0) @binding(4) var<storage, read_write> changed : atomic<u32>;
+@group(0) @binding(5) var<storage, read_write> roots : array<u32>;
+@group(0) @binding(6) var<storage, read_write> stats : array<ComponentStats>;
+
+fn in_bounds(coords : vec2<i32>) -> bool {
+    return coords.x >= 0 && coords.y >= 0 && coords.x < i32(params.width) && coords.y < i32(params.height);
+}
+
+fn pixel_index(coords : vec2<i32>) -> u32 {
+    return u32(coords.y) * params.width + u32(coords.x);
+}
+
+@compute @workgroup_size(16, 16)
+fn init_labels(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let coords = vec2<i32>(global_id.xy);
+    if !in_bounds(coords) {
+        return;
+    }
+
+    let color = textureLoad(input_texture, coords, 0);
+    let index = pixel_index(coords);
+    var label = 0u;
+    if dot(color.rgb, vec3<f32>(0.299, 0.587, 0.114)) >= params.threshold {
+        label = index + 1u;
+    }
+    atomicStore(&labels[index], label);
+}
+
+@compute @workgroup_size(16, 16)
+fn propagate(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let coords = vec2<i32>(global_id.xy);
+    if !in_bounds(coords) {
+        return;
+    }
+
+    let index = pixel_index(coords);
+    let label = atomicLoad(&labels[index]);
+    if label == 0u {
+        return;
+    }
+
+    var smallest = label;
+    for (var dy = -1; dy <= 1; dy = dy + 1) {
+        for (var dx = -1; dx <= 1; dx = dx + 1) {
+            // 4 连通时跳过对角线
+            if params.connectivity == 4u && dx != 0 && dy != 0 {
+                continue;
+            }
+            let neighbor = coords + vec2<i32>(dx, dy);
+            if !in_bounds(neighbor) {
+                continue;
+            }
+            let neighbor_label = atomicLoad(&labels[pixel_index(neighbor)]);
+            if neighbor_label != 0u {
+                smallest = min(smallest, neighbor_label);
+            }
+        }
+    }
+
+    // 指针跳跃: 直接指向邻居标签所指向的标签
+    smallest = min(smallest, atomicLoad(&labels[smallest - 1u]));
+    if smallest < label {
+        atomicMin(&labels[index], smallest);
+        atomicMin(&labels[label - 1u], smallest);
+        atomicStore(&changed, 1u);
+    }
+}
+
+@compute @workgroup_size(16, 16)
+fn mark_roots(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let coords = vec2<i32>(global_id.xy);
+    if !in_bounds(coords) {
+        return;
+    }
+
+    let index = pixel_index(coords);
+    var is_root = 0u;
+    if atomicLoad(&labels[index]) == index + 1u {
+        is_root = 1u;
+    }
+    roots[index] = is_root;
+}
+
+@compute @workgroup_size(16, 16)
+fn finalize(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let coords = vec2<i32>(global_id.xy);
+    if !in_bounds(coords) {
+        return;
+    }
+
+    let label = atomicLoad(&labels[pixel_index(coords)]);
+    if label == 0u {
+        textureStore(label_texture, coords, vec4<u32>(0u));
+        return;
+    }
+
+    // roots 此时是 inclusive 前缀和
+    let component = roots[label - 1u];
+    textureStore(label_texture, coords, vec4<u32>(component));
+
+    let x = u32(coords.x);
+    let y = u32(coords.y);
+    let i = component - 1u;
+    atomicAdd(&stats[i].area, 1u);
+    atomicMin(&stats[i].min_x, x);
+    atomicMin(&stats[i].min_y, y);
+    atomicMax(&stats[i].max_x, x);
+    atomicMax(&stats[i].max_y, y);
+    // 低 32 位溢出时向高位进位
+    let old_x = atomicAdd(&stats[i].sum_x_low, x);
+    if old_x + x < old_x {
+        atomicAdd(&stats[i].sum_x_high, 1u);
+    }
+    let old_y = atomicAdd(&stats[i].sum_y_low, y);
+    if old_y + y < old_y {
+        atomicAdd(&stats[i].sum_y_high, 1u);
+    }
+}
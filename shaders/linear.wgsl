@@ -0,0 +1,31 @@
+// 线性光处理
+// decode: 把 sRGB 编码的输入显式解码为线性值，写入浮点纹理
+// encode: 把线性值显式编码为 sRGB
+// srgb.wgsl 由 Rust 端拼接在本文件前面
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<STORAGE_FORMAT, write>;
+
+@compute @workgroup_size(16, 16)
+fn decode(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = vec2<i32>(textureDimensions(input_texture));
+    let coords = vec2<i32>(global_id.xy);
+    if coords.x >= dimensions.x || coords.y >= dimensions.y {
+        return;
+    }
+
+    let color = textureLoad(input_texture, coords, 0);
+    textureStore(output_texture, coords, vec4<f32>(srgb_to_linear(color.rgb), color.a));
+}
+
+@compute @workgroup_size(16, 16)
+fn encode(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = vec2<i32>(textureDimensions(input_texture));
+    let coords = vec2<i32>(global_id.xy);
+    if coords.x >= dimensions.x || coords.y >= dimensions.y {
+        return;
+    }
+
+    let color = textureLoad(input_texture, coords, 0);
+    textureStore(output_texture, coords, vec4<f32>(linear_to_srgb(color.rgb), color.a));
+}
@@ -0,0 +1,109 @@
+// 应用 .cube 查找表
+// 3D LUT 存放在 N×N×N 的 3D 纹理中，x 对应红色、y 对应绿色、z 对应蓝色
+// 1D LUT 存放在 storage buffer 中，每个通道单独查找并线性插值
+// 两种 LUT 各有一个入口，自动推导的布局里只有用到的那个 binding
+// alpha 保持不变
+
+struct Params {
+    domain_min : vec4<f32>,
+    domain_max : vec4<f32>,
+    size : u32,
+    // 0: 三线性, 1: 四面体
+    interpolation : u32,
+    _padding : vec2<u32>,
+}
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<STORAGE_FORMAT, write>;
+@group(0) @binding(2) var lut_texture : texture_3d<f32>;
+@group(0) @binding(3) var<uniform> params : Params;
+@group(0) @binding(4) var<storage, read> lut_entries : array<vec4<f32>>;
+
+fn lut(index : vec3<i32>) -> vec3<f32> {
+    return textureLoad(lut_texture, index, 0).rgb;
+}
+
+fn lookup_1d(position : vec3<f32>) -> vec3<f32> {
+    let last = i32(params.size) - 1;
+    let low = vec3<i32>(floor(position));
+    let high = min(low + 1, vec3<i32>(last));
+    let t = position - floor(position);
+    return vec3<f32>(
+        mix(lut_entries[low.r].r, lut_entries[high.r].r, t.r),
+        mix(lut_entries[low.g].g, lut_entries[high.g].g, t.g),
+        mix(lut_entries[low.b].b, lut_entries[high.b].b, t.b),
+    );
+}
+
+fn lookup_3d(position : vec3<f32>) -> vec3<f32> {
+    let last = i32(params.size) - 1;
+    let base = vec3<i32>(floor(position));
+    let next = min(base + 1, vec3<i32>(last));
+    let f = position - floor(position);
+
+    let c000 = lut(base);
+    let c111 = lut(next);
+    let c100 = lut(vec3<i32>(next.x, base.y, base.z));
+    let c010 = lut(vec3<i32>(base.x, next.y, base.z));
+    let c001 = lut(vec3<i32>(base.x, base.y, next.z));
+    let c110 = lut(vec3<i32>(next.x, next.y, base.z));
+    let c101 = lut(vec3<i32>(next.x, base.y, next.z));
+    let c011 = lut(vec3<i32>(base.x, next.y, next.z));
+
+    if params.interpolation == 0u {
+        let c00 = mix(c000, c100, f.x);
+        let c10 = mix(c010, c110, f.x);
+        let c01 = mix(c001, c101, f.x);
+        let c11 = mix(c011, c111, f.x);
+        return mix(mix(c00, c10, f.y), mix(c01, c11, f.y), f.z);
+    }
+
+    // 四面体插值: 按 f 各分量的大小关系选择立方体中的一个四面体
+    if f.x > f.y {
+        if f.y > f.z {
+            return c000 + f.x * (c100 - c000) + f.y * (c110 - c100) + f.z * (c111 - c110);
+        }
+        if f.x > f.z {
+            return c000 + f.x * (c100 - c000) + f.z * (c101 - c100) + f.y * (c111 - c101);
+        }
+        return c000 + f.z * (c001 - c000) + f.x * (c101 - c001) + f.y * (c111 - c101);
+    }
+    if f.z > f.y {
+        return c000 + f.z * (c001 - c000) + f.y * (c011 - c001) + f.x * (c111 - c011);
+    }
+    if f.z > f.x {
+        return c000 + f.y * (c010 - c000) + f.z * (c011 - c010) + f.x * (c111 - c011);
+    }
+    return c000 + f.y * (c010 - c000) + f.x * (c110 - c010) + f.z * (c111 - c110);
+}
+
+// 输入颜色在定义域中的位置，单位为格点
+fn lut_position(color : vec4<f32>) -> vec3<f32> {
+    let range = max(params.domain_max.rgb - params.domain_min.rgb, vec3<f32>(1e-6));
+    let normalized = clamp((color.rgb - params.domain_min.rgb) / range, vec3<f32>(0.0), vec3<f32>(1.0));
+    return normalized * f32(params.size - 1u);
+}
+
+@compute @workgroup_size(16, 16)
+fn apply_lut_1d(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = vec2<i32>(textureDimensions(input_texture));
+    let coords = vec2<i32>(global_id.xy);
+    if coords.x >= dimensions.x || coords.y >= dimensions.y {
+        return;
+    }
+
+    let color = textureLoad(input_texture, coords, 0);
+    textureStore(output_texture, coords, vec4<f32>(lookup_1d(lut_position(color)), color.a));
+}
+
+@compute @workgroup_size(16, 16)
+fn apply_lut_3d(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = vec2<i32>(textureDimensions(input_texture));
+    let coords = vec2<i32>(global_id.xy);
+    if coords.x >= dimensions.x || coords.y >= dimensions.y {
+        return;
+    }
+
+    let color = textureLoad(input_texture, coords, 0);
+    textureStore(output_texture, coords, vec4<f32>(lookup_3d(lut_position(color)), color.a));
+}
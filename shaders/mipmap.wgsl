@@ -0,0 +1,106 @@
+// 由上一层 mip 生成下一层，下一层的大小为 max(1, floor(上一层 / 2))
+// 奇数尺寸时每个输出像素覆盖 2.5 个(或更多)输入像素，所有滤波都按实际的缩放比例计算权重
+// srgb.wgsl 由 Rust 端拼接在本文件前面
+
+struct Params {
+    // 0: 盒式(按覆盖面积加权)，1: Kaiser 窗 sinc，2: 高斯
+    filter_type : u32,
+    // 1 表示颜色是 sRGB 编码的，在线性光中平均后再编码，alpha 始终按线性平均
+    srgb : u32,
+    _padding : vec2<u32>,
+}
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<STORAGE_FORMAT, write>;
+@group(0) @binding(2) var<uniform> params : Params;
+
+const PI : f32 = 3.14159265;
+// Kaiser 和高斯滤波的半径，以输出像素为单位
+const KAISER_WIDTH : f32 = 3.0;
+const KAISER_ALPHA : f32 = 4.0;
+const GAUSSIAN_WIDTH : f32 = 1.5;
+const GAUSSIAN_SIGMA : f32 = 0.5;
+
+// 第一类零阶修正贝塞尔函数的级数展开
+fn bessel_i0(x : f32) -> f32 {
+    var sum = 1.0;
+    var term = 1.0;
+    let half_x2 = x * x / 4.0;
+    for (var k = 1; k < 20; k++) {
+        term *= half_x2 / f32(k * k);
+        sum += term;
+    }
+    return sum;
+}
+
+fn sinc(x : f32) -> f32 {
+    if abs(x) < 1e-5 {
+        return 1.0;
+    }
+    return sin(PI * x) / (PI * x);
+}
+
+// d 为到输出像素中心的距离，以输出像素为单位
+fn filter_weight(d : f32) -> f32 {
+    if params.filter_type == 1u {
+        let t = d / KAISER_WIDTH;
+        if abs(t) >= 1.0 {
+            return 0.0;
+        }
+        return sinc(d) * bessel_i0(KAISER_ALPHA * sqrt(1.0 - t * t)) / bessel_i0(KAISER_ALPHA);
+    }
+    return exp(-d * d / (2.0 * GAUSSIAN_SIGMA * GAUSSIAN_SIGMA));
+}
+
+fn load(coords : vec2<i32>, dimensions : vec2<i32>) -> vec4<f32> {
+    let color = textureLoad(input_texture, clamp(coords, vec2<i32>(0), dimensions - 1), 0);
+    if params.srgb == 1u {
+        return vec4<f32>(srgb_to_linear(color.rgb), color.a);
+    }
+    return color;
+}
+
+@compute @workgroup_size(16, 16)
+fn downsample(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let output_dimensions = textureDimensions(output_texture);
+    if global_id.x >= output_dimensions.x || global_id.y >= output_dimensions.y {
+        return;
+    }
+    let dimensions = vec2<i32>(textureDimensions(input_texture));
+    let scale = vec2<f32>(dimensions) / vec2<f32>(output_dimensions);
+    let coords = vec2<f32>(global_id.xy);
+
+    var sum = vec4<f32>(0.0);
+    var weight_sum = 0.0;
+    if params.filter_type == 0u {
+        // 输出像素覆盖输入的 [start, end)，边界上的像素按覆盖的长度加权
+        let start = coords * scale;
+        let end = start + scale;
+        for (var y = i32(floor(start.y)); f32(y) < end.y; y++) {
+            let wy = min(end.y, f32(y + 1)) - max(start.y, f32(y));
+            for (var x = i32(floor(start.x)); f32(x) < end.x; x++) {
+                let wx = min(end.x, f32(x + 1)) - max(start.x, f32(x));
+                sum += wx * wy * load(vec2<i32>(x, y), dimensions);
+                weight_sum += wx * wy;
+            }
+        }
+    } else {
+        let width = select(GAUSSIAN_WIDTH, KAISER_WIDTH, params.filter_type == 1u);
+        let center = (coords + 0.5) * scale;
+        let radius = width * scale;
+        for (var y = i32(floor(center.y - radius.y)); f32(y) <= center.y + radius.y; y++) {
+            let wy = filter_weight((f32(y) + 0.5 - center.y) / scale.y);
+            for (var x = i32(floor(center.x - radius.x)); f32(x) <= center.x + radius.x; x++) {
+                let wx = filter_weight((f32(x) + 0.5 - center.x) / scale.x);
+                sum += wx * wy * load(vec2<i32>(x, y), dimensions);
+                weight_sum += wx * wy;
+            }
+        }
+    }
+
+    var color = sum / weight_sum;
+    if params.srgb == 1u {
+        color = vec4<f32>(linear_to_srgb(color.rgb), color.a);
+    }
+    textureStore(output_texture, global_id.xy, color);
+}
@@ -0,0 +1,62 @@
+// 形态学运算，逐通道取结构元素覆盖范围内的最小值(腐蚀)或最大值(膨胀)，alpha 保持不变
+// 图像外的像素不参与计算
+// difference: 输出 first - second，用于形态学梯度、顶帽和黑帽
+
+struct Params {
+    // 结构元素的宽高，锚点在中心
+    element_width : u32,
+    element_height : u32,
+    // 0: 腐蚀, 1: 膨胀
+    operation : u32,
+    _padding : u32,
+}
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<STORAGE_FORMAT, write>;
+@group(0) @binding(2) var<storage, read> element : array<u32>;
+@group(0) @binding(3) var<uniform> params : Params;
+@group(0) @binding(4) var second_texture : texture_2d<f32>;
+
+@compute @workgroup_size(16, 16)
+fn morph(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = vec2<i32>(textureDimensions(input_texture));
+    let coords = vec2<i32>(global_id.xy);
+    if coords.x >= dimensions.x || coords.y >= dimensions.y {
+        return;
+    }
+
+    let anchor = vec2<i32>(i32(params.element_width / 2u), i32(params.element_height / 2u));
+    let center = textureLoad(input_texture, coords, 0);
+    var result = center.rgb;
+    for (var y = 0u; y < params.element_height; y = y + 1u) {
+        for (var x = 0u; x < params.element_width; x = x + 1u) {
+            if element[y * params.element_width + x] == 0u {
+                continue;
+            }
+            let sample_coords = coords + vec2<i32>(i32(x), i32(y)) - anchor;
+            if any(sample_coords < vec2<i32>(0)) || any(sample_coords >= dimensions) {
+                continue;
+            }
+            let value = textureLoad(input_texture, sample_coords, 0).rgb;
+            if params.operation == 0u {
+                result = min(result, value);
+            } else {
+                result = max(result, value);
+            }
+        }
+    }
+    textureStore(output_texture, coords, vec4<f32>(result, center.a));
+}
+
+@compute @workgroup_size(16, 16)
+fn difference(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = vec2<i32>(textureDimensions(input_texture));
+    let coords = vec2<i32>(global_id.xy);
+    if coords.x >= dimensions.x || coords.y >= dimensions.y {
+        return;
+    }
+
+    let first = textureLoad(input_texture, coords, 0);
+    let second = textureLoad(second_texture, coords, 0);
+    textureStore(output_texture, coords, vec4<f32>(max(first.rgb - second.rgb, vec3<f32>(0.0)), first.a));
+}
@@ -0,0 +1,38 @@
+// 扩展边界: 输出像素 coords 对应输入像素 coords - offset，越界时按 mode 取值
+// border.wgsl 由 Rust 端拼接在本文件前面
+
+struct Params {
+    // 左边和上边扩展的像素数
+    offset : vec2<i32>,
+    // 0: 常量, 1: 重复边缘像素, 2: 镜像
+    mode : u32,
+    _padding : u32,
+    constant : vec4<f32>,
+}
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<STORAGE_FORMAT, write>;
+@group(0) @binding(2) var<uniform> params : Params;
+
+@compute @workgroup_size(16, 16)
+fn pad(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let output_dimensions = vec2<i32>(textureDimensions(output_texture));
+    let coords = vec2<i32>(global_id.xy);
+    if coords.x >= output_dimensions.x || coords.y >= output_dimensions.y {
+        return;
+    }
+
+    let dimensions = vec2<i32>(textureDimensions(input_texture));
+    let source = coords - params.offset;
+    let inside = all(source >= vec2<i32>(0)) && all(source < dimensions);
+
+    var color = params.constant;
+    if inside {
+        color = textureLoad(input_texture, source, 0);
+    } else if params.mode == 1u {
+        color = textureLoad(input_texture, border_coords(source, dimensions, EDGE_CLAMP), 0);
+    } else if params.mode == 2u {
+        color = textureLoad(input_texture, border_coords(source, dimensions, EDGE_MIRROR), 0);
+    }
+    textureStore(output_texture, coords, color);
+}
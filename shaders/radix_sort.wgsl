@@ -0,0 +1,127 @@
+// LSD 基数排序，每趟处理 4 位(16 个桶)
+// count: 每个工作组统计块内各数位的个数，按 [数位][块] 的顺序写入 block_counts
+// block_counts 做 exclusive 前缀和之后就是每个块中每个数位的起始位置
+// scatter: 按稳定顺序把键(和值)写到目标位置
+// 块数超过单维上限时工作组会分摊到 y 维。
+
+struct Params {
+    len: u32,
+    shift: u32,
+    num_blocks: u32,
+    has_values: u32,
+}
+
+const WORKGROUP_SIZE: u32 = 256u;
+const RADIX: u32 = 16u;
+
+@group(0) @binding(0) var<storage, read> keys_in : array<u32>;
+@group(0) @binding(1) var<storage, read_write> keys_out : array<u32>;
+@group(0) @binding(2) var<storage, read> values_in : array<u32>;
+@group(0) @binding(3) var<storage, read_write> values_out : array<u32>;
+@group(0) @binding(4) var<storage, read_write> block_counts : array<u32>;
+@group(0) @binding(5) var<uniform> params : Params;
+
+var<workgroup> local_counts : array<atomic<u32>, RADIX>;
+// 块内各数位个数的前缀和，每个数位占 16 位: low 存数位 0~7，high 存数位 8~15
+var<workgroup> prefix_low : array<vec4<u32>, WORKGROUP_SIZE>;
+var<workgroup> prefix_high : array<vec4<u32>, WORKGROUP_SIZE>;
+
+fn digit_of(key : u32) -> u32 {
+    return (key >> params.shift) & (RADIX - 1u);
+}
+
+@compute @workgroup_size(256)
+fn count(
+    @builtin(local_invocation_id) local_id : vec3u,
+    @builtin(workgroup_id) group_id : vec3u,
+    @builtin(num_workgroups) num_workgroups : vec3u,
+) {
+    let group = group_id.x + group_id.y * num_workgroups.x;
+    if group >= params.num_blocks {
+        return;
+    }
+
+    if local_id.x < RADIX {
+        atomicStore(&local_counts[local_id.x], 0u);
+    }
+    workgroupBarrier();
+
+    let i = group * WORKGROUP_SIZE + local_id.x;
+    if i < params.len {
+        atomicAdd(&local_counts[digit_of(keys_in[i])], 1u);
+    }
+    workgroupBarrier();
+
+    if local_id.x < RADIX {
+        block_counts[local_id.x * params.num_blocks + group] = atomicLoad(&local_counts[local_id.x]);
+    }
+}
+
+// 数位 digit 对应的 16 位计数为 1，其它为 0; digit >= RADIX 时全为 0
+fn one_hot(digit : u32, high : bool) -> vec4<u32> {
+    let base = select(0u, 8u, high);
+    var result = vec4<u32>(0u);
+    if digit >= base && digit < base + 8u {
+        let d = digit - base;
+        result[d / 2u] = 1u << (16u * (d % 2u));
+    }
+    return result;
+}
+
+// 从打包的计数中取出 digit 的个数
+fn unpack_count(low : vec4<u32>, high : vec4<u32>, digit : u32) -> u32 {
+    let d = digit % 8u;
+    let word = select(low, high, digit >= 8u)[d / 2u];
+    return (word >> (16u * (d % 2u))) & 0xffffu;
+}
+
+// block_counts 此时是扫描后的偏移
+@compute @workgroup_size(256)
+fn scatter(
+    @builtin(local_invocation_id) local_id : vec3u,
+    @builtin(workgroup_id) group_id : vec3u,
+    @builtin(num_workgroups) num_workgroups : vec3u,
+) {
+    let group = group_id.x + group_id.y * num_workgroups.x;
+    if group >= params.num_blocks {
+        return;
+    }
+
+    let i = group * WORKGROUP_SIZE + local_id.x;
+
+    // 越界的线程使用一个不存在的数位，不影响其他元素的排名
+    var digit = RADIX;
+    if i < params.len {
+        digit = digit_of(keys_in[i]);
+    }
+
+    // 对 16 个数位同时做 inclusive 前缀和(Hillis-Steele)，计数不超过 256，16 位足够
+    let t = local_id.x;
+    prefix_low[t] = one_hot(digit, false);
+    prefix_high[t] = one_hot(digit, true);
+    for (var offset = 1u; offset < WORKGROUP_SIZE; offset = offset << 1u) {
+        workgroupBarrier();
+        var low = prefix_low[t];
+        var high = prefix_high[t];
+        if t >= offset {
+            low = low + prefix_low[t - offset];
+            high = high + prefix_high[t - offset];
+        }
+        workgroupBarrier();
+        prefix_low[t] = low;
+        prefix_high[t] = high;
+    }
+
+    if i >= params.len {
+        return;
+    }
+
+    // 块内排在前面的相同数位个数(不含自己)，保证排序稳定
+    let rank = unpack_count(prefix_low[t], prefix_high[t], digit) - 1u;
+
+    let dst = block_counts[digit * params.num_blocks + group] + rank;
+    keys_out[dst] = keys_in[i];
+    if params.has_values != 0u {
+        values_out[dst] = values_in[i];
+    }
+}
@@ -0,0 +1,28 @@
+// 重映射: 输出像素 (x, y) 取输入图像中 map(x, y) 处的值，map 以像素下标为单位
+// border.wgsl 和 interpolate.wgsl 由 Rust 端拼接在本文件前面
+
+struct Params {
+    // 0: 最近邻, 1: 双线性, 2: 双三次
+    interpolation : u32,
+    // 0~2: border.wgsl 中的 EDGE_*, 3: 常量
+    border : u32,
+    _padding : vec2<u32>,
+    constant : vec4<f32>,
+}
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<STORAGE_FORMAT, write>;
+@group(0) @binding(2) var<uniform> params : Params;
+@group(0) @binding(3) var map_texture : texture_2d<f32>;
+
+@compute @workgroup_size(16, 16)
+fn remap(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let output_dimensions = vec2<i32>(textureDimensions(output_texture));
+    let coords = vec2<i32>(global_id.xy);
+    if coords.x >= output_dimensions.x || coords.y >= output_dimensions.y {
+        return;
+    }
+
+    let position = textureLoad(map_texture, coords, 0).xy;
+    textureStore(output_texture, coords, sample_at(position));
+}
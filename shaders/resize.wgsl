@@ -0,0 +1,132 @@
+// 可分离缩放，每次沿一个方向重采样(与 image crate 的做法相同: 先垂直后水平)
+// 输出像素 d 的中心在源图像中的位置为 src_offset + (d + 0.5) * scale
+// 缩小时卷积核按 scale 拉宽，越界的采样点被丢弃，权重重新归一化
+
+struct Params {
+    // 0: 水平, 1: 垂直
+    direction : u32,
+    // 0: 最近邻, 1: 双线性, 2: 双三次(Catmull-Rom), 3: 区域平均, 4: Lanczos3
+    filter_type : u32,
+    // 源图像中参与缩放的区间 [src_offset, src_offset + dst_len * scale)
+    src_offset : f32,
+    scale : f32,
+}
+
+const PI : f32 = 3.14159265358979;
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<STORAGE_FORMAT, write>;
+@group(0) @binding(2) var<uniform> params : Params;
+
+fn sinc(x : f32) -> f32 {
+    if abs(x) < 1e-6 {
+        return 1.0;
+    }
+    let a = PI * x;
+    return sin(a) / a;
+}
+
+fn support() -> f32 {
+    switch params.filter_type {
+        case 1u: {
+            return 1.0;
+        }
+        case 2u: {
+            return 2.0;
+        }
+        case 4u: {
+            return 3.0;
+        }
+        default: {
+            return 0.5;
+        }
+    }
+}
+
+fn kernel(x : f32) -> f32 {
+    let t = abs(x);
+    switch params.filter_type {
+        case 1u: {
+            return max(1.0 - t, 0.0);
+        }
+        case 2u: {
+            // Catmull-Rom (a = -0.5)
+            if t < 1.0 {
+                return 1.5 * t * t * t - 2.5 * t * t + 1.0;
+            }
+            if t < 2.0 {
+                return -0.5 * t * t * t + 2.5 * t * t - 4.0 * t + 2.0;
+            }
+            return 0.0;
+        }
+        case 4u: {
+            if t < 3.0 {
+                return sinc(t) * sinc(t / 3.0);
+            }
+            return 0.0;
+        }
+        default: {
+            return 0.0;
+        }
+    }
+}
+
+fn source_coords(position : i32, line : i32) -> vec2<i32> {
+    if params.direction == 0u {
+        return vec2<i32>(position, line);
+    }
+    return vec2<i32>(line, position);
+}
+
+@compute @workgroup_size(16, 16)
+fn resample(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let output_dimensions = vec2<i32>(textureDimensions(output_texture));
+    let coords = vec2<i32>(global_id.xy);
+    if coords.x >= output_dimensions.x || coords.y >= output_dimensions.y {
+        return;
+    }
+
+    let input_dimensions = vec2<i32>(textureDimensions(input_texture));
+    var position = coords.x;
+    var line = coords.y;
+    var length = input_dimensions.x;
+    if params.direction == 1u {
+        position = coords.y;
+        line = coords.x;
+        length = input_dimensions.y;
+    }
+
+    let center = params.src_offset + (f32(position) + 0.5) * params.scale;
+
+    // 最近邻
+    if params.filter_type == 0u {
+        let index = clamp(i32(floor(center)), 0, length - 1);
+        textureStore(output_texture, coords, textureLoad(input_texture, source_coords(index, line), 0));
+        return;
+    }
+
+    let ratio = max(params.scale, 1.0);
+    let radius = support() * ratio;
+    let left = max(i32(floor(center - radius)), 0);
+    let right = min(i32(ceil(center + radius)), length);
+
+    var sum = vec4<f32>(0.0);
+    var total = 0.0;
+    for (var i = left; i < right; i = i + 1) {
+        var weight = 0.0;
+        if params.filter_type == 3u {
+            // 区域平均: 源像素 [i, i + 1) 与输出像素覆盖区间的重叠长度
+            let start = center - 0.5 * params.scale;
+            let end = center + 0.5 * params.scale;
+            weight = max(min(end, f32(i + 1)) - max(start, f32(i)), 0.0);
+        } else {
+            weight = kernel((f32(i) + 0.5 - center) / ratio);
+        }
+        sum = sum + weight * textureLoad(input_texture, source_coords(i, line), 0);
+        total = total + weight;
+    }
+    if total != 0.0 {
+        sum = sum / total;
+    }
+    textureStore(output_texture, coords, sum);
+}
@@ -0,0 +1,121 @@
+// 前缀和(Blelloch 工作高效扫描)
+// Elem、IDENTITY 和 combine 由 Rust 端根据元素类型和运算拼接在本文件前面。
+// 每个工作组 256 个线程，处理一个 512 元素的块:
+// scan_blocks 对块内做扫描并输出块总和，块总和再递归扫描后由 add_block_offsets 加回各块。
+// 块数超过单维上限时工作组会分摊到 y 维。
+
+struct Params {
+    len: u32,
+    // 0: 不包含当前元素(exclusive), 1: 包含当前元素(inclusive)
+    inclusive: u32,
+}
+
+const WORKGROUP_SIZE: u32 = 256u;
+const BLOCK_SIZE: u32 = 512u;
+
+@group(0) @binding(0) var<storage, read> input : array<Elem>;
+@group(0) @binding(1) var<storage, read_write> output : array<Elem>;
+@group(0) @binding(2) var<storage, read_write> block_sums : array<Elem>;
+@group(0) @binding(3) var<uniform> params : Params;
+
+var<workgroup> temp : array<Elem, BLOCK_SIZE>;
+
+@compute @workgroup_size(256)
+fn scan_blocks(
+    @builtin(local_invocation_id) local_id : vec3u,
+    @builtin(workgroup_id) group_id : vec3u,
+    @builtin(num_workgroups) num_workgroups : vec3u,
+) {
+    let lid = local_id.x;
+    let group = group_id.x + group_id.y * num_workgroups.x;
+    let base = group * BLOCK_SIZE;
+    // 多出来的工作组整组退出
+    if base >= params.len {
+        return;
+    }
+    let ai = lid;
+    let bi = lid + WORKGROUP_SIZE;
+
+    // 超出数组长度的位置用单位元填充
+    var a = IDENTITY;
+    var b = IDENTITY;
+    if base + ai < params.len {
+        a = input[base + ai];
+    }
+    if base + bi < params.len {
+        b = input[base + bi];
+    }
+    temp[ai] = a;
+    temp[bi] = b;
+
+    // 上扫(归约)阶段
+    var offset = 1u;
+    for (var d = BLOCK_SIZE >> 1u; d > 0u; d = d >> 1u) {
+        workgroupBarrier();
+        if lid < d {
+            let left = offset * (2u * lid + 1u) - 1u;
+            let right = offset * (2u * lid + 2u) - 1u;
+            temp[right] = combine(temp[left], temp[right]);
+        }
+        offset = offset << 1u;
+    }
+
+    workgroupBarrier();
+    if lid == 0u {
+        block_sums[group] = temp[BLOCK_SIZE - 1u];
+        temp[BLOCK_SIZE - 1u] = IDENTITY;
+    }
+
+    // 下扫阶段，保持左右顺序，combine 不需要满足交换律
+    for (var d = 1u; d < BLOCK_SIZE; d = d << 1u) {
+        offset = offset >> 1u;
+        workgroupBarrier();
+        if lid < d {
+            let left = offset * (2u * lid + 1u) - 1u;
+            let right = offset * (2u * lid + 2u) - 1u;
+            let t = temp[left];
+            temp[left] = temp[right];
+            temp[right] = combine(temp[right], t);
+        }
+    }
+    workgroupBarrier();
+
+    if base + ai < params.len {
+        var value = temp[ai];
+        if params.inclusive != 0u {
+            value = combine(value, a);
+        }
+        output[base + ai] = value;
+    }
+    if base + bi < params.len {
+        var value = temp[bi];
+        if params.inclusive != 0u {
+            value = combine(value, b);
+        }
+        output[base + bi] = value;
+    }
+}
+
+// block_sums 此时存放的是已经扫描过的块偏移
+@compute @workgroup_size(256)
+fn add_block_offsets(
+    @builtin(local_invocation_id) local_id : vec3u,
+    @builtin(workgroup_id) group_id : vec3u,
+    @builtin(num_workgroups) num_workgroups : vec3u,
+) {
+    let group = group_id.x + group_id.y * num_workgroups.x;
+    let base = group * BLOCK_SIZE;
+    if base >= params.len {
+        return;
+    }
+    let offset = block_sums[group];
+    let ai = base + local_id.x;
+    let bi = ai + WORKGROUP_SIZE;
+
+    if ai < params.len {
+        output[ai] = combine(offset, output[ai]);
+    }
+    if bi < params.len {
+        output[bi] = combine(offset, output[bi]);
+    }
+}
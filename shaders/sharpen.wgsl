@@ -0,0 +1,53 @@
+// 锐化的逐像素运算，input 和 second 大小相同，除 add_detail 外 alpha 取自 input
+// unsharp: input + amount (input - second)，second 为模糊后的图像，差值不超过 threshold 的通道保持不变
+// difference: amount (input - second)，amount 为 1 时就是拉普拉斯金字塔的细节层
+// 每个入口都读 params，自动推导的布局相同
+// add_detail: input + amount second，把放大 amount 倍的细节层加回基础层，
+//             alpha 取自细节层(difference 保留了对应高斯层的 alpha)，避免重建时 alpha 被上采样模糊
+
+struct Params {
+    amount : f32,
+    threshold : f32,
+    _padding : vec2<f32>,
+}
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<STORAGE_FORMAT, write>;
+@group(0) @binding(2) var<uniform> params : Params;
+@group(0) @binding(3) var second_texture : texture_2d<f32>;
+
+@compute @workgroup_size(16, 16)
+fn unsharp(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = textureDimensions(input_texture);
+    if global_id.x >= dimensions.x || global_id.y >= dimensions.y {
+        return;
+    }
+    let color = textureLoad(input_texture, global_id.xy, 0);
+    let blurred = textureLoad(second_texture, global_id.xy, 0);
+    let detail = color.rgb - blurred.rgb;
+    let mask = abs(detail) > vec3<f32>(params.threshold);
+    let sharpened = color.rgb + params.amount * select(vec3<f32>(0.0), detail, mask);
+    textureStore(output_texture, global_id.xy, vec4<f32>(sharpened, color.a));
+}
+
+@compute @workgroup_size(16, 16)
+fn difference(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = textureDimensions(input_texture);
+    if global_id.x >= dimensions.x || global_id.y >= dimensions.y {
+        return;
+    }
+    let color = textureLoad(input_texture, global_id.xy, 0);
+    let second = textureLoad(second_texture, global_id.xy, 0);
+    textureStore(output_texture, global_id.xy, vec4<f32>(params.amount * (color.rgb - second.rgb), color.a));
+}
+
+@compute @workgroup_size(16, 16)
+fn add_detail(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let dimensions = textureDimensions(input_texture);
+    if global_id.x >= dimensions.x || global_id.y >= dimensions.y {
+        return;
+    }
+    let color = textureLoad(input_texture, global_id.xy, 0);
+    let detail = textureLoad(second_texture, global_id.xy, 0);
+    textureStore(output_texture, global_id.xy, vec4<f32>(color.rgb + params.amount * detail.rgb, detail.a));
+}
@@ -0,0 +1,15 @@
+// sRGB 编码和线性光之间的转换(IEC 61966-2-1)
+// 由 Rust 端拼接在需要它的着色器前面
+
+fn srgb_to_linear(c : vec3<f32>) -> vec3<f32> {
+    let low = c / 12.92;
+    let high = pow((c + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(high, low, c <= vec3<f32>(0.04045));
+}
+
+fn linear_to_srgb(c : vec3<f32>) -> vec3<f32> {
+    let v = max(c, vec3<f32>(0.0));
+    let low = v * 12.92;
+    let high = 1.055 * pow(v, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, v <= vec3<f32>(0.0031308));
+}
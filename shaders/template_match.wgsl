@@ -0,0 +1,218 @@
+// 模板匹配: 模板在图像上滑动，得分图的 (x, y) 对应模板左上角放在图像 (x, y) 处的得分
+// 图像和模板都是灰度的，只读取 r 通道
+
+struct Params {
+    // 0: 差的平方和(SSD)，1: 差的绝对值之和(SAD)，2: 零均值归一化互相关(ZNCC)
+    method : u32,
+    // 要返回的匹配数
+    count : u32,
+    // 两个匹配的最小距离，更近的候选被抑制
+    min_distance : f32,
+    // 得分图的宽度，用于计算像素下标 y * width + x
+    width : u32,
+}
+
+// 候选位置，key 越大越好(SSD、SAD 取负值)
+struct Candidate {
+    position : vec2<u32>,
+    score : f32,
+    key : f32,
+}
+
+@group(0) @binding(0) var image_texture : texture_2d<f32>;
+@group(0) @binding(1) var score_texture : texture_storage_2d<r32float, write>;
+@group(0) @binding(2) var<uniform> params : Params;
+@group(0) @binding(3) var template_texture : texture_2d<f32>;
+// [模板均值, 模板与均值之差的平方和]
+@group(0) @binding(4) var<storage, read_write> template_stats : array<f32>;
+@group(0) @binding(5) var score_source : texture_2d<f32>;
+// 得分图中的局部最大值，顺序不固定
+@group(0) @binding(6) var<storage, read_write> candidates : array<Candidate>;
+@group(0) @binding(7) var<storage, read_write> results : array<Candidate>;
+// 写入 candidates 的候选数
+@group(0) @binding(8) var<storage, read_write> candidate_total : atomic<u32>;
+
+const WORKGROUP_SIZE : u32 = 256u;
+const SUPPRESSED : f32 = -3.40282347e+38;
+const NONE : u32 = 0xffffffffu;
+
+var<workgroup> sums : array<f32, WORKGROUP_SIZE>;
+var<workgroup> square_sums : array<f32, WORKGROUP_SIZE>;
+var<workgroup> best_keys : array<f32, WORKGROUP_SIZE>;
+var<workgroup> best_indices : array<u32, WORKGROUP_SIZE>;
+
+// 模板的均值和方差只需要计算一次，由单个工作组完成
+@compute @workgroup_size(256)
+fn template_statistics(@builtin(local_invocation_index) local_index : u32) {
+    let size = textureDimensions(template_texture);
+    let count = size.x * size.y;
+    var sum = 0.0;
+    var square_sum = 0.0;
+    for (var i = local_index; i < count; i += WORKGROUP_SIZE) {
+        let t = textureLoad(template_texture, vec2<u32>(i % size.x, i / size.x), 0).r;
+        sum += t;
+        square_sum += t * t;
+    }
+    sums[local_index] = sum;
+    square_sums[local_index] = square_sum;
+    workgroupBarrier();
+    for (var stride = WORKGROUP_SIZE / 2u; stride > 0u; stride = stride / 2u) {
+        if local_index < stride {
+            sums[local_index] += sums[local_index + stride];
+            square_sums[local_index] += square_sums[local_index + stride];
+        }
+        workgroupBarrier();
+    }
+
+    if local_index == 0u {
+        let mean = sums[0] / f32(count);
+        template_stats[0] = mean;
+        template_stats[1] = max(square_sums[0] - sums[0] * mean, 0.0);
+    }
+}
+
+@compute @workgroup_size(16, 16)
+fn match_template(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let score_size = textureDimensions(score_texture);
+    if global_id.x >= score_size.x || global_id.y >= score_size.y {
+        return;
+    }
+    let size = textureDimensions(template_texture);
+    let origin = global_id.xy;
+
+    var score = 0.0;
+    if params.method == 2u {
+        // 一次遍历累加 ΣI、ΣI²、ΣIT，再由模板的统计量得到协方差和方差
+        var sum = 0.0;
+        var square_sum = 0.0;
+        var cross_sum = 0.0;
+        for (var y = 0u; y < size.y; y++) {
+            for (var x = 0u; x < size.x; x++) {
+                let i = textureLoad(image_texture, origin + vec2<u32>(x, y), 0).r;
+                let t = textureLoad(template_texture, vec2<u32>(x, y), 0).r;
+                sum += i;
+                square_sum += i * i;
+                cross_sum += i * t;
+            }
+        }
+        let mean = template_stats[0];
+        let covariance = cross_sum - sum * mean;
+        let variance = max(square_sum - sum * sum / f32(size.x * size.y), 0.0);
+        let denominator = sqrt(variance * template_stats[1]);
+        // 平坦区域或平坦模板没有可比的结构，得分为 0
+        score = select(0.0, covariance / denominator, denominator > 1e-6);
+    } else {
+        for (var y = 0u; y < size.y; y++) {
+            for (var x = 0u; x < size.x; x++) {
+                let d = textureLoad(image_texture, origin + vec2<u32>(x, y), 0).r - textureLoad(template_texture, vec2<u32>(x, y), 0).r;
+                score += select(d * d, abs(d), params.method == 1u);
+            }
+        }
+    }
+    textureStore(score_texture, origin, vec4<f32>(score, 0.0, 0.0, 1.0));
+}
+
+fn to_key(score : f32) -> f32 {
+    return select(-score, score, params.method == 2u);
+}
+
+fn pixel_index(position : vec2<u32>) -> u32 {
+    return position.x + position.y * params.width;
+}
+
+// key 更大，或者 key 相同而像素下标更小
+fn precedes(key : f32, index : u32, other_key : f32, other_index : u32) -> bool {
+    return key > other_key || (key == other_key && index < other_index);
+}
+
+// 候选 slot 的像素下标，NONE 排在所有像素之后
+fn candidate_pixel(slot : u32) -> u32 {
+    if slot == NONE {
+        return NONE;
+    }
+    return pixel_index(candidates[slot].position);
+}
+
+// 工作组内求最好的候选，比较规则见 precedes，结果在下标 0
+fn reduce_best(local_index : u32) {
+    for (var stride = WORKGROUP_SIZE / 2u; stride > 0u; stride = stride / 2u) {
+        if local_index < stride {
+            let other_key = best_keys[local_index + stride];
+            let other_index = best_indices[local_index + stride];
+            if precedes(other_key, candidate_pixel(other_index), best_keys[local_index], candidate_pixel(best_indices[local_index])) {
+                best_keys[local_index] = other_key;
+                best_indices[local_index] = other_index;
+            }
+        }
+        workgroupBarrier();
+    }
+}
+
+// 第一步: 比 3x3 邻域内其它位置都好的位置作为候选，追加到 candidates。
+// 得分相同时像素下标小的优先，所以相邻的两个位置不会同时成为候选
+@compute @workgroup_size(16, 16)
+fn local_maxima(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let size = textureDimensions(score_source);
+    if global_id.x >= size.x || global_id.y >= size.y {
+        return;
+    }
+    let position = global_id.xy;
+    let score = textureLoad(score_source, position, 0).r;
+    let key = to_key(score);
+    let index = pixel_index(position);
+    for (var dy = -1; dy <= 1; dy++) {
+        for (var dx = -1; dx <= 1; dx++) {
+            let neighbor = vec2<i32>(position) + vec2<i32>(dx, dy);
+            if (dx == 0 && dy == 0) || any(neighbor < vec2<i32>(0)) || any(neighbor >= vec2<i32>(size)) {
+                continue;
+            }
+            let neighbor_key = to_key(textureLoad(score_source, neighbor, 0).r);
+            if !precedes(key, index, neighbor_key, pixel_index(vec2<u32>(neighbor))) {
+                return;
+            }
+        }
+    }
+
+    let slot = atomicAdd(&candidate_total, 1u);
+    if slot < arrayLength(&candidates) {
+        candidates[slot] = Candidate(position, score, key);
+    }
+}
+
+// 第二步: 单个工作组依次选出最好的候选，并抑制它附近的候选
+@compute @workgroup_size(256)
+fn select_best(@builtin(local_invocation_index) local_index : u32) {
+    let candidate_count = min(atomicLoad(&candidate_total), arrayLength(&candidates));
+    for (var i = 0u; i < params.count; i++) {
+        var best_key = SUPPRESSED;
+        var best_index = NONE;
+        for (var c = local_index; c < candidate_count; c += WORKGROUP_SIZE) {
+            let key = candidates[c].key;
+            if key > SUPPRESSED && precedes(key, candidate_pixel(c), best_key, candidate_pixel(best_index)) {
+                best_key = key;
+                best_index = c;
+            }
+        }
+        best_keys[local_index] = best_key;
+        best_indices[local_index] = best_index;
+        workgroupBarrier();
+        reduce_best(local_index);
+
+        let winner = workgroupUniformLoad(&best_indices[0]);
+        if winner == NONE {
+            break;
+        }
+        let best = candidates[winner];
+        storageBarrier();
+        if local_index == 0u {
+            results[i] = best;
+        }
+        for (var c = local_index; c < candidate_count; c += WORKGROUP_SIZE) {
+            if distance(vec2<f32>(candidates[c].position), vec2<f32>(best.position)) < max(params.min_distance, 0.5) {
+                candidates[c].key = SUPPRESSED;
+            }
+        }
+        storageBarrier();
+        workgroupBarrier();
+    }
+}
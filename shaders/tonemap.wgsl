@@ -0,0 +1,171 @@
+// HDR 色调映射: 先统计对数平均亮度(自动曝光用)，再把线性的 HDR 颜色映射到 0~1 并做 gamma 编码
+
+struct Params {
+    // 0 只做曝光和 gamma，1 Reinhard，2 局部 Reinhard，3 ACES，4 Hable
+    curve : u32,
+    // 1 表示用 key / 对数平均亮度作为曝光倍数
+    auto_exposure : u32,
+    // 手动曝光的倍数 2^EV
+    exposure_scale : f32,
+    key : f32,
+    inverse_gamma : f32,
+    // 映射为纯白的亮度(曝光之后)
+    white : f32,
+    _padding : vec2<f32>,
+}
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(2) var<uniform> params : Params;
+// [对数平均亮度, 最大亮度]
+@group(0) @binding(3) var<storage, read_write> stats : array<f32>;
+// 每个工作组两个值: 对数亮度之和、最大亮度
+@group(0) @binding(4) var<storage, read_write> partials : array<f32>;
+// 局部 Reinhard 的邻域平均颜色
+@group(0) @binding(5) var adaptation_texture : texture_2d<f32>;
+
+const DELTA : f32 = 1e-4;
+const WORKGROUP_SIZE : u32 = 256u;
+
+var<workgroup> log_sums : array<f32, WORKGROUP_SIZE>;
+var<workgroup> maxima : array<f32, WORKGROUP_SIZE>;
+
+fn luminance(c : vec3<f32>) -> f32 {
+    return dot(c, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+// 工作组内的树形归约，结果在下标 0
+fn reduce_workgroup(local_index : u32) {
+    for (var stride = WORKGROUP_SIZE / 2u; stride > 0u; stride = stride / 2u) {
+        if local_index < stride {
+            log_sums[local_index] += log_sums[local_index + stride];
+            maxima[local_index] = max(maxima[local_index], maxima[local_index + stride]);
+        }
+        workgroupBarrier();
+    }
+}
+
+@compute @workgroup_size(16, 16)
+fn log_luminance(
+    @builtin(global_invocation_id) global_id : vec3<u32>,
+    @builtin(local_invocation_index) local_index : u32,
+    @builtin(workgroup_id) group_id : vec3<u32>,
+    @builtin(num_workgroups) groups : vec3<u32>,
+) {
+    let size = textureDimensions(input_texture);
+    var log_sum = 0.0;
+    var maximum = 0.0;
+    if global_id.x < size.x && global_id.y < size.y {
+        let l = max(luminance(textureLoad(input_texture, global_id.xy, 0).rgb), 0.0);
+        log_sum = log(DELTA + l);
+        maximum = l;
+    }
+    log_sums[local_index] = log_sum;
+    maxima[local_index] = maximum;
+    workgroupBarrier();
+    reduce_workgroup(local_index);
+
+    if local_index == 0u {
+        let group = group_id.x + group_id.y * groups.x;
+        partials[2u * group] = log_sums[0];
+        partials[2u * group + 1u] = maxima[0];
+    }
+}
+
+@compute @workgroup_size(256)
+fn reduce_luminance(@builtin(local_invocation_index) local_index : u32) {
+    let size = textureDimensions(input_texture);
+    let group_count = arrayLength(&partials) / 2u;
+    var log_sum = 0.0;
+    var maximum = 0.0;
+    for (var i = local_index; i < group_count; i += WORKGROUP_SIZE) {
+        log_sum += partials[2u * i];
+        maximum = max(maximum, partials[2u * i + 1u]);
+    }
+    log_sums[local_index] = log_sum;
+    maxima[local_index] = maximum;
+    workgroupBarrier();
+    reduce_workgroup(local_index);
+
+    if local_index == 0u {
+        stats[0] = exp(log_sums[0] / f32(size.x * size.y));
+        stats[1] = maxima[0];
+    }
+}
+
+// 按亮度缩放颜色，保持色相
+fn scale_luminance(c : vec3<f32>, l : f32, mapped : f32) -> vec3<f32> {
+    if l <= 0.0 {
+        return vec3<f32>(0.0);
+    }
+    return c * (mapped / l);
+}
+
+// 扩展的 Reinhard: L (1 + L / white²) / (1 + L)
+fn reinhard(c : vec3<f32>, white : f32) -> vec3<f32> {
+    let l = luminance(c);
+    return scale_luminance(c, l, l * (1.0 + l / (white * white)) / (1.0 + l));
+}
+
+// 局部 Reinhard: 用邻域平均亮度代替像素自身的亮度做分母
+fn reinhard_local(c : vec3<f32>, adaptation : f32) -> vec3<f32> {
+    let l = luminance(c);
+    return scale_luminance(c, l, l / (1.0 + adaptation));
+}
+
+// ACES filmic 的拟合曲线，参考 https://knarkowicz.wordpress.com/2016/01/06/aces-filmic-tone-mapping-curve/
+fn aces(c : vec3<f32>) -> vec3<f32> {
+    return (c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14);
+}
+
+// Uncharted 2 的 Hable 曲线
+fn hable_curve(x : vec3<f32>) -> vec3<f32> {
+    let a = 0.15;
+    let b = 0.50;
+    let c = 0.10;
+    let d = 0.20;
+    let e = 0.02;
+    let f = 0.30;
+    return (x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f) - e / f;
+}
+
+fn hable(c : vec3<f32>, white : f32) -> vec3<f32> {
+    return hable_curve(2.0 * c) / hable_curve(vec3<f32>(white));
+}
+
+@compute @workgroup_size(16, 16)
+fn tonemap(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let size = textureDimensions(input_texture);
+    if global_id.x >= size.x || global_id.y >= size.y {
+        return;
+    }
+    let coords = global_id.xy;
+    let color = textureLoad(input_texture, coords, 0);
+
+    var scale = params.exposure_scale;
+    if params.auto_exposure == 1u {
+        scale = params.key / max(stats[0], DELTA);
+    }
+    let hdr = max(color.rgb * scale, vec3<f32>(0.0));
+
+    var mapped = hdr;
+    switch params.curve {
+        case 1u: {
+            mapped = reinhard(hdr, params.white);
+        }
+        case 2u: {
+            let adaptation = luminance(textureLoad(adaptation_texture, coords, 0).rgb) * scale;
+            mapped = reinhard_local(hdr, max(adaptation, 0.0));
+        }
+        case 3u: {
+            mapped = aces(hdr);
+        }
+        case 4u: {
+            mapped = hable(hdr, params.white);
+        }
+        default: {}
+    }
+
+    let ldr = pow(clamp(mapped, vec3<f32>(0.0), vec3<f32>(1.0)), vec3<f32>(params.inverse_gamma));
+    textureStore(output_texture, coords, vec4<f32>(ldr, clamp(color.a, 0.0, 1.0)));
+}
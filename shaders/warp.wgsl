@@ -0,0 +1,38 @@
+// 仿射和透视变换，使用逆映射: 输出像素 (x, y) 取输入图像中 inverse * (x, y, 1) 处的值
+// 坐标以像素下标为单位(与 OpenCV 相同)
+// border.wgsl 和 interpolate.wgsl 由 Rust 端拼接在本文件前面
+
+struct Params {
+    // 逆变换矩阵的三行
+    row0 : vec4<f32>,
+    row1 : vec4<f32>,
+    row2 : vec4<f32>,
+    // 0: 最近邻, 1: 双线性, 2: 双三次
+    interpolation : u32,
+    // 0~2: border.wgsl 中的 EDGE_*, 3: 常量
+    border : u32,
+    _padding : vec2<u32>,
+    constant : vec4<f32>,
+}
+
+@group(0) @binding(0) var input_texture : texture_2d<f32>;
+@group(0) @binding(1) var output_texture : texture_storage_2d<STORAGE_FORMAT, write>;
+@group(0) @binding(2) var<uniform> params : Params;
+
+@compute @workgroup_size(16, 16)
+fn warp(@builtin(global_invocation_id) global_id : vec3<u32>) {
+    let output_dimensions = vec2<i32>(textureDimensions(output_texture));
+    let coords = vec2<i32>(global_id.xy);
+    if coords.x >= output_dimensions.x || coords.y >= output_dimensions.y {
+        return;
+    }
+
+    let p = vec3<f32>(vec2<f32>(coords), 1.0);
+    let w = dot(params.row2.xyz, p);
+    if abs(w) < 1e-8 {
+        textureStore(output_texture, coords, params.constant);
+        return;
+    }
+    let position = vec2<f32>(dot(params.row0.xyz, p), dot(params.row1.xyz, p)) / w;
+    textureStore(output_texture, coords, sample_at(position));
+}
@@ -0,0 +1,22 @@
+// BT.601 有限范围(16~235)的 YCbCr 与 RGB 互转，分量范围都是 0~1
+// 由 Rust 端拼接在使用它的着色器前面
+
+fn ycbcr_to_rgb(ycbcr : vec3<f32>) -> vec3<f32> {
+    let y = ycbcr.x - 0.0625;
+    let u = ycbcr.y - 0.5;
+    let v = ycbcr.z - 0.5;
+    return vec3<f32>(
+        1.164 * y + 1.596 * v,
+        1.164 * y - 0.813 * v - 0.392 * u,
+        1.164 * y + 2.017 * u,
+    );
+}
+
+// ycbcr_to_rgb 的逆变换
+fn rgb_to_ycbcr(rgb : vec3<f32>) -> vec3<f32> {
+    return vec3<f32>(
+        dot(rgb, vec3<f32>(0.256862, 0.504246, 0.097999)) + 0.0625,
+        dot(rgb, vec3<f32>(-0.148234, -0.290997, 0.439231)) + 0.5,
+        dot(rgb, vec3<f32>(0.439231, -0.367758, -0.071473)) + 0.5,
+    );
+}
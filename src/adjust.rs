@@ -0,0 +1,385 @@
+#![allow(dead_code)]
+
+use anyhow::Ok;
+use anyhow::Result;
+use image::load_from_memory;
+
+use crate::buffer::GpuBuffer;
+use crate::texture::create_output_texture;
+use crate::texture::image_from_texture;
+use crate::texture::texture_from_image;
+use crate::texture::with_storage_format;
+use crate::utils::compute_work_group_count;
+use crate::utils::create_bind_group;
+use crate::utils::create_pipeline;
+use crate::utils::create_shader;
+use crate::utils::dispatch;
+use crate::utils::request_device;
+
+/// 白平衡不做调整时的色温
+pub const NEUTRAL_TEMPERATURE: f32 = 6500.0;
+
+/// 色相和饱和度调整所在的颜色空间
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HueSpace {
+    /// 饱和度为 0 时变为最大通道的灰度
+    #[default]
+    Hsv = 0,
+    /// 饱和度为 0 时变为 (最大值 + 最小值) / 2 的灰度；超出 0~1 的颜色会先截断
+    Hsl = 1,
+}
+
+/// 颜色调整参数，默认值不改变图像
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorAdjustments {
+    /// 加到每个通道上，范围 -1~1
+    pub brightness: f32,
+    /// 以 0.5 为中心缩放，1 为不变
+    pub contrast: f32,
+    /// 输出 = 输入^(1/gamma)，1 为不变
+    pub gamma: f32,
+    /// 曝光补偿，单位 EV，乘以 2^exposure
+    pub exposure: f32,
+    /// 饱和度的倍数，0 为灰度，1 为不变
+    pub saturation: f32,
+    /// 色相旋转的角度
+    pub hue: f32,
+    /// saturation 和 hue 所在的颜色空间
+    pub hue_space: HueSpace,
+    /// 拍摄时光源的色温(开尔文)，低于 6500 时画面变冷以抵消偏暖的光源
+    pub temperature: f32,
+    /// 色调，范围 -1~1，正值偏品红，负值偏绿
+    pub tint: f32,
+}
+
+impl Default for ColorAdjustments {
+    fn default() -> Self {
+        Self {
+            brightness: 0.0,
+            contrast: 1.0,
+            gamma: 1.0,
+            exposure: 0.0,
+            saturation: 1.0,
+            hue: 0.0,
+            hue_space: HueSpace::Hsv,
+            temperature: NEUTRAL_TEMPERATURE,
+            tint: 0.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct AdjustParams {
+    white_balance: [f32; 4],
+    exposure_scale: f32,
+    brightness: f32,
+    contrast: f32,
+    saturation: f32,
+    hue: f32,
+    inverse_gamma: f32,
+    hue_space: u32,
+    _padding: f32,
+}
+
+impl From<&ColorAdjustments> for AdjustParams {
+    fn from(adjustments: &ColorAdjustments) -> Self {
+        let [r, g, b] = white_balance_gains(adjustments.temperature, adjustments.tint);
+        Self {
+            white_balance: [r, g, b, 1.0],
+            exposure_scale: adjustments.exposure.exp2(),
+            brightness: adjustments.brightness,
+            contrast: adjustments.contrast,
+            saturation: adjustments.saturation,
+            hue: adjustments.hue,
+            inverse_gamma: 1.0 / adjustments.gamma.max(1e-3),
+            hue_space: adjustments.hue_space as u32,
+            _padding: 0.0,
+        }
+    }
+}
+
+/// 黑体在 kelvin 色温下的近似颜色，范围 0~1
+/// 参考 https://tannerhelland.com/2012/09/18/convert-temperature-rgb-algorithm-code.html
+pub fn kelvin_to_rgb(kelvin: f32) -> [f32; 3] {
+    let t = kelvin.clamp(1000.0, 40000.0) / 100.0;
+    let red = if t <= 66.0 { 255.0 } else { 329.69873 * (t - 60.0).powf(-0.13320476) };
+    let green = if t <= 66.0 {
+        99.4708 * t.ln() - 161.11957
+    } else {
+        288.12216 * (t - 60.0).powf(-0.07551485)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.51773 * (t - 10.0).ln() - 305.0448
+    };
+    [red, green, blue].map(|value| (value / 255.0).clamp(0.0, 1.0))
+}
+
+/// 把 temperature 色温的光源校正为 6500K 时各通道的增益，保持亮度不变
+pub fn white_balance_gains(temperature: f32, tint: f32) -> [f32; 3] {
+    let source = kelvin_to_rgb(temperature);
+    let target = kelvin_to_rgb(NEUTRAL_TEMPERATURE);
+    let mut gains = [0.0; 3];
+    for (gain, (target, source)) in gains.iter_mut().zip(target.iter().zip(&source)) {
+        *gain = target / source.max(1e-3);
+    }
+    gains[1] *= 1.0 - 0.2 * tint.clamp(-1.0, 1.0);
+
+    let luminance = 0.2126 * gains[0] + 0.7152 * gains[1] + 0.0722 * gains[2];
+    gains.map(|gain| gain / luminance)
+}
+
+/// 编译好的颜色调整流水线，参数通过 uniform 更新，适合界面上拖动滑块时反复执行
+pub struct ColorAdjuster {
+    pipeline: wgpu::ComputePipeline,
+    params: GpuBuffer<AdjustParams>,
+    format: wgpu::TextureFormat,
+}
+
+impl ColorAdjuster {
+    /// format 为输出纹理的格式
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Result<Self> {
+        let source = with_storage_format(include_str!("../shaders/adjust.wgsl"), format)?;
+        let shader = create_shader(device, "adjust_shader_module", &source);
+        Ok(Self {
+            pipeline: create_pipeline(device, &shader, "adjust"),
+            params: GpuBuffer::uniform(device, &AdjustParams::from(&ColorAdjustments::default())),
+            format,
+        })
+    }
+
+    /// 更新参数，只写入 uniform 缓冲区
+    pub fn set(&self, queue: &wgpu::Queue, adjustments: &ColorAdjustments) -> Result<()> {
+        self.params.write(queue, &[AdjustParams::from(adjustments)])
+    }
+
+    /// 把调整的命令记录到 encoder 中，output 需要与 input 大小相同
+    pub fn encode(&self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, input: &wgpu::Texture, output: &wgpu::Texture) -> Result<()> {
+        anyhow::ensure!(
+            output.format() == self.format,
+            "output format {:?} does not match {:?}",
+            output.format(),
+            self.format
+        );
+        let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+        let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = create_bind_group(
+            device,
+            &self.pipeline,
+            &[
+                (0, wgpu::BindingResource::TextureView(&input_view)),
+                (1, wgpu::BindingResource::TextureView(&output_view)),
+                (2, self.params.as_entire_binding()),
+            ],
+        );
+        let (x, y) = compute_work_group_count((input.width(), input.height()), (16, 16));
+        dispatch(encoder, &self.pipeline, &bind_group, (x, y, 1));
+        Ok(())
+    }
+
+    /// 用当前参数处理 input，结果写入 output
+    pub fn apply_into(&self, device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, output: &wgpu::Texture) -> Result<()> {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        self.encode(device, &mut encoder, input, output)?;
+        queue.submit(Some(encoder.finish()));
+        Ok(())
+    }
+
+    /// 用当前参数处理 input，返回新的纹理
+    pub fn apply(&self, device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture) -> Result<wgpu::Texture> {
+        let output = create_output_texture(device, input.width(), input.height(), self.format);
+        self.apply_into(device, queue, input, &output)?;
+        Ok(output)
+    }
+}
+
+/// 一次性的颜色调整，输出格式与输入相同
+pub fn adjust_color(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, adjustments: &ColorAdjustments) -> Result<wgpu::Texture> {
+    let adjuster = ColorAdjuster::new(device, input.format())?;
+    adjuster.set(queue, adjustments)?;
+    adjuster.apply(device, queue, input)
+}
+
+/// 颜色调整
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+
+    let input_image = load_from_memory(include_bytes!("../images/capture.jpg"))?.to_rgba8();
+    let input_texture = texture_from_image(&device, &queue, &input_image);
+
+    // 模拟拖动滑块: 同一个流水线只更新参数
+    let adjuster = ColorAdjuster::new(&device, input_texture.format())?;
+    let output = create_output_texture(&device, input_texture.width(), input_texture.height(), input_texture.format());
+    let settings = [
+        (ColorAdjustments { exposure: 1.0, ..Default::default() }, "exposure"),
+        (ColorAdjustments { contrast: 1.5, brightness: -0.05, ..Default::default() }, "contrast"),
+        (ColorAdjustments { gamma: 2.2, ..Default::default() }, "gamma"),
+        (ColorAdjustments { saturation: 0.0, ..Default::default() }, "desaturate"),
+        (ColorAdjustments { hue: 120.0, saturation: 1.3, ..Default::default() }, "hue"),
+        (ColorAdjustments { saturation: 0.0, hue_space: HueSpace::Hsl, ..Default::default() }, "desaturate_hsl"),
+        (ColorAdjustments { temperature: 3500.0, tint: 0.2, ..Default::default() }, "white_balance"),
+    ];
+    for (adjustments, name) in settings {
+        adjuster.set(&queue, &adjustments)?;
+        adjuster.apply_into(&device, &queue, &input_texture, &output)?;
+        image_from_texture(&device, &queue, &output)?.save(format!("./outputs/capture_adjust_{name}.png"))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::rgba32f_from_texture;
+    use crate::texture::texture_from_rgba32f;
+    use crate::utils::random_image;
+    use crate::utils::random_rgba32f;
+    use crate::utils::test_device;
+    use image::Rgba32FImage;
+
+    fn cpu_hue(c: [f32; 3], max: f32, delta: f32) -> f32 {
+        if delta <= 0.0 {
+            return 0.0;
+        }
+        let h = if max == c[0] {
+            (c[1] - c[2]) / delta
+        } else if max == c[1] {
+            (c[2] - c[0]) / delta + 2.0
+        } else {
+            (c[0] - c[1]) / delta + 4.0
+        };
+        (h / 6.0).rem_euclid(1.0)
+    }
+
+    fn cpu_hue_to_rgb(h: f32) -> [f32; 3] {
+        [0.0, 4.0, 2.0].map(|k: f32| (((h + k / 6.0).rem_euclid(1.0) * 6.0 - 3.0).abs() - 1.0).clamp(0.0, 1.0))
+    }
+
+    /// CPU 上的颜色调整，顺序与 adjust.wgsl 相同
+    fn cpu_adjust(image: &Rgba32FImage, adjustments: &ColorAdjustments) -> Rgba32FImage {
+        let params = AdjustParams::from(adjustments);
+        let mut output = image.clone();
+        for pixel in output.pixels_mut() {
+            let mut rgb: [f32; 3] = std::array::from_fn(|i| {
+                let value = pixel[i] * params.white_balance[i] * params.exposure_scale;
+                ((value - 0.5) * params.contrast + 0.5 + params.brightness).max(0.0)
+            });
+            if params.hue != 0.0 || params.saturation != 1.0 {
+                let shift = |h: f32| (h + params.hue / 360.0).rem_euclid(1.0);
+                if adjustments.hue_space == HueSpace::Hsv {
+                    let max = rgb[0].max(rgb[1]).max(rgb[2]);
+                    let delta = max - rgb[0].min(rgb[1]).min(rgb[2]);
+                    let s = if max > 0.0 { delta / max } else { 0.0 };
+                    let s = (s * params.saturation).clamp(0.0, 1.0);
+                    let hue = cpu_hue_to_rgb(shift(cpu_hue(rgb, max, delta)));
+                    rgb = hue.map(|p| max * (1.0 + (p - 1.0) * s));
+                } else {
+                    let c = rgb.map(|v| v.min(1.0));
+                    let (max, min) = (c[0].max(c[1]).max(c[2]), c[0].min(c[1]).min(c[2]));
+                    let l = (max + min) / 2.0;
+                    let range = 1.0 - (2.0 * l - 1.0).abs();
+                    let s = if range > 0.0 { (max - min) / range } else { 0.0 };
+                    let chroma = (s * params.saturation).clamp(0.0, 1.0) * range;
+                    rgb = cpu_hue_to_rgb(shift(cpu_hue(c, max, max - min))).map(|p| (l + chroma * (p - 0.5)).max(0.0));
+                }
+            }
+            for (value, rgb) in pixel.0.iter_mut().zip(rgb) {
+                *value = rgb.powf(params.inverse_gamma);
+            }
+        }
+        output
+    }
+
+    #[test]
+    fn adjust_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_rgba32f(32, 24, 61);
+        let texture = texture_from_rgba32f(&device, &queue, &input, wgpu::TextureFormat::Rgba32Float).unwrap();
+        let mut settings = vec![
+            ColorAdjustments { brightness: 0.1, contrast: 1.4, ..Default::default() },
+            ColorAdjustments { exposure: 1.5, gamma: 2.2, ..Default::default() },
+            ColorAdjustments { temperature: 3200.0, tint: -0.3, ..Default::default() },
+            ColorAdjustments { temperature: 9000.0, tint: 0.5, exposure: -0.5, ..Default::default() },
+        ];
+        for hue_space in [HueSpace::Hsv, HueSpace::Hsl] {
+            settings.push(ColorAdjustments { saturation: 0.0, hue_space, ..Default::default() });
+            settings.push(ColorAdjustments { saturation: 1.7, hue: -75.0, hue_space, ..Default::default() });
+            settings.push(ColorAdjustments { hue: 200.0, exposure: 1.0, gamma: 0.8, hue_space, ..Default::default() });
+        }
+        let adjuster = ColorAdjuster::new(&device, wgpu::TextureFormat::Rgba32Float).unwrap();
+        for adjustments in &settings {
+            adjuster.set(&queue, adjustments).unwrap();
+            let output = rgba32f_from_texture(&device, &queue, &adjuster.apply(&device, &queue, &texture).unwrap()).unwrap();
+            let expected = cpu_adjust(&input, adjustments);
+            // pow、除法和 fract 在 GPU 上不是正确舍入的，按相对误差 1e-4 比较
+            for (a, b) in output.as_raw().iter().zip(expected.as_raw()) {
+                assert!((a - b).abs() <= 1e-4 * b.abs().max(1.0), "{adjustments:?}: {a} {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn default_is_lossless() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_image(30, 20, 62);
+        let texture = texture_from_image(&device, &queue, &input);
+        for hue_space in [HueSpace::Hsv, HueSpace::Hsl] {
+            let adjustments = ColorAdjustments { hue_space, ..Default::default() };
+            let output = adjust_color(&device, &queue, &texture, &adjustments).unwrap();
+            assert!(image_from_texture(&device, &queue, &output).unwrap() == input);
+        }
+    }
+
+    #[test]
+    fn hsv_and_hsl_differ_in_lightness() {
+        let Some((device, queue)) = test_device() else { return };
+        let colors = [[1.0, 0.0, 0.0, 1.0], [0.2, 0.4, 0.8, 0.5]];
+        let input = Rgba32FImage::from_fn(2, 1, |x, _| image::Rgba(colors[x as usize]));
+        let texture = texture_from_rgba32f(&device, &queue, &input, wgpu::TextureFormat::Rgba32Float).unwrap();
+        let run = |adjustments: ColorAdjustments| {
+            let output = adjust_color(&device, &queue, &texture, &adjustments).unwrap();
+            rgba32f_from_texture(&device, &queue, &output).unwrap()
+        };
+        let close = |a: [f32; 4], b: [f32; 4]| a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-5);
+
+        // 去饱和: HSV 取最大通道，HSL 取最大值和最小值的平均，alpha 不变
+        let hsv = run(ColorAdjustments { saturation: 0.0, ..Default::default() });
+        assert!(close(hsv.get_pixel(0, 0).0, [1.0, 1.0, 1.0, 1.0]));
+        assert!(close(hsv.get_pixel(1, 0).0, [0.8, 0.8, 0.8, 0.5]));
+        let hsl = run(ColorAdjustments { saturation: 0.0, hue_space: HueSpace::Hsl, ..Default::default() });
+        assert!(close(hsl.get_pixel(0, 0).0, [0.5, 0.5, 0.5, 1.0]));
+        assert!(close(hsl.get_pixel(1, 0).0, [0.5, 0.5, 0.5, 0.5]));
+
+        // 两种空间里旋转 120 度都把红色变成绿色
+        for hue_space in [HueSpace::Hsv, HueSpace::Hsl] {
+            let rotated = run(ColorAdjustments { hue: 120.0, hue_space, ..Default::default() });
+            assert!(close(rotated.get_pixel(0, 0).0, [0.0, 1.0, 0.0, 1.0]), "{hue_space:?}");
+        }
+    }
+
+    #[test]
+    fn white_balance_preserves_luminance() {
+        let neutral = white_balance_gains(NEUTRAL_TEMPERATURE, 0.0);
+        assert!(neutral.iter().all(|gain| (gain - 1.0).abs() < 1e-5));
+        for (temperature, tint) in [(2500.0, 0.0), (4000.0, 0.5), (12000.0, -1.0)] {
+            let [r, g, b] = white_balance_gains(temperature, tint);
+            assert!((0.2126 * r + 0.7152 * g + 0.0722 * b - 1.0).abs() < 1e-5);
+        }
+        // 暖光源要压低红色
+        let [r, _, b] = white_balance_gains(3000.0, 0.0);
+        assert!(r < b);
+    }
+
+    #[test]
+    fn encode_rejects_other_formats() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = texture_from_image(&device, &queue, &random_image(4, 4, 63));
+        let adjuster = ColorAdjuster::new(&device, wgpu::TextureFormat::Rgba32Float).unwrap();
+        assert!(adjuster.apply_into(&device, &queue, &input, &input).is_err());
+    }
+}
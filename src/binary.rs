@@ -11,7 +11,6 @@ use wgpu::PipelineCompilationOptions;
 use crate::utils::padded_bytes_per_row;
 
 /// 填充索引
-
 pub fn main() -> Result<()>{
     let instance = wgpu::Instance::default();
     let adapter = instance
@@ -117,8 +116,8 @@ pub fn main() -> Result<()>{
         let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default() );
         cpass.set_pipeline(&compute_pipeline);
         cpass.set_bind_group(0, &bind_group, &[]);
-        let workgroup_count_x = (width + 16 - 1) / 16;
-        let workgroup_count_y = (height + 16 - 1) / 16;
+        let workgroup_count_x = width.div_ceil(16);
+        let workgroup_count_y = height.div_ceil(16);
         println!("workgroup_count_x={workgroup_count_x}");
         println!("workgroup_count_y={workgroup_count_y}");
         cpass.dispatch_workgroups(workgroup_count_x, workgroup_count_y, 1);
@@ -0,0 +1,390 @@
+#![allow(dead_code)]
+
+use anyhow::Ok;
+use anyhow::Result;
+use image::load_from_memory;
+
+use crate::buffer::GpuBuffer;
+use crate::texture::create_output_texture;
+use crate::texture::image_from_texture;
+use crate::texture::intermediate_format;
+use crate::texture::texture_from_image;
+use crate::texture::with_storage_format;
+use crate::utils::create_bind_group;
+use crate::utils::create_pipeline;
+use crate::utils::create_shader;
+use crate::utils::dispatch;
+use crate::utils::request_device;
+
+/// 共享内存版本支持的最大半径，与 blur.wgsl 中的 MAX_RADIUS 一致
+pub const MAX_RADIUS: u32 = 64;
+/// 与 blur.wgsl 一致
+const TILE_SIZE: u32 = 256;
+const RUNNING_SUM_WORKGROUP_SIZE: u32 = 64;
+
+/// 越界像素的取值方式，与 border.wgsl 一致
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EdgeMode {
+    /// 重复边缘像素: aaa|abc|ccc
+    #[default]
+    Clamp = 0,
+    /// 镜像(不重复边缘像素): cb|abc|ba
+    Mirror = 1,
+    /// 平铺: bc|abc|ab
+    Wrap = 2,
+}
+
+impl EdgeMode {
+    /// 把越界的下标映射回 [0, length)，与 border.wgsl 的 border_index 相同
+    pub fn border_index(self, index: i32, length: i32) -> i32 {
+        match self {
+            EdgeMode::Clamp => index.clamp(0, length - 1),
+            EdgeMode::Wrap => index.rem_euclid(length),
+            EdgeMode::Mirror if length == 1 => 0,
+            EdgeMode::Mirror => {
+                let period = 2 * (length - 1);
+                let i = index.abs() % period;
+                if i >= length {
+                    period - i
+                } else {
+                    i
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Direction {
+    Horizontal = 0,
+    Vertical = 1,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct BlurParams {
+    direction: u32,
+    radius: u32,
+    edge_mode: u32,
+    _padding: u32,
+}
+
+/// 归一化的一维高斯核，长度为 2 * radius + 1
+pub fn gaussian_kernel(sigma: f32, radius: u32) -> Vec<f32> {
+    let sigma = sigma.max(1e-3);
+    let weights: Vec<f32> = (-(radius as i32)..=radius as i32)
+        .map(|x| (-(x * x) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let sum: f32 = weights.iter().sum();
+    weights.iter().map(|w| w / sum).collect()
+}
+
+/// 默认半径取 3 sigma
+pub fn gaussian_radius(sigma: f32) -> u32 {
+    (3.0 * sigma).ceil().max(1.0) as u32
+}
+
+fn blur_shader(device: &wgpu::Device, output_format: wgpu::TextureFormat) -> Result<wgpu::ShaderModule> {
+    let source = format!("{}\n{}", include_str!("../shaders/border.wgsl"), include_str!("../shaders/blur.wgsl"));
+    Ok(create_shader(device, "blur_shader_module", &with_storage_format(&source, output_format)?))
+}
+
+fn params_buffer(device: &wgpu::Device, direction: Direction, radius: u32, edge: EdgeMode) -> GpuBuffer<BlurParams> {
+    GpuBuffer::uniform(
+        device,
+        &BlurParams {
+            direction: direction as u32,
+            radius,
+            edge_mode: edge as u32,
+            _padding: 0,
+        },
+    )
+}
+
+/// 沿一个方向做一维卷积
+#[allow(clippy::too_many_arguments)]
+fn encode_blur_pass(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    input: &wgpu::Texture,
+    output: &wgpu::Texture,
+    weights: &GpuBuffer<f32>,
+    direction: Direction,
+    radius: u32,
+    edge: EdgeMode,
+) -> Result<()> {
+    let shader = blur_shader(device, output.format())?;
+    let pipeline = create_pipeline(device, &shader, "blur_pass");
+    let params = params_buffer(device, direction, radius, edge);
+
+    let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+    let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = create_bind_group(
+        device,
+        &pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(&input_view)),
+            (1, wgpu::BindingResource::TextureView(&output_view)),
+            (2, weights.as_entire_binding()),
+            (3, params.as_entire_binding()),
+        ],
+    );
+
+    let (length, lines) = match direction {
+        Direction::Horizontal => (input.width(), input.height()),
+        Direction::Vertical => (input.height(), input.width()),
+    };
+    dispatch(encoder, &pipeline, &bind_group, (length.div_ceil(TILE_SIZE), lines, 1));
+    Ok(())
+}
+
+/// 用滑动窗口求和沿一个方向做盒式模糊，耗时与半径无关
+fn encode_running_sum_pass(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    input: &wgpu::Texture,
+    output: &wgpu::Texture,
+    direction: Direction,
+    radius: u32,
+    edge: EdgeMode,
+) -> Result<()> {
+    let shader = blur_shader(device, output.format())?;
+    let pipeline = create_pipeline(device, &shader, "box_running_sum");
+    let params = params_buffer(device, direction, radius, edge);
+
+    let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+    let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = create_bind_group(
+        device,
+        &pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(&input_view)),
+            (1, wgpu::BindingResource::TextureView(&output_view)),
+            (3, params.as_entire_binding()),
+        ],
+    );
+
+    let lines = match direction {
+        Direction::Horizontal => input.height(),
+        Direction::Vertical => input.width(),
+    };
+    dispatch(encoder, &pipeline, &bind_group, (lines.div_ceil(RUNNING_SUM_WORKGROUP_SIZE), 1, 1));
+    Ok(())
+}
+
+/// 用同一个一维核先水平再垂直卷积，weights 的长度必须是奇数且半径不超过 MAX_RADIUS
+pub fn separable_filter(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, weights: &[f32], edge: EdgeMode) -> Result<wgpu::Texture> {
+    anyhow::ensure!(weights.len() % 2 == 1, "kernel length {} must be odd", weights.len());
+    let radius = (weights.len() / 2) as u32;
+    anyhow::ensure!(
+        radius <= MAX_RADIUS,
+        "radius {radius} exceeds {MAX_RADIUS}, use fast_blur for large radii"
+    );
+
+    let (width, height) = (input.width(), input.height());
+    let intermediate = create_output_texture(device, width, height, intermediate_format(input.format()));
+    let output = create_output_texture(device, width, height, input.format());
+    let weights = GpuBuffer::storage(device, weights);
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encode_blur_pass(device, &mut encoder, input, &intermediate, &weights, Direction::Horizontal, radius, edge)?;
+    encode_blur_pass(device, &mut encoder, &intermediate, &output, &weights, Direction::Vertical, radius, edge)?;
+    queue.submit(Some(encoder.finish()));
+
+    Ok(output)
+}
+
+/// 高斯模糊，radius 为 None 时取 3 sigma
+pub fn gaussian_blur(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    input: &wgpu::Texture,
+    sigma: f32,
+    radius: Option<u32>,
+    edge: EdgeMode,
+) -> Result<wgpu::Texture> {
+    let radius = radius.unwrap_or_else(|| gaussian_radius(sigma));
+    separable_filter(device, queue, input, &gaussian_kernel(sigma, radius), edge)
+}
+
+/// 盒式模糊(均值滤波)，窗口为 (2 * radius + 1)²
+pub fn box_blur(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, radius: u32, edge: EdgeMode) -> Result<wgpu::Texture> {
+    let size = 2 * radius as usize + 1;
+    separable_filter(device, queue, input, &vec![1.0 / size as f32; size], edge)
+}
+
+/// 逼近给定 sigma 的高斯模糊时 3 次盒式模糊各自的半径
+/// 参考 http://blog.ivank.net/fastest-gaussian-blur.html
+fn box_radii_for_gaussian(sigma: f32) -> [u32; 3] {
+    let n = 3.0;
+    let ideal_width = (12.0 * sigma * sigma / n + 1.0).sqrt();
+    let mut lower = ideal_width.floor();
+    if lower as i32 % 2 == 0 {
+        lower -= 1.0;
+    }
+    let upper = lower + 2.0;
+    let ideal_count = (12.0 * sigma * sigma - n * lower * lower - 4.0 * n * lower - 3.0 * n) / (-4.0 * lower - 4.0);
+    let count = ideal_count.round() as i32;
+
+    let mut radii = [0; 3];
+    for (i, radius) in radii.iter_mut().enumerate() {
+        let width = if (i as i32) < count { lower } else { upper };
+        *radius = ((width - 1.0) / 2.0).max(0.0) as u32;
+    }
+    radii
+}
+
+/// 用 3 次滑动窗口盒式模糊逼近高斯模糊，适合很大的半径
+pub fn fast_blur(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, sigma: f32, edge: EdgeMode) -> Result<wgpu::Texture> {
+    let (width, height) = (input.width(), input.height());
+    let horizontal = create_output_texture(device, width, height, intermediate_format(input.format()));
+    let vertical = create_output_texture(device, width, height, intermediate_format(input.format()));
+    let output = create_output_texture(device, width, height, input.format());
+
+    let radii = box_radii_for_gaussian(sigma);
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let mut source = input;
+    for (i, &radius) in radii.iter().enumerate() {
+        let target = if i == radii.len() - 1 { &output } else { &vertical };
+        encode_running_sum_pass(device, &mut encoder, source, &horizontal, Direction::Horizontal, radius, edge)?;
+        encode_running_sum_pass(device, &mut encoder, &horizontal, target, Direction::Vertical, radius, edge)?;
+        source = &vertical;
+    }
+    queue.submit(Some(encoder.finish()));
+
+    Ok(output)
+}
+
+/// 高斯模糊和盒式模糊
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+
+    let input_image = load_from_memory(include_bytes!("../images/sushi.png"))?.to_rgba8();
+    let input_texture = texture_from_image(&device, &queue, &input_image);
+
+    let gaussian = gaussian_blur(&device, &queue, &input_texture, 4.0, None, EdgeMode::Mirror)?;
+    image_from_texture(&device, &queue, &gaussian)?.save("./outputs/sushi-gaussian.png")?;
+
+    let boxed = box_blur(&device, &queue, &input_texture, 5, EdgeMode::Clamp)?;
+    image_from_texture(&device, &queue, &boxed)?.save("./outputs/sushi-box.png")?;
+
+    let fast = fast_blur(&device, &queue, &input_texture, 30.0, EdgeMode::Wrap)?;
+    image_from_texture(&device, &queue, &fast)?.save("./outputs/sushi-fast-blur.png")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::rgba32f_from_texture;
+    use crate::texture::texture_from_rgba32f;
+    use crate::utils::max_abs_diff;
+    use crate::utils::max_u8_diff;
+    use crate::utils::random_image;
+    use crate::utils::random_rgba32f;
+    use crate::utils::test_device;
+    use image::Rgba32FImage;
+
+    /// CPU 上先水平再垂直的一维卷积
+    fn cpu_separable(image: &Rgba32FImage, weights: &[f32], edge: EdgeMode) -> Rgba32FImage {
+        let (width, height) = image.dimensions();
+        let radius = (weights.len() / 2) as i32;
+        let pass = |image: &Rgba32FImage, horizontal: bool| {
+            Rgba32FImage::from_fn(width, height, |x, y| {
+                let mut sum = [0.0f32; 4];
+                for (k, &weight) in weights.iter().enumerate() {
+                    let offset = k as i32 - radius;
+                    let (sx, sy) = if horizontal {
+                        (edge.border_index(x as i32 + offset, width as i32) as u32, y)
+                    } else {
+                        (x, edge.border_index(y as i32 + offset, height as i32) as u32)
+                    };
+                    for (sum, &value) in sum.iter_mut().zip(image.get_pixel(sx, sy).0.iter()) {
+                        *sum += weight * value;
+                    }
+                }
+                image::Rgba(sum)
+            })
+        };
+        pass(&pass(image, true), false)
+    }
+
+    const EDGES: [EdgeMode; 3] = [EdgeMode::Clamp, EdgeMode::Mirror, EdgeMode::Wrap];
+
+    #[test]
+    fn gaussian_blur_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        // 宽度超过一个 256 像素的 tile，高度小于半径，检查 tile 之间的衔接和边缘处理
+        let input = random_rgba32f(300, 7, 1);
+        let texture = texture_from_rgba32f(&device, &queue, &input, wgpu::TextureFormat::Rgba32Float).unwrap();
+        for edge in EDGES {
+            for (sigma, radius) in [(1.5, None), (6.0, Some(MAX_RADIUS))] {
+                let output = gaussian_blur(&device, &queue, &texture, sigma, radius, edge).unwrap();
+                let expected = cpu_separable(&input, &gaussian_kernel(sigma, radius.unwrap_or_else(|| gaussian_radius(sigma))), edge);
+                // 32 位浮点的中间结果，误差只来自累加顺序
+                let diff = max_abs_diff(rgba32f_from_texture(&device, &queue, &output).unwrap().as_raw(), expected.as_raw());
+                assert!(diff <= 1e-5, "{edge:?} sigma {sigma}: {diff}");
+            }
+        }
+    }
+
+    #[test]
+    fn box_blur_matches_cpu_on_rgba8() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_image(67, 45, 2);
+        let texture = texture_from_image(&device, &queue, &input);
+        let input_f32 = Rgba32FImage::from_fn(67, 45, |x, y| image::Rgba(input.get_pixel(x, y).0.map(|v| v as f32 / 255.0)));
+        for edge in EDGES {
+            let output = image_from_texture(&device, &queue, &box_blur(&device, &queue, &texture, 5, edge).unwrap()).unwrap();
+            let expected = cpu_separable(&input_f32, &[1.0 / 11.0; 11], edge);
+            let expected = image::RgbaImage::from_fn(67, 45, |x, y| image::Rgba(expected.get_pixel(x, y).0.map(|v| (v * 255.0).round() as u8)));
+            // 中间结果是 Rgba16Float，加上最后的 8 位量化，误差不超过 1 级
+            let diff = max_u8_diff(&output, &expected);
+            assert!(diff <= 1, "{edge:?}: {diff}");
+        }
+    }
+
+    #[test]
+    fn fast_blur_matches_three_box_passes() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_rgba32f(90, 70, 3);
+        let texture = texture_from_rgba32f(&device, &queue, &input, wgpu::TextureFormat::Rgba32Float).unwrap();
+        // 半径大于图像尺寸时边缘处理也要正确
+        for (sigma, edge) in [(2.0, EdgeMode::Clamp), (8.0, EdgeMode::Mirror), (60.0, EdgeMode::Wrap)] {
+            let output = fast_blur(&device, &queue, &texture, sigma, edge).unwrap();
+            let mut expected = input.clone();
+            for radius in box_radii_for_gaussian(sigma) {
+                let size = 2 * radius as usize + 1;
+                expected = cpu_separable(&expected, &vec![1.0 / size as f32; size], edge);
+            }
+            // 滑动窗口沿整行累加，误差随行长增长，这里的尺寸下不超过 1e-4
+            let diff = max_abs_diff(rgba32f_from_texture(&device, &queue, &output).unwrap().as_raw(), expected.as_raw());
+            assert!(diff <= 1e-4, "{edge:?} sigma {sigma}: {diff}");
+        }
+    }
+
+    #[test]
+    fn box_radii_approximate_gaussian_variance() {
+        // 宽度为 w 的盒式滤波方差为 (w² - 1) / 12，3 次叠加后应接近 sigma²
+        for sigma in [1.0f32, 2.5, 8.0, 30.0] {
+            let variance: f32 = box_radii_for_gaussian(sigma)
+                .iter()
+                .map(|&radius| {
+                    let width = (2 * radius + 1) as f32;
+                    (width * width - 1.0) / 12.0
+                })
+                .sum();
+            assert!((variance.sqrt() - sigma).abs() <= 0.5, "sigma {sigma}: {}", variance.sqrt());
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_kernels() {
+        let Some((device, queue)) = test_device() else { return };
+        let texture = texture_from_image(&device, &queue, &random_image(8, 8, 4));
+        assert!(separable_filter(&device, &queue, &texture, &[0.5, 0.5], EdgeMode::Clamp).is_err());
+        let too_long = vec![0.0; 2 * MAX_RADIUS as usize + 3];
+        assert!(separable_filter(&device, &queue, &texture, &too_long, EdgeMode::Clamp).is_err());
+    }
+}
@@ -0,0 +1,430 @@
+#![allow(dead_code)]
+
+use std::future::Future;
+use std::marker::PhantomData;
+use std::ops::Bound;
+use std::ops::RangeBounds;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::sync::OnceLock;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Waker;
+use std::time::Duration;
+use anyhow::Ok;
+use anyhow::Result;
+use pollster::FutureExt;
+use wgpu::util::BufferInitDescriptor;
+use wgpu::util::DeviceExt;
+
+/// 带类型的 GPU 缓冲区，封装了上传、读回(staging 缓冲区 + map_async)和子区间视图。
+///
+/// 用途由构造函数决定，而不是按之后的绑定方式推断(wgpu 在创建时就需要用途):
+/// * `storage` 系列: STORAGE | COPY_SRC | COPY_DST，可以绑定为 storage 并随时读写
+/// * `uniform`: UNIFORM | COPY_SRC | COPY_DST
+/// * `with_usage` / `zeroed_with_usage`: 指定的用途 | COPY_SRC | COPY_DST
+pub struct GpuBuffer<T: bytemuck::Pod> {
+    buffer: wgpu::Buffer,
+    len: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: bytemuck::Pod> GpuBuffer<T> {
+    /// 从切片创建 storage 缓冲区
+    pub fn storage(device: &wgpu::Device, data: &[T]) -> Self {
+        Self::with_usage(device, data, wgpu::BufferUsages::STORAGE)
+    }
+
+    /// 创建 len 个元素、内容为 0 的 storage 缓冲区
+    pub fn storage_zeroed(device: &wgpu::Device, len: usize) -> Self {
+        Self::zeroed_with_usage(device, len, wgpu::BufferUsages::STORAGE)
+    }
+
+    /// 从单个值创建 uniform 缓冲区
+    pub fn uniform(device: &wgpu::Device, value: &T) -> Self {
+        Self::with_usage(device, std::slice::from_ref(value), wgpu::BufferUsages::UNIFORM)
+    }
+
+    /// 用指定用途创建，总会附加 COPY_SRC | COPY_DST 以便 read/write
+    pub fn with_usage(device: &wgpu::Device, data: &[T], usage: wgpu::BufferUsages) -> Self {
+        // wgpu 的拷贝需要 4 字节对齐，长度不足时补 0
+        let mut contents = bytemuck::cast_slice::<T, u8>(data).to_vec();
+        contents.resize(aligned_size(contents.len() as u64).max(4) as usize, 0);
+
+        let buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            usage: usage | wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+            contents: &contents,
+        });
+        Self {
+            buffer,
+            len: data.len(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn zeroed_with_usage(device: &wgpu::Device, len: usize, usage: wgpu::BufferUsages) -> Self {
+        let size = aligned_size((std::mem::size_of::<T>() * len) as u64).max(4);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: usage | wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self {
+            buffer,
+            len,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 底层的 wgpu 缓冲区
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    pub fn as_entire_binding(&self) -> wgpu::BindingResource<'_> {
+        self.view(..).as_binding()
+    }
+
+    /// 子区间视图，range 以元素为单位
+    pub fn view(&self, range: impl RangeBounds<usize>) -> GpuBufferView<'_, T> {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end + 1,
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => self.len,
+        };
+        assert!(start <= end && end <= self.len, "range {start}..{end} out of bounds for length {}", self.len);
+        GpuBufferView {
+            buffer: self,
+            offset: start,
+            len: end - start,
+        }
+    }
+
+    /// 从头写入 data
+    pub fn write(&self, queue: &wgpu::Queue, data: &[T]) -> Result<()> {
+        self.view(..data.len().min(self.len)).write(queue, data)
+    }
+
+    /// 读回全部元素，等待 GPU 时不阻塞调用线程
+    pub async fn read(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<Vec<T>> {
+        self.view(..).read(device, queue).await
+    }
+
+    /// read 的阻塞版本
+    pub fn read_blocking(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<Vec<T>> {
+        self.view(..).read_blocking(device, queue)
+    }
+}
+
+/// GpuBuffer 的子区间
+pub struct GpuBufferView<'a, T: bytemuck::Pod> {
+    buffer: &'a GpuBuffer<T>,
+    offset: usize,
+    len: usize,
+}
+
+impl<'a, T: bytemuck::Pod> GpuBufferView<'a, T> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn byte_offset(&self) -> u64 {
+        (self.offset * std::mem::size_of::<T>()) as u64
+    }
+
+    fn byte_size(&self) -> u64 {
+        (self.len * std::mem::size_of::<T>()) as u64
+    }
+
+    /// 作为绑定资源，storage 绑定时偏移需要满足 min_storage_buffer_offset_alignment。
+    /// 空视图不能绑定: wgpu 把大小 None 解释为到缓冲区末尾
+    pub fn as_binding(&self) -> wgpu::BindingResource<'a> {
+        assert!(!self.is_empty(), "cannot bind an empty buffer view");
+        wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+            buffer: self.buffer.buffer(),
+            offset: self.byte_offset(),
+            size: wgpu::BufferSize::new(self.byte_size()),
+        })
+    }
+
+    /// 写入视图区间，偏移和长度需要是 4 字节的倍数
+    pub fn write(&self, queue: &wgpu::Queue, data: &[T]) -> Result<()> {
+        anyhow::ensure!(
+            data.len() == self.len,
+            "data length {} does not match view length {}",
+            data.len(),
+            self.len
+        );
+        let bytes = bytemuck::cast_slice::<T, u8>(data);
+        anyhow::ensure!(
+            self.byte_offset().is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT)
+                && (bytes.len() as u64).is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT),
+            "write range must be aligned to {} bytes",
+            wgpu::COPY_BUFFER_ALIGNMENT
+        );
+        queue.write_buffer(&self.buffer.buffer, self.byte_offset(), bytes);
+        Ok(())
+    }
+
+    /// 读回视图区间，等待 GPU 时不阻塞调用线程
+    pub async fn read(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<Vec<T>> {
+        read_range(device, queue, &self.buffer.buffer, self.byte_offset(), self.len).await
+    }
+
+    /// read 的阻塞版本
+    pub fn read_blocking(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<Vec<T>> {
+        read_range_blocking(device, queue, &self.buffer.buffer, self.byte_offset(), self.len)
+    }
+}
+
+fn aligned_size(size: u64) -> u64 {
+    size.div_ceil(wgpu::COPY_BUFFER_ALIGNMENT) * wgpu::COPY_BUFFER_ALIGNMENT
+}
+
+/// 已提交拷贝、等待映射的读回
+struct PendingRead {
+    staging: wgpu::Buffer,
+    submission: wgpu::SubmissionIndex,
+    mapped: MapFuture,
+    /// 所需数据在 staging 中的字节范围
+    start: usize,
+    size: usize,
+}
+
+impl PendingRead {
+    /// 把 byte_offset 开始的 len 个元素拷贝到 staging 缓冲区并请求映射，拷贝范围会向外扩展到 4 字节对齐。
+    /// 长度为 0 时返回 None
+    fn begin<T: bytemuck::Pod>(device: &wgpu::Device, queue: &wgpu::Queue, buffer: &wgpu::Buffer, byte_offset: u64, len: usize) -> Option<Self> {
+        let size = (std::mem::size_of::<T>() * len) as u64;
+        if size == 0 {
+            return None;
+        }
+
+        let copy_start = byte_offset / wgpu::COPY_BUFFER_ALIGNMENT * wgpu::COPY_BUFFER_ALIGNMENT;
+        let copy_end = aligned_size(byte_offset + size).min(buffer.size());
+        let copy_size = copy_end - copy_start;
+
+        // 获取用于在未映射状态下读取的 GPU 缓冲区
+        let staging = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: copy_size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.copy_buffer_to_buffer(buffer, copy_start, &staging, 0, copy_size);
+        let submission = queue.submit(Some(encoder.finish()));
+
+        let mapped = MapFuture::default();
+        let state = mapped.state.clone();
+        staging.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            let mut state = state.lock().unwrap();
+            state.result = Some(result);
+            state.done = true;
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+
+        Some(Self {
+            staging,
+            submission,
+            mapped,
+            start: (byte_offset - copy_start) as usize,
+            size: size as usize,
+        })
+    }
+
+    /// 映射完成后复制出数据
+    fn finish<T: bytemuck::Pod>(staging: wgpu::Buffer, start: usize, size: usize) -> Vec<T> {
+        let data = staging.slice(..).get_mapped_range();
+        let mut result = vec![T::zeroed(); size / std::mem::size_of::<T>()];
+        bytemuck::cast_slice_mut::<T, u8>(&mut result).copy_from_slice(&data[start..start + size]);
+        drop(data);
+        staging.unmap();
+        result
+    }
+}
+
+/// 从任意带 COPY_SRC 用途的缓冲区读回 byte_offset 开始的 len 个元素。
+/// 映射由共用的后台线程轮询设备推进，等待 GPU 时不阻塞调用线程，可以在 async 运行时中 await
+pub async fn read_range<T: bytemuck::Pod>(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    buffer: &wgpu::Buffer,
+    byte_offset: u64,
+    len: usize,
+) -> Result<Vec<T>> {
+    let Some(read) = PendingRead::begin::<T>(device, queue, buffer, byte_offset, len) else {
+        return Ok(vec![]);
+    };
+    Poller::shared().watch(device, read.mapped.state.clone());
+    read.mapped.await?;
+    Ok(PendingRead::finish(read.staging, read.start, read.size))
+}
+
+/// read_range 的阻塞版本，在调用线程上等待拷贝完成
+pub fn read_range_blocking<T: bytemuck::Pod>(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    buffer: &wgpu::Buffer,
+    byte_offset: u64,
+    len: usize,
+) -> Result<Vec<T>> {
+    let Some(read) = PendingRead::begin::<T>(device, queue, buffer, byte_offset, len) else {
+        return Ok(vec![]);
+    };
+    // map_async 的回调在 poll 中执行
+    device.poll(wgpu::Maintain::wait_for(read.submission));
+    read.mapped.block_on()?;
+    Ok(PendingRead::finish(read.staging, read.start, read.size))
+}
+
+#[derive(Default)]
+struct MapState {
+    result: Option<std::result::Result<(), wgpu::BufferAsyncError>>,
+    /// 回调已经执行，result 被 Future 取走后仍为 true
+    done: bool,
+    waker: Option<Waker>,
+}
+
+/// map_async 回调完成时就绪的 Future
+#[derive(Default)]
+struct MapFuture {
+    state: Arc<Mutex<MapState>>,
+}
+
+impl Future for MapFuture {
+    type Output = std::result::Result<(), wgpu::BufferAsyncError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap();
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// 所有异步读回共用的后台线程。有未完成的映射时反复以 Maintain::Poll 轮询对应的设备，
+/// 回调在这个线程上执行并唤醒 Future; 没有时阻塞在条件变量上
+#[derive(Default)]
+struct Poller {
+    pending: Mutex<Vec<(wgpu::Device, Arc<Mutex<MapState>>)>>,
+    wakeup: Condvar,
+}
+
+impl Poller {
+    /// 两次轮询之间的间隔
+    const INTERVAL: Duration = Duration::from_micros(200);
+
+    fn shared() -> &'static Poller {
+        static POLLER: OnceLock<&'static Poller> = OnceLock::new();
+        POLLER.get_or_init(|| {
+            let poller: &'static Poller = Box::leak(Box::default());
+            std::thread::Builder::new()
+                .name("gpu-buffer-poller".into())
+                .spawn(|| poller.run())
+                .expect("failed to spawn the buffer poller thread");
+            poller
+        })
+    }
+
+    fn watch(&self, device: &wgpu::Device, state: Arc<Mutex<MapState>>) {
+        self.pending.lock().unwrap().push((device.clone(), state));
+        self.wakeup.notify_one();
+    }
+
+    fn run(&self) {
+        loop {
+            let devices: Vec<wgpu::Device> = {
+                let mut pending = self.pending.lock().unwrap();
+                pending.retain(|(_, state)| !state.lock().unwrap().done);
+                while pending.is_empty() {
+                    pending = self.wakeup.wait(pending).unwrap();
+                }
+                pending.iter().map(|(device, _)| device.clone()).collect()
+            };
+            // 不持有锁，回调中会锁 MapState，其它线程也可能同时登记新的读回
+            for device in &devices {
+                device.poll(wgpu::Maintain::Poll);
+            }
+            std::thread::sleep(Self::INTERVAL);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_device;
+
+    #[test]
+    fn view_reads_and_writes_sub_range() {
+        let Some((device, queue)) = test_device() else { return };
+        let buffer = GpuBuffer::storage(&device, &(0..10u32).collect::<Vec<_>>());
+        buffer.view(3..5).write(&queue, &[30, 40]).unwrap();
+        assert_eq!(buffer.view(2..6).read_blocking(&device, &queue).unwrap(), vec![2, 30, 40, 5]);
+        assert_eq!(buffer.read_blocking(&device, &queue).unwrap(), vec![0, 1, 2, 30, 40, 5, 6, 7, 8, 9]);
+
+        // u16 视图的偏移不是 4 字节对齐，读回时向外扩展
+        let halves = GpuBuffer::storage(&device, &[1u16, 2, 3, 4, 5]);
+        assert_eq!(halves.view(1..4).read_blocking(&device, &queue).unwrap(), vec![2, 3, 4]);
+        assert!(halves.view(2..2).read_blocking(&device, &queue).unwrap().is_empty());
+    }
+
+    #[test]
+    fn empty_view_cannot_be_bound() {
+        let Some((device, _queue)) = test_device() else { return };
+        let buffer = GpuBuffer::<u32>::storage_zeroed(&device, 4);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _ = buffer.view(2..2).as_binding();
+        }));
+        assert!(result.is_err());
+    }
+
+    /// 不轮询设备、只靠 Waker 唤醒的执行器，任务在自己的线程上 await，主线程从不调用 device.poll
+    #[test]
+    fn read_awaits_without_polling_on_the_caller() {
+        let Some((device, queue)) = test_device() else { return };
+        let data: Vec<u32> = (0..1000).collect();
+        let buffer = GpuBuffer::storage(&device, &data);
+        let halves = GpuBuffer::storage(&device, &[1u16, 2, 3, 4, 5]);
+
+        let task = async {
+            // 多个读回同时等待
+            let view = halves.view(1..4);
+            let (all, part) = (buffer.read(&device, &queue), view.read(&device, &queue));
+            (all.await.unwrap(), part.await.unwrap())
+        };
+        let (all, part) = std::thread::scope(|scope| scope.spawn(|| task.block_on()).join().unwrap());
+        assert_eq!(all, data);
+        assert_eq!(part, vec![2, 3, 4]);
+        assert!(halves.view(2..2).read(&device, &queue).block_on().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,433 @@
+#![allow(dead_code)]
+
+use anyhow::Ok;
+use anyhow::Result;
+use image::load_from_memory;
+
+use crate::buffer::GpuBuffer;
+use crate::composite::composite;
+use crate::composite::CompositeOptions;
+use crate::texture::create_output_texture;
+use crate::texture::create_texture;
+use crate::texture::image_from_texture;
+use crate::texture::texture_from_image;
+use crate::texture::with_storage_format;
+use crate::utils::compute_work_group_count;
+use crate::utils::create_bind_group;
+use crate::utils::create_pipeline;
+use crate::utils::create_shader;
+use crate::utils::dispatch;
+use crate::utils::request_device;
+
+/// 抠像参数，距离都以 CbCr 平面(范围 0~1)为单位
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChromaKeyConfig {
+    /// 键色 (r, g, b)，范围 0~1
+    pub key_color: [f32; 3],
+    /// 色度距离小于 similarity 的像素完全透明
+    pub similarity: f32,
+    /// 从透明过渡到不透明的距离范围，越大边缘越柔和
+    pub smoothness: f32,
+    /// 去溢色的距离范围，0 表示不去溢色
+    pub spill: f32,
+}
+
+impl Default for ChromaKeyConfig {
+    fn default() -> Self {
+        Self {
+            key_color: [0.0, 1.0, 0.0],
+            similarity: 0.3,
+            smoothness: 0.08,
+            spill: 0.1,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct ChromaKeyParams {
+    key_chroma: [f32; 2],
+    similarity: f32,
+    smoothness: f32,
+    spill: f32,
+    _padding: [f32; 3],
+}
+
+impl From<&ChromaKeyConfig> for ChromaKeyParams {
+    fn from(config: &ChromaKeyConfig) -> Self {
+        let [_, cb, cr] = rgb_to_ycbcr(config.key_color);
+        Self {
+            key_chroma: [cb, cr],
+            similarity: config.similarity,
+            smoothness: config.smoothness,
+            spill: config.spill,
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+/// 与 ycbcr.wgsl 中的 rgb_to_ycbcr 相同
+pub fn rgb_to_ycbcr([r, g, b]: [f32; 3]) -> [f32; 3] {
+    [
+        0.256862 * r + 0.504246 * g + 0.097999 * b + 0.0625,
+        -0.148234 * r - 0.290997 * g + 0.439231 * b + 0.5,
+        0.439231 * r - 0.367758 * g - 0.071473 * b + 0.5,
+    ]
+}
+
+/// 相机的一帧 NV21 数据: 全分辨率的 Y 平面和半分辨率交错存放的 VU 平面。
+/// 宽或高为奇数时色度平面向上取整，即 width.div_ceil(2) x height.div_ceil(2) 个 VU 对。
+/// 纹理可以复用，每一帧只需要 write
+pub struct Nv21Frame {
+    y: wgpu::Texture,
+    vu: wgpu::Texture,
+}
+
+impl Nv21Frame {
+    pub fn new(device: &wgpu::Device, width: u32, height: u32) -> Result<Self> {
+        anyhow::ensure!(width > 0 && height > 0, "invalid NV21 frame size {width}x{height}");
+        let usage = wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST;
+        Ok(Self {
+            y: create_texture(device, width, height, wgpu::TextureFormat::R8Unorm, usage),
+            vu: create_texture(device, width.div_ceil(2), height.div_ceil(2), wgpu::TextureFormat::Rg8Unorm, usage),
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.y.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.y.height()
+    }
+
+    /// 一帧的字节数，宽高都为偶数时为 width * height * 3 / 2
+    pub fn frame_len(&self) -> usize {
+        (self.y.width() * self.y.height() + 2 * self.vu.width() * self.vu.height()) as usize
+    }
+
+    /// 上传一帧数据，长度至少为 frame_len
+    pub fn write(&self, queue: &wgpu::Queue, data: &[u8]) -> Result<()> {
+        let y_len = (self.width() * self.height()) as usize;
+        anyhow::ensure!(
+            data.len() >= self.frame_len(),
+            "NV21 frame has {} bytes, expected {}",
+            data.len(),
+            self.frame_len()
+        );
+        queue.write_texture(
+            self.y.as_image_copy(),
+            &data[..y_len],
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(self.width()),
+                rows_per_image: None,
+            },
+            self.y.size(),
+        );
+        queue.write_texture(
+            self.vu.as_image_copy(),
+            &data[y_len..self.frame_len()],
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(2 * self.vu.width()),
+                rows_per_image: None,
+            },
+            self.vu.size(),
+        );
+        Ok(())
+    }
+}
+
+/// 编译好的抠像流水线，参数通过 uniform 更新，适合逐帧处理视频
+pub struct ChromaKeyer {
+    rgba_pipeline: wgpu::ComputePipeline,
+    nv21_pipeline: wgpu::ComputePipeline,
+    params: GpuBuffer<ChromaKeyParams>,
+    format: wgpu::TextureFormat,
+}
+
+impl ChromaKeyer {
+    /// format 为输出纹理的格式
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Result<Self> {
+        let source = format!("{}\n{}", include_str!("../shaders/ycbcr.wgsl"), include_str!("../shaders/chroma_key.wgsl"));
+        let shader = create_shader(device, "chroma_key_shader_module", &with_storage_format(&source, format)?);
+        Ok(Self {
+            rgba_pipeline: create_pipeline(device, &shader, "chroma_key"),
+            nv21_pipeline: create_pipeline(device, &shader, "chroma_key_nv21"),
+            params: GpuBuffer::uniform(device, &ChromaKeyParams::from(&ChromaKeyConfig::default())),
+            format,
+        })
+    }
+
+    /// 更新参数，只写入 uniform 缓冲区
+    pub fn set(&self, queue: &wgpu::Queue, config: &ChromaKeyConfig) -> Result<()> {
+        self.params.write(queue, &[ChromaKeyParams::from(config)])
+    }
+
+    fn check_output(&self, output: &wgpu::Texture, (width, height): (u32, u32)) -> Result<()> {
+        anyhow::ensure!(
+            output.format() == self.format,
+            "output format {:?} does not match {:?}",
+            output.format(),
+            self.format
+        );
+        anyhow::ensure!(
+            output.width() == width && output.height() == height,
+            "output is {}x{}, expected {width}x{height}",
+            output.width(),
+            output.height()
+        );
+        Ok(())
+    }
+
+    /// 把 RGBA 纹理的抠像命令记录到 encoder 中，output 需要与 input 大小相同
+    pub fn encode(&self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, input: &wgpu::Texture, output: &wgpu::Texture) -> Result<()> {
+        self.check_output(output, (input.width(), input.height()))?;
+        let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+        let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = create_bind_group(
+            device,
+            &self.rgba_pipeline,
+            &[
+                (0, wgpu::BindingResource::TextureView(&input_view)),
+                (1, wgpu::BindingResource::TextureView(&output_view)),
+                (2, self.params.as_entire_binding()),
+            ],
+        );
+        let (x, y) = compute_work_group_count((input.width(), input.height()), (16, 16));
+        dispatch(encoder, &self.rgba_pipeline, &bind_group, (x, y, 1));
+        Ok(())
+    }
+
+    /// 直接对 NV21 帧抠像，output 需要与帧大小相同
+    pub fn encode_nv21(&self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, frame: &Nv21Frame, output: &wgpu::Texture) -> Result<()> {
+        self.check_output(output, (frame.width(), frame.height()))?;
+        let y_view = frame.y.create_view(&wgpu::TextureViewDescriptor::default());
+        let vu_view = frame.vu.create_view(&wgpu::TextureViewDescriptor::default());
+        let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = create_bind_group(
+            device,
+            &self.nv21_pipeline,
+            &[
+                (1, wgpu::BindingResource::TextureView(&output_view)),
+                (2, self.params.as_entire_binding()),
+                (3, wgpu::BindingResource::TextureView(&y_view)),
+                (4, wgpu::BindingResource::TextureView(&vu_view)),
+            ],
+        );
+        let (x, y) = compute_work_group_count((frame.width(), frame.height()), (16, 16));
+        dispatch(encoder, &self.nv21_pipeline, &bind_group, (x, y, 1));
+        Ok(())
+    }
+
+    /// 用当前参数处理 RGBA 纹理，返回带 alpha 的新纹理
+    pub fn apply(&self, device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture) -> Result<wgpu::Texture> {
+        let output = create_output_texture(device, input.width(), input.height(), self.format);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        self.encode(device, &mut encoder, input, &output)?;
+        queue.submit(Some(encoder.finish()));
+        Ok(output)
+    }
+
+    /// 用当前参数处理 NV21 帧，结果写入 output
+    pub fn apply_nv21_into(&self, device: &wgpu::Device, queue: &wgpu::Queue, frame: &Nv21Frame, output: &wgpu::Texture) -> Result<()> {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        self.encode_nv21(device, &mut encoder, frame, output)?;
+        queue.submit(Some(encoder.finish()));
+        Ok(())
+    }
+}
+
+/// 一次性的抠像，输出格式与输入相同
+pub fn chroma_key(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, config: &ChromaKeyConfig) -> Result<wgpu::Texture> {
+    let keyer = ChromaKeyer::new(device, input.format())?;
+    keyer.set(queue, config)?;
+    keyer.apply(device, queue, input)
+}
+
+/// 色键抠像
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+
+    // 把带透明通道的图标放在绿幕前，抠像后应该还原图标的形状
+    let logo = load_from_memory(include_bytes!("../images/rust.png"))?.to_rgba8();
+    let green = image::Rgba([0, 177, 64, 255]);
+    let mut green_screen = image::RgbaImage::from_pixel(logo.width(), logo.height(), green);
+    image::imageops::overlay(&mut green_screen, &logo, 0, 0);
+    green_screen.save("./outputs/rust_green_screen.png")?;
+
+    let config = ChromaKeyConfig {
+        key_color: [0.0, 177.0 / 255.0, 64.0 / 255.0],
+        ..Default::default()
+    };
+    let input_texture = texture_from_image(&device, &queue, &green_screen);
+    let keyed = chroma_key(&device, &queue, &input_texture, &config)?;
+    image_from_texture(&device, &queue, &keyed)?.save("./outputs/rust_keyed.png")?;
+
+    // 把抠出的图标合成到新的背景上
+    let background = texture_from_image(&device, &queue, &load_from_memory(include_bytes!("../images/capture.jpg"))?.to_rgba8());
+    let options = CompositeOptions {
+        position: (50, 50),
+        ..Default::default()
+    };
+    let output = composite(&device, &queue, &background, &keyed, &options)?;
+    image_from_texture(&device, &queue, &output)?.save("./outputs/capture_keyed_overlay.png")?;
+
+    // 直接处理 NV21 相机帧，同一个流水线可以逐帧复用
+    let (width, height) = (1280, 960);
+    let frame = Nv21Frame::new(&device, width, height)?;
+    frame.write(&queue, include_bytes!("../images/capture.yuv"))?;
+
+    let keyer = ChromaKeyer::new(&device, wgpu::TextureFormat::Rgba8Unorm)?;
+    let config = ChromaKeyConfig {
+        key_color: [0.9, 0.9, 0.9],
+        similarity: 0.05,
+        smoothness: 0.05,
+        spill: 0.0,
+    };
+    keyer.set(&queue, &config)?;
+    let output = create_output_texture(&device, width, height, wgpu::TextureFormat::Rgba8Unorm);
+    keyer.apply_nv21_into(&device, &queue, &frame, &output)?;
+    image_from_texture(&device, &queue, &output)?.save("./outputs/capture_keyed_nv21.png")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::radix_sort::xorshift;
+    use crate::texture::rgba32f_from_texture;
+    use crate::texture::texture_from_rgba32f;
+    use crate::utils::max_abs_diff;
+    use crate::utils::max_u8_diff;
+    use crate::utils::random_rgba32f;
+    use crate::utils::test_device;
+    use image::Rgba32FImage;
+
+    /// CPU 上的抠像，与 chroma_key.wgsl 中的 key 相同
+    fn cpu_key(rgb: [f32; 3], alpha: f32, ycbcr: [f32; 3], config: &ChromaKeyConfig) -> [f32; 4] {
+        let params = ChromaKeyParams::from(config);
+        let distance = ((ycbcr[1] - params.key_chroma[0]).powi(2) + (ycbcr[2] - params.key_chroma[1]).powi(2)).sqrt();
+        let base = distance - config.similarity;
+        let mask = (base / config.smoothness.max(1e-4)).clamp(0.0, 1.0).powf(1.5);
+        let spill = (base / config.spill.max(1e-4)).clamp(0.0, 1.0).powf(1.5);
+        let gray = 0.2126 * rgb[0] + 0.7152 * rgb[1] + 0.0722 * rgb[2];
+        let [r, g, b] = rgb.map(|c| (gray * (1.0 - spill) + c * spill).clamp(0.0, 1.0));
+        [r, g, b, alpha * mask]
+    }
+
+    /// 与 ycbcr.wgsl 中的 ycbcr_to_rgb 相同
+    fn cpu_ycbcr_to_rgb([y, u, v]: [f32; 3]) -> [f32; 3] {
+        let (y, u, v) = (y - 0.0625, u - 0.5, v - 0.5);
+        [1.164 * y + 1.596 * v, 1.164 * y - 0.813 * v - 0.392 * u, 1.164 * y + 2.017 * u]
+    }
+
+    /// 键色附近、过渡带和远处的颜色都有
+    fn configs() -> [ChromaKeyConfig; 3] {
+        [
+            ChromaKeyConfig::default(),
+            ChromaKeyConfig {
+                key_color: [0.1, 0.2, 0.9],
+                similarity: 0.1,
+                smoothness: 0.2,
+                spill: 0.3,
+            },
+            ChromaKeyConfig {
+                key_color: [0.5, 0.5, 0.5],
+                similarity: 0.02,
+                smoothness: 0.0,
+                spill: 0.0,
+            },
+        ]
+    }
+
+    #[test]
+    fn rgba_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_rgba32f(37, 23, 111);
+        let texture = texture_from_rgba32f(&device, &queue, &input, wgpu::TextureFormat::Rgba32Float).unwrap();
+        let keyer = ChromaKeyer::new(&device, wgpu::TextureFormat::Rgba32Float).unwrap();
+        for config in configs() {
+            keyer.set(&queue, &config).unwrap();
+            let output = rgba32f_from_texture(&device, &queue, &keyer.apply(&device, &queue, &texture).unwrap()).unwrap();
+            let expected = Rgba32FImage::from_fn(37, 23, |x, y| {
+                let [r, g, b, a] = input.get_pixel(x, y).0;
+                image::Rgba(cpu_key([r, g, b], a, rgb_to_ycbcr([r, g, b]), &config))
+            });
+            // pow 和 sqrt 在 GPU 上不是正确舍入的，过渡带里的误差约 1e-6
+            let diff = max_abs_diff(output.as_raw(), expected.as_raw());
+            assert!(diff <= 1e-5, "{config:?}: {diff}");
+        }
+    }
+
+    #[test]
+    fn nv21_matches_cpu_for_odd_sizes() {
+        let Some((device, queue)) = test_device() else { return };
+        let keyer = ChromaKeyer::new(&device, wgpu::TextureFormat::Rgba8Unorm).unwrap();
+        for (width, height) in [(1, 1), (5, 3), (17, 9), (16, 8)] {
+            let frame = Nv21Frame::new(&device, width, height).unwrap();
+            let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+            assert_eq!(frame.frame_len(), (width * height + 2 * chroma_width * chroma_height) as usize);
+            let mut state = width * 31 + height;
+            let data: Vec<u8> = (0..frame.frame_len()).map(|_| xorshift(&mut state) as u8).collect();
+            frame.write(&queue, &data).unwrap();
+
+            for config in configs() {
+                keyer.set(&queue, &config).unwrap();
+                let output = create_output_texture(&device, width, height, wgpu::TextureFormat::Rgba8Unorm);
+                keyer.apply_nv21_into(&device, &queue, &frame, &output).unwrap();
+                let expected = image::RgbaImage::from_fn(width, height, |x, y| {
+                    let vu = (width * height + (y / 2) * 2 * chroma_width + (x / 2) * 2) as usize;
+                    let ycbcr = [data[(y * width + x) as usize], data[vu + 1], data[vu]].map(|v| v as f32 / 255.0);
+                    let rgb = cpu_ycbcr_to_rgb(ycbcr).map(|v| v.clamp(0.0, 1.0));
+                    image::Rgba(cpu_key(rgb, 1.0, ycbcr, &config).map(|v| (v * 255.0).round() as u8))
+                });
+                // 只差在最后的 8 位量化上
+                let diff = max_u8_diff(&image_from_texture(&device, &queue, &output).unwrap(), &expected);
+                assert!(diff <= 1, "{width}x{height} {config:?}: {diff}");
+            }
+        }
+    }
+
+    #[test]
+    fn green_screen_is_removed() {
+        let Some((device, queue)) = test_device() else { return };
+        // 绿幕前的红色和蓝色方块
+        let green = image::Rgba([0, 177, 64, 255]);
+        let input = image::RgbaImage::from_fn(32, 32, |x, y| match (x / 8, y / 8) {
+            (1, 1) => image::Rgba([200, 30, 40, 255]),
+            (2, 2) => image::Rgba([20, 40, 220, 255]),
+            _ => green,
+        });
+        let config = ChromaKeyConfig {
+            key_color: [0.0, 177.0 / 255.0, 64.0 / 255.0],
+            ..Default::default()
+        };
+        let texture = texture_from_image(&device, &queue, &input);
+        let output = image_from_texture(&device, &queue, &chroma_key(&device, &queue, &texture, &config).unwrap()).unwrap();
+        for (pixel, keyed) in input.pixels().zip(output.pixels()) {
+            if *pixel == green {
+                assert_eq!(keyed[3], 0);
+            } else {
+                // 离键色很远的颜色完全不透明，也不去溢色
+                assert_eq!(keyed, pixel);
+            }
+        }
+    }
+
+    #[test]
+    fn nv21_frame_validation() {
+        let Some((device, queue)) = test_device() else { return };
+        assert!(Nv21Frame::new(&device, 0, 4).is_err());
+        let frame = Nv21Frame::new(&device, 3, 3).unwrap();
+        assert_eq!(frame.frame_len(), 9 + 8);
+        assert!(frame.write(&queue, &[0; 16]).is_err());
+        assert!(frame.write(&queue, &[0; 17]).is_ok());
+
+        let keyer = ChromaKeyer::new(&device, wgpu::TextureFormat::Rgba8Unorm).unwrap();
+        let wrong_size = create_output_texture(&device, 4, 3, wgpu::TextureFormat::Rgba8Unorm);
+        assert!(keyer.apply_nv21_into(&device, &queue, &frame, &wrong_size).is_err());
+    }
+}
@@ -0,0 +1,365 @@
+#![allow(dead_code)]
+
+use anyhow::Ok;
+use anyhow::Result;
+use image::load_from_memory;
+
+use crate::buffer::GpuBuffer;
+use crate::resize::resize;
+use crate::resize::ResizeFilter;
+use crate::texture::create_output_texture;
+use crate::texture::image_from_texture;
+use crate::texture::texture_from_image;
+use crate::texture::with_storage_format;
+use crate::utils::compute_work_group_count;
+use crate::utils::create_bind_group;
+use crate::utils::create_pipeline;
+use crate::utils::create_shader;
+use crate::utils::dispatch;
+use crate::utils::request_device;
+
+/// Porter-Duff 合成算子，与 composite.wgsl 一致
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PorterDuff {
+    /// 前景盖在背景上
+    #[default]
+    Over = 0,
+    /// 只保留前景落在背景内的部分
+    In = 1,
+    /// 只保留前景落在背景外的部分
+    Out = 2,
+    /// 前景只画在背景上，背景的形状不变
+    Atop = 3,
+    /// 两者不重叠的部分
+    Xor = 4,
+}
+
+/// 混合模式(与 Photoshop 和 CSS mix-blend-mode 相同)，与 composite.wgsl 一致
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    #[default]
+    Normal = 0,
+    Multiply = 1,
+    Screen = 2,
+    Overlay = 3,
+    SoftLight = 4,
+    Difference = 5,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CompositeOptions {
+    pub operator: PorterDuff,
+    pub blend: BlendMode,
+    /// 前景左上角在背景中的位置，可以为负数或超出背景
+    pub position: (i32, i32),
+    /// 前景的不透明度，范围 0~1
+    pub opacity: f32,
+    /// 输入和输出是否都是预乘 alpha 的颜色
+    pub premultiplied: bool,
+}
+
+impl Default for CompositeOptions {
+    fn default() -> Self {
+        Self {
+            operator: PorterDuff::default(),
+            blend: BlendMode::default(),
+            position: (0, 0),
+            opacity: 1.0,
+            premultiplied: false,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct CompositeParams {
+    offset: [i32; 2],
+    porter_duff: u32,
+    blend: u32,
+    opacity: f32,
+    premultiplied: u32,
+    _padding: [u32; 2],
+}
+
+/// 把 source 按 options 合成到 backdrop 上，两者大小可以不同，输出的大小和格式与 backdrop 相同
+pub fn composite(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    backdrop: &wgpu::Texture,
+    source: &wgpu::Texture,
+    options: &CompositeOptions,
+) -> Result<wgpu::Texture> {
+    let (width, height) = (backdrop.width(), backdrop.height());
+    let output = create_output_texture(device, width, height, backdrop.format());
+
+    let shader = create_shader(
+        device,
+        "composite_shader_module",
+        &with_storage_format(include_str!("../shaders/composite.wgsl"), backdrop.format())?,
+    );
+    let pipeline = create_pipeline(device, &shader, "composite");
+    let params = GpuBuffer::uniform(
+        device,
+        &CompositeParams {
+            offset: [options.position.0, options.position.1],
+            porter_duff: options.operator as u32,
+            blend: options.blend as u32,
+            opacity: options.opacity.clamp(0.0, 1.0),
+            premultiplied: options.premultiplied as u32,
+            _padding: [0; 2],
+        },
+    );
+
+    let backdrop_view = backdrop.create_view(&wgpu::TextureViewDescriptor::default());
+    let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+    let source_view = source.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = create_bind_group(
+        device,
+        &pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(&backdrop_view)),
+            (1, wgpu::BindingResource::TextureView(&output_view)),
+            (2, params.as_entire_binding()),
+            (3, wgpu::BindingResource::TextureView(&source_view)),
+        ],
+    );
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let (x, y) = compute_work_group_count((width, height), (16, 16));
+    dispatch(&mut encoder, &pipeline, &bind_group, (x, y, 1));
+    queue.submit(Some(encoder.finish()));
+
+    Ok(output)
+}
+
+/// 图像合成和混合模式
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+
+    let backdrop_image = load_from_memory(include_bytes!("../images/capture.jpg"))?.to_rgba8();
+    let logo_image = load_from_memory(include_bytes!("../images/rust.png"))?.to_rgba8();
+    let backdrop = texture_from_image(&device, &queue, &backdrop_image);
+    let logo = texture_from_image(&device, &queue, &logo_image);
+    let (width, height) = backdrop_image.dimensions();
+
+    // 前景部分超出右下角
+    let position = (width as i32 - 200, height as i32 - 150);
+    let output = composite(
+        &device,
+        &queue,
+        &backdrop,
+        &logo,
+        &CompositeOptions {
+            position,
+            ..Default::default()
+        },
+    )?;
+    image_from_texture(&device, &queue, &output)?.save("./outputs/capture_logo_corner.png")?;
+
+    // 半透明水印
+    let watermark = composite(
+        &device,
+        &queue,
+        &backdrop,
+        &logo,
+        &CompositeOptions {
+            position: (20, 20),
+            opacity: 0.4,
+            ..Default::default()
+        },
+    )?;
+    image_from_texture(&device, &queue, &watermark)?.save("./outputs/capture_watermark.png")?;
+
+    // 缩略图叠加在右上角
+    let thumbnail = resize(&device, &queue, &backdrop, width / 4, height / 4, ResizeFilter::Area)?;
+    let options = CompositeOptions {
+        position: ((width - width / 4 - 10) as i32, 10),
+        ..Default::default()
+    };
+    let output = composite(&device, &queue, &backdrop, &thumbnail, &options)?;
+    image_from_texture(&device, &queue, &output)?.save("./outputs/capture_thumbnail_overlay.png")?;
+
+    let blends = [
+        (BlendMode::Multiply, "multiply"),
+        (BlendMode::Screen, "screen"),
+        (BlendMode::Overlay, "overlay"),
+        (BlendMode::SoftLight, "soft_light"),
+        (BlendMode::Difference, "difference"),
+    ];
+    for (blend, name) in blends {
+        let options = CompositeOptions {
+            blend,
+            position: (100, 50),
+            ..Default::default()
+        };
+        let output = composite(&device, &queue, &backdrop, &logo, &options)?;
+        image_from_texture(&device, &queue, &output)?.save(format!("./outputs/capture_blend_{name}.png"))?;
+    }
+
+    // Porter-Duff 算子: 用半透明的圆形背景观察效果
+    let circle = image::RgbaImage::from_fn(logo_image.width(), logo_image.height(), |x, y| {
+        let (dx, dy) = (x as f32 - 100.0, y as f32 - 100.0);
+        if dx * dx + dy * dy < 120.0 * 120.0 {
+            image::Rgba([30, 144, 255, 200])
+        } else {
+            image::Rgba([0, 0, 0, 0])
+        }
+    });
+    let circle = texture_from_image(&device, &queue, &circle);
+    let operators = [
+        (PorterDuff::Over, "over"),
+        (PorterDuff::In, "in"),
+        (PorterDuff::Out, "out"),
+        (PorterDuff::Atop, "atop"),
+        (PorterDuff::Xor, "xor"),
+    ];
+    for (operator, name) in operators {
+        let options = CompositeOptions {
+            operator,
+            position: (80, 80),
+            ..Default::default()
+        };
+        let output = composite(&device, &queue, &circle, &logo, &options)?;
+        image_from_texture(&device, &queue, &output)?.save(format!("./outputs/porter_duff_{name}.png"))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::radix_sort::xorshift;
+    use crate::texture::rgba32f_from_texture;
+    use crate::texture::texture_from_rgba32f;
+    use crate::utils::max_abs_diff;
+    use crate::utils::max_u8_diff;
+    use crate::utils::random_image;
+    use crate::utils::test_device;
+    use image::Rgba32FImage;
+
+    /// alpha 有一部分为 0 和 1 的随机浮点图像，颜色按 premultiplied 预乘
+    fn random_layer(width: u32, height: u32, seed: u32, premultiplied: bool) -> Rgba32FImage {
+        let mut state = seed;
+        let mut random = || xorshift(&mut state) as f32 / u32::MAX as f32;
+        Rgba32FImage::from_fn(width, height, |_, _| {
+            let alpha = [0.0, 1.0, random()][(random() * 2.999) as usize];
+            let rgb = [random(), random(), random()];
+            let scale = if premultiplied { alpha } else { 1.0 };
+            image::Rgba([rgb[0] * scale, rgb[1] * scale, rgb[2] * scale, alpha])
+        })
+    }
+
+    fn cpu_blend(mode: BlendMode, cb: f32, cs: f32) -> f32 {
+        let hard_light = |cb: f32, cs: f32| if cs <= 0.5 { cb * 2.0 * cs } else { 1.0 - (1.0 - cb) * (1.0 - (2.0 * cs - 1.0)) };
+        match mode {
+            BlendMode::Normal => cs,
+            BlendMode::Multiply => cb * cs,
+            BlendMode::Screen => cb + cs - cb * cs,
+            BlendMode::Overlay => hard_light(cs, cb),
+            BlendMode::SoftLight if cs <= 0.5 => cb - (1.0 - 2.0 * cs) * cb * (1.0 - cb),
+            BlendMode::SoftLight => {
+                let d = if cb <= 0.25 { ((16.0 * cb - 12.0) * cb + 4.0) * cb } else { cb.sqrt() };
+                cb + (2.0 * cs - 1.0) * (d - cb)
+            }
+            BlendMode::Difference => (cb - cs).abs(),
+        }
+    }
+
+    /// CPU 上的合成，公式与 composite.wgsl 相同
+    fn cpu_composite(backdrop: &Rgba32FImage, source: &Rgba32FImage, options: &CompositeOptions) -> Rgba32FImage {
+        let unpremultiply = |c: [f32; 4]| {
+            let c = match options.premultiplied {
+                false => c,
+                true if c[3] <= 0.0 => [0.0; 4],
+                true => [c[0] / c[3], c[1] / c[3], c[2] / c[3], c[3]],
+            };
+            c.map(|v| v.clamp(0.0, 1.0))
+        };
+        Rgba32FImage::from_fn(backdrop.width(), backdrop.height(), |x, y| {
+            let b = unpremultiply(backdrop.get_pixel(x, y).0);
+            let (sx, sy) = (x as i32 - options.position.0, y as i32 - options.position.1);
+            let s = if sx >= 0 && sy >= 0 && sx < source.width() as i32 && sy < source.height() as i32 {
+                unpremultiply(source.get_pixel(sx as u32, sy as u32).0)
+            } else {
+                [0.0; 4]
+            };
+            let (alpha_s, alpha_b) = (s[3] * options.opacity.clamp(0.0, 1.0), b[3]);
+            let (fa, fb) = match options.operator {
+                PorterDuff::Over => (1.0, 1.0 - alpha_s),
+                PorterDuff::In => (alpha_b, 0.0),
+                PorterDuff::Out => (1.0 - alpha_b, 0.0),
+                PorterDuff::Atop => (alpha_b, 1.0 - alpha_s),
+                PorterDuff::Xor => (1.0 - alpha_b, 1.0 - alpha_s),
+            };
+            let alpha = alpha_s * fa + alpha_b * fb;
+            let color: [f32; 3] = std::array::from_fn(|i| {
+                let blended = (1.0 - alpha_b) * s[i] + alpha_b * cpu_blend(options.blend, b[i], s[i]);
+                alpha_s * fa * blended + alpha_b * fb * b[i]
+            });
+            match (options.premultiplied, alpha > 0.0) {
+                (true, _) => image::Rgba([color[0], color[1], color[2], alpha]),
+                (false, true) => image::Rgba([color[0] / alpha, color[1] / alpha, color[2] / alpha, alpha]),
+                (false, false) => image::Rgba([0.0; 4]),
+            }
+        })
+    }
+
+    #[test]
+    fn composite_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        let operators = [PorterDuff::Over, PorterDuff::In, PorterDuff::Out, PorterDuff::Atop, PorterDuff::Xor];
+        let blends = [
+            BlendMode::Normal,
+            BlendMode::Multiply,
+            BlendMode::Screen,
+            BlendMode::Overlay,
+            BlendMode::SoftLight,
+            BlendMode::Difference,
+        ];
+        for premultiplied in [false, true] {
+            let backdrop = random_layer(40, 30, 101, premultiplied);
+            let source = random_layer(25, 18, 102, premultiplied);
+            let backdrop_texture = texture_from_rgba32f(&device, &queue, &backdrop, wgpu::TextureFormat::Rgba32Float).unwrap();
+            let source_texture = texture_from_rgba32f(&device, &queue, &source, wgpu::TextureFormat::Rgba32Float).unwrap();
+            for operator in operators {
+                for blend in blends {
+                    // 前景一部分超出左上角，另一次超出右下角
+                    for (position, opacity) in [((-5, -7), 1.0), ((28, 20), 0.6)] {
+                        let options = CompositeOptions { operator, blend, position, opacity, premultiplied };
+                        let output = composite(&device, &queue, &backdrop_texture, &source_texture, &options).unwrap();
+                        let output = rgba32f_from_texture(&device, &queue, &output).unwrap();
+                        // 32 位浮点输出，误差只来自计算顺序(非预乘时还有一次除以 alpha)
+                        let diff = max_abs_diff(output.as_raw(), cpu_composite(&backdrop, &source, &options).as_raw());
+                        assert!(diff <= 1e-5, "{options:?}: {diff}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn over_matches_image_crate() {
+        let Some((device, queue)) = test_device() else { return };
+        // 背景不透明，前景有各种 alpha
+        let mut backdrop_image = random_image(40, 30, 103);
+        backdrop_image.pixels_mut().for_each(|pixel| pixel[3] = 255);
+        let logo_image = random_image(25, 18, 104);
+        let backdrop = texture_from_image(&device, &queue, &backdrop_image);
+        let logo = texture_from_image(&device, &queue, &logo_image);
+        for position in [(-5, -7), (28, 20), (7, 6)] {
+            let options = CompositeOptions { position, ..Default::default() };
+            let output = image_from_texture(&device, &queue, &composite(&device, &queue, &backdrop, &logo, &options).unwrap()).unwrap();
+            let mut expected = backdrop_image.clone();
+            image::imageops::overlay(&mut expected, &logo_image, position.0 as i64, position.1 as i64);
+            // image crate 在 f32 中计算后截断取整，与 GPU 的四舍五入最多差 1 级
+            let diff = max_u8_diff(&output, &expected);
+            assert!(diff <= 1, "{position:?}: {diff}");
+        }
+
+        // 不透明度为 0 时背景不变
+        let options = CompositeOptions { opacity: 0.0, ..Default::default() };
+        let output = composite(&device, &queue, &backdrop, &logo, &options).unwrap();
+        assert!(image_from_texture(&device, &queue, &output).unwrap() == backdrop_image);
+    }
+}
@@ -0,0 +1,292 @@
+#![allow(dead_code)]
+
+use anyhow::Ok;
+use anyhow::Result;
+use image::load_from_memory;
+
+use crate::blur::EdgeMode;
+use crate::buffer::GpuBuffer;
+use crate::texture::create_output_texture;
+use crate::texture::image_from_texture;
+use crate::texture::texture_from_image;
+use crate::texture::with_storage_format;
+use crate::utils::compute_work_group_count;
+use crate::utils::create_bind_group;
+use crate::utils::create_pipeline;
+use crate::utils::create_shader;
+use crate::utils::dispatch;
+use crate::utils::request_device;
+
+/// K×K 卷积核，K 为奇数，权重按行优先存放
+#[derive(Clone, Debug, PartialEq)]
+pub struct Kernel {
+    size: u32,
+    weights: Vec<f32>,
+}
+
+impl Kernel {
+    pub fn new(size: u32, weights: Vec<f32>) -> Result<Self> {
+        anyhow::ensure!(size % 2 == 1, "kernel size {size} must be odd");
+        anyhow::ensure!(
+            weights.len() == (size * size) as usize,
+            "expected {} weights for a {size}x{size} kernel, got {}",
+            size * size,
+            weights.len()
+        );
+        Ok(Self { size, weights })
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+
+    /// 除以权重之和，和为 0 时(如边缘检测核)保持不变
+    pub fn normalized(&self) -> Self {
+        let sum: f32 = self.weights.iter().sum();
+        if sum.abs() < f32::EPSILON {
+            return self.clone();
+        }
+        Self {
+            size: self.size,
+            weights: self.weights.iter().map(|w| w / sum).collect(),
+        }
+    }
+
+    pub fn identity() -> Self {
+        Self::new(3, vec![0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0]).unwrap()
+    }
+
+    pub fn sharpen() -> Self {
+        Self::new(3, vec![0.0, -1.0, 0.0, -1.0, 5.0, -1.0, 0.0, -1.0, 0.0]).unwrap()
+    }
+
+    /// 浮雕，通常配合 0.5 的偏移使用
+    pub fn emboss() -> Self {
+        Self::new(3, vec![-2.0, -1.0, 0.0, -1.0, 1.0, 1.0, 0.0, 1.0, 2.0]).unwrap()
+    }
+
+    /// 拉普拉斯边缘检测
+    pub fn laplacian() -> Self {
+        Self::new(3, vec![-1.0, -1.0, -1.0, -1.0, 8.0, -1.0, -1.0, -1.0, -1.0]).unwrap()
+    }
+}
+
+/// 卷积参数
+#[derive(Clone, Copy, Debug)]
+pub struct ConvolutionOptions {
+    /// 是否先把权重归一化
+    pub normalize: bool,
+    /// 加到每个通道上的偏移(0~1)
+    pub bias: f32,
+    pub edge: EdgeMode,
+    /// 为 true 时保留原 alpha
+    pub skip_alpha: bool,
+}
+
+impl Default for ConvolutionOptions {
+    fn default() -> Self {
+        Self {
+            normalize: false,
+            bias: 0.0,
+            edge: EdgeMode::Clamp,
+            skip_alpha: true,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct ConvolutionParams {
+    radius: u32,
+    edge_mode: u32,
+    skip_alpha: u32,
+    bias: f32,
+}
+
+/// 用任意 K×K 卷积核处理纹理，输出格式与输入相同(Rgba8Unorm、Rgba16Float、Rgba32Float 或 R32Float)
+pub fn convolve(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    input: &wgpu::Texture,
+    kernel: &Kernel,
+    options: ConvolutionOptions,
+) -> Result<wgpu::Texture> {
+    let (width, height) = (input.width(), input.height());
+    let source = format!("{}\n{}", include_str!("../shaders/border.wgsl"), include_str!("../shaders/convolution.wgsl"));
+    let shader = create_shader(device, "convolution_shader_module", &with_storage_format(&source, input.format())?);
+    let pipeline = create_pipeline(device, &shader, "convolve");
+
+    let output = create_output_texture(device, width, height, input.format());
+    let kernel = if options.normalize { kernel.normalized() } else { kernel.clone() };
+    let weights = GpuBuffer::storage(device, kernel.weights());
+    let params = GpuBuffer::uniform(
+        device,
+        &ConvolutionParams {
+            radius: kernel.size() / 2,
+            edge_mode: options.edge as u32,
+            skip_alpha: options.skip_alpha as u32,
+            bias: options.bias,
+        },
+    );
+
+    let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+    let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = create_bind_group(
+        device,
+        &pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(&input_view)),
+            (1, wgpu::BindingResource::TextureView(&output_view)),
+            (2, weights.as_entire_binding()),
+            (3, params.as_entire_binding()),
+        ],
+    );
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let (x, y) = compute_work_group_count((width, height), (16, 16));
+    dispatch(&mut encoder, &pipeline, &bind_group, (x, y, 1));
+    queue.submit(Some(encoder.finish()));
+
+    Ok(output)
+}
+
+/// 通用二维卷积
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+
+    let input_image = load_from_memory(include_bytes!("../images/capture.jpg"))?.to_rgba8();
+    let input_texture = texture_from_image(&device, &queue, &input_image);
+
+    let sharpened = convolve(&device, &queue, &input_texture, &Kernel::sharpen(), ConvolutionOptions::default())?;
+    image_from_texture(&device, &queue, &sharpened)?.save("./outputs/capture_sharpen.png")?;
+
+    let emboss_options = ConvolutionOptions {
+        bias: 0.5,
+        ..Default::default()
+    };
+    let embossed = convolve(&device, &queue, &input_texture, &Kernel::emboss(), emboss_options)?;
+    image_from_texture(&device, &queue, &embossed)?.save("./outputs/capture_emboss.png")?;
+
+    let blur_options = ConvolutionOptions {
+        normalize: true,
+        edge: EdgeMode::Mirror,
+        ..Default::default()
+    };
+    let blurred = convolve(&device, &queue, &input_texture, &Kernel::new(5, vec![1.0; 25])?, blur_options)?;
+    image_from_texture(&device, &queue, &blurred)?.save("./outputs/capture_convolve_box.png")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::rgba32f_from_texture;
+    use crate::texture::texture_from_luma32f;
+    use crate::texture::texture_from_rgba32f;
+    use crate::utils::max_abs_diff;
+    use crate::utils::max_u8_diff;
+    use crate::utils::random_image;
+    use crate::utils::random_rgba32f;
+    use crate::utils::test_device;
+    use image::Rgba32FImage;
+
+    /// CPU 上的卷积，与 convolution.wgsl 的计算顺序相同
+    fn cpu_convolve(image: &Rgba32FImage, kernel: &Kernel, options: ConvolutionOptions) -> Rgba32FImage {
+        let (width, height) = image.dimensions();
+        let kernel = if options.normalize { kernel.normalized() } else { kernel.clone() };
+        let size = kernel.size() as i32;
+        let radius = size / 2;
+        Rgba32FImage::from_fn(width, height, |x, y| {
+            let mut sum = [options.bias; 4];
+            for ky in 0..size {
+                for kx in 0..size {
+                    let sx = options.edge.border_index(x as i32 + kx - radius, width as i32) as u32;
+                    let sy = options.edge.border_index(y as i32 + ky - radius, height as i32) as u32;
+                    let weight = kernel.weights()[(ky * size + kx) as usize];
+                    for (sum, &value) in sum.iter_mut().zip(image.get_pixel(sx, sy).0.iter()) {
+                        *sum += weight * value;
+                    }
+                }
+            }
+            if options.skip_alpha {
+                sum[3] = image.get_pixel(x, y)[3];
+            }
+            image::Rgba(sum)
+        })
+    }
+
+    #[test]
+    fn convolve_matches_cpu_on_float() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_rgba32f(37, 29, 11);
+        let texture = texture_from_rgba32f(&device, &queue, &input, wgpu::TextureFormat::Rgba32Float).unwrap();
+        let kernels = [
+            Kernel::sharpen(),
+            Kernel::emboss(),
+            Kernel::laplacian(),
+            Kernel::new(5, (0..25).map(|i| (i % 7) as f32 - 2.0).collect()).unwrap(),
+        ];
+        for kernel in &kernels {
+            for edge in [EdgeMode::Clamp, EdgeMode::Mirror, EdgeMode::Wrap] {
+                for (normalize, bias, skip_alpha) in [(false, 0.0, true), (true, 0.5, false)] {
+                    let options = ConvolutionOptions { normalize, bias, edge, skip_alpha };
+                    let output = convolve(&device, &queue, &texture, kernel, options).unwrap();
+                    let expected = cpu_convolve(&input, kernel, options);
+                    // 32 位浮点输出不截断，误差只来自累加顺序
+                    let diff = max_abs_diff(rgba32f_from_texture(&device, &queue, &output).unwrap().as_raw(), expected.as_raw());
+                    assert!(diff <= 1e-5, "{kernel:?} {options:?}: {diff}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn convolve_rgba8_clamps_and_rounds() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_image(40, 30, 12);
+        let texture = texture_from_image(&device, &queue, &input);
+        let input_f32 = Rgba32FImage::from_fn(40, 30, |x, y| image::Rgba(input.get_pixel(x, y).0.map(|v| v as f32 / 255.0)));
+        let options = ConvolutionOptions::default();
+        let output = image_from_texture(&device, &queue, &convolve(&device, &queue, &texture, &Kernel::sharpen(), options).unwrap()).unwrap();
+        let expected = cpu_convolve(&input_f32, &Kernel::sharpen(), options);
+        let expected = image::RgbaImage::from_fn(40, 30, |x, y| {
+            image::Rgba(expected.get_pixel(x, y).0.map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8))
+        });
+        // 只有最后的 8 位量化，舍入方式不同时差 1 级
+        let diff = max_u8_diff(&output, &expected);
+        assert!(diff <= 1, "{diff}");
+    }
+
+    #[test]
+    fn convolve_single_channel() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_rgba32f(20, 20, 13);
+        let luma = image::ImageBuffer::from_fn(20, 20, |x, y| image::Luma([input.get_pixel(x, y)[0]]));
+        let texture = texture_from_luma32f(&device, &queue, &luma);
+        let output = convolve(&device, &queue, &texture, &Kernel::laplacian(), ConvolutionOptions::default()).unwrap();
+        assert_eq!(output.format(), wgpu::TextureFormat::R32Float);
+
+        let gray = Rgba32FImage::from_fn(20, 20, |x, y| {
+            let v = luma.get_pixel(x, y)[0];
+            image::Rgba([v, v, v, 1.0])
+        });
+        let expected = cpu_convolve(&gray, &Kernel::laplacian(), ConvolutionOptions::default());
+        let diff = max_abs_diff(rgba32f_from_texture(&device, &queue, &output).unwrap().as_raw(), expected.as_raw());
+        assert!(diff <= 1e-5, "{diff}");
+    }
+
+    #[test]
+    fn kernel_validation_and_normalization() {
+        assert!(Kernel::new(2, vec![0.25; 4]).is_err());
+        assert!(Kernel::new(3, vec![1.0; 8]).is_err());
+        let normalized = Kernel::new(3, vec![2.0; 9]).unwrap().normalized();
+        assert!(normalized.weights().iter().all(|&w| (w - 1.0 / 9.0).abs() < 1e-7));
+        // 权重之和为 0 时保持不变
+        assert_eq!(Kernel::laplacian().normalized(), Kernel::laplacian());
+    }
+}
@@ -0,0 +1,410 @@
+#![allow(dead_code)]
+
+use anyhow::Ok;
+use anyhow::Result;
+use image::load_from_memory;
+use image::GrayImage;
+use image::ImageBuffer;
+use image::Luma;
+use image::RgbaImage;
+
+use crate::blur::box_blur;
+use crate::blur::EdgeMode;
+use crate::buffer::GpuBuffer;
+use crate::radix_sort::xorshift;
+use crate::texture::create_output_texture;
+use crate::texture::image_from_texture;
+use crate::texture::rgba32f_from_texture;
+use crate::texture::texture_from_image;
+use crate::texture::texture_from_luma32f;
+use crate::texture::with_storage_format;
+use crate::utils::compute_work_group_count;
+use crate::utils::create_bind_group;
+use crate::utils::create_pipeline;
+use crate::utils::create_shader;
+use crate::utils::dispatch;
+use crate::utils::request_device;
+
+/// 双边滤波的最大窗口半径，窗口半径取 2 倍空间 sigma
+pub const MAX_BILATERAL_RADIUS: u32 = 16;
+/// 非局部均值的最大搜索半径和块半径
+pub const MAX_SEARCH_RADIUS: u32 = 10;
+pub const MAX_PATCH_RADIUS: u32 = 3;
+
+/// 导向滤波中间结果的格式，乘积和方差需要浮点精度
+const GUIDED_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+
+/// 非局部均值的参数
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NlMeansConfig {
+    /// 滤波强度，与噪声的标准差(范围 0~1)相当
+    pub h: f32,
+    pub search_radius: u32,
+    pub patch_radius: u32,
+}
+
+impl Default for NlMeansConfig {
+    fn default() -> Self {
+        Self {
+            h: 0.1,
+            search_radius: 5,
+            patch_radius: 1,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct DenoiseParams {
+    radius: i32,
+    search_radius: i32,
+    patch_radius: i32,
+    single_channel: u32,
+    spatial_sigma: f32,
+    range_sigma: f32,
+    epsilon: f32,
+    h: f32,
+}
+
+fn denoise_shader(device: &wgpu::Device, format: wgpu::TextureFormat) -> Result<wgpu::ShaderModule> {
+    let source = format!("{}\n{}", include_str!("../shaders/border.wgsl"), include_str!("../shaders/denoise.wgsl"));
+    Ok(create_shader(device, "denoise_shader_module", &with_storage_format(&source, format)?))
+}
+
+/// 只读 input、写 output 的单步滤波
+fn run_filter(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, entry_point: &str, params: &DenoiseParams) -> Result<wgpu::Texture> {
+    let (width, height) = (input.width(), input.height());
+    let output = create_output_texture(device, width, height, input.format());
+
+    let shader = denoise_shader(device, input.format())?;
+    let pipeline = create_pipeline(device, &shader, entry_point);
+    let params = GpuBuffer::uniform(device, params);
+
+    let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+    let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = create_bind_group(
+        device,
+        &pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(&input_view)),
+            (1, wgpu::BindingResource::TextureView(&output_view)),
+            (2, params.as_entire_binding()),
+        ],
+    );
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let (x, y) = compute_work_group_count((width, height), (16, 16));
+    dispatch(&mut encoder, &pipeline, &bind_group, (x, y, 1));
+    queue.submit(Some(encoder.finish()));
+
+    Ok(output)
+}
+
+/// 双边滤波: 权重为空间距离和颜色距离两个高斯的乘积，颜色范围 0~1
+pub fn bilateral_filter(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, spatial_sigma: f32, range_sigma: f32) -> Result<wgpu::Texture> {
+    let radius = (2.0 * spatial_sigma).ceil() as u32;
+    anyhow::ensure!(
+        radius <= MAX_BILATERAL_RADIUS,
+        "spatial sigma {spatial_sigma} needs radius {radius}, the limit is {MAX_BILATERAL_RADIUS}"
+    );
+    let params = DenoiseParams {
+        radius: radius as i32,
+        spatial_sigma: spatial_sigma.max(1e-3),
+        range_sigma: range_sigma.max(1e-3),
+        ..Default::default()
+    };
+    run_filter(device, queue, input, "bilateral", &params)
+}
+
+/// 导向滤波(He et al.)，guide 为 None 时用输入自身引导。
+/// 彩色导向图取亮度作为引导值，窗口为 (2 * radius + 1)²，epsilon 越大越平滑。
+/// epsilon 必须大于 0，否则平坦区域的方差为 0，系数为 0/0
+pub fn guided_filter(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    input: &wgpu::Texture,
+    guide: Option<&wgpu::Texture>,
+    radius: u32,
+    epsilon: f32,
+) -> Result<wgpu::Texture> {
+    anyhow::ensure!(epsilon > 0.0, "guided filter epsilon must be positive, got {epsilon}");
+    let guide = guide.unwrap_or(input);
+    let (width, height) = (input.width(), input.height());
+    anyhow::ensure!(
+        guide.width() == width && guide.height() == height,
+        "guide is {}x{}, input is {width}x{height}",
+        guide.width(),
+        guide.height()
+    );
+
+    let shader = denoise_shader(device, input.format())?;
+    let params = GpuBuffer::uniform(
+        device,
+        &DenoiseParams {
+            single_channel: (guide.format() == wgpu::TextureFormat::R32Float) as u32,
+            epsilon,
+            ..Default::default()
+        },
+    );
+    let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+    let guide_view = guide.create_view(&wgpu::TextureViewDescriptor::default());
+    let (x, y) = compute_work_group_count((width, height), (16, 16));
+
+    // 两个输出目标的一步，返回 (first_target, second_target)
+    let run_step = |entry_point: &str, sources: Option<(&wgpu::Texture, &wgpu::Texture)>| -> (wgpu::Texture, wgpu::Texture) {
+        let first = create_output_texture(device, width, height, GUIDED_FORMAT);
+        let second = create_output_texture(device, width, height, GUIDED_FORMAT);
+        let first_view = first.create_view(&wgpu::TextureViewDescriptor::default());
+        let second_view = second.create_view(&wgpu::TextureViewDescriptor::default());
+        let pipeline = create_pipeline(device, &shader, entry_point);
+        let bind_group = match sources {
+            None => create_bind_group(
+                device,
+                &pipeline,
+                &[
+                    (0, wgpu::BindingResource::TextureView(&input_view)),
+                    (2, params.as_entire_binding()),
+                    (3, wgpu::BindingResource::TextureView(&guide_view)),
+                    (4, wgpu::BindingResource::TextureView(&first_view)),
+                    (5, wgpu::BindingResource::TextureView(&second_view)),
+                ],
+            ),
+            Some((first_source, second_source)) => create_bind_group(
+                device,
+                &pipeline,
+                &[
+                    (2, params.as_entire_binding()),
+                    (4, wgpu::BindingResource::TextureView(&first_view)),
+                    (5, wgpu::BindingResource::TextureView(&second_view)),
+                    (6, wgpu::BindingResource::TextureView(&first_source.create_view(&wgpu::TextureViewDescriptor::default()))),
+                    (7, wgpu::BindingResource::TextureView(&second_source.create_view(&wgpu::TextureViewDescriptor::default()))),
+                ],
+            ),
+        };
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        dispatch(&mut encoder, &pipeline, &bind_group, (x, y, 1));
+        queue.submit(Some(encoder.finish()));
+        (first, second)
+    };
+
+    // mean(p)、mean(I)、mean(I p)、mean(I²)
+    let (values, products) = run_step("guided_products", None);
+    let means = box_blur(device, queue, &values, radius, EdgeMode::Clamp)?;
+    let correlations = box_blur(device, queue, &products, radius, EdgeMode::Clamp)?;
+
+    // a、b 及其均值
+    let (a, b) = run_step("guided_coefficients", Some((&means, &correlations)));
+    let mean_a = box_blur(device, queue, &a, radius, EdgeMode::Clamp)?;
+    let mean_b = box_blur(device, queue, &b, radius, EdgeMode::Clamp)?;
+
+    let output = create_output_texture(device, width, height, input.format());
+    let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+    let mean_a_view = mean_a.create_view(&wgpu::TextureViewDescriptor::default());
+    let mean_b_view = mean_b.create_view(&wgpu::TextureViewDescriptor::default());
+    let pipeline = create_pipeline(device, &shader, "guided_output");
+    let bind_group = create_bind_group(
+        device,
+        &pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(&input_view)),
+            (1, wgpu::BindingResource::TextureView(&output_view)),
+            (2, params.as_entire_binding()),
+            (3, wgpu::BindingResource::TextureView(&guide_view)),
+            (6, wgpu::BindingResource::TextureView(&mean_a_view)),
+            (7, wgpu::BindingResource::TextureView(&mean_b_view)),
+        ],
+    );
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    dispatch(&mut encoder, &pipeline, &bind_group, (x, y, 1));
+    queue.submit(Some(encoder.finish()));
+
+    Ok(output)
+}
+
+/// 非局部均值去噪，计算量为 (2 * search_radius + 1)² (2 * patch_radius + 1)²，只适合小窗口
+pub fn non_local_means(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, config: &NlMeansConfig) -> Result<wgpu::Texture> {
+    anyhow::ensure!(
+        config.search_radius <= MAX_SEARCH_RADIUS && config.patch_radius <= MAX_PATCH_RADIUS,
+        "search radius {} or patch radius {} is too large",
+        config.search_radius,
+        config.patch_radius
+    );
+    let params = DenoiseParams {
+        search_radius: config.search_radius as i32,
+        patch_radius: config.patch_radius as i32,
+        h: config.h.max(1e-3),
+        ..Default::default()
+    };
+    run_filter(device, queue, input, "nl_means", &params)
+}
+
+/// 峰值为 1 的 PSNR(dB)
+pub fn psnr(a: &[f32], b: &[f32]) -> f64 {
+    let mse = a.iter().zip(b).map(|(&a, &b)| ((a - b) as f64).powi(2)).sum::<f64>() / a.len().max(1) as f64;
+    10.0 * (1.0 / mse.max(1e-12)).log10()
+}
+
+/// 标准正态分布的随机数(Box-Muller)
+fn gaussian_noise(seed: &mut u32) -> f32 {
+    let u1 = (xorshift(seed) as f32 + 1.0) / (u32::MAX as f32 + 2.0);
+    let u2 = xorshift(seed) as f32 / u32::MAX as f32;
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos()
+}
+
+/// 给 RGB 通道加标准差为 sigma(范围 0~1)的高斯噪声
+fn add_noise(image: &RgbaImage, sigma: f32, seed: &mut u32) -> RgbaImage {
+    let mut noisy = image.clone();
+    for pixel in noisy.pixels_mut() {
+        for c in 0..3 {
+            let value = pixel[c] as f32 / 255.0 + sigma * gaussian_noise(seed);
+            pixel[c] = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+    }
+    noisy
+}
+
+/// 双边滤波、导向滤波和非局部均值去噪
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+    let mut seed = 0x9e37_79b9;
+    let sigma = 0.08;
+
+    // 在干净的图像上加高斯噪声
+    let clean = load_from_memory(include_bytes!("../images/sushi.png"))?.to_rgba8();
+    let noisy = add_noise(&clean, sigma, &mut seed);
+    noisy.save("./outputs/sushi_noisy.png")?;
+
+    let noisy_texture = texture_from_image(&device, &queue, &noisy);
+    let results = [
+        ("bilateral", bilateral_filter(&device, &queue, &noisy_texture, 3.0, 0.2)?),
+        ("guided", guided_filter(&device, &queue, &noisy_texture, None, 4, 0.01)?),
+        ("nl_means", non_local_means(&device, &queue, &noisy_texture, &NlMeansConfig::default())?),
+    ];
+    for (name, texture) in results {
+        image_from_texture(&device, &queue, &texture)?.save(format!("./outputs/sushi_denoise_{name}.png"))?;
+    }
+
+    // 单通道 R32Float 输入
+    let clean_gray = load_from_memory(include_bytes!("../images/sushi.png"))?.to_luma32f();
+    let noisy_gray = ImageBuffer::<Luma<f32>, Vec<f32>>::from_fn(clean_gray.width(), clean_gray.height(), |x, y| {
+        Luma([clean_gray.get_pixel(x, y)[0] + sigma * gaussian_noise(&mut seed)])
+    });
+    let noisy_texture = texture_from_luma32f(&device, &queue, &noisy_gray);
+    let results = [
+        ("bilateral", bilateral_filter(&device, &queue, &noisy_texture, 3.0, 0.2)?),
+        ("guided", guided_filter(&device, &queue, &noisy_texture, None, 4, 0.01)?),
+        ("nl_means", non_local_means(&device, &queue, &noisy_texture, &NlMeansConfig::default())?),
+    ];
+    for (name, texture) in results {
+        let values = rgba32f_from_texture(&device, &queue, &texture)?;
+        let gray = GrayImage::from_fn(clean_gray.width(), clean_gray.height(), |x, y| {
+            Luma([(values.get_pixel(x, y)[0].clamp(0.0, 1.0) * 255.0).round() as u8])
+        });
+        gray.save(format!("./outputs/sushi_gray_denoise_{name}.png"))?;
+    }
+
+    // 真实的弱光照片
+    let capture = texture_from_image(&device, &queue, &load_from_memory(include_bytes!("../images/capture.jpg"))?.to_rgba8());
+    let bilateral = bilateral_filter(&device, &queue, &capture, 2.0, 0.1)?;
+    image_from_texture(&device, &queue, &bilateral)?.save("./outputs/capture_bilateral.png")?;
+    let guided = guided_filter(&device, &queue, &capture, None, 4, 0.005)?;
+    image_from_texture(&device, &queue, &guided)?.save("./outputs/capture_guided.png")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_device;
+
+    /// 去噪后 PSNR 至少提高的分贝数
+    const THRESHOLD: f64 = 3.0;
+    const SIGMA: f32 = 0.08;
+
+    /// RGBA8 图像的 RGB 通道，范围 0~1
+    fn rgb_values(image: &RgbaImage) -> Vec<f32> {
+        image.pixels().flat_map(|pixel| [0, 1, 2].map(|c| pixel[c] as f32 / 255.0)).collect()
+    }
+
+    /// 64x64 的合成图像: 平滑的渐变背景上有一个亮的圆和一个暗的矩形，既有平坦区域也有锐利的边缘。
+    /// 彩色导向滤波用亮度引导，所以边缘两侧的亮度要有明显差别
+    fn synthetic(x: u32, y: u32) -> [f32; 3] {
+        let (dx, dy) = (x as f32 - 22.0, y as f32 - 24.0);
+        if dx * dx + dy * dy < 12.0 * 12.0 {
+            [0.9, 0.8, 0.3]
+        } else if (38..58).contains(&x) && (30..54).contains(&y) {
+            [0.1, 0.15, 0.35]
+        } else {
+            [0.3 + 0.4 * x as f32 / 64.0, 0.4, 0.6 - 0.3 * y as f32 / 64.0]
+        }
+    }
+
+    fn filters(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture) -> [(&'static str, wgpu::Texture); 3] {
+        [
+            ("bilateral", bilateral_filter(device, queue, input, 3.0, 0.2).unwrap()),
+            ("guided", guided_filter(device, queue, input, None, 4, 0.01).unwrap()),
+            ("nl_means", non_local_means(device, queue, input, &NlMeansConfig::default()).unwrap()),
+        ]
+    }
+
+    #[test]
+    fn denoise_rgba_improves_psnr() {
+        let Some((device, queue)) = test_device() else { return };
+        let clean = RgbaImage::from_fn(64, 64, |x, y| {
+            let [r, g, b] = synthetic(x, y).map(|v| (v * 255.0).round() as u8);
+            image::Rgba([r, g, b, 255])
+        });
+        let noisy = add_noise(&clean, SIGMA, &mut 0x9e37_79b9);
+        let clean_values = rgb_values(&clean);
+        let noisy_psnr = psnr(&rgb_values(&noisy), &clean_values);
+
+        for (name, texture) in filters(&device, &queue, &texture_from_image(&device, &queue, &noisy)) {
+            let gain = psnr(&rgb_values(&image_from_texture(&device, &queue, &texture).unwrap()), &clean_values) - noisy_psnr;
+            assert!(gain >= THRESHOLD, "{name}: {gain:.2}dB");
+        }
+    }
+
+    #[test]
+    fn denoise_single_channel_improves_psnr() {
+        let Some((device, queue)) = test_device() else { return };
+        let clean = ImageBuffer::<Luma<f32>, Vec<f32>>::from_fn(64, 64, |x, y| {
+            let [r, g, b] = synthetic(x, y);
+            Luma([0.299 * r + 0.587 * g + 0.114 * b])
+        });
+        let mut seed = 0x1234_5678;
+        let noisy = ImageBuffer::<Luma<f32>, Vec<f32>>::from_fn(64, 64, |x, y| Luma([clean.get_pixel(x, y)[0] + SIGMA * gaussian_noise(&mut seed)]));
+        let noisy_psnr = psnr(noisy.as_raw(), clean.as_raw());
+
+        for (name, texture) in filters(&device, &queue, &texture_from_luma32f(&device, &queue, &noisy)) {
+            assert_eq!(texture.format(), wgpu::TextureFormat::R32Float, "{name}");
+            let values: Vec<f32> = rgba32f_from_texture(&device, &queue, &texture).unwrap().pixels().map(|pixel| pixel[0]).collect();
+            let gain = psnr(&values, clean.as_raw()) - noisy_psnr;
+            assert!(gain >= THRESHOLD, "{name}: {gain:.2}dB");
+        }
+    }
+
+    #[test]
+    fn guided_filter_keeps_constant_image() {
+        let Some((device, queue)) = test_device() else { return };
+        let constant = RgbaImage::from_pixel(24, 20, image::Rgba([90, 140, 200, 255]));
+        let texture = texture_from_image(&device, &queue, &constant);
+        // 平坦区域的方差为 0，很小的 epsilon 也不能产生 NaN
+        for epsilon in [1e-4, 1e-8] {
+            let output = guided_filter(&device, &queue, &texture, None, 3, epsilon).unwrap();
+            let values = rgba32f_from_texture(&device, &queue, &output).unwrap();
+            assert!(values.as_raw().iter().all(|v| v.is_finite()), "{epsilon}");
+            assert!(image_from_texture(&device, &queue, &output).unwrap() == constant, "{epsilon}");
+        }
+        for epsilon in [0.0, -0.01, f32::NAN] {
+            assert!(guided_filter(&device, &queue, &texture, None, 3, epsilon).is_err(), "{epsilon}");
+        }
+    }
+
+    #[test]
+    fn psnr_of_known_error() {
+        // 均方误差 0.01 对应 20dB
+        assert!((psnr(&[0.1, 0.3], &[0.2, 0.2]) - 20.0).abs() < 1e-5);
+        assert!(psnr(&[0.5], &[0.5]) >= 100.0);
+    }
+}
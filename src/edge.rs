@@ -0,0 +1,470 @@
+#![allow(dead_code)]
+
+use anyhow::Ok;
+use anyhow::Result;
+use image::load_from_memory;
+
+use crate::blur::gaussian_blur;
+use crate::blur::EdgeMode;
+use crate::buffer::GpuBuffer;
+use crate::texture::create_output_texture;
+use crate::texture::image_from_texture;
+use crate::texture::texture_from_image;
+use crate::utils::compute_work_group_count;
+use crate::utils::create_bind_group;
+use crate::utils::create_pipeline;
+use crate::utils::create_shader;
+use crate::utils::dispatch;
+use crate::utils::request_device;
+
+/// 梯度纹理的格式，每个像素为 (gx, gy, 幅值, 方向(弧度))
+pub const GRADIENT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+
+/// 每次提交执行的滞后阈值迭代次数，之后读回 changed 判断是否收敛
+const HYSTERESIS_ITERATIONS_PER_SUBMIT: u32 = 8;
+
+/// 梯度算子，核已归一化，单位阶跃边缘的幅值为 1
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GradientOperator {
+    #[default]
+    Sobel = 0,
+    /// 旋转对称性更好
+    Scharr = 1,
+}
+
+/// Canny 参数，阈值以梯度幅值(亮度范围 0~1)为单位
+#[derive(Clone, Copy, Debug)]
+pub struct CannyConfig {
+    /// 预先做高斯平滑的 sigma，<= 0 时不平滑
+    pub sigma: f32,
+    pub low_threshold: f32,
+    pub high_threshold: f32,
+    pub operator: GradientOperator,
+}
+
+impl Default for CannyConfig {
+    fn default() -> Self {
+        Self {
+            sigma: 1.4,
+            low_threshold: 0.05,
+            high_threshold: 0.15,
+            operator: GradientOperator::Sobel,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct EdgeParams {
+    width: u32,
+    height: u32,
+    kernel: u32,
+    edge_mode: u32,
+    low_threshold: f32,
+    high_threshold: f32,
+    _padding: [u32; 2],
+}
+
+fn edge_shader(device: &wgpu::Device) -> wgpu::ShaderModule {
+    let source = format!("{}\n{}", include_str!("../shaders/border.wgsl"), include_str!("../shaders/edge.wgsl"));
+    create_shader(device, "edge_shader_module", &source)
+}
+
+fn params_buffer(device: &wgpu::Device, texture: &wgpu::Texture, operator: GradientOperator, low: f32, high: f32) -> GpuBuffer<EdgeParams> {
+    GpuBuffer::uniform(
+        device,
+        &EdgeParams {
+            width: texture.width(),
+            height: texture.height(),
+            kernel: operator as u32,
+            edge_mode: EdgeMode::Clamp as u32,
+            low_threshold: low,
+            high_threshold: high,
+            _padding: [0; 2],
+        },
+    )
+}
+
+/// 把计算梯度的命令记录到 encoder 中
+fn encode_gradient(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    shader: &wgpu::ShaderModule,
+    input: &wgpu::Texture,
+    params: &GpuBuffer<EdgeParams>,
+) -> wgpu::Texture {
+    let (width, height) = (input.width(), input.height());
+    let gradient = create_output_texture(device, width, height, GRADIENT_FORMAT);
+    let pipeline = create_pipeline(device, shader, "compute_gradient");
+
+    let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+    let gradient_view = gradient.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = create_bind_group(
+        device,
+        &pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(&input_view)),
+            (1, wgpu::BindingResource::TextureView(&gradient_view)),
+            (4, params.as_entire_binding()),
+        ],
+    );
+
+    let (x, y) = compute_work_group_count((width, height), (16, 16));
+    dispatch(encoder, &pipeline, &bind_group, (x, y, 1));
+    gradient
+}
+
+/// 计算亮度的梯度，返回 Rgba32Float 纹理: (gx, gy, 幅值, 方向(弧度))
+pub fn gradient(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, operator: GradientOperator) -> Result<wgpu::Texture> {
+    let shader = edge_shader(device);
+    let params = params_buffer(device, input, operator, 0.0, 0.0);
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let gradient = encode_gradient(device, &mut encoder, &shader, input, &params);
+    queue.submit(Some(encoder.finish()));
+
+    Ok(gradient)
+}
+
+/// 梯度幅值不小于 threshold 的像素为白色，其余为黑色
+pub fn gradient_mask(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    input: &wgpu::Texture,
+    operator: GradientOperator,
+    threshold: f32,
+) -> Result<wgpu::Texture> {
+    let (width, height) = (input.width(), input.height());
+    let shader = edge_shader(device);
+    let params = params_buffer(device, input, operator, threshold, threshold);
+    let mask = create_output_texture(device, width, height, wgpu::TextureFormat::Rgba8Unorm);
+    let pipeline = create_pipeline(device, &shader, "threshold_magnitude");
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let gradient = encode_gradient(device, &mut encoder, &shader, input, &params);
+
+    let gradient_view = gradient.create_view(&wgpu::TextureViewDescriptor::default());
+    let mask_view = mask.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = create_bind_group(
+        device,
+        &pipeline,
+        &[
+            (2, wgpu::BindingResource::TextureView(&gradient_view)),
+            (3, wgpu::BindingResource::TextureView(&mask_view)),
+            (4, params.as_entire_binding()),
+        ],
+    );
+    let (x, y) = compute_work_group_count((width, height), (16, 16));
+    dispatch(&mut encoder, &pipeline, &bind_group, (x, y, 1));
+    queue.submit(Some(encoder.finish()));
+
+    Ok(mask)
+}
+
+/// Canny 边缘检测: 高斯平滑、梯度、非极大值抑制、双阈值和滞后阈值，返回二值图
+pub fn canny(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, config: CannyConfig) -> Result<wgpu::Texture> {
+    anyhow::ensure!(
+        config.low_threshold <= config.high_threshold,
+        "low threshold {} is greater than high threshold {}",
+        config.low_threshold,
+        config.high_threshold
+    );
+    let (width, height) = (input.width(), input.height());
+
+    let smoothed = if config.sigma > 0.0 {
+        Some(gaussian_blur(device, queue, input, config.sigma, None, EdgeMode::Mirror)?)
+    } else {
+        None
+    };
+    let source = smoothed.as_ref().unwrap_or(input);
+
+    let shader = edge_shader(device);
+    let params = params_buffer(device, input, config.operator, config.low_threshold, config.high_threshold);
+    let states = GpuBuffer::<u32>::storage_zeroed(device, (width * height) as usize);
+    let changed = GpuBuffer::<u32>::storage_zeroed(device, 1);
+    let mask = create_output_texture(device, width, height, wgpu::TextureFormat::Rgba8Unorm);
+
+    let suppress_pipeline = create_pipeline(device, &shader, "suppress");
+    let hysteresis_pipeline = create_pipeline(device, &shader, "hysteresis");
+    let finalize_pipeline = create_pipeline(device, &shader, "finalize");
+    let (x, y) = compute_work_group_count((width, height), (16, 16));
+
+    // 梯度、非极大值抑制和双阈值
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let gradient = encode_gradient(device, &mut encoder, &shader, source, &params);
+    let gradient_view = gradient.create_view(&wgpu::TextureViewDescriptor::default());
+    let suppress_bind_group = create_bind_group(
+        device,
+        &suppress_pipeline,
+        &[
+            (2, wgpu::BindingResource::TextureView(&gradient_view)),
+            (4, params.as_entire_binding()),
+            (5, states.as_entire_binding()),
+        ],
+    );
+    dispatch(&mut encoder, &suppress_pipeline, &suppress_bind_group, (x, y, 1));
+    queue.submit(Some(encoder.finish()));
+
+    // 滞后阈值: 强边缘沿着弱边缘逐步扩散，直到一轮中没有任何变化
+    let hysteresis_bind_group = create_bind_group(
+        device,
+        &hysteresis_pipeline,
+        &[
+            (4, params.as_entire_binding()),
+            (5, states.as_entire_binding()),
+            (6, changed.as_entire_binding()),
+        ],
+    );
+    loop {
+        changed.write(queue, &[0])?;
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        for _ in 0..HYSTERESIS_ITERATIONS_PER_SUBMIT {
+            dispatch(&mut encoder, &hysteresis_pipeline, &hysteresis_bind_group, (x, y, 1));
+        }
+        queue.submit(Some(encoder.finish()));
+        if changed.read_blocking(device, queue)?[0] == 0 {
+            break;
+        }
+    }
+
+    let mask_view = mask.create_view(&wgpu::TextureViewDescriptor::default());
+    let finalize_bind_group = create_bind_group(
+        device,
+        &finalize_pipeline,
+        &[
+            (3, wgpu::BindingResource::TextureView(&mask_view)),
+            (4, params.as_entire_binding()),
+            (5, states.as_entire_binding()),
+        ],
+    );
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    dispatch(&mut encoder, &finalize_pipeline, &finalize_bind_group, (x, y, 1));
+    queue.submit(Some(encoder.finish()));
+
+    Ok(mask)
+}
+
+/// Sobel、Scharr 和 Canny 边缘检测
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+
+    let input_image = load_from_memory(include_bytes!("../images/capture.jpg"))?.to_rgba8();
+    let input_texture = texture_from_image(&device, &queue, &input_image);
+
+    let sobel_mask = gradient_mask(&device, &queue, &input_texture, GradientOperator::Sobel, 0.1)?;
+    image_from_texture(&device, &queue, &sobel_mask)?.save("./outputs/capture_sobel.png")?;
+
+    let scharr_mask = gradient_mask(&device, &queue, &input_texture, GradientOperator::Scharr, 0.1)?;
+    image_from_texture(&device, &queue, &scharr_mask)?.save("./outputs/capture_scharr.png")?;
+
+    let canny_mask = canny(&device, &queue, &input_texture, CannyConfig::default())?;
+    image_from_texture(&device, &queue, &canny_mask)?.save("./outputs/capture_canny.png")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::rgba32f_from_texture;
+    use crate::texture::texture_from_rgba32f;
+    use crate::utils::random_rgba32f;
+    use crate::utils::test_device;
+    use image::Rgba32FImage;
+    use std::collections::VecDeque;
+
+    /// CPU 上的梯度，返回每个像素的 (gx, gy)
+    fn cpu_gradient(image: &Rgba32FImage, operator: GradientOperator) -> Vec<(f32, f32)> {
+        let (width, height) = (image.width() as i32, image.height() as i32);
+        let luminance = |x: i32, y: i32| {
+            let pixel = image.get_pixel(x.clamp(0, width - 1) as u32, y.clamp(0, height - 1) as u32);
+            0.299 * pixel[0] + 0.587 * pixel[1] + 0.114 * pixel[2]
+        };
+        let (side, center) = match operator {
+            GradientOperator::Sobel => (1.0 / 4.0, 2.0 / 4.0),
+            GradientOperator::Scharr => (3.0 / 16.0, 10.0 / 16.0),
+        };
+        let mut gradients = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let gx = side * (luminance(x + 1, y - 1) - luminance(x - 1, y - 1))
+                    + center * (luminance(x + 1, y) - luminance(x - 1, y))
+                    + side * (luminance(x + 1, y + 1) - luminance(x - 1, y + 1));
+                let gy = side * (luminance(x - 1, y + 1) - luminance(x - 1, y - 1))
+                    + center * (luminance(x, y + 1) - luminance(x, y - 1))
+                    + side * (luminance(x + 1, y + 1) - luminance(x + 1, y - 1));
+                gradients.push((gx, gy));
+            }
+        }
+        gradients
+    }
+
+    /// CPU 上的非极大值抑制、双阈值和滞后阈值，输入为 GPU 计算的梯度纹理，
+    /// 这样两边比较的是完全相同的幅值，结果应该逐像素一致
+    fn cpu_canny_from_gradient(gradient: &Rgba32FImage, low: f32, high: f32) -> Vec<bool> {
+        let (width, height) = (gradient.width() as i32, gradient.height() as i32);
+        let magnitude = |x: i32, y: i32| {
+            if x < 0 || y < 0 || x >= width || y >= height {
+                0.0
+            } else {
+                gradient.get_pixel(x as u32, y as u32)[2]
+            }
+        };
+        let mut states = vec![0u8; (width * height) as usize];
+        for y in 0..height {
+            for x in 0..width {
+                let pixel = gradient.get_pixel(x as u32, y as u32);
+                let mut angle = pixel[3].to_degrees();
+                if angle < 0.0 {
+                    angle += 180.0;
+                }
+                let (dx, dy) = if (22.5..67.5).contains(&angle) {
+                    (1, 1)
+                } else if (67.5..112.5).contains(&angle) {
+                    (0, 1)
+                } else if (112.5..157.5).contains(&angle) {
+                    (-1, 1)
+                } else {
+                    (1, 0)
+                };
+                let m = pixel[2];
+                if m >= magnitude(x + dx, y + dy) && m > magnitude(x - dx, y - dy) {
+                    states[(y * width + x) as usize] = if m >= high { 2 } else if m >= low { 1 } else { 0 };
+                }
+            }
+        }
+
+        // 从强边缘出发做 8 邻域的广度优先搜索
+        let mut queue: VecDeque<i32> = (0..width * height).filter(|&i| states[i as usize] == 2).collect();
+        while let Some(index) = queue.pop_front() {
+            let (x, y) = (index % width, index / width);
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let (nx, ny) = (x + dx, y + dy);
+                    if nx >= 0 && ny >= 0 && nx < width && ny < height && states[(ny * width + nx) as usize] == 1 {
+                        states[(ny * width + nx) as usize] = 2;
+                        queue.push_back(ny * width + nx);
+                    }
+                }
+            }
+        }
+        states.iter().map(|&state| state == 2).collect()
+    }
+
+    /// 背景上一条蛇形的低对比度带(只产生弱边缘)，起点处叠加一个高对比度方块(强边缘)，
+    /// 右下角另有一个孤立的低对比度方块
+    fn snake_image() -> Rgba32FImage {
+        Rgba32FImage::from_fn(96, 96, |x, y| {
+            let bar = (8..88).contains(&y) && (y - 8) % 8 < 3 && (8..88).contains(&x);
+            let row = (y.saturating_sub(8)) / 8;
+            let connector = (8..88).contains(&y) && (y - 8) % 8 >= 3 && if row % 2 == 0 { (85..88).contains(&x) } else { (8..11).contains(&x) };
+            let strong = (4..14).contains(&x) && (4..14).contains(&y);
+            let isolated = (91..95).contains(&x) && (91..95).contains(&y);
+            let value = if strong {
+                0.9
+            } else if bar || connector || isolated {
+                0.4
+            } else {
+                0.3
+            };
+            image::Rgba([value, value, value, 1.0])
+        })
+    }
+
+    fn mask_pixels(device: &wgpu::Device, queue: &wgpu::Queue, mask: &wgpu::Texture) -> Vec<bool> {
+        image_from_texture(device, queue, mask).unwrap().pixels().map(|pixel| pixel[0] == 255).collect()
+    }
+
+    #[test]
+    fn gradient_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_rgba32f(45, 33, 21);
+        let texture = texture_from_rgba32f(&device, &queue, &input, wgpu::TextureFormat::Rgba32Float).unwrap();
+        for operator in [GradientOperator::Sobel, GradientOperator::Scharr] {
+            let output = rgba32f_from_texture(&device, &queue, &gradient(&device, &queue, &texture, operator).unwrap()).unwrap();
+            for (pixel, (gx, gy)) in output.pixels().zip(cpu_gradient(&input, operator)) {
+                // 分量和幅值的误差不超过 1e-5，幅值足够大时方向的误差不超过 1e-3 弧度
+                assert!((pixel[0] - gx).abs() <= 1e-5 && (pixel[1] - gy).abs() <= 1e-5, "{operator:?} {pixel:?} vs {gx} {gy}");
+                let magnitude = (gx * gx + gy * gy).sqrt();
+                assert!((pixel[2] - magnitude).abs() <= 1e-5, "{operator:?} {} vs {magnitude}", pixel[2]);
+                if magnitude > 1e-2 {
+                    let difference = (pixel[3] - gy.atan2(gx)).rem_euclid(std::f32::consts::TAU);
+                    assert!(difference.min(std::f32::consts::TAU - difference) <= 1e-3, "{operator:?} {} vs {}", pixel[3], gy.atan2(gx));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unit_step_has_unit_magnitude() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = Rgba32FImage::from_fn(16, 16, |x, _| if x < 8 { image::Rgba([0.0, 0.0, 0.0, 1.0]) } else { image::Rgba([1.0; 4]) });
+        let texture = texture_from_rgba32f(&device, &queue, &input, wgpu::TextureFormat::Rgba32Float).unwrap();
+        for operator in [GradientOperator::Sobel, GradientOperator::Scharr] {
+            let output = rgba32f_from_texture(&device, &queue, &gradient(&device, &queue, &texture, operator).unwrap()).unwrap();
+            // 中心差分: 阶跃两侧的像素幅值都是 1
+            assert!((output.get_pixel(7, 8)[2] - 1.0).abs() <= 1e-5);
+            assert!((output.get_pixel(8, 8)[2] - 1.0).abs() <= 1e-5);
+            assert_eq!(output.get_pixel(3, 8)[2], 0.0);
+        }
+    }
+
+    #[test]
+    fn gradient_mask_thresholds_magnitude() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_rgba32f(40, 30, 22);
+        let texture = texture_from_rgba32f(&device, &queue, &input, wgpu::TextureFormat::Rgba32Float).unwrap();
+        let threshold = 0.2;
+        let mask = mask_pixels(&device, &queue, &gradient_mask(&device, &queue, &texture, GradientOperator::Sobel, threshold).unwrap());
+        for (&edge, (gx, gy)) in mask.iter().zip(cpu_gradient(&input, GradientOperator::Sobel)) {
+            let magnitude = (gx * gx + gy * gy).sqrt();
+            // 与阈值相差不到 1e-5 的像素两种结果都可以
+            if (magnitude - threshold).abs() > 1e-5 {
+                assert_eq!(edge, magnitude >= threshold);
+            }
+        }
+    }
+
+    #[test]
+    fn canny_matches_cpu_reference() {
+        let Some((device, queue)) = test_device() else { return };
+        let inputs = [snake_image(), random_rgba32f(70, 50, 23)];
+        for input in &inputs {
+            let texture = texture_from_rgba32f(&device, &queue, input, wgpu::TextureFormat::Rgba32Float).unwrap();
+            for config in [
+                CannyConfig { sigma: 0.0, ..Default::default() },
+                CannyConfig { operator: GradientOperator::Scharr, ..Default::default() },
+            ] {
+                let mask = mask_pixels(&device, &queue, &canny(&device, &queue, &texture, config).unwrap());
+
+                // 用同样的步骤在 GPU 上得到梯度，再在 CPU 上完成后续步骤
+                let smoothed = gaussian_blur(&device, &queue, &texture, config.sigma, None, EdgeMode::Mirror).unwrap();
+                let source = if config.sigma > 0.0 { &smoothed } else { &texture };
+                let gradient = rgba32f_from_texture(&device, &queue, &gradient(&device, &queue, source, config.operator).unwrap()).unwrap();
+                let expected = cpu_canny_from_gradient(&gradient, config.low_threshold, config.high_threshold);
+                assert!(mask == expected, "{config:?}: {} pixels differ", mask.iter().zip(&expected).filter(|(a, b)| a != b).count());
+            }
+        }
+    }
+
+    #[test]
+    fn canny_hysteresis_follows_long_weak_edges() {
+        let Some((device, queue)) = test_device() else { return };
+        let texture = texture_from_rgba32f(&device, &queue, &snake_image(), wgpu::TextureFormat::Rgba32Float).unwrap();
+        let config = CannyConfig { sigma: 0.0, ..Default::default() };
+        let mask = mask_pixels(&device, &queue, &canny(&device, &queue, &texture, config).unwrap());
+        let edge_near = |x0: u32, x1: u32, y0: u32, y1: u32| (y0..y1).any(|y| (x0..x1).any(|x| mask[(y * 96 + x) as usize]));
+        // 蛇形带的末端离强边缘几百个像素，需要多轮滞后迭代才能到达
+        assert!(edge_near(40, 50, 78, 84));
+        // 孤立的弱边缘不应该保留
+        assert!(!edge_near(88, 96, 88, 96));
+    }
+
+    #[test]
+    fn canny_rejects_inverted_thresholds() {
+        let Some((device, queue)) = test_device() else { return };
+        let texture = texture_from_rgba32f(&device, &queue, &snake_image(), wgpu::TextureFormat::Rgba32Float).unwrap();
+        let config = CannyConfig { low_threshold: 0.3, high_threshold: 0.1, ..Default::default() };
+        assert!(canny(&device, &queue, &texture, config).is_err());
+    }
+}
@@ -4,8 +4,74 @@ use pollster::FutureExt;
 use wgpu::MemoryHints;
 use wgpu::PipelineCompilationOptions;
 
+use crate::buffer::GpuBuffer;
+use crate::texture::create_output_texture;
+use crate::texture::with_storage_format;
+use crate::utils::compute_work_group_count;
+use crate::utils::create_bind_group;
+use crate::utils::create_pipeline;
+use crate::utils::create_shader;
+use crate::utils::dispatch;
 use crate::utils::padded_bytes_per_row;
 
+/// 灰度算法
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GrayscaleMode {
+    /// BT.601 加权，直接作用于 sRGB 编码值(grayscale.wgsl 的做法)
+    #[default]
+    Bt601 = 0,
+    /// BT.709 相对亮度，在线性光中计算后重新编码为 sRGB
+    Bt709 = 1,
+    /// CIE L*，感知均匀的明度
+    CieLightness = 2,
+    /// 各通道平均
+    Average = 3,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GrayscaleParams {
+    mode: u32,
+    _padding: [u32; 3],
+}
+
+/// 按 mode 把 sRGB 编码的纹理转为灰度，输出格式与输入相同
+#[allow(dead_code)]
+pub fn grayscale_texture(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, mode: GrayscaleMode) -> Result<wgpu::Texture> {
+    let (width, height) = (input.width(), input.height());
+    let output = create_output_texture(device, width, height, input.format());
+
+    let source = format!("{}\n{}", include_str!("../shaders/srgb.wgsl"), include_str!("../shaders/grayscale_mode.wgsl"));
+    let shader = create_shader(device, "grayscale_mode_shader_module", &with_storage_format(&source, input.format())?);
+    let pipeline = create_pipeline(device, &shader, "grayscale");
+    let params = GpuBuffer::uniform(
+        device,
+        &GrayscaleParams {
+            mode: mode as u32,
+            _padding: [0; 3],
+        },
+    );
+
+    let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+    let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = create_bind_group(
+        device,
+        &pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(&input_view)),
+            (1, wgpu::BindingResource::TextureView(&output_view)),
+            (2, params.as_entire_binding()),
+        ],
+    );
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let (x, y) = compute_work_group_count((width, height), (16, 16));
+    dispatch(&mut encoder, &pipeline, &bind_group, (x, y, 1));
+    queue.submit(Some(encoder.finish()));
+
+    Ok(output)
+}
+
 #[allow(dead_code)]
 pub fn grayscale() -> Result<()>{
     let instance = wgpu::Instance::default();
@@ -175,21 +241,50 @@ pub fn grayscale() -> Result<()>{
     Ok(())
 }
 
-/// Compute the amount of work groups to be dispatched for an image, based on the work group size.
-/// Chances are, the group will not match perfectly, like an image of width 100, for a workgroup size of 32.
-/// To make sure the that the whole 100 pixels are visited, then we would need a count of 4, as 4 * 32 = 128,
-/// which is bigger than 100. A count of 3 would be too little, as it means 96, so four columns (or, 100 - 96) would be ignored.
-///
-/// # Arguments
-///
-/// * `(width, height)` - The dimension of the image we are working on.
-/// * `(workgroup_width, workgroup_height)` - The width and height dimensions of the compute workgroup.
-fn compute_work_group_count(
-    (width, height): (u32, u32),
-    (workgroup_width, workgroup_height): (u32, u32),
-) -> (u32, u32) {
-    let x = (width + workgroup_width - 1) / workgroup_width;
-    let y = (height + workgroup_height - 1) / workgroup_height;
-
-    (x, y)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::image_from_texture;
+    use crate::texture::texture_from_image;
+    use crate::utils::test_device;
+    use image::RgbaImage;
+
+    fn cpu_gray(pixel: [u8; 4], mode: GrayscaleMode) -> f64 {
+        let decode = |v: u8| {
+            let c = v as f64 / 255.0;
+            if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+        };
+        let [r, g, b, _] = pixel;
+        let luminance = 0.2126 * decode(r) + 0.7152 * decode(g) + 0.0722 * decode(b);
+        match mode {
+            GrayscaleMode::Bt601 => (0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64) / 255.0,
+            GrayscaleMode::Bt709 => {
+                if luminance <= 0.0031308 { luminance * 12.92 } else { 1.055 * luminance.powf(1.0 / 2.4) - 0.055 }
+            }
+            GrayscaleMode::CieLightness => {
+                let lightness = if luminance <= 216.0 / 24389.0 { luminance * 24389.0 / 27.0 } else { 116.0 * luminance.cbrt() - 16.0 };
+                lightness / 100.0
+            }
+            GrayscaleMode::Average => (r as f64 + g as f64 + b as f64) / 3.0 / 255.0,
+        }
+    }
+
+    #[test]
+    fn grayscale_modes_match_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = RgbaImage::from_fn(64, 48, |x, y| image::Rgba([(x * 4) as u8, (y * 5) as u8, ((x * y) % 256) as u8, (x + y) as u8]));
+        let texture = texture_from_image(&device, &queue, &input);
+
+        for mode in [GrayscaleMode::Bt601, GrayscaleMode::Bt709, GrayscaleMode::CieLightness, GrayscaleMode::Average] {
+            let output = image_from_texture(&device, &queue, &grayscale_texture(&device, &queue, &texture, mode).unwrap()).unwrap();
+            // 8 位输出的舍入方式不同，误差不超过 1 级
+            for (input, output) in input.pixels().zip(output.pixels()) {
+                let expected = (cpu_gray(input.0, mode).clamp(0.0, 1.0) * 255.0).round() as u8;
+                assert!(output[0].abs_diff(expected) <= 1, "{mode:?} {input:?}: {} vs {expected}", output[0]);
+                assert_eq!(output[0], output[1]);
+                assert_eq!(output[0], output[2]);
+                assert_eq!(output[3], input[3]);
+            }
+        }
+    }
 }
@@ -0,0 +1,153 @@
+#![allow(dead_code)]
+
+use anyhow::Ok;
+use anyhow::Result;
+use image::Rgba;
+use image::Rgba32FImage;
+
+use crate::blur::gaussian_blur;
+use crate::blur::EdgeMode;
+use crate::histogram::equalize;
+use crate::resize::resize;
+use crate::resize::ResizeFilter;
+use crate::texture::rgba16_from_texture;
+use crate::texture::rgba32f_from_texture;
+use crate::texture::texture_from_dynamic;
+use crate::texture::texture_from_rgba32f;
+use crate::texture::Rgba16Image;
+use crate::utils::request_device;
+
+/// 高位深和浮点图像
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+
+    // 16 位的平滑渐变，8 位纹理只能保留 256 级
+    let (width, height) = (1024, 256);
+    let gradient = Rgba16Image::from_fn(width, height, |x, y| {
+        let value = ((x * 64 + y / 4) as f32 / (width * 64) as f32 * 65535.0) as u16;
+        Rgba([value, 65535 - value, value / 2, 65535])
+    });
+    gradient.save("./outputs/gradient16.png")?;
+    gradient.save("./outputs/gradient16.tiff")?;
+
+    // 从文件读回，按位深上传为 Rgba32Float(可以无损保存 16 位)
+    let texture = texture_from_dynamic(&device, &queue, &image::open("./outputs/gradient16.tiff")?, wgpu::TextureFormat::Rgba32Float)?;
+    let blurred = rgba16_from_texture(&device, &queue, &gaussian_blur(&device, &queue, &texture, 3.0, None, EdgeMode::Clamp)?)?;
+    blurred.save("./outputs/gradient16_blur.png")?;
+
+    // Rgba16Float 占用一半的显存，有 11 位有效精度
+    let half = texture_from_dynamic(&device, &queue, &image::open("./outputs/gradient16.png")?, wgpu::TextureFormat::Rgba16Float)?;
+    let resized = resize(&device, &queue, &half, width / 3, height / 3, ResizeFilter::Lanczos3)?;
+    rgba16_from_texture(&device, &queue, &resized)?.save("./outputs/gradient16_resize.tiff")?;
+    // 均衡化按 256 级映射亮度
+    let equalized = rgba16_from_texture(&device, &queue, &equalize(&device, &queue, &texture)?)?;
+    equalized.save("./outputs/gradient16_equalize.png")?;
+
+    // 超出 0~1 的浮点数据(如 HDR 辐射度)原样保存到 EXR 和浮点 TIFF
+    let radiance = Rgba32FImage::from_fn(width, height, |x, y| {
+        let value = 16.0 * (x as f32 / width as f32).powi(2) + y as f32 / height as f32;
+        Rgba([value, value * 0.5, value * 0.25, 1.0])
+    });
+    let texture = texture_from_rgba32f(&device, &queue, &radiance, wgpu::TextureFormat::Rgba32Float)?;
+    let blurred = rgba32f_from_texture(&device, &queue, &gaussian_blur(&device, &queue, &texture, 3.0, None, EdgeMode::Clamp)?)?;
+    blurred.save("./outputs/radiance_blur.exr")?;
+    blurred.save("./outputs/radiance_blur.tiff")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::random_rgba32f;
+    use crate::utils::test_device;
+
+    /// 不同灰度级的数量，用来判断结果是否还保留 8 位以上的精度
+    fn distinct_levels(image: &Rgba16Image) -> usize {
+        let mut levels: Vec<u16> = image.pixels().map(|pixel| pixel[0]).collect();
+        levels.sort_unstable();
+        levels.dedup();
+        levels.len()
+    }
+
+    fn gradient(width: u32, height: u32) -> Rgba16Image {
+        Rgba16Image::from_fn(width, height, |x, y| {
+            let value = ((x * height + y) as f32 / (width * height) as f32 * 65535.0) as u16;
+            Rgba([value, 65535 - value, value / 2, 65535])
+        })
+    }
+
+    #[test]
+    fn sixteen_bit_files_round_trip() {
+        let Some((device, queue)) = test_device() else { return };
+        let gradient = gradient(300, 40);
+        let directory = std::env::temp_dir().join(format!("high_depth_test_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        for name in ["gradient16.png", "gradient16.tiff"] {
+            let path = directory.join(name);
+            gradient.save(&path).unwrap();
+            let loaded = image::open(&path).unwrap();
+            // Rgba32Float 的 24 位尾数可以精确表示 65536 级，往返无损
+            let texture = texture_from_dynamic(&device, &queue, &loaded, wgpu::TextureFormat::Rgba32Float).unwrap();
+            assert_eq!(texture.format(), wgpu::TextureFormat::Rgba32Float);
+            assert!(rgba16_from_texture(&device, &queue, &texture).unwrap() == gradient, "{name}");
+
+            // Rgba16Float 在 0.5~1 之间的间隔是 2^-11，往返误差不超过半个间隔，即 16 级
+            let texture = texture_from_dynamic(&device, &queue, &loaded, wgpu::TextureFormat::Rgba16Float).unwrap();
+            assert_eq!(texture.format(), wgpu::TextureFormat::Rgba16Float);
+            let round_trip = rgba16_from_texture(&device, &queue, &texture).unwrap();
+            let diff = round_trip.as_raw().iter().zip(gradient.as_raw()).map(|(&a, &b)| a.abs_diff(b)).max().unwrap();
+            assert!(diff <= 16, "{name}: {diff}");
+        }
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        // 8 位图像仍然使用 Rgba8Unorm
+        let rgba8 = image::DynamicImage::ImageRgba8(image::RgbaImage::new(4, 4));
+        let texture = texture_from_dynamic(&device, &queue, &rgba8, wgpu::TextureFormat::Rgba16Float).unwrap();
+        assert_eq!(texture.format(), wgpu::TextureFormat::Rgba8Unorm);
+        assert!(texture_from_dynamic(&device, &queue, &image::DynamicImage::ImageRgba16(gradient), wgpu::TextureFormat::Rgba8Unorm).is_err());
+    }
+
+    #[test]
+    fn float_textures_keep_values() {
+        let Some((device, queue)) = test_device() else { return };
+        // 超出 0~1 的值
+        let input = Rgba32FImage::from_fn(33, 17, |x, y| {
+            let pixel = random_rgba32f(33, 17, 81).get_pixel(x, y).0;
+            Rgba(pixel.map(|v| v * 40.0 - 8.0))
+        });
+        let texture = texture_from_rgba32f(&device, &queue, &input, wgpu::TextureFormat::Rgba32Float).unwrap();
+        assert!(rgba32f_from_texture(&device, &queue, &texture).unwrap() == input);
+
+        // 半精度的相对误差不超过 2^-11
+        let texture = texture_from_rgba32f(&device, &queue, &input, wgpu::TextureFormat::Rgba16Float).unwrap();
+        let round_trip = rgba32f_from_texture(&device, &queue, &texture).unwrap();
+        for (a, b) in round_trip.as_raw().iter().zip(input.as_raw()) {
+            assert!((a - b).abs() <= b.abs() * 2f32.powi(-11) + 1e-7, "{a} {b}");
+        }
+    }
+
+    #[test]
+    fn filters_keep_more_than_eight_bits() {
+        let Some((device, queue)) = test_device() else { return };
+        let gradient = gradient(1024, 8);
+        let image = image::DynamicImage::ImageRgba16(gradient);
+        for format in [wgpu::TextureFormat::Rgba16Float, wgpu::TextureFormat::Rgba32Float] {
+            let texture = texture_from_dynamic(&device, &queue, &image, format).unwrap();
+            let blurred = gaussian_blur(&device, &queue, &texture, 2.0, None, EdgeMode::Clamp).unwrap();
+            assert_eq!(blurred.format(), format);
+            // 8 位纹理最多 256 级，浮点纹理应该保留渐变中的大部分级数
+            let levels = distinct_levels(&rgba16_from_texture(&device, &queue, &blurred).unwrap());
+            assert!(levels > 1000, "{format:?}: {levels}");
+
+            let resized = resize(&device, &queue, &texture, 700, 8, ResizeFilter::Lanczos3).unwrap();
+            assert_eq!(resized.format(), format);
+            let levels = distinct_levels(&rgba16_from_texture(&device, &queue, &resized).unwrap());
+            assert!(levels > 600, "{format:?}: {levels}");
+
+            // 均衡化只有 256 级，见 histogram::BINS
+            let equalized = equalize(&device, &queue, &texture).unwrap();
+            assert_eq!(equalized.format(), format);
+        }
+    }
+}
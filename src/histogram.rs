@@ -0,0 +1,474 @@
+#![allow(dead_code)]
+
+use anyhow::Ok;
+use anyhow::Result;
+use image::load_from_memory;
+
+use crate::buffer::GpuBuffer;
+use crate::texture::create_output_texture;
+use crate::texture::image_from_texture;
+use crate::texture::texture_from_image;
+use crate::texture::with_storage_format;
+use crate::utils::compute_work_group_count;
+use crate::utils::create_bind_group;
+use crate::utils::create_pipeline;
+use crate::utils::create_shader;
+use crate::utils::dispatch;
+use crate::utils::request_device;
+
+/// 每个通道的桶数。所有纹理格式都按 256 级统计，
+/// 16 位和浮点输入的亮度在均衡化和 CLAHE 中也先量化到 256 级再映射(色度保持原来的精度)
+pub const BINS: usize = 256;
+
+/// 各通道 256 个桶的直方图，亮度使用 BT.601 系数(与 grayscale.wgsl 相同)
+#[derive(Clone, Debug)]
+pub struct Histogram {
+    pub red: [u32; BINS],
+    pub green: [u32; BINS],
+    pub blue: [u32; BINS],
+    pub luminance: [u32; BINS],
+}
+
+impl Histogram {
+    fn from_bins(bins: &[u32]) -> Self {
+        let channel = |index: usize| {
+            let mut channel = [0; BINS];
+            channel.copy_from_slice(&bins[index * BINS..(index + 1) * BINS]);
+            channel
+        };
+        Self {
+            red: channel(0),
+            green: channel(1),
+            blue: channel(2),
+            luminance: channel(3),
+        }
+    }
+}
+
+/// CLAHE 参数
+#[derive(Clone, Copy, Debug)]
+pub struct ClaheConfig {
+    /// 水平和垂直方向的 tile 数
+    pub tiles: (u32, u32),
+    /// 裁剪上限，是每个桶平均像素数的倍数，<= 0 时不裁剪(即普通的自适应均衡化)
+    pub clip_limit: f32,
+}
+
+impl Default for ClaheConfig {
+    fn default() -> Self {
+        Self {
+            tiles: (8, 8),
+            clip_limit: 2.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct ClaheParams {
+    tiles: [u32; 2],
+    clip_limit: f32,
+    _padding: u32,
+}
+
+/// 把统计直方图的命令记录到 encoder 中，bins 需要 4 * 256 个 u32 且初始为 0
+fn encode_histogram(device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture, bins: &GpuBuffer<u32>) {
+    let shader = create_shader(device, "histogram_shader_module", include_str!("../shaders/histogram.wgsl"));
+    let pipeline = create_pipeline(device, &shader, "main");
+
+    let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = create_bind_group(
+        device,
+        &pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(&texture_view)),
+            (1, bins.as_entire_binding()),
+        ],
+    );
+
+    let (x, y) = compute_work_group_count((texture.width(), texture.height()), (16, 16));
+    dispatch(encoder, &pipeline, &bind_group, (x, y, 1));
+}
+
+/// 统计 R、G、B 和亮度直方图
+pub fn histogram(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) -> Result<Histogram> {
+    let bins = GpuBuffer::<u32>::storage_zeroed(device, 4 * BINS);
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encode_histogram(device, &mut encoder, texture, &bins);
+    queue.submit(Some(encoder.finish()));
+
+    Ok(Histogram::from_bins(&bins.read_blocking(device, queue)?))
+}
+
+/// 全局直方图均衡化，只调整亮度，色度保持不变，输出格式与输入相同。
+/// 亮度按 BINS 级映射，高位深输入会损失亮度精度
+pub fn equalize(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture) -> Result<wgpu::Texture> {
+    let (width, height) = (input.width(), input.height());
+    let output = create_output_texture(device, width, height, input.format());
+
+    let bins = GpuBuffer::<u32>::storage_zeroed(device, 4 * BINS);
+    let lut = GpuBuffer::<f32>::storage_zeroed(device, BINS);
+
+    let shader = create_shader(
+        device,
+        "equalize_shader_module",
+        &with_storage_format(include_str!("../shaders/equalize.wgsl"), input.format())?,
+    );
+    let build_lut_pipeline = create_pipeline(device, &shader, "build_lut");
+    let apply_pipeline = create_pipeline(device, &shader, "apply");
+
+    let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+    let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let build_lut_bind_group = create_bind_group(
+        device,
+        &build_lut_pipeline,
+        &[(2, bins.as_entire_binding()), (3, lut.as_entire_binding())],
+    );
+    let apply_bind_group = create_bind_group(
+        device,
+        &apply_pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(&input_view)),
+            (1, wgpu::BindingResource::TextureView(&output_view)),
+            (3, lut.as_entire_binding()),
+        ],
+    );
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encode_histogram(device, &mut encoder, input, &bins);
+    dispatch(&mut encoder, &build_lut_pipeline, &build_lut_bind_group, (1, 1, 1));
+    let (x, y) = compute_work_group_count((width, height), (16, 16));
+    dispatch(&mut encoder, &apply_pipeline, &apply_bind_group, (x, y, 1));
+    queue.submit(Some(encoder.finish()));
+
+    Ok(output)
+}
+
+/// 限制对比度的自适应直方图均衡化(CLAHE)，输出格式与输入相同。
+/// 与 equalize 一样按 BINS 级映射亮度
+pub fn clahe(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, config: ClaheConfig) -> Result<wgpu::Texture> {
+    let (width, height) = (input.width(), input.height());
+    let (tiles_x, tiles_y) = config.tiles;
+    anyhow::ensure!(
+        tiles_x > 0 && tiles_y > 0 && tiles_x <= width && tiles_y <= height,
+        "invalid tile grid {tiles_x}x{tiles_y} for a {width}x{height} image"
+    );
+    let output = create_output_texture(device, width, height, input.format());
+
+    let tile_count = (tiles_x * tiles_y) as usize;
+    let tile_bins = GpuBuffer::<u32>::storage_zeroed(device, tile_count * BINS);
+    let tile_luts = GpuBuffer::<f32>::storage_zeroed(device, tile_count * BINS);
+    let params = GpuBuffer::uniform(
+        device,
+        &ClaheParams {
+            tiles: [tiles_x, tiles_y],
+            clip_limit: config.clip_limit,
+            _padding: 0,
+        },
+    );
+
+    let shader = create_shader(
+        device,
+        "clahe_shader_module",
+        &with_storage_format(include_str!("../shaders/clahe.wgsl"), input.format())?,
+    );
+    let histograms_pipeline = create_pipeline(device, &shader, "tile_histograms");
+    let luts_pipeline = create_pipeline(device, &shader, "build_tile_luts");
+    let apply_pipeline = create_pipeline(device, &shader, "apply");
+
+    let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+    let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let histograms_bind_group = create_bind_group(
+        device,
+        &histograms_pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(&input_view)),
+            (2, tile_bins.as_entire_binding()),
+            (4, params.as_entire_binding()),
+        ],
+    );
+    let luts_bind_group = create_bind_group(
+        device,
+        &luts_pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(&input_view)),
+            (2, tile_bins.as_entire_binding()),
+            (3, tile_luts.as_entire_binding()),
+            (4, params.as_entire_binding()),
+        ],
+    );
+    let apply_bind_group = create_bind_group(
+        device,
+        &apply_pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(&input_view)),
+            (1, wgpu::BindingResource::TextureView(&output_view)),
+            (3, tile_luts.as_entire_binding()),
+            (4, params.as_entire_binding()),
+        ],
+    );
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    dispatch(&mut encoder, &histograms_pipeline, &histograms_bind_group, (tiles_x, tiles_y, 1));
+    dispatch(&mut encoder, &luts_pipeline, &luts_bind_group, (tiles_x, tiles_y, 1));
+    let (x, y) = compute_work_group_count((width, height), (16, 16));
+    dispatch(&mut encoder, &apply_pipeline, &apply_bind_group, (x, y, 1));
+    queue.submit(Some(encoder.finish()));
+
+    Ok(output)
+}
+
+/// 直方图和直方图均衡化
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+
+    let input_image = load_from_memory(include_bytes!("../images/capture.jpg"))?.to_rgba8();
+    let input_texture = texture_from_image(&device, &queue, &input_image);
+
+    let histogram = histogram(&device, &queue, &input_texture)?;
+    let total: u32 = histogram.luminance.iter().sum();
+    println!("像素总数:{total} 亮度直方图:{:?}", histogram.luminance);
+
+    let equalized = equalize(&device, &queue, &input_texture)?;
+    image_from_texture(&device, &queue, &equalized)?.save("./outputs/capture_equalize.png")?;
+
+    let clahe_texture = clahe(&device, &queue, &input_texture, ClaheConfig::default())?;
+    image_from_texture(&device, &queue, &clahe_texture)?.save("./outputs/capture_clahe.png")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::radix_sort::xorshift;
+    use crate::texture::rgba32f_from_texture;
+    use crate::texture::texture_from_rgba32f;
+    use crate::utils::random_image;
+    use crate::utils::test_device;
+    use image::Rgba32FImage;
+
+    /// BT.601 亮度，与着色器中的 dot 相同
+    fn luma(rgb: [f32; 3]) -> f32 {
+        0.299 * rgb[0] + 0.587 * rgb[1] + 0.114 * rgb[2]
+    }
+
+    fn to_bin(value: f32) -> usize {
+        (value.clamp(0.0, 1.0) * 255.0 + 0.5) as usize
+    }
+
+    /// 亮度正好落在两个桶的分界附近，GPU 和 CPU 的舍入可能不同
+    fn ambiguous(value: f32) -> bool {
+        let scaled = value.clamp(0.0, 1.0) * 255.0;
+        (scaled - scaled.floor() - 0.5).abs() < 1e-4
+    }
+
+    /// 24 位精度的随机浮点图像，亮度几乎不会落在桶的分界上
+    fn random_float_image(width: u32, height: u32, mut seed: u32) -> Rgba32FImage {
+        Rgba32FImage::from_fn(width, height, |_, _| image::Rgba([0; 4].map(|_| (xorshift(&mut seed) >> 8) as f32 / (1 << 24) as f32)))
+    }
+
+    fn replace_luma(rgb: [f32; 3], luma: f32, new_luma: f32) -> [f32; 3] {
+        rgb.map(|c| (c + (new_luma - luma)).clamp(0.0, 1.0))
+    }
+
+    /// 与 equalize.wgsl 的 build_lut 相同
+    fn cpu_equalize_lut(luminance: &[u32]) -> Vec<f32> {
+        let cdf: Vec<u32> = luminance.iter().scan(0, |sum, &count| {
+            *sum += count;
+            Some(*sum)
+        }).collect();
+        let cdf_min = cdf.iter().copied().find(|&c| c != 0).unwrap_or(0);
+        let total = cdf[BINS - 1];
+        cdf.iter()
+            .map(|&c| if c < cdf_min || total <= cdf_min { 0.0 } else { (c - cdf_min) as f32 / (total - cdf_min) as f32 })
+            .collect()
+    }
+
+    fn cpu_equalize(image: &Rgba32FImage) -> Rgba32FImage {
+        let mut luminance = vec![0; BINS];
+        for pixel in image.pixels() {
+            luminance[to_bin(luma([pixel[0], pixel[1], pixel[2]]))] += 1;
+        }
+        let lut = cpu_equalize_lut(&luminance);
+        Rgba32FImage::from_fn(image.width(), image.height(), |x, y| {
+            let pixel = image.get_pixel(x, y);
+            let rgb = [pixel[0], pixel[1], pixel[2]];
+            let gray = luma(rgb);
+            let [r, g, b] = replace_luma(rgb, gray, lut[to_bin(gray)]);
+            image::Rgba([r, g, b, pixel[3]])
+        })
+    }
+
+    /// 与 clahe.wgsl 相同: 每个 tile 的直方图裁剪后生成映射表，再在相邻 4 个 tile 之间双线性插值
+    fn cpu_clahe(image: &Rgba32FImage, config: ClaheConfig) -> Rgba32FImage {
+        let (width, height) = image.dimensions();
+        let (tiles_x, tiles_y) = config.tiles;
+        let tile_start = |tx: u32, ty: u32| (tx * width / tiles_x, ty * height / tiles_y);
+        let gray = |x: u32, y: u32| {
+            let pixel = image.get_pixel(x, y);
+            luma([pixel[0], pixel[1], pixel[2]])
+        };
+
+        let mut luts = vec![];
+        for ty in 0..tiles_y {
+            for tx in 0..tiles_x {
+                let (x0, y0) = tile_start(tx, ty);
+                let (x1, y1) = tile_start(tx + 1, ty + 1);
+                let mut bins = vec![0u32; BINS];
+                for y in y0..y1 {
+                    for x in x0..x1 {
+                        bins[to_bin(gray(x, y))] += 1;
+                    }
+                }
+                let pixels = ((x1 - x0) * (y1 - y0)).max(1);
+                let limit = if config.clip_limit > 0.0 {
+                    ((config.clip_limit * pixels as f32 / 256.0) as u32).max(1)
+                } else {
+                    u32::MAX
+                };
+                let excess: u32 = bins.iter().map(|&count| count.saturating_sub(limit)).sum();
+                let mut cdf = 0;
+                let lut: Vec<f32> = bins
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &count)| {
+                        cdf += count.min(limit) + excess / 256 + ((i as u32) < excess % 256) as u32;
+                        cdf as f32 / pixels as f32
+                    })
+                    .collect();
+                luts.push(lut);
+            }
+        }
+
+        let tile_size = (width as f32 / tiles_x as f32, height as f32 / tiles_y as f32);
+        let lerp = |a: f32, b: f32, t: f32| a * (1.0 - t) + b * t;
+        Rgba32FImage::from_fn(width, height, |x, y| {
+            let pixel = image.get_pixel(x, y);
+            let rgb = [pixel[0], pixel[1], pixel[2]];
+            let value = luma(rgb);
+            let bin = to_bin(value);
+            let position = ((x as f32 + 0.5) / tile_size.0 - 0.5, (y as f32 + 0.5) / tile_size.1 - 0.5);
+            let base = (position.0.floor(), position.1.floor());
+            let f = (position.0 - base.0, position.1 - base.1);
+            let clamp_x = |t: f32| t.clamp(0.0, (tiles_x - 1) as f32) as usize;
+            let clamp_y = |t: f32| t.clamp(0.0, (tiles_y - 1) as f32) as usize;
+            let (x0, x1, y0, y1) = (clamp_x(base.0), clamp_x(base.0 + 1.0), clamp_y(base.1), clamp_y(base.1 + 1.0));
+            let lut = |tx: usize, ty: usize| luts[tx + ty * tiles_x as usize][bin];
+            let top = lerp(lut(x0, y0), lut(x1, y0), f.0);
+            let bottom = lerp(lut(x0, y1), lut(x1, y1), f.0);
+            let [r, g, b] = replace_luma(rgb, value, lerp(top, bottom, f.1));
+            image::Rgba([r, g, b, pixel[3]])
+        })
+    }
+
+    fn max_diff(a: &Rgba32FImage, b: &Rgba32FImage) -> f32 {
+        a.as_raw().iter().zip(b.as_raw()).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn histogram_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        // 不是 16 的倍数，检查边缘工作组中的越界线程
+        let input = random_image(53, 37, 4);
+        let histogram = histogram(&device, &queue, &texture_from_image(&device, &queue, &input)).unwrap();
+
+        let mut expected = [[0u32; BINS]; 4];
+        let mut ambiguous_pixels = 0;
+        for pixel in input.pixels() {
+            let rgb = [0, 1, 2].map(|c| pixel[c] as f32 / 255.0);
+            for c in 0..3 {
+                // 8 位输入的每个值正好落在一个桶的中心
+                expected[c][pixel[c] as usize] += 1;
+            }
+            expected[3][to_bin(luma(rgb))] += 1;
+            ambiguous_pixels += ambiguous(luma(rgb)) as u32;
+        }
+        assert_eq!(histogram.red, expected[0]);
+        assert_eq!(histogram.green, expected[1]);
+        assert_eq!(histogram.blue, expected[2]);
+        // 亮度落在分界上的像素可能被 GPU 放到相邻的桶，每个这样的像素最多造成 2 的差别
+        let luminance_diff: u32 = histogram.luminance.iter().zip(&expected[3]).map(|(a, b)| a.abs_diff(*b)).sum();
+        assert!(luminance_diff <= 2 * ambiguous_pixels, "{luminance_diff} {ambiguous_pixels}");
+
+        let pixels = input.width() * input.height();
+        for channel in [&histogram.red, &histogram.green, &histogram.blue, &histogram.luminance] {
+            assert_eq!(channel.iter().sum::<u32>(), pixels);
+        }
+    }
+
+    #[test]
+    fn equalize_lut_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        // 灰色像素的输出亮度就是映射表的值; 各灰度出现的次数不同，映射表不是线性的
+        let input = Rgba32FImage::from_fn(64, 48, |x, y| {
+            let value = ((x * y / 7) % 200 + 30) as f32 / 255.0;
+            image::Rgba([value, value, value, 1.0])
+        });
+        let mut luminance = vec![0; BINS];
+        for pixel in input.pixels() {
+            luminance[to_bin(pixel[0])] += 1;
+        }
+        let lut = cpu_equalize_lut(&luminance);
+        assert_eq!(lut[30], 0.0);
+        assert_eq!(lut[229], 1.0);
+
+        let texture = texture_from_rgba32f(&device, &queue, &input, wgpu::TextureFormat::Rgba32Float).unwrap();
+        let output = rgba32f_from_texture(&device, &queue, &equalize(&device, &queue, &texture).unwrap()).unwrap();
+        for (pixel, expected) in output.pixels().zip(input.pixels()) {
+            let expected = lut[to_bin(expected[0])];
+            assert!((0..3).all(|c| (pixel[c] - expected).abs() < 1e-5), "{pixel:?} {expected}");
+        }
+    }
+
+    #[test]
+    fn equalize_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_float_image(45, 31, 6);
+        let texture = texture_from_rgba32f(&device, &queue, &input, wgpu::TextureFormat::Rgba32Float).unwrap();
+        let output = equalize(&device, &queue, &texture).unwrap();
+        assert_eq!(output.format(), wgpu::TextureFormat::Rgba32Float);
+        let diff = max_diff(&rgba32f_from_texture(&device, &queue, &output).unwrap(), &cpu_equalize(&input));
+        assert!(diff < 1e-5, "{diff}");
+    }
+
+    #[test]
+    fn clahe_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_float_image(37, 29, 7);
+        // 37x29 不能被 3x4 整除，各 tile 的像素数不同
+        let configs = [
+            ClaheConfig {
+                tiles: (3, 4),
+                clip_limit: 2.0,
+            },
+            ClaheConfig {
+                tiles: (3, 4),
+                clip_limit: 0.0,
+            },
+            ClaheConfig {
+                tiles: (5, 2),
+                clip_limit: 1.5,
+            },
+        ];
+        // Rgba16Float 的输入先量化到半精度，CPU 从读回的输入算起，输出写回时的舍入误差不超过 2^-11
+        for (format, tolerance) in [(wgpu::TextureFormat::Rgba32Float, 1e-5), (wgpu::TextureFormat::Rgba16Float, 1e-3)] {
+            let texture = texture_from_rgba32f(&device, &queue, &input, format).unwrap();
+            let stored = rgba32f_from_texture(&device, &queue, &texture).unwrap();
+            for config in configs {
+                let output = clahe(&device, &queue, &texture, config).unwrap();
+                assert_eq!(output.format(), format);
+                let diff = max_diff(&rgba32f_from_texture(&device, &queue, &output).unwrap(), &cpu_clahe(&stored, config));
+                assert!(diff < tolerance, "{format:?} {config:?}: {diff}");
+            }
+        }
+
+        let texture = texture_from_image(&device, &queue, &random_image(8, 8, 1));
+        for tiles in [(0, 1), (9, 1), (1, 9)] {
+            assert!(clahe(&device, &queue, &texture, ClaheConfig { tiles, clip_limit: 2.0 }).is_err());
+        }
+    }
+}
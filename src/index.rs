@@ -1,15 +1,51 @@
 #![allow(dead_code)]
 
-use std::borrow::Cow;
 use anyhow::Ok;
 use anyhow::Result;
 use pollster::FutureExt;
-use wgpu::util::BufferInitDescriptor;
-use wgpu::util::DeviceExt;
-use wgpu::PipelineCompilationOptions;
 
-/// 填充索引
+use crate::buffer::GpuBuffer;
+use crate::utils::create_bind_group;
+use crate::utils::create_pipeline;
+use crate::utils::create_shader;
+use crate::utils::dispatch;
+use crate::utils::linear_workgroup_count;
+use crate::utils::linear_workgroup_size;
+
+/// output[i] = input[i] + 1，只处理 input 的长度，output 更长时其余部分保持不变
+pub fn add_one(device: &wgpu::Device, queue: &wgpu::Queue, input: &GpuBuffer<f32>, output: &GpuBuffer<f32>) -> Result<()> {
+    anyhow::ensure!(output.len() >= input.len(), "output length {} is shorter than input length {}", output.len(), input.len());
+    if input.is_empty() {
+        return Ok(());
+    }
+
+    // 数组长度，着色器据此跳过多余的线程
+    let params = GpuBuffer::uniform(device, &(input.len() as u32));
+
+    // 工作组大小根据设备限制选择
+    let limits = device.limits();
+    let workgroup_size = linear_workgroup_size(&limits, 256);
+    let source = format!("const WORKGROUP_SIZE: u32 = {workgroup_size}u;\n{}", include_str!("../shaders/index.wgsl"));
+    let shader = create_shader(device, "compute_shader_module", &source);
+    let pipeline = create_pipeline(device, &shader, "main");
+    let bind_group = create_bind_group(
+        device,
+        &pipeline,
+        &[
+            (0, input.as_entire_binding()),
+            (1, output.as_entire_binding()),
+            (2, params.as_entire_binding()),
+        ],
+    );
 
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let (x, y) = linear_workgroup_count(&limits, input.len() as u32, workgroup_size);
+    dispatch(&mut encoder, &pipeline, &bind_group, (x, y, 1));
+    queue.submit(Some(encoder.finish()));
+    Ok(())
+}
+
+/// 填充索引
 pub fn main() -> Result<()>{
     let instance = wgpu::Instance::default();
     let adapter = instance
@@ -28,101 +64,47 @@ pub fn main() -> Result<()>{
     }
     println!("input_array:{:?}", input_array);
     
-    let input_buffer = device.create_buffer_init(&BufferInitDescriptor {
-        label: None,
-        usage: wgpu::BufferUsages::STORAGE,
-        contents: bytemuck::cast_slice(input_array),
-    });
+    let input_buffer = GpuBuffer::storage(&device, input_array);
 
     // 结果数组
-    let output_buffer_size = std::mem::size_of::<f32>() * input_array.len();
-
-    let output_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-        label: None,
-        size: output_buffer_size as u64,
-        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
-        mapped_at_creation: false,
-    });
-
-    // 计算着色器
-
-    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-        label: Some("compute_shader_module"),
-        source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("../shaders/index.wgsl"))),
-    });
-
-    // 流水线设置
-    let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-        label: Some("compute_pipeline"),
-        layout: None,
-        module: &shader,
-        entry_point: Some("main"),
-        compilation_options: PipelineCompilationOptions::default(),
-        cache: None
-    });
-
-    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-        layout: &compute_pipeline.get_bind_group_layout(0),
-        entries: &[
-            wgpu::BindGroupEntry {
-                binding: 0,
-                resource: input_buffer.as_entire_binding(),
-            },
-            wgpu::BindGroupEntry {
-                binding: 1,
-                resource: output_buffer.as_entire_binding(),
-            }
-        ],
-        label: Some("bind_group"),
-    });
-
-    println!("shader 创建成功:{:?}", shader);
+    let output_buffer = GpuBuffer::<f32>::storage_zeroed(&device, input_array.len());
 
-    // 命令提交
-
-    let mut encoder = device.create_command_encoder(
-        &wgpu::CommandEncoderDescriptor { label: None },
-    );
-
-    {
-        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default() );
-        cpass.set_pipeline(&compute_pipeline);
-        cpass.set_bind_group(0, &bind_group, &[]);
-        let workgroup_count_x = (input_array.len() as f32 / 8.).ceil() as u32;
-        println!("workgroup_count_x={workgroup_count_x}");
-        cpass.dispatch_workgroups(workgroup_count_x, 1, 1);
-    }
-
-    // 获取用于在未映射状态下读取的 GPU 缓冲区
-    let gpu_read_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-        label: None,
-        size: output_buffer_size as u64,
-        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
-        mapped_at_creation: false,
-    });
-
-    // 编码用于将缓冲区复制到缓冲区的命令。
-    encoder.copy_buffer_to_buffer(&output_buffer, 0, &gpu_read_buffer, 0, output_buffer_size as u64);
-
-    // Submit GPU commands.
-    queue.submit(Some(encoder.finish()));
+    add_one(&device, &queue, &input_buffer, &output_buffer)?;
 
     println!("command提交成功..");
 
-    // 读取结果矩阵
-
-    let buffer_slice = gpu_read_buffer.slice(..);
-    buffer_slice.map_async(wgpu::MapMode::Read, |_| {});
-
-    device.poll(wgpu::Maintain::Wait);
-
-    let padded = buffer_slice.get_mapped_range();
-    let mut data: Vec<u8> = vec![0; output_buffer_size];
-    data.copy_from_slice(&padded);
-
-    let data:&[f32] = bytemuck::cast_slice(&padded[..]);
+    // 读取结果数组
+    let data = output_buffer.read_blocking(&device, &queue)?;
 
     println!("转换成功{:?}", data);
     
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_device;
+
+    #[test]
+    fn tail_beyond_length_is_untouched() {
+        let Some((device, queue)) = test_device() else { return };
+        // 长度不是工作组大小的倍数，最后一个工作组中多余的线程不能写入
+        for len in [1, 255, 257, 1000] {
+            let input = GpuBuffer::storage(&device, &(0..len).map(|i| i as f32).collect::<Vec<_>>());
+            let output = GpuBuffer::storage(&device, &vec![-1.0f32; len + 300]);
+            add_one(&device, &queue, &input, &output).unwrap();
+            let data = output.read_blocking(&device, &queue).unwrap();
+            assert!(data[..len].iter().enumerate().all(|(i, &v)| v == i as f32 + 1.0), "len {len}");
+            assert!(data[len..].iter().all(|&v| v == -1.0), "len {len}");
+        }
+    }
+
+    #[test]
+    fn short_output_is_rejected() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = GpuBuffer::storage(&device, &[1.0f32; 8]);
+        let output = GpuBuffer::<f32>::storage_zeroed(&device, 7);
+        assert!(add_one(&device, &queue, &input, &output).is_err());
+    }
+}
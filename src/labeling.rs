@@ -0,0 +1,465 @@
+#![allow(dead_code)]
+
+use anyhow::Ok;
+use anyhow::Result;
+use image::load_from_memory;
+
+use crate::buffer::GpuBuffer;
+use crate::scan::ScanKind;
+use crate::scan::Scanner;
+use crate::texture::create_output_texture;
+use crate::texture::read_texture;
+use crate::texture::texture_from_image;
+use crate::utils::compute_work_group_count;
+use crate::utils::create_bind_group;
+use crate::utils::create_pipeline;
+use crate::utils::create_shader;
+use crate::utils::dispatch;
+use crate::utils::request_device;
+
+/// 每次提交执行的标签传播次数，之后读回 changed 判断是否收敛
+const PROPAGATE_ITERATIONS_PER_SUBMIT: u32 = 16;
+
+/// 连通方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Connectivity {
+    /// 上下左右
+    Four = 4,
+    /// 包括对角线
+    #[default]
+    Eight = 8,
+}
+
+/// 单个连通域的统计信息
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ComponentStats {
+    /// 从 1 开始，与标签纹理中的值相同
+    pub label: u32,
+    /// 像素数
+    pub area: u32,
+    /// 包围盒 (min_x, min_y, max_x, max_y)，包含边界
+    pub bbox: (u32, u32, u32, u32),
+    pub centroid: (f32, f32),
+}
+
+/// 连通域标记的结果
+pub struct Components {
+    /// R32Uint 纹理，背景为 0，连通域按首个像素的行扫描顺序编号为 1..=n
+    pub labels: wgpu::Texture,
+    pub stats: Vec<ComponentStats>,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct LabelingParams {
+    width: u32,
+    height: u32,
+    connectivity: u32,
+    threshold: f32,
+}
+
+/// 与 labeling.wgsl 中的 ComponentStats 对应
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct RawStats {
+    area: u32,
+    min_x: u32,
+    min_y: u32,
+    max_x: u32,
+    max_y: u32,
+    sum_x_low: u32,
+    sum_x_high: u32,
+    sum_y_low: u32,
+    sum_y_high: u32,
+}
+
+impl RawStats {
+    fn empty() -> Self {
+        Self {
+            min_x: u32::MAX,
+            min_y: u32::MAX,
+            ..Self::zeroed()
+        }
+    }
+
+    fn zeroed() -> Self {
+        bytemuck::Zeroable::zeroed()
+    }
+
+    fn to_stats(self, label: u32) -> ComponentStats {
+        let sum_x = ((self.sum_x_high as u64) << 32 | self.sum_x_low as u64) as f64;
+        let sum_y = ((self.sum_y_high as u64) << 32 | self.sum_y_low as u64) as f64;
+        let area = self.area.max(1) as f64;
+        ComponentStats {
+            label,
+            area: self.area,
+            bbox: (self.min_x, self.min_y, self.max_x, self.max_y),
+            centroid: ((sum_x / area) as f32, (sum_y / area) as f32),
+        }
+    }
+}
+
+/// 对亮度不小于 threshold(0~1)的前景像素做连通域标记，并统计每个连通域的面积、包围盒和质心
+pub fn label_components(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    input: &wgpu::Texture,
+    connectivity: Connectivity,
+    threshold: f32,
+) -> Result<Components> {
+    let (width, height) = (input.width(), input.height());
+    let len = (width * height) as usize;
+
+    let shader = create_shader(device, "labeling_shader_module", include_str!("../shaders/labeling.wgsl"));
+    let init_pipeline = create_pipeline(device, &shader, "init_labels");
+    let propagate_pipeline = create_pipeline(device, &shader, "propagate");
+    let mark_roots_pipeline = create_pipeline(device, &shader, "mark_roots");
+    let finalize_pipeline = create_pipeline(device, &shader, "finalize");
+
+    let params = GpuBuffer::uniform(
+        device,
+        &LabelingParams {
+            width,
+            height,
+            connectivity: connectivity as u32,
+            threshold,
+        },
+    );
+    let labels = GpuBuffer::<u32>::storage_zeroed(device, len);
+    let changed = GpuBuffer::<u32>::storage_zeroed(device, 1);
+    let roots = GpuBuffer::<u32>::storage_zeroed(device, len);
+    let offsets = GpuBuffer::<u32>::storage_zeroed(device, len);
+    let (x, y) = compute_work_group_count((width, height), (16, 16));
+
+    let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+    let init_bind_group = create_bind_group(
+        device,
+        &init_pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(&input_view)),
+            (2, labels.as_entire_binding()),
+            (3, params.as_entire_binding()),
+        ],
+    );
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    dispatch(&mut encoder, &init_pipeline, &init_bind_group, (x, y, 1));
+    queue.submit(Some(encoder.finish()));
+
+    // 标签传播直到一轮中没有任何变化
+    let propagate_bind_group = create_bind_group(
+        device,
+        &propagate_pipeline,
+        &[
+            (2, labels.as_entire_binding()),
+            (3, params.as_entire_binding()),
+            (4, changed.as_entire_binding()),
+        ],
+    );
+    loop {
+        changed.write(queue, &[0])?;
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        for _ in 0..PROPAGATE_ITERATIONS_PER_SUBMIT {
+            dispatch(&mut encoder, &propagate_pipeline, &propagate_bind_group, (x, y, 1));
+        }
+        queue.submit(Some(encoder.finish()));
+        if changed.read_blocking(device, queue)?[0] == 0 {
+            break;
+        }
+    }
+
+    // 根的 inclusive 前缀和就是从 1 开始的连续编号
+    let mark_roots_bind_group = create_bind_group(
+        device,
+        &mark_roots_pipeline,
+        &[
+            (2, labels.as_entire_binding()),
+            (3, params.as_entire_binding()),
+            (5, roots.as_entire_binding()),
+        ],
+    );
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    dispatch(&mut encoder, &mark_roots_pipeline, &mark_roots_bind_group, (x, y, 1));
+    Scanner::new::<u32>(device).encode(device, &mut encoder, roots.buffer(), offsets.buffer(), len as u32, ScanKind::Inclusive);
+    queue.submit(Some(encoder.finish()));
+
+    let count = offsets.view(len - 1..).read_blocking(device, queue)?[0] as usize;
+
+    // 空数组不能绑定，至少保留一个元素
+    let stats = GpuBuffer::storage(device, &vec![RawStats::empty(); count.max(1)]);
+    let label_texture = create_output_texture(device, width, height, wgpu::TextureFormat::R32Uint);
+    let label_view = label_texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let finalize_bind_group = create_bind_group(
+        device,
+        &finalize_pipeline,
+        &[
+            (1, wgpu::BindingResource::TextureView(&label_view)),
+            (2, labels.as_entire_binding()),
+            (3, params.as_entire_binding()),
+            (5, offsets.as_entire_binding()),
+            (6, stats.as_entire_binding()),
+        ],
+    );
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    dispatch(&mut encoder, &finalize_pipeline, &finalize_bind_group, (x, y, 1));
+    queue.submit(Some(encoder.finish()));
+
+    let stats = stats.read_blocking(device, queue)?;
+    Ok(Components {
+        labels: label_texture,
+        stats: stats
+            .into_iter()
+            .take(count)
+            .enumerate()
+            .map(|(i, raw)| raw.to_stats(i as u32 + 1))
+            .collect(),
+    })
+}
+
+/// 读回标签纹理
+pub fn read_labels(device: &wgpu::Device, queue: &wgpu::Queue, labels: &wgpu::Texture) -> Result<Vec<u32>> {
+    Ok(bytemuck::pod_collect_to_vec(&read_texture(device, queue, labels)?))
+}
+
+/// 连通域标记
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+
+    let input_image = load_from_memory(include_bytes!("../images/rust.png"))?.to_rgba8();
+    let input_texture = texture_from_image(&device, &queue, &input_image);
+
+    for connectivity in [Connectivity::Four, Connectivity::Eight] {
+        let components = label_components(&device, &queue, &input_texture, connectivity, 0.5)?;
+        println!("{connectivity:?} 连通: 连通域数量:{}", components.stats.len());
+        if let Some(largest) = components.stats.iter().max_by_key(|stats| stats.area) {
+            println!("最大的连通域:{largest:?}");
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::random_image;
+    use crate::utils::test_device;
+    use image::RgbaImage;
+
+    /// CPU 上的洪水填充标记，按行扫描顺序编号
+    fn cpu_flood_fill(image: &image::RgbaImage, connectivity: Connectivity, threshold: f32) -> (Vec<u32>, Vec<ComponentStats>) {
+        let (width, height) = (image.width() as i32, image.height() as i32);
+        let foreground: Vec<bool> = image
+            .pixels()
+            .map(|pixel| (0.299 * pixel[0] as f32 + 0.587 * pixel[1] as f32 + 0.114 * pixel[2] as f32) / 255.0 >= threshold)
+            .collect();
+
+        let mut labels = vec![0u32; foreground.len()];
+        let mut stats = vec![];
+        for start in 0..foreground.len() {
+            if !foreground[start] || labels[start] != 0 {
+                continue;
+            }
+            let label = stats.len() as u32 + 1;
+            let (mut area, mut sum_x, mut sum_y) = (0u64, 0u64, 0u64);
+            let mut bbox = (u32::MAX, u32::MAX, 0, 0);
+            labels[start] = label;
+            let mut stack = vec![start];
+            while let Some(index) = stack.pop() {
+                let (x, y) = (index as i32 % width, index as i32 / width);
+                area += 1;
+                sum_x += x as u64;
+                sum_y += y as u64;
+                bbox = (bbox.0.min(x as u32), bbox.1.min(y as u32), bbox.2.max(x as u32), bbox.3.max(y as u32));
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        if (dx == 0 && dy == 0) || (connectivity == Connectivity::Four && dx != 0 && dy != 0) {
+                            continue;
+                        }
+                        let (nx, ny) = (x + dx, y + dy);
+                        if nx < 0 || ny < 0 || nx >= width || ny >= height {
+                            continue;
+                        }
+                        let neighbor = (ny * width + nx) as usize;
+                        if foreground[neighbor] && labels[neighbor] == 0 {
+                            labels[neighbor] = label;
+                            stack.push(neighbor);
+                        }
+                    }
+                }
+            }
+            stats.push(ComponentStats {
+                label,
+                area: area as u32,
+                bbox,
+                centroid: ((sum_x as f64 / area as f64) as f32, (sum_y as f64 / area as f64) as f32),
+            });
+        }
+        (labels, stats)
+    }
+
+    /// 在 width x height 的网格上画前景
+    struct Mask {
+        width: u32,
+        height: u32,
+        pixels: Vec<bool>,
+    }
+
+    impl Mask {
+        fn new(width: u32, height: u32) -> Self {
+            Self { width, height, pixels: vec![false; (width * height) as usize] }
+        }
+
+        fn set(&mut self, x: u32, y: u32) {
+            self.pixels[(y * self.width + x) as usize] = true;
+        }
+
+        fn rect(&mut self, x0: u32, y0: u32, x1: u32, y1: u32) {
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    self.set(x, y);
+                }
+            }
+        }
+
+        /// 线宽 1、臂间距 1 的方形螺旋，左上角在 (x, y)
+        fn spiral(&mut self, x: u32, y: u32, size: u32) {
+            let (mut px, mut py) = (x as i32, y as i32);
+            let directions = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+            let mut lengths = vec![size as i32 - 1];
+            let mut length = size as i32 - 1;
+            while length > 0 {
+                lengths.extend([length, length]);
+                length -= 2;
+            }
+            self.set(px as u32, py as u32);
+            for (i, &length) in lengths.iter().enumerate() {
+                let (dx, dy) = directions[i % 4];
+                for _ in 0..length {
+                    px += dx;
+                    py += dy;
+                    self.set(px as u32, py as u32);
+                }
+            }
+        }
+
+        /// 开口朝上的 U 形，外框为 (x0, y0)..(x1, y1)，线宽 thickness
+        fn u_shape(&mut self, x0: u32, y0: u32, x1: u32, y1: u32, thickness: u32) {
+            self.rect(x0, y0, x0 + thickness, y1);
+            self.rect(x1 - thickness, y0, x1, y1);
+            self.rect(x0, y1 - thickness, x1, y1);
+        }
+
+        fn to_image(&self) -> RgbaImage {
+            RgbaImage::from_fn(self.width, self.height, |x, y| {
+                let value = if self.pixels[(y * self.width + x) as usize] { 255 } else { 0 };
+                image::Rgba([value, value, value, 255])
+            })
+        }
+    }
+
+    /// 包含螺旋、嵌套的 U 形、对角线、棋盘格、环和孤立像素的测试图
+    fn synthetic_mask() -> RgbaImage {
+        let mut mask = Mask::new(160, 120);
+        mask.spiral(2, 2, 61);
+        mask.spiral(100, 60, 40);
+        // 嵌套的 U 形，最后才在底部汇合，单遍扫描的算法最容易在这里出错
+        for i in 0..4 {
+            mask.u_shape(66 + 4 * i, 2 + 4 * i, 130 - 4 * i, 50 - 4 * i, 2);
+        }
+        mask.u_shape(134, 2, 158, 30, 1);
+        // 只在对角线方向相连的阶梯和棋盘格: 8 连通时是一个连通域，4 连通时每个像素都是单独的连通域
+        for i in 0..30 {
+            mask.set(2 + i, 70 + i);
+            mask.set(40 - i / 2 * 2 + i % 2, 70 + i);
+        }
+        for y in 102..118 {
+            for x in 2..30 {
+                if (x + y) % 2 == 0 {
+                    mask.set(x, y);
+                }
+            }
+        }
+        // 环和其中的岛
+        mask.u_shape(50, 66, 90, 106, 3);
+        mask.rect(50, 66, 90, 69);
+        mask.rect(66, 82, 74, 90);
+        // 孤立像素和贴着图像边缘的像素
+        mask.set(0, 0);
+        mask.set(159, 119);
+        mask.set(159, 0);
+        mask.set(45, 112);
+        mask.to_image()
+    }
+
+    fn assert_matches_flood_fill(device: &wgpu::Device, queue: &wgpu::Queue, image: &RgbaImage, connectivity: Connectivity) {
+        let texture = texture_from_image(device, queue, image);
+        let components = label_components(device, queue, &texture, connectivity, 0.5).unwrap();
+        let (expected_labels, expected_stats) = cpu_flood_fill(image, connectivity, 0.5);
+
+        assert_eq!(components.stats.len(), expected_stats.len(), "{connectivity:?}: component count");
+        assert!(read_labels(device, queue, &components.labels).unwrap() == expected_labels, "{connectivity:?}: labels differ");
+        for (actual, expected) in components.stats.iter().zip(&expected_stats) {
+            assert_eq!(actual.label, expected.label);
+            assert_eq!(actual.area, expected.area, "{connectivity:?} {expected:?}");
+            assert_eq!(actual.bbox, expected.bbox, "{connectivity:?} {expected:?}");
+            // 质心由整数和相除得到，误差只来自最后转换为 f32
+            assert!((actual.centroid.0 - expected.centroid.0).abs() <= 1e-3, "{connectivity:?} {actual:?} vs {expected:?}");
+            assert!((actual.centroid.1 - expected.centroid.1).abs() <= 1e-3, "{connectivity:?} {actual:?} vs {expected:?}");
+        }
+    }
+
+    #[test]
+    fn synthetic_mask_matches_flood_fill() {
+        let Some((device, queue)) = test_device() else { return };
+        let image = synthetic_mask();
+        for connectivity in [Connectivity::Four, Connectivity::Eight] {
+            assert_matches_flood_fill(&device, &queue, &image, connectivity);
+        }
+    }
+
+    #[test]
+    fn spiral_is_one_component() {
+        let Some((device, queue)) = test_device() else { return };
+        let mut mask = Mask::new(64, 64);
+        mask.spiral(1, 1, 61);
+        let image = mask.to_image();
+        let texture = texture_from_image(&device, &queue, &image);
+        for connectivity in [Connectivity::Four, Connectivity::Eight] {
+            let components = label_components(&device, &queue, &texture, connectivity, 0.5).unwrap();
+            assert_eq!(components.stats.len(), 1, "{connectivity:?}");
+            let area = mask.pixels.iter().filter(|&&p| p).count() as u32;
+            assert_eq!(components.stats[0].area, area);
+            assert_eq!(components.stats[0].bbox, (1, 1, 61, 61));
+        }
+    }
+
+    #[test]
+    fn random_mask_matches_flood_fill() {
+        let Some((device, queue)) = test_device() else { return };
+        // 约 45% 的前景，接近渗流阈值，连通域的形状很不规则
+        let image = random_image(97, 61, 41);
+        let image = RgbaImage::from_fn(97, 61, |x, y| {
+            let value = if image.get_pixel(x, y)[0] < 115 { 255 } else { 0 };
+            image::Rgba([value, value, value, 255])
+        });
+        for connectivity in [Connectivity::Four, Connectivity::Eight] {
+            assert_matches_flood_fill(&device, &queue, &image, connectivity);
+        }
+    }
+
+    #[test]
+    fn empty_and_full_images() {
+        let Some((device, queue)) = test_device() else { return };
+        let empty = RgbaImage::from_pixel(20, 10, image::Rgba([0, 0, 0, 255]));
+        let components = label_components(&device, &queue, &texture_from_image(&device, &queue, &empty), Connectivity::Eight, 0.5).unwrap();
+        assert!(components.stats.is_empty());
+        assert!(read_labels(&device, &queue, &components.labels).unwrap().iter().all(|&label| label == 0));
+
+        let full = RgbaImage::from_pixel(20, 10, image::Rgba([255; 4]));
+        let components = label_components(&device, &queue, &texture_from_image(&device, &queue, &full), Connectivity::Four, 0.5).unwrap();
+        assert_eq!(components.stats.len(), 1);
+        assert_eq!(components.stats[0].area, 200);
+        assert_eq!(components.stats[0].bbox, (0, 0, 19, 9));
+        assert_eq!(components.stats[0].centroid, (9.5, 4.5));
+    }
+}
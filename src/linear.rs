@@ -0,0 +1,181 @@
+#![allow(dead_code)]
+
+use anyhow::Ok;
+use anyhow::Result;
+use image::load_from_memory;
+
+use crate::blur::gaussian_blur;
+use crate::blur::EdgeMode;
+use crate::grayscale::grayscale_texture;
+use crate::grayscale::GrayscaleMode;
+use crate::resize::resize;
+use crate::resize::ResizeFilter;
+use crate::texture::create_output_texture;
+use crate::texture::image_from_texture;
+use crate::texture::texture_from_image;
+use crate::texture::with_storage_format;
+use crate::utils::compute_work_group_count;
+use crate::utils::create_bind_group;
+use crate::utils::create_pipeline;
+use crate::utils::create_shader;
+use crate::utils::dispatch;
+use crate::utils::request_device;
+
+/// 线性光纹理的格式，8 位不足以存放线性值
+pub const LINEAR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+fn encode_conversion(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    input_view: &wgpu::TextureView,
+    output: &wgpu::Texture,
+    entry_point: &str,
+) -> Result<()> {
+    let source = format!("{}\n{}", include_str!("../shaders/srgb.wgsl"), include_str!("../shaders/linear.wgsl"));
+    let shader = create_shader(device, "linear_shader_module", &with_storage_format(&source, output.format())?);
+    let pipeline = create_pipeline(device, &shader, entry_point);
+
+    let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = create_bind_group(
+        device,
+        &pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(input_view)),
+            (1, wgpu::BindingResource::TextureView(&output_view)),
+        ],
+    );
+    let (x, y) = compute_work_group_count((output.width(), output.height()), (16, 16));
+    dispatch(encoder, &pipeline, &bind_group, (x, y, 1));
+    Ok(())
+}
+
+/// 把 sRGB 编码的 Rgba8Unorm 纹理解码为线性光的 Rgba16Float 纹理。
+/// 在着色器中显式解码，不依赖 Rgba8UnormSrgb 视图(GL 等后端不支持 view_formats)
+pub fn to_linear(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture) -> Result<wgpu::Texture> {
+    anyhow::ensure!(
+        input.format() == wgpu::TextureFormat::Rgba8Unorm,
+        "expected an Rgba8Unorm texture, got {:?}",
+        input.format()
+    );
+    let output = create_output_texture(device, input.width(), input.height(), LINEAR_FORMAT);
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+    encode_conversion(device, &mut encoder, &input_view, &output, "decode")?;
+    queue.submit(Some(encoder.finish()));
+
+    Ok(output)
+}
+
+/// 把线性光纹理编码为 sRGB 的 Rgba8Unorm 纹理(storage 纹理不支持 sRGB 格式，所以显式转换)
+pub fn from_linear(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture) -> Result<wgpu::Texture> {
+    let output = create_output_texture(device, input.width(), input.height(), wgpu::TextureFormat::Rgba8Unorm);
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+    encode_conversion(device, &mut encoder, &input_view, &output, "encode")?;
+    queue.submit(Some(encoder.finish()));
+
+    Ok(output)
+}
+
+/// 在线性光中执行 operation: 先解码，operation 的输入和输出都是线性光纹理，最后重新编码为 sRGB
+pub fn in_linear_light<F>(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, operation: F) -> Result<wgpu::Texture>
+where
+    F: FnOnce(&wgpu::Device, &wgpu::Queue, &wgpu::Texture) -> Result<wgpu::Texture>,
+{
+    let linear = to_linear(device, queue, input)?;
+    let processed = operation(device, queue, &linear)?;
+    from_linear(device, queue, &processed)
+}
+
+/// 线性光处理和灰度算法
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+
+    let input_image = load_from_memory(include_bytes!("../images/sushi.png"))?.to_rgba8();
+    let input_texture = texture_from_image(&device, &queue, &input_image);
+
+    // 同样的模糊和缩放，线性光中的边缘不会变暗
+    let blurred = in_linear_light(&device, &queue, &input_texture, |device, queue, linear| {
+        gaussian_blur(device, queue, linear, 6.0, None, EdgeMode::Clamp)
+    })?;
+    image_from_texture(&device, &queue, &blurred)?.save("./outputs/sushi_blur_linear.png")?;
+    let blurred = gaussian_blur(&device, &queue, &input_texture, 6.0, None, EdgeMode::Clamp)?;
+    image_from_texture(&device, &queue, &blurred)?.save("./outputs/sushi_blur_srgb.png")?;
+
+    let (width, height) = (input_texture.width() / 4, input_texture.height() / 4);
+    let resized = in_linear_light(&device, &queue, &input_texture, |device, queue, linear| {
+        resize(device, queue, linear, width, height, ResizeFilter::Lanczos3)
+    })?;
+    image_from_texture(&device, &queue, &resized)?.save("./outputs/sushi_resize_linear.png")?;
+
+    let modes = [
+        (GrayscaleMode::Bt601, "bt601"),
+        (GrayscaleMode::Bt709, "bt709"),
+        (GrayscaleMode::CieLightness, "lightness"),
+        (GrayscaleMode::Average, "average"),
+    ];
+    for (mode, name) in modes {
+        let gray = grayscale_texture(&device, &queue, &input_texture, mode)?;
+        image_from_texture(&device, &queue, &gray)?.save(format!("./outputs/sushi_gray_{name}.png"))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::rgba32f_from_texture;
+    use crate::utils::test_device;
+    use image::RgbaImage;
+
+    fn cpu_srgb_to_linear(v: u8) -> f32 {
+        let c = v as f32 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    }
+
+    /// 全部 256 级，alpha 取反序以检查它不被转换
+    fn all_levels() -> RgbaImage {
+        RgbaImage::from_fn(256, 1, |x, _| image::Rgba([x as u8, x as u8, x as u8, 255 - x as u8]))
+    }
+
+    #[test]
+    fn to_linear_matches_cpu_decode() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = all_levels();
+        let linear = to_linear(&device, &queue, &texture_from_image(&device, &queue, &input)).unwrap();
+        assert_eq!(linear.format(), LINEAR_FORMAT);
+
+        // Rgba16Float 有 11 位有效精度，相对误差不超过 1e-3
+        let output = rgba32f_from_texture(&device, &queue, &linear).unwrap();
+        for (x, pixel) in output.pixels().enumerate() {
+            let expected = cpu_srgb_to_linear(x as u8);
+            for &value in &pixel.0[..3] {
+                assert!((value - expected).abs() <= expected * 1e-3 + 1e-6, "level {x}: {value} vs {expected}");
+            }
+            assert!((pixel[3] - (255 - x) as f32 / 255.0).abs() <= 1e-3);
+        }
+    }
+
+    #[test]
+    fn linear_round_trip_is_lossless() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = all_levels();
+        let linear = to_linear(&device, &queue, &texture_from_image(&device, &queue, &input)).unwrap();
+        let round_trip = image_from_texture(&device, &queue, &from_linear(&device, &queue, &linear).unwrap()).unwrap();
+        assert_eq!(round_trip, input);
+    }
+
+    #[test]
+    fn to_linear_rejects_other_formats() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = create_output_texture(&device, 4, 4, LINEAR_FORMAT);
+        assert!(to_linear(&device, &queue, &input).is_err());
+    }
+}
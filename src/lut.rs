@@ -0,0 +1,663 @@
+#![allow(dead_code)]
+
+use std::fmt::Write;
+use std::path::Path;
+use anyhow::Ok;
+use anyhow::Result;
+
+use crate::adjust::ColorAdjuster;
+use crate::adjust::ColorAdjustments;
+use crate::buffer::GpuBuffer;
+use crate::texture::create_output_texture;
+use crate::texture::create_texture;
+use crate::texture::image_from_texture;
+use crate::texture::read_texture;
+use crate::texture::texture_from_image;
+use crate::texture::with_storage_format;
+use crate::utils::compute_work_group_count;
+use crate::utils::create_bind_group;
+use crate::utils::create_pipeline;
+use crate::utils::create_shader;
+use crate::utils::dispatch;
+use crate::utils::request_device;
+use crate::yuv2rgb::yuv_to_rgba_cpu;
+
+/// LUT 的维度
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LutKind {
+    /// 每个通道单独映射，共 size 个条目
+    OneDimensional,
+    /// size³ 个条目，红色变化最快
+    ThreeDimensional,
+}
+
+/// 3D LUT 采样时的插值方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LutInterpolation {
+    Trilinear = 0,
+    /// 只用立方体中的 4 个顶点，中性灰更准确
+    #[default]
+    Tetrahedral = 1,
+}
+
+/// 1D LUT 的最大条目数
+pub const MAX_1D_SIZE: u32 = 1 << 16;
+/// 3D LUT 每条边的最大格点数
+pub const MAX_3D_SIZE: u32 = 256;
+
+/// Adobe/Resolve 的 .cube 查找表
+#[derive(Clone, Debug, PartialEq)]
+pub struct CubeLut {
+    pub title: Option<String>,
+    pub kind: LutKind,
+    pub size: u32,
+    pub domain_min: [f32; 3],
+    pub domain_max: [f32; 3],
+    pub data: Vec<[f32; 3]>,
+}
+
+impl CubeLut {
+    /// 不改变颜色的 3D LUT，size 至少为 2
+    pub fn identity(size: u32) -> Result<Self> {
+        let mut data = Vec::with_capacity(entry_count(LutKind::ThreeDimensional, size)?);
+        let scale = 1.0 / (size - 1) as f32;
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    data.push([r as f32 * scale, g as f32 * scale, b as f32 * scale]);
+                }
+            }
+        }
+        Ok(Self {
+            title: None,
+            kind: LutKind::ThreeDimensional,
+            size,
+            domain_min: [0.0; 3],
+            domain_max: [1.0; 3],
+            data,
+        })
+    }
+
+    /// 解析 .cube 文本
+    pub fn parse(source: &str) -> Result<Self> {
+        let mut title = None;
+        let mut kind_and_size = None;
+        let mut domain_min = [0.0; 3];
+        let mut domain_max = [1.0; 3];
+        let mut data = vec![];
+
+        let parse_triple = |values: &[&str], line_number: usize| -> Result<[f32; 3]> {
+            anyhow::ensure!(values.len() == 3, "line {line_number}: expected 3 values");
+            let mut triple = [0.0; 3];
+            for (value, text) in triple.iter_mut().zip(values) {
+                *value = text.parse().map_err(|_| anyhow::anyhow!("line {line_number}: invalid number {text:?}"))?;
+            }
+            Ok(triple)
+        };
+
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let keyword = words.next().unwrap_or_default();
+            let values: Vec<&str> = words.collect();
+            match keyword {
+                "TITLE" => title = Some(line["TITLE".len()..].trim().trim_matches('"').to_string()),
+                "LUT_1D_SIZE" | "LUT_3D_SIZE" => {
+                    let size: u32 = values
+                        .first()
+                        .and_then(|value| value.parse().ok())
+                        .ok_or(anyhow::anyhow!("line {line_number}: invalid {keyword}"))?;
+                    let kind = if keyword == "LUT_1D_SIZE" {
+                        LutKind::OneDimensional
+                    } else {
+                        LutKind::ThreeDimensional
+                    };
+                    kind_and_size = Some((kind, size));
+                }
+                "DOMAIN_MIN" => domain_min = parse_triple(&values, line_number)?,
+                "DOMAIN_MAX" => domain_max = parse_triple(&values, line_number)?,
+                "LUT_1D_INPUT_RANGE" | "LUT_3D_INPUT_RANGE" => {
+                    anyhow::ensure!(values.len() == 2, "line {line_number}: expected 2 values");
+                    let min: f32 = values[0].parse()?;
+                    let max: f32 = values[1].parse()?;
+                    domain_min = [min; 3];
+                    domain_max = [max; 3];
+                }
+                _ if keyword.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                    // 忽略不认识的关键字
+                }
+                _ => data.push(parse_triple(&line.split_whitespace().collect::<Vec<_>>(), line_number)?),
+            }
+        }
+
+        let (kind, size) = kind_and_size.ok_or(anyhow::anyhow!("missing LUT_1D_SIZE or LUT_3D_SIZE"))?;
+        let expected = entry_count(kind, size)?;
+        anyhow::ensure!(data.len() == expected, "expected {expected} entries, got {}", data.len());
+
+        Ok(Self {
+            title,
+            kind,
+            size,
+            domain_min,
+            domain_max,
+            data,
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// 输出为 .cube 文本
+    pub fn to_cube_string(&self) -> String {
+        let mut output = String::new();
+        if let Some(title) = &self.title {
+            writeln!(output, "TITLE \"{title}\"").unwrap();
+        }
+        let keyword = match self.kind {
+            LutKind::OneDimensional => "LUT_1D_SIZE",
+            LutKind::ThreeDimensional => "LUT_3D_SIZE",
+        };
+        writeln!(output, "{keyword} {}", self.size).unwrap();
+        let [r, g, b] = self.domain_min;
+        writeln!(output, "DOMAIN_MIN {r:.6} {g:.6} {b:.6}").unwrap();
+        let [r, g, b] = self.domain_max;
+        writeln!(output, "DOMAIN_MAX {r:.6} {g:.6} {b:.6}").unwrap();
+        for [r, g, b] in &self.data {
+            writeln!(output, "{r:.6} {g:.6} {b:.6}").unwrap();
+        }
+        output
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        Ok(std::fs::write(path, self.to_cube_string())?)
+    }
+}
+
+/// kind 和 size 对应的条目数，size 超出 2~MAX_*_SIZE 时返回错误
+fn entry_count(kind: LutKind, size: u32) -> Result<usize> {
+    anyhow::ensure!(size >= 2, "LUT size {size} is too small");
+    let (max, dimensions) = match kind {
+        LutKind::OneDimensional => (MAX_1D_SIZE, 1),
+        LutKind::ThreeDimensional => (MAX_3D_SIZE, 3),
+    };
+    anyhow::ensure!(size <= max, "LUT size {size} exceeds {max}");
+    (0..dimensions)
+        .try_fold(1usize, |count, _| count.checked_mul(size as usize))
+        .ok_or(anyhow::anyhow!("LUT size {size} overflows"))
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct LutParams {
+    domain_min: [f32; 4],
+    domain_max: [f32; 4],
+    size: u32,
+    interpolation: u32,
+    _padding: [u32; 2],
+}
+
+/// LUT 在 GPU 上的存放方式
+enum LutStorage {
+    /// 1D LUT 放在 storage buffer 中，不受纹理尺寸限制
+    Entries(GpuBuffer<[f32; 4]>),
+    /// 3D LUT 放在 N×N×N 的 3D 纹理中
+    Texture(wgpu::Texture),
+}
+
+/// 已上传到 GPU 的 LUT
+pub struct GpuLut {
+    storage: LutStorage,
+    size: u32,
+    domain_min: [f32; 3],
+    domain_max: [f32; 3],
+}
+
+impl GpuLut {
+    /// 1D LUT 上传为 storage buffer，3D LUT 上传为 Rgba32Float 的 3D 纹理，超出设备限制时返回错误
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, lut: &CubeLut) -> Result<Self> {
+        let expected = entry_count(lut.kind, lut.size)?;
+        anyhow::ensure!(lut.data.len() == expected, "expected {expected} entries, got {}", lut.data.len());
+        let texels: Vec<[f32; 4]> = lut.data.iter().map(|&[r, g, b]| [r, g, b, 1.0]).collect();
+        let limits = device.limits();
+
+        let storage = match lut.kind {
+            LutKind::OneDimensional => {
+                let bytes = std::mem::size_of_val(texels.as_slice()) as u64;
+                anyhow::ensure!(
+                    bytes <= limits.max_storage_buffer_binding_size as u64,
+                    "1D LUT of {} entries exceeds max_storage_buffer_binding_size {}",
+                    lut.size,
+                    limits.max_storage_buffer_binding_size
+                );
+                LutStorage::Entries(GpuBuffer::storage(device, &texels))
+            }
+            LutKind::ThreeDimensional => {
+                anyhow::ensure!(
+                    lut.size <= limits.max_texture_dimension_3d,
+                    "3D LUT size {} exceeds max_texture_dimension_3d {}",
+                    lut.size,
+                    limits.max_texture_dimension_3d
+                );
+                let size = wgpu::Extent3d {
+                    width: lut.size,
+                    height: lut.size,
+                    depth_or_array_layers: lut.size,
+                };
+                let texture = device.create_texture(&wgpu::TextureDescriptor {
+                    label: Some("lut texture"),
+                    size,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D3,
+                    format: wgpu::TextureFormat::Rgba32Float,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                    view_formats: &[],
+                });
+                queue.write_texture(
+                    texture.as_image_copy(),
+                    bytemuck::cast_slice(&texels),
+                    wgpu::TexelCopyBufferLayout {
+                        offset: 0,
+                        bytes_per_row: Some(16 * lut.size),
+                        rows_per_image: Some(lut.size),
+                    },
+                    size,
+                );
+                LutStorage::Texture(texture)
+            }
+        };
+
+        Ok(Self {
+            storage,
+            size: lut.size,
+            domain_min: lut.domain_min,
+            domain_max: lut.domain_max,
+        })
+    }
+
+    /// 对 input 应用 LUT，输出格式与输入相同
+    pub fn apply(&self, device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, interpolation: LutInterpolation) -> Result<wgpu::Texture> {
+        let (width, height) = (input.width(), input.height());
+        let output = create_output_texture(device, width, height, input.format());
+
+        let source = with_storage_format(include_str!("../shaders/lut.wgsl"), input.format())?;
+        let shader = create_shader(device, "lut_shader_module", &source);
+        let entry_point = match self.storage {
+            LutStorage::Entries(_) => "apply_lut_1d",
+            LutStorage::Texture(_) => "apply_lut_3d",
+        };
+        let pipeline = create_pipeline(device, &shader, entry_point);
+
+        let [min_r, min_g, min_b] = self.domain_min;
+        let [max_r, max_g, max_b] = self.domain_max;
+        let params = GpuBuffer::uniform(
+            device,
+            &LutParams {
+                domain_min: [min_r, min_g, min_b, 0.0],
+                domain_max: [max_r, max_g, max_b, 1.0],
+                size: self.size,
+                interpolation: interpolation as u32,
+                _padding: [0; 2],
+            },
+        );
+
+        let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+        let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+        let lut_view;
+        let lut_binding = match &self.storage {
+            LutStorage::Entries(entries) => (4, entries.as_entire_binding()),
+            LutStorage::Texture(texture) => {
+                lut_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                (2, wgpu::BindingResource::TextureView(&lut_view))
+            }
+        };
+        let bind_group = create_bind_group(
+            device,
+            &pipeline,
+            &[
+                (0, wgpu::BindingResource::TextureView(&input_view)),
+                (1, wgpu::BindingResource::TextureView(&output_view)),
+                lut_binding,
+                (3, params.as_entire_binding()),
+            ],
+        );
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        let (x, y) = compute_work_group_count((width, height), (16, 16));
+        dispatch(&mut encoder, &pipeline, &bind_group, (x, y, 1));
+        queue.submit(Some(encoder.finish()));
+
+        Ok(output)
+    }
+}
+
+/// 在 GPU 上依次执行 chain 中的颜色调整，把结果烘焙为 size³ 的 3D LUT
+pub fn bake_adjustments(device: &wgpu::Device, queue: &wgpu::Queue, chain: &[ColorAdjustments], size: u32) -> Result<CubeLut> {
+    let mut lut = CubeLut::identity(size)?;
+
+    // 格点按 LUT 的顺序排成 size x size² 的图像: x 为红色，y = 绿色 + 蓝色 * size
+    let format = wgpu::TextureFormat::Rgba32Float;
+    let (width, height) = (size, size * size);
+    let lattice = create_texture(
+        device,
+        width,
+        height,
+        format,
+        wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::COPY_SRC,
+    );
+    let texels: Vec<[f32; 4]> = lut.data.iter().map(|&[r, g, b]| [r, g, b, 1.0]).collect();
+    queue.write_texture(
+        lattice.as_image_copy(),
+        bytemuck::cast_slice(&texels),
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(16 * width),
+            rows_per_image: None,
+        },
+        lattice.size(),
+    );
+
+    // 每一步都要写 uniform，所以逐步提交
+    let adjuster = ColorAdjuster::new(device, format)?;
+    let mut current = lattice;
+    for adjustments in chain {
+        let output = create_output_texture(device, width, height, format);
+        adjuster.set(queue, adjustments)?;
+        adjuster.apply_into(device, queue, &current, &output)?;
+        current = output;
+    }
+
+    let pixels: Vec<[f32; 4]> = bytemuck::pod_collect_to_vec(&read_texture(device, queue, &current)?);
+    lut.data = pixels.iter().map(|&[r, g, b, _]| [r, g, b]).collect();
+    lut.title = Some("baked adjustments".to_string());
+    Ok(lut)
+}
+
+/// .cube LUT 调色
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+
+    // yuv2rgb 中的帧
+    let input_image = image::load_from_memory(include_bytes!("../images/capture.jpg"))?.to_rgba8();
+    let (width, height) = input_image.dimensions();
+    let frame = yuv_to_rgba_cpu(include_bytes!("../images/capture.yuv"), width as i32, height as i32);
+    let frame_image = image::RgbaImage::from_raw(width, height, frame).ok_or(anyhow::anyhow!("invalid yuv frame"))?;
+    let frame_texture = texture_from_image(&device, &queue, &frame_image);
+
+    // 把一组调整烘焙成 LUT 并保存，再读回来应用
+    let chain = [
+        ColorAdjustments {
+            temperature: 5000.0,
+            ..Default::default()
+        },
+        ColorAdjustments {
+            contrast: 1.2,
+            saturation: 1.2,
+            ..Default::default()
+        },
+    ];
+    let baked = bake_adjustments(&device, &queue, &chain, 33)?;
+    baked.save("./outputs/grade.cube")?;
+    let loaded = CubeLut::load("./outputs/grade.cube")?;
+
+    let gpu_lut = GpuLut::new(&device, &queue, &loaded)?;
+    for (interpolation, name) in [(LutInterpolation::Trilinear, "trilinear"), (LutInterpolation::Tetrahedral, "tetrahedral")] {
+        let graded = gpu_lut.apply(&device, &queue, &frame_texture, interpolation)?;
+        image_from_texture(&device, &queue, &graded)?.save(format!("./outputs/capture_lut_{name}.png"))?;
+    }
+
+    // 1D LUT: 提亮暗部的曲线
+    let curve = CubeLut::parse(
+        &(0..16)
+            .map(|i| {
+                let value = (i as f32 / 15.0).powf(0.7);
+                format!("{value} {value} {value}\n")
+            })
+            .fold("LUT_1D_SIZE 16\n".to_string(), |text, line| text + &line),
+    )?;
+    let curved = GpuLut::new(&device, &queue, &curve)?.apply(&device, &queue, &frame_texture, LutInterpolation::default())?;
+    image_from_texture(&device, &queue, &curved)?.save("./outputs/capture_lut_1d.png")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adjust::adjust_color;
+    use crate::adjust::HueSpace;
+    use crate::radix_sort::xorshift;
+    use crate::texture::rgba32f_from_texture;
+    use crate::texture::texture_from_rgba32f;
+    use crate::utils::max_abs_diff;
+    use crate::utils::random_image;
+    use crate::utils::random_rgba32f;
+    use crate::utils::test_device;
+    use image::Rgba32FImage;
+
+    /// CPU 上查表，与 lut.wgsl 相同
+    fn cpu_lookup(lut: &CubeLut, color: [f32; 3], interpolation: LutInterpolation) -> [f32; 3] {
+        let last = lut.size as usize - 1;
+        let position: [f32; 3] = std::array::from_fn(|i| {
+            let range = (lut.domain_max[i] - lut.domain_min[i]).max(1e-6);
+            ((color[i] - lut.domain_min[i]) / range).clamp(0.0, 1.0) * last as f32
+        });
+        let base = position.map(|p| p.floor() as usize);
+        let next = base.map(|b| (b + 1).min(last));
+        let f: [f32; 3] = std::array::from_fn(|i| position[i] - position[i].floor());
+        let add = |a: [f32; 3], b: [f32; 3], t: f32| -> [f32; 3] { std::array::from_fn(|i| a[i] + t * b[i]) };
+        let sub = |a: [f32; 3], b: [f32; 3]| -> [f32; 3] { std::array::from_fn(|i| a[i] - b[i]) };
+        let mix = |a: [f32; 3], b: [f32; 3], t: f32| -> [f32; 3] { std::array::from_fn(|i| a[i] * (1.0 - t) + b[i] * t) };
+
+        if lut.kind == LutKind::OneDimensional {
+            return std::array::from_fn(|i| mix(lut.data[base[i]], lut.data[next[i]], f[i])[i]);
+        }
+        let c = |x: bool, y: bool, z: bool| {
+            let index = |high: bool, i: usize| if high { next[i] } else { base[i] };
+            lut.data[index(x, 0) + (index(y, 1) + index(z, 2) * lut.size as usize) * lut.size as usize]
+        };
+        let (c000, c111) = (c(false, false, false), c(true, true, true));
+        let (c100, c010, c001) = (c(true, false, false), c(false, true, false), c(false, false, true));
+        let (c110, c101, c011) = (c(true, true, false), c(true, false, true), c(false, true, true));
+        if interpolation == LutInterpolation::Trilinear {
+            let c00 = mix(c000, c100, f[0]);
+            let c10 = mix(c010, c110, f[0]);
+            let c01 = mix(c001, c101, f[0]);
+            let c11 = mix(c011, c111, f[0]);
+            return mix(mix(c00, c10, f[1]), mix(c01, c11, f[1]), f[2]);
+        }
+        // 依次走过的三个顶点，与 lut.wgsl 中的分支对应
+        let [x, y, z] = f;
+        let (steps, corners) = if x > y {
+            if y > z {
+                ([x, y, z], [c100, c110, c111])
+            } else if x > z {
+                ([x, z, y], [c100, c101, c111])
+            } else {
+                ([z, x, y], [c001, c101, c111])
+            }
+        } else if z > y {
+            ([z, y, x], [c001, c011, c111])
+        } else if z > x {
+            ([y, z, x], [c010, c011, c111])
+        } else {
+            ([y, x, z], [c010, c110, c111])
+        };
+        let mut result = add(c000, sub(corners[0], c000), steps[0]);
+        result = add(result, sub(corners[1], corners[0]), steps[1]);
+        add(result, sub(corners[2], corners[1]), steps[2])
+    }
+
+    fn random_lut(kind: LutKind, size: u32, seed: u32) -> CubeLut {
+        let mut state = seed;
+        let count = if kind == LutKind::OneDimensional { size } else { size * size * size };
+        CubeLut {
+            title: None,
+            kind,
+            size,
+            domain_min: [-0.1, 0.0, 0.05],
+            domain_max: [1.1, 1.0, 0.9],
+            data: (0..count).map(|_| [(); 3].map(|_| xorshift(&mut state) as f32 / u32::MAX as f32)).collect(),
+        }
+    }
+
+    #[test]
+    fn apply_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        // 输入超出 0~1，覆盖定义域外的截断
+        let input = Rgba32FImage::from_fn(32, 24, |x, y| {
+            let pixel = random_rgba32f(32, 24, 71).get_pixel(x, y).0;
+            image::Rgba([pixel[0] * 1.4 - 0.2, pixel[1], pixel[2] * 1.2, pixel[3]])
+        });
+        let texture = texture_from_rgba32f(&device, &queue, &input, wgpu::TextureFormat::Rgba32Float).unwrap();
+        let cases = [
+            (random_lut(LutKind::ThreeDimensional, 5, 72), LutInterpolation::Trilinear),
+            (random_lut(LutKind::ThreeDimensional, 5, 72), LutInterpolation::Tetrahedral),
+            (random_lut(LutKind::ThreeDimensional, 2, 73), LutInterpolation::Tetrahedral),
+            (random_lut(LutKind::OneDimensional, 9, 74), LutInterpolation::Trilinear),
+        ];
+        for (lut, interpolation) in cases {
+            let output = GpuLut::new(&device, &queue, &lut).unwrap().apply(&device, &queue, &texture, interpolation).unwrap();
+            let output = rgba32f_from_texture(&device, &queue, &output).unwrap();
+            let expected = Rgba32FImage::from_fn(32, 24, |x, y| {
+                let [r, g, b, a] = input.get_pixel(x, y).0;
+                let [r, g, b] = cpu_lookup(&lut, [r, g, b], interpolation);
+                image::Rgba([r, g, b, a])
+            });
+            // 表中的值在 0~1 之间，误差只来自插值的计算顺序
+            let diff = max_abs_diff(output.as_raw(), expected.as_raw());
+            assert!(diff <= 1e-5, "{:?} {interpolation:?}: {diff}", lut.kind);
+        }
+    }
+
+    #[test]
+    fn identity_is_lossless() {
+        assert!(CubeLut::identity(1).is_err());
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_image(30, 20, 75);
+        let texture = texture_from_image(&device, &queue, &input);
+        let lut = GpuLut::new(&device, &queue, &CubeLut::identity(17).unwrap()).unwrap();
+        for interpolation in [LutInterpolation::Trilinear, LutInterpolation::Tetrahedral] {
+            let output = lut.apply(&device, &queue, &texture, interpolation).unwrap();
+            // 恒等表的插值结果与输入只差浮点舍入，量化到 8 位后不变
+            assert!(image_from_texture(&device, &queue, &output).unwrap() == input, "{interpolation:?}");
+        }
+    }
+
+    #[test]
+    fn baked_lut_matches_direct_adjustments() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_rgba32f(32, 24, 76);
+        let texture = texture_from_rgba32f(&device, &queue, &input, wgpu::TextureFormat::Rgba32Float).unwrap();
+        let direct = |chain: &[ColorAdjustments]| {
+            let mut output = adjust_color(&device, &queue, &texture, &chain[0]).unwrap();
+            for adjustments in &chain[1..] {
+                output = adjust_color(&device, &queue, &output, adjustments).unwrap();
+            }
+            rgba32f_from_texture(&device, &queue, &output).unwrap()
+        };
+        let baked = |chain: &[ColorAdjustments], size: u32, interpolation: LutInterpolation| {
+            let lut = bake_adjustments(&device, &queue, chain, size).unwrap();
+            let output = GpuLut::new(&device, &queue, &lut).unwrap().apply(&device, &queue, &texture, interpolation).unwrap();
+            rgba32f_from_texture(&device, &queue, &output).unwrap()
+        };
+
+        // 白平衡、曝光和不截断的对比度对 RGB 是线性的，三线性插值可以精确还原
+        let linear = [
+            ColorAdjustments { temperature: 5000.0, tint: 0.2, ..Default::default() },
+            ColorAdjustments { exposure: 0.5, contrast: 0.8, ..Default::default() },
+        ];
+        let diff = max_abs_diff(baked(&linear, 5, LutInterpolation::Trilinear).as_raw(), direct(&linear).as_raw());
+        assert!(diff <= 1e-5, "{diff}");
+
+        // 非线性的调整只在格点上精确。gamma 在 0 附近很陡，色相/饱和度在色相分区处有折点，
+        // 33 格时格点之间的插值误差最大约 6 级，平均不到 0.25 级。超出 0~1 的值在 LUT 中会被截断，只比较可以显示的部分
+        let graded = [
+            ColorAdjustments { temperature: 5000.0, ..Default::default() },
+            ColorAdjustments { contrast: 1.2, saturation: 1.2, gamma: 1.2, ..Default::default() },
+            ColorAdjustments { hue: 15.0, hue_space: HueSpace::Hsl, ..Default::default() },
+        ];
+        let expected = direct(&graded);
+        for interpolation in [LutInterpolation::Trilinear, LutInterpolation::Tetrahedral] {
+            let output = baked(&graded, 33, interpolation);
+            let diffs: Vec<f32> = output
+                .as_raw()
+                .iter()
+                .zip(expected.as_raw())
+                .map(|(a, b)| (a.clamp(0.0, 1.0) - b.clamp(0.0, 1.0)).abs() * 255.0)
+                .collect();
+            let max = diffs.iter().copied().fold(0.0, f32::max);
+            let mean = diffs.iter().sum::<f32>() / diffs.len() as f32;
+            assert!(max <= 8.0 && mean <= 0.5, "{interpolation:?}: max {max} mean {mean}");
+        }
+        assert!(bake_adjustments(&device, &queue, &linear, 1).is_err());
+    }
+
+    #[test]
+    fn parse_and_write_cube_files() {
+        let source = "# comment\nTITLE \"warm\"\nLUT_3D_SIZE 2\nDOMAIN_MIN 0 0 0\nDOMAIN_MAX 1 2 1\nLUT_3D_INPUT_RANGE_UNKNOWN 1\n\n\
+                      0 0 0\n1 0 0\n0 1 0\n1 1 0\n0 0 1\n1 0 1\n0 1 1\n1 1 1\n";
+        let lut = CubeLut::parse(source).unwrap();
+        assert_eq!(lut.title.as_deref(), Some("warm"));
+        assert_eq!((lut.kind, lut.size, lut.domain_max), (LutKind::ThreeDimensional, 2, [1.0, 2.0, 1.0]));
+        assert_eq!(lut.data, CubeLut::identity(2).unwrap().data);
+        assert_eq!(CubeLut::parse(&lut.to_cube_string()).unwrap(), lut);
+
+        let curve = CubeLut::parse("LUT_1D_SIZE 3\nLUT_1D_INPUT_RANGE 0 4\n0 0 0\n0.5 0.25 0.1\n1 1 1\n").unwrap();
+        assert_eq!((curve.kind, curve.domain_min, curve.domain_max), (LutKind::OneDimensional, [0.0; 3], [4.0; 3]));
+
+        // 写出时保留 6 位小数
+        let identity = CubeLut::identity(17).unwrap();
+        let reparsed = CubeLut::parse(&identity.to_cube_string()).unwrap();
+        let flatten = |lut: &CubeLut| lut.data.iter().flatten().copied().collect::<Vec<f32>>();
+        assert!(max_abs_diff(&flatten(&reparsed), &flatten(&identity)) <= 5e-7);
+
+        assert!(CubeLut::parse("0 0 0\n").is_err());
+        assert!(CubeLut::parse("LUT_1D_SIZE 1\n0 0 0\n").is_err());
+        assert!(CubeLut::parse("LUT_1D_SIZE 2\n0 0 0\n").is_err());
+        assert!(CubeLut::parse("LUT_1D_SIZE 2\n0 0 0\n1 x 1\n").is_err());
+        assert!(CubeLut::parse("LUT_1D_SIZE 2\n0 0\n1 1 1\n").is_err());
+
+        // 2000000³ 在 u32 中会溢出，必须在分配和比较条目数之前拒绝
+        assert!(CubeLut::parse("LUT_3D_SIZE 2000000\n0 0 0\n").is_err());
+        assert!(CubeLut::parse(&format!("LUT_3D_SIZE {}\n0 0 0\n", MAX_3D_SIZE + 1)).is_err());
+        assert!(CubeLut::parse(&format!("LUT_1D_SIZE {}\n0 0 0\n", MAX_1D_SIZE + 1)).is_err());
+        assert!(CubeLut::identity(MAX_3D_SIZE + 1).is_err());
+        assert_eq!(entry_count(LutKind::ThreeDimensional, MAX_3D_SIZE).unwrap(), 1 << 24);
+    }
+
+    #[test]
+    fn large_1d_lut_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_rgba32f(40, 30, 76);
+        let texture = texture_from_rgba32f(&device, &queue, &input, wgpu::TextureFormat::Rgba32Float).unwrap();
+        // 超过 max_texture_dimension_3d (2048) 和 max_texture_dimension_2d 的 1D LUT
+        let max_dimension = device.limits().max_texture_dimension_3d.max(device.limits().max_texture_dimension_2d);
+        for size in [4096, max_dimension + 3, MAX_1D_SIZE] {
+            let lut = random_lut(LutKind::OneDimensional, size, 77);
+            let output = GpuLut::new(&device, &queue, &lut).unwrap().apply(&device, &queue, &texture, LutInterpolation::Trilinear).unwrap();
+            let output = rgba32f_from_texture(&device, &queue, &output).unwrap();
+            let expected = Rgba32FImage::from_fn(40, 30, |x, y| {
+                let [r, g, b, a] = input.get_pixel(x, y).0;
+                let [r, g, b] = cpu_lookup(&lut, [r, g, b], LutInterpolation::Trilinear);
+                image::Rgba([r, g, b, a])
+            });
+            let diff = max_abs_diff(output.as_raw(), expected.as_raw());
+            assert!(diff <= 1e-5, "{size}: {diff}");
+        }
+
+        let mut truncated = random_lut(LutKind::OneDimensional, 16, 78);
+        truncated.data.pop();
+        assert!(GpuLut::new(&device, &queue, &truncated).is_err());
+        let oversized = CubeLut {
+            size: MAX_1D_SIZE + 1,
+            ..random_lut(LutKind::OneDimensional, 2, 78)
+        };
+        assert!(GpuLut::new(&device, &queue, &oversized).is_err());
+    }
+}
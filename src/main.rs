@@ -2,6 +2,8 @@ use anyhow::Ok;
 use anyhow::Result;
 
 mod utils;
+mod buffer;
+mod texture;
 mod triangle;
 mod grayscale;
 mod yuv2rgb;
@@ -10,6 +12,30 @@ mod matrix2;
 mod index;
 mod binary;
 mod rotate;
+mod scan;
+mod radix_sort;
+mod histogram;
+mod blur;
+mod convolution;
+mod edge;
+mod morphology;
+mod labeling;
+mod resize;
+mod roi;
+mod warp;
+mod remap;
+mod adjust;
+mod lut;
+mod linear;
+mod high_depth;
+mod tonemap;
+mod composite;
+mod chroma_key;
+mod denoise;
+mod sharpen;
+mod mipmap;
+mod pyramid;
+mod template_match;
 
 fn main() -> Result<()> {
     // 绘制三角形
@@ -27,5 +53,53 @@ fn main() -> Result<()> {
     // binary::main()?;
     // 图像旋转
     // rotate::main()?;
+    // 前缀和
+    // scan::main()?;
+    // 基数排序
+    // radix_sort::main()?;
+    // 直方图和均衡化
+    // histogram::main()?;
+    // 图像模糊
+    // blur::main()?;
+    // 二维卷积
+    // convolution::main()?;
+    // 边缘检测
+    // edge::main()?;
+    // 形态学运算
+    // morphology::main()?;
+    // 连通域标记
+    // labeling::main()?;
+    // 图像缩放
+    // resize::main()?;
+    // 裁剪、扩展边界和局部处理
+    // roi::main()?;
+    // 仿射变换和透视变换
+    // warp::main()?;
+    // 重映射和镜头去畸变
+    // remap::main()?;
+    // 颜色调整
+    // adjust::main()?;
+    // LUT 调色
+    // lut::main()?;
+    // 线性光处理和灰度算法
+    // linear::main()?;
+    // 高位深和浮点图像
+    // high_depth::main()?;
+    // HDR 色调映射
+    // tonemap::main()?;
+    // 图像合成和混合模式
+    // composite::main()?;
+    // 色键抠像
+    // chroma_key::main()?;
+    // 保边去噪
+    // denoise::main()?;
+    // 锐化和细节增强
+    // sharpen::main()?;
+    // Mipmap 生成
+    // mipmap::main()?;
+    // 图像金字塔
+    // pyramid::main()?;
+    // 模板匹配
+    // template_match::main()?;
     Ok(())
 }
@@ -2,14 +2,13 @@ use std::borrow::Cow;
 use anyhow::Ok;
 use anyhow::Result;
 use pollster::FutureExt;
-use wgpu::util::BufferInitDescriptor;
-use wgpu::util::DeviceExt;
 use wgpu::PipelineCompilationOptions;
 use wgpu::PipelineLayoutDescriptor;
 
+use crate::buffer::GpuBuffer;
+
 /// 矩阵计算
 /// 参考 https://developer.chrome.com/docs/capabilities/web-apis/gpu-compute?hl=zh-cn
-
 #[allow(dead_code)]
 pub fn main() -> Result<()>{
     let instance = wgpu::Instance::default();
@@ -29,11 +28,7 @@ pub fn main() -> Result<()>{
         5., 6., 7., 8.
       ];
     
-    let first_matrix_buffer = device.create_buffer_init(&BufferInitDescriptor {
-        label: None,
-        usage: wgpu::BufferUsages::STORAGE,
-        contents: bytemuck::cast_slice(first_matrix),
-    });
+    let first_matrix_buffer = GpuBuffer::storage(&device, first_matrix);
 
     // 第二个矩阵
     let second_matrix = &[
@@ -44,21 +39,12 @@ pub fn main() -> Result<()>{
         7., 8.
       ];
     
-    let second_matrix_buffer = device.create_buffer_init(&BufferInitDescriptor {
-        label: None,
-        usage: wgpu::BufferUsages::STORAGE,
-        contents: bytemuck::cast_slice(second_matrix),
-    });
+    let second_matrix_buffer = GpuBuffer::storage(&device, second_matrix);
 
     // 结果矩阵
-    let result_matrix_buffer_size = std::mem::size_of::<f32>() * (2 + first_matrix[0] as usize * second_matrix[1] as usize);
+    let result_matrix_len = 2 + first_matrix[0] as usize * second_matrix[1] as usize;
 
-    let result_matrix_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-        label: None,
-        size: result_matrix_buffer_size as u64,
-        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
-        mapped_at_creation: false,
-    });
+    let result_matrix_buffer = GpuBuffer::<f32>::storage_zeroed(&device, result_matrix_len);
 
     // 绑定组布局和绑定组
     let bind_group_layout =
@@ -155,38 +141,13 @@ pub fn main() -> Result<()>{
         cpass.dispatch_workgroups(workgroup_count_x, workgroup_count_y, 1);
     }
 
-    // 获取用于在未映射状态下读取的 GPU 缓冲区
-    // 创建一个 GPU 缓冲区作为目的地，以使用 copyBufferToBuffer 复制结果矩阵缓冲区。
-    // 最后，使用 copyEncoder.finish() 完成编码命令，然后使用 GPU 命令调用 device.queue.submit()，将这些命令提交到 GPU 设备队列。
-    let gpu_read_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-        label: None,
-        size: result_matrix_buffer_size as u64,
-        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
-        mapped_at_creation: false,
-    });
-
-    // 编码用于将缓冲区复制到缓冲区的命令。
-    encoder.copy_buffer_to_buffer(&result_matrix_buffer, 0, &gpu_read_buffer, 0, result_matrix_buffer_size as u64);
-
     // Submit GPU commands.
     queue.submit(Some(encoder.finish()));
 
     println!("command提交成功..");
 
     // 读取结果矩阵
-
-    let buffer_slice = gpu_read_buffer.slice(..);
-    buffer_slice.map_async(wgpu::MapMode::Read, |_| {});
-
-    device.poll(wgpu::Maintain::Wait);
-
-    let padded = buffer_slice.get_mapped_range();
-    let mut data: Vec<u8> = vec![0; result_matrix_buffer_size];
-    data.copy_from_slice(&padded);
-
-    println!("读取成功{:?}", data);
-
-    let data:&[f32] = bytemuck::cast_slice(&padded[..]);
+    let data = result_matrix_buffer.read_blocking(&device, &queue)?;
 
     println!("转换成功{:?}", data);
     
@@ -0,0 +1,330 @@
+#![allow(dead_code)]
+
+use anyhow::Ok;
+use anyhow::Result;
+use image::load_from_memory;
+
+use crate::buffer::GpuBuffer;
+use crate::roi::crop;
+use crate::roi::Rect;
+use crate::texture::create_output_texture;
+use crate::texture::create_texture_with_mips;
+use crate::texture::image_from_texture;
+use crate::texture::mip_level_count;
+use crate::texture::mip_view;
+use crate::texture::rgba32f_from_texture;
+use crate::texture::texture_from_image;
+use crate::texture::texture_from_luma32f;
+use crate::texture::with_storage_format;
+use crate::texture::OUTPUT_USAGE;
+use crate::utils::compute_work_group_count;
+use crate::utils::create_bind_group;
+use crate::utils::create_pipeline;
+use crate::utils::create_shader;
+use crate::utils::dispatch;
+use crate::utils::request_device;
+
+/// 生成下一层 mip 的滤波方式，与 mipmap.wgsl 一致
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MipFilter {
+    /// 按覆盖面积加权平均
+    #[default]
+    Box = 0,
+    /// Kaiser 窗 sinc，更锐利，可能有轻微振铃
+    Kaiser = 1,
+    /// 高斯，用于高斯金字塔
+    Gaussian = 2,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MipOptions {
+    pub filter: MipFilter,
+    /// Rgba8Unorm 纹理中的颜色是否为 sRGB 编码，是则在线性光中平均。其它格式的数据总是按线性处理
+    pub srgb: bool,
+}
+
+impl Default for MipOptions {
+    fn default() -> Self {
+        Self {
+            filter: MipFilter::default(),
+            srgb: true,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct MipParams {
+    filter_type: u32,
+    srgb: u32,
+    _padding: [u32; 2],
+}
+
+/// 第 level 层 mip 的大小
+pub fn mip_level_size(texture: &wgpu::Texture, level: u32) -> (u32, u32) {
+    ((texture.width() >> level).max(1), (texture.height() >> level).max(1))
+}
+
+/// 由第 0 层逐层生成其余各层 mip，纹理需要有 TEXTURE_BINDING 和 COPY_DST 用途
+pub fn generate_mipmaps(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture, options: &MipOptions) -> Result<()> {
+    let required = wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST;
+    anyhow::ensure!(
+        texture.usage().contains(required),
+        "texture usage {:?} must contain {required:?}",
+        texture.usage()
+    );
+    if texture.mip_level_count() == 1 {
+        return Ok(());
+    }
+
+    let format = texture.format();
+    let source = format!("{}\n{}", include_str!("../shaders/srgb.wgsl"), include_str!("../shaders/mipmap.wgsl"));
+    let shader = create_shader(device, "mipmap_shader_module", &with_storage_format(&source, format)?);
+    let pipeline = create_pipeline(device, &shader, "downsample");
+    let params = GpuBuffer::uniform(
+        device,
+        &MipParams {
+            filter_type: options.filter as u32,
+            srgb: (options.srgb && format == wgpu::TextureFormat::Rgba8Unorm) as u32,
+            _padding: [0; 2],
+        },
+    );
+
+    // 每一层单独一个 pass，读取上一层的写入结果。
+    // 先写入临时纹理再复制到对应的 mip 层: GL 后端绑定采样视图时会改写整个纹理的 base/max level，
+    // 同一纹理的其它层作为 storage 纹理时写入会被丢弃
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    for level in 1..texture.mip_level_count() {
+        let (width, height) = mip_level_size(texture, level);
+        let output = create_output_texture(device, width, height, format);
+        let input_view = mip_view(texture, level - 1);
+        let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = create_bind_group(
+            device,
+            &pipeline,
+            &[
+                (0, wgpu::BindingResource::TextureView(&input_view)),
+                (1, wgpu::BindingResource::TextureView(&output_view)),
+                (2, params.as_entire_binding()),
+            ],
+        );
+        let (x, y) = compute_work_group_count((width, height), (16, 16));
+        dispatch(&mut encoder, &pipeline, &bind_group, (x, y, 1));
+        encoder.copy_texture_to_texture(
+            output.as_image_copy(),
+            wgpu::TexelCopyTextureInfo {
+                texture,
+                mip_level: level,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            output.size(),
+        );
+    }
+    queue.submit(Some(encoder.finish()));
+
+    Ok(())
+}
+
+/// 复制 input 到新纹理的第 0 层并生成 levels 层 mip，levels 为 None 时生成到 1x1
+pub fn mipmapped(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, levels: Option<u32>, options: &MipOptions) -> Result<wgpu::Texture> {
+    let (width, height) = (input.width(), input.height());
+    let full = mip_level_count(width, height);
+    let levels = levels.unwrap_or(full);
+    anyhow::ensure!(levels >= 1 && levels <= full, "a {width}x{height} texture has 1 to {full} mip levels, got {levels}");
+
+    let texture = create_texture_with_mips(device, width, height, input.format(), OUTPUT_USAGE, levels);
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.copy_texture_to_texture(input.as_image_copy(), texture.as_image_copy(), input.size());
+    queue.submit(Some(encoder.finish()));
+
+    generate_mipmaps(device, queue, &texture, options)?;
+    Ok(texture)
+}
+
+/// 把第 level 层 mip 复制为单独的纹理，便于读回或交给其它操作
+pub fn extract_mip_level(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture, level: u32) -> Result<wgpu::Texture> {
+    anyhow::ensure!(
+        level < texture.mip_level_count(),
+        "level {level} is out of range, the texture has {} levels",
+        texture.mip_level_count()
+    );
+    let (width, height) = mip_level_size(texture, level);
+    let output = create_output_texture(device, width, height, texture.format());
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.copy_texture_to_texture(
+        wgpu::TexelCopyTextureInfo {
+            texture,
+            mip_level: level,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        output.as_image_copy(),
+        output.size(),
+    );
+    queue.submit(Some(encoder.finish()));
+
+    Ok(output)
+}
+
+/// 全部 mip 层，依次复制为单独的纹理
+pub fn mip_levels(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) -> Result<Vec<wgpu::Texture>> {
+    (0..texture.mip_level_count())
+        .map(|level| extract_mip_level(device, queue, texture, level))
+        .collect()
+}
+
+/// mip 链生成
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+
+    // 裁剪出奇数尺寸的图像
+    let capture = texture_from_image(&device, &queue, &load_from_memory(include_bytes!("../images/capture.jpg"))?.to_rgba8());
+    let input_texture = crop(&device, &queue, &capture, Rect::new(100, 100, 333, 217))?;
+
+    let texture = mipmapped(&device, &queue, &input_texture, None, &MipOptions::default())?;
+    for (level, level_texture) in mip_levels(&device, &queue, &texture)?.iter().enumerate() {
+        image_from_texture(&device, &queue, level_texture)?.save(format!("./outputs/capture_mip_box_{level}.png"))?;
+    }
+
+    let options = [
+        (MipOptions { filter: MipFilter::Kaiser, srgb: true }, "kaiser"),
+        (MipOptions { filter: MipFilter::Box, srgb: false }, "box_nonlinear"),
+    ];
+    for (options, name) in options {
+        let texture = mipmapped(&device, &queue, &input_texture, Some(4), &options)?;
+        for (level, level_texture) in mip_levels(&device, &queue, &texture)?.iter().enumerate() {
+            image_from_texture(&device, &queue, level_texture)?.save(format!("./outputs/capture_mip_{name}_{level}.png"))?;
+        }
+    }
+
+    // 单通道纹理
+    let gray = load_from_memory(include_bytes!("../images/sushi.png"))?.to_luma32f();
+    let gray_texture = texture_from_luma32f(&device, &queue, &gray);
+    let texture = mipmapped(&device, &queue, &gray_texture, Some(4), &MipOptions::default())?;
+    for (level, image) in mip_levels(&device, &queue, &texture)?.iter().enumerate() {
+        let image = rgba32f_from_texture(&device, &queue, image)?;
+        image::DynamicImage::ImageRgba32F(image).to_luma8().save(format!("./outputs/sushi_gray_mip_{level}.png"))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::max_u8_diff;
+    use crate::utils::random_image;
+    use crate::utils::test_device;
+    use image::RgbaImage;
+
+    /// CPU 上的盒式降采样(按覆盖面积加权)，srgb 为 true 时颜色在线性光中平均
+    fn cpu_box_downsample(image: &RgbaImage, srgb: bool) -> RgbaImage {
+        let (width, height) = image.dimensions();
+        let (out_width, out_height) = ((width / 2).max(1), (height / 2).max(1));
+        let (sx, sy) = (width as f64 / out_width as f64, height as f64 / out_height as f64);
+        let decode = |v: u8| {
+            let c = v as f64 / 255.0;
+            if !srgb {
+                c
+            } else if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        let encode = |c: f64| {
+            let v = if !srgb {
+                c
+            } else if c <= 0.0031308 {
+                c * 12.92
+            } else {
+                1.055 * c.powf(1.0 / 2.4) - 0.055
+            };
+            (v.clamp(0.0, 1.0) * 255.0).round() as u8
+        };
+        RgbaImage::from_fn(out_width, out_height, |x, y| {
+            let (x0, x1) = (x as f64 * sx, (x + 1) as f64 * sx);
+            let (y0, y1) = (y as f64 * sy, (y + 1) as f64 * sy);
+            let mut sum = [0.0f64; 4];
+            let mut weight_sum = 0.0;
+            for py in y0.floor() as u32..y1.ceil() as u32 {
+                let wy = y1.min(py as f64 + 1.0) - y0.max(py as f64);
+                for px in x0.floor() as u32..x1.ceil() as u32 {
+                    let wx = x1.min(px as f64 + 1.0) - x0.max(px as f64);
+                    let pixel = image.get_pixel(px, py);
+                    for (c, sum) in sum.iter_mut().enumerate() {
+                        let value = if c < 3 { decode(pixel[c]) } else { pixel[c] as f64 / 255.0 };
+                        *sum += wx * wy * value;
+                    }
+                    weight_sum += wx * wy;
+                }
+            }
+            let [r, g, b, a] = sum.map(|sum| sum / weight_sum);
+            image::Rgba([encode(r), encode(g), encode(b), (a * 255.0).round() as u8])
+        })
+    }
+
+    #[test]
+    fn box_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        // 奇数尺寸，每个输出像素覆盖 2.x 个输入像素
+        let input = random_image(45, 38, 5);
+        let texture = texture_from_image(&device, &queue, &input);
+        for srgb in [false, true] {
+            let mipmapped = mipmapped(&device, &queue, &texture, None, &MipOptions { filter: MipFilter::Box, srgb }).unwrap();
+            let levels = mip_levels(&device, &queue, &mipmapped).unwrap();
+            assert_eq!(levels.len(), 6);
+            let mut previous = input.clone();
+            for (level, level_texture) in levels.iter().enumerate().skip(1) {
+                let image = image_from_texture(&device, &queue, level_texture).unwrap();
+                assert_eq!(image.dimensions(), mip_level_size(&mipmapped, level as u32));
+                // 每层都从 GPU 的上一层算起，避免逐层累积舍入误差，剩下的只有写回 8 位时的舍入
+                assert!(max_u8_diff(&image, &cpu_box_downsample(&previous, srgb)) <= 1, "srgb {srgb} level {level}");
+                previous = image;
+            }
+        }
+    }
+
+    #[test]
+    fn filters_keep_constant_images() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = RgbaImage::from_pixel(37, 21, image::Rgba([30, 120, 200, 180]));
+        let texture = texture_from_image(&device, &queue, &input);
+        for filter in [MipFilter::Box, MipFilter::Kaiser, MipFilter::Gaussian] {
+            for srgb in [false, true] {
+                let mipmapped = mipmapped(&device, &queue, &texture, None, &MipOptions { filter, srgb }).unwrap();
+                for level in mip_levels(&device, &queue, &mipmapped).unwrap() {
+                    let image = image_from_texture(&device, &queue, &level).unwrap();
+                    let expected = RgbaImage::from_pixel(image.width(), image.height(), image::Rgba([30, 120, 200, 180]));
+                    // 权重归一化，只有 sRGB 转换的舍入误差
+                    assert!(max_u8_diff(&image, &expected) <= 1, "{filter:?} srgb {srgb}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn single_channel_box_chain_ends_at_mean() {
+        let Some((device, queue)) = test_device() else { return };
+        let gray = image::ImageBuffer::from_fn(27, 14, |x, y| image::Luma([((x * 7 + y * 13) % 31) as f32 / 31.0]));
+        let mean = gray.as_raw().iter().map(|&v| v as f64).sum::<f64>() / gray.as_raw().len() as f64;
+        let texture = mipmapped(&device, &queue, &texture_from_luma32f(&device, &queue, &gray), None, &MipOptions::default()).unwrap();
+        assert_eq!(texture.format(), wgpu::TextureFormat::R32Float);
+        let last = extract_mip_level(&device, &queue, &texture, texture.mip_level_count() - 1).unwrap();
+        assert_eq!((last.width(), last.height()), (1, 1));
+        // 每层的输出像素覆盖相同的面积，所以逐层的面积平均就是全图均值
+        let value = rgba32f_from_texture(&device, &queue, &last).unwrap().get_pixel(0, 0)[0];
+        assert!((value as f64 - mean).abs() < 1e-5, "{value} {mean}");
+    }
+
+    #[test]
+    fn invalid_levels_are_rejected() {
+        let Some((device, queue)) = test_device() else { return };
+        let texture = texture_from_image(&device, &queue, &random_image(8, 5, 1));
+        assert!(mipmapped(&device, &queue, &texture, Some(0), &MipOptions::default()).is_err());
+        assert!(mipmapped(&device, &queue, &texture, Some(5), &MipOptions::default()).is_err());
+        assert!(extract_mip_level(&device, &queue, &texture, 1).is_err());
+    }
+}
@@ -0,0 +1,405 @@
+#![allow(dead_code)]
+
+use anyhow::Ok;
+use anyhow::Result;
+use image::load_from_memory;
+
+use crate::buffer::GpuBuffer;
+use crate::texture::create_output_texture;
+use crate::texture::image_from_texture;
+use crate::texture::texture_from_image;
+use crate::texture::with_storage_format;
+use crate::utils::compute_work_group_count;
+use crate::utils::create_bind_group;
+use crate::utils::create_pipeline;
+use crate::utils::create_shader;
+use crate::utils::dispatch;
+use crate::utils::request_device;
+
+/// 结构元素的形状
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ElementShape {
+    #[default]
+    Rect,
+    Cross,
+    Ellipse,
+}
+
+/// 结构元素，锚点在中心
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StructuringElement {
+    width: u32,
+    height: u32,
+    mask: Vec<u32>,
+}
+
+impl StructuringElement {
+    /// 宽高必须是奇数
+    pub fn new(shape: ElementShape, width: u32, height: u32) -> Result<Self> {
+        anyhow::ensure!(
+            width % 2 == 1 && height % 2 == 1,
+            "structuring element size {width}x{height} must be odd"
+        );
+        let (center_x, center_y) = ((width / 2) as f32, (height / 2) as f32);
+        let mut mask = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let inside = match shape {
+                    ElementShape::Rect => true,
+                    ElementShape::Cross => x == width / 2 || y == height / 2,
+                    ElementShape::Ellipse => {
+                        let dx = (x as f32 - center_x) / (center_x + 0.5);
+                        let dy = (y as f32 - center_y) / (center_y + 0.5);
+                        dx * dx + dy * dy <= 1.0
+                    }
+                };
+                mask.push(inside as u32);
+            }
+        }
+        Ok(Self { width, height, mask })
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        self.mask[(y * self.width + x) as usize] != 0
+    }
+}
+
+/// 形态学运算
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MorphOperation {
+    Erode,
+    Dilate,
+    /// 开运算: 先腐蚀再膨胀，去除小的亮噪点
+    Open,
+    /// 闭运算: 先膨胀再腐蚀，填充小的暗孔洞
+    Close,
+    /// 膨胀 - 腐蚀
+    Gradient,
+    /// 原图 - 开运算
+    TopHat,
+    /// 闭运算 - 原图
+    BlackHat,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct MorphParams {
+    element_width: u32,
+    element_height: u32,
+    operation: u32,
+    _padding: u32,
+}
+
+struct Morphology<'a> {
+    device: &'a wgpu::Device,
+    morph_pipeline: wgpu::ComputePipeline,
+    difference_pipeline: wgpu::ComputePipeline,
+    element: GpuBuffer<u32>,
+    erode_params: GpuBuffer<MorphParams>,
+    dilate_params: GpuBuffer<MorphParams>,
+}
+
+impl<'a> Morphology<'a> {
+    fn new(device: &'a wgpu::Device, format: wgpu::TextureFormat, element: &StructuringElement) -> Result<Self> {
+        let source = with_storage_format(include_str!("../shaders/morphology.wgsl"), format)?;
+        let shader = create_shader(device, "morphology_shader_module", &source);
+        let params = |operation| {
+            GpuBuffer::uniform(
+                device,
+                &MorphParams {
+                    element_width: element.width,
+                    element_height: element.height,
+                    operation,
+                    _padding: 0,
+                },
+            )
+        };
+        Ok(Self {
+            device,
+            morph_pipeline: create_pipeline(device, &shader, "morph"),
+            difference_pipeline: create_pipeline(device, &shader, "difference"),
+            element: GpuBuffer::storage(device, &element.mask),
+            erode_params: params(0),
+            dilate_params: params(1),
+        })
+    }
+
+    fn output_like(&self, input: &wgpu::Texture) -> wgpu::Texture {
+        create_output_texture(self.device, input.width(), input.height(), input.format())
+    }
+
+    /// 腐蚀或膨胀 iterations 次，iterations 至少为 1。
+    /// 多次迭代时在两个纹理之间交替读写，不为每次迭代分配新纹理
+    fn encode_morph(&self, encoder: &mut wgpu::CommandEncoder, input: &wgpu::Texture, dilate: bool, iterations: u32) -> wgpu::Texture {
+        let params = if dilate { &self.dilate_params } else { &self.erode_params };
+        let (x, y) = compute_work_group_count((input.width(), input.height()), (16, 16));
+
+        let mut targets: Vec<wgpu::Texture> = (0..iterations.min(2)).map(|_| self.output_like(input)).collect();
+        for i in 0..iterations as usize {
+            let source = if i == 0 { input } else { &targets[(i - 1) % 2] };
+            let input_view = source.create_view(&wgpu::TextureViewDescriptor::default());
+            let output_view = targets[i % 2].create_view(&wgpu::TextureViewDescriptor::default());
+            let bind_group = create_bind_group(
+                self.device,
+                &self.morph_pipeline,
+                &[
+                    (0, wgpu::BindingResource::TextureView(&input_view)),
+                    (1, wgpu::BindingResource::TextureView(&output_view)),
+                    (2, self.element.as_entire_binding()),
+                    (3, params.as_entire_binding()),
+                ],
+            );
+            dispatch(encoder, &self.morph_pipeline, &bind_group, (x, y, 1));
+        }
+        targets.swap_remove((iterations as usize - 1) % 2)
+    }
+
+    /// first - second，小于 0 时截断为 0
+    fn encode_difference(&self, encoder: &mut wgpu::CommandEncoder, first: &wgpu::Texture, second: &wgpu::Texture) -> wgpu::Texture {
+        let output = self.output_like(first);
+        let first_view = first.create_view(&wgpu::TextureViewDescriptor::default());
+        let second_view = second.create_view(&wgpu::TextureViewDescriptor::default());
+        let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = create_bind_group(
+            self.device,
+            &self.difference_pipeline,
+            &[
+                (0, wgpu::BindingResource::TextureView(&first_view)),
+                (1, wgpu::BindingResource::TextureView(&output_view)),
+                (4, wgpu::BindingResource::TextureView(&second_view)),
+            ],
+        );
+        let (x, y) = compute_work_group_count((first.width(), first.height()), (16, 16));
+        dispatch(encoder, &self.difference_pipeline, &bind_group, (x, y, 1));
+        output
+    }
+}
+
+/// 形态学运算，适用于二值图和灰度图(逐通道计算)。
+/// 复合运算中的腐蚀和膨胀各执行 iterations 次，例如开运算为腐蚀 n 次后膨胀 n 次，iterations 不能为 0
+pub fn morphology(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    input: &wgpu::Texture,
+    operation: MorphOperation,
+    element: &StructuringElement,
+    iterations: u32,
+) -> Result<wgpu::Texture> {
+    anyhow::ensure!(iterations > 0, "iterations must be at least 1");
+    let morphology = Morphology::new(device, input.format(), element)?;
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let output = match operation {
+        MorphOperation::Erode => morphology.encode_morph(&mut encoder, input, false, iterations),
+        MorphOperation::Dilate => morphology.encode_morph(&mut encoder, input, true, iterations),
+        MorphOperation::Open => {
+            let eroded = morphology.encode_morph(&mut encoder, input, false, iterations);
+            morphology.encode_morph(&mut encoder, &eroded, true, iterations)
+        }
+        MorphOperation::Close => {
+            let dilated = morphology.encode_morph(&mut encoder, input, true, iterations);
+            morphology.encode_morph(&mut encoder, &dilated, false, iterations)
+        }
+        MorphOperation::Gradient => {
+            let dilated = morphology.encode_morph(&mut encoder, input, true, iterations);
+            let eroded = morphology.encode_morph(&mut encoder, input, false, iterations);
+            morphology.encode_difference(&mut encoder, &dilated, &eroded)
+        }
+        MorphOperation::TopHat => {
+            let eroded = morphology.encode_morph(&mut encoder, input, false, iterations);
+            let opened = morphology.encode_morph(&mut encoder, &eroded, true, iterations);
+            morphology.encode_difference(&mut encoder, input, &opened)
+        }
+        MorphOperation::BlackHat => {
+            let dilated = morphology.encode_morph(&mut encoder, input, true, iterations);
+            let closed = morphology.encode_morph(&mut encoder, &dilated, false, iterations);
+            morphology.encode_difference(&mut encoder, &closed, input)
+        }
+    };
+    queue.submit(Some(encoder.finish()));
+
+    Ok(output)
+}
+
+pub fn erode(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, element: &StructuringElement, iterations: u32) -> Result<wgpu::Texture> {
+    morphology(device, queue, input, MorphOperation::Erode, element, iterations)
+}
+
+pub fn dilate(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, element: &StructuringElement, iterations: u32) -> Result<wgpu::Texture> {
+    morphology(device, queue, input, MorphOperation::Dilate, element, iterations)
+}
+
+/// 形态学运算
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+
+    // 二值化后的图像
+    let mut input_image = load_from_memory(include_bytes!("../images/rust.png"))?.to_rgba8();
+    for pixel in input_image.pixels_mut() {
+        let luminance = 0.299 * pixel[0] as f32 + 0.587 * pixel[1] as f32 + 0.114 * pixel[2] as f32;
+        let value = if luminance >= 0.19 * 255.0 { 255 } else { 0 };
+        *pixel = image::Rgba([value, value, value, 255]);
+    }
+    let input_texture = texture_from_image(&device, &queue, &input_image);
+
+    let element = StructuringElement::new(ElementShape::Ellipse, 5, 5)?;
+    let eroded = erode(&device, &queue, &input_texture, &element, 1)?;
+    image_from_texture(&device, &queue, &eroded)?.save("./outputs/rust_erode.png")?;
+
+    let operations = [
+        (MorphOperation::Open, "open"),
+        (MorphOperation::Close, "close"),
+        (MorphOperation::Gradient, "gradient"),
+        (MorphOperation::TopHat, "tophat"),
+        (MorphOperation::BlackHat, "blackhat"),
+    ];
+    let cross = StructuringElement::new(ElementShape::Cross, 3, 3)?;
+    for (operation, name) in operations {
+        let output = morphology(&device, &queue, &input_texture, operation, &cross, 2)?;
+        image_from_texture(&device, &queue, &output)?.save(format!("./outputs/rust_{name}.png"))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::rgba32f_from_texture;
+    use crate::texture::texture_from_rgba32f;
+    use crate::utils::random_image;
+    use crate::utils::random_rgba32f;
+    use crate::utils::test_device;
+    use image::Rgba32FImage;
+
+    /// CPU 上的一次腐蚀或膨胀，图像外的像素不参与计算，alpha 不变
+    fn cpu_morph(image: &Rgba32FImage, element: &StructuringElement, dilate: bool) -> Rgba32FImage {
+        let (width, height) = image.dimensions();
+        let (element_width, element_height) = element.size();
+        Rgba32FImage::from_fn(width, height, |x, y| {
+            let mut result = *image.get_pixel(x, y);
+            for ey in 0..element_height {
+                for ex in 0..element_width {
+                    let sx = x as i32 + ex as i32 - (element_width / 2) as i32;
+                    let sy = y as i32 + ey as i32 - (element_height / 2) as i32;
+                    if !element.contains(ex, ey) || sx < 0 || sy < 0 || sx >= width as i32 || sy >= height as i32 {
+                        continue;
+                    }
+                    let pixel = image.get_pixel(sx as u32, sy as u32);
+                    for channel in 0..3 {
+                        result[channel] = if dilate { result[channel].max(pixel[channel]) } else { result[channel].min(pixel[channel]) };
+                    }
+                }
+            }
+            result
+        })
+    }
+
+    fn cpu_repeat(image: &Rgba32FImage, element: &StructuringElement, dilate: bool, iterations: u32) -> Rgba32FImage {
+        (0..iterations).fold(image.clone(), |image, _| cpu_morph(&image, element, dilate))
+    }
+
+    /// first - second，截断为 0，alpha 取 first
+    fn cpu_difference(first: &Rgba32FImage, second: &Rgba32FImage) -> Rgba32FImage {
+        Rgba32FImage::from_fn(first.width(), first.height(), |x, y| {
+            let (a, b) = (first.get_pixel(x, y), second.get_pixel(x, y));
+            image::Rgba([(a[0] - b[0]).max(0.0), (a[1] - b[1]).max(0.0), (a[2] - b[2]).max(0.0), a[3]])
+        })
+    }
+
+    fn cpu_morphology(image: &Rgba32FImage, operation: MorphOperation, element: &StructuringElement, n: u32) -> Rgba32FImage {
+        let erode = |image: &Rgba32FImage| cpu_repeat(image, element, false, n);
+        let dilate = |image: &Rgba32FImage| cpu_repeat(image, element, true, n);
+        match operation {
+            MorphOperation::Erode => erode(image),
+            MorphOperation::Dilate => dilate(image),
+            MorphOperation::Open => dilate(&erode(image)),
+            MorphOperation::Close => erode(&dilate(image)),
+            MorphOperation::Gradient => cpu_difference(&dilate(image), &erode(image)),
+            MorphOperation::TopHat => cpu_difference(image, &dilate(&erode(image))),
+            MorphOperation::BlackHat => cpu_difference(&erode(&dilate(image)), image),
+        }
+    }
+
+    const OPERATIONS: [MorphOperation; 7] = [
+        MorphOperation::Erode,
+        MorphOperation::Dilate,
+        MorphOperation::Open,
+        MorphOperation::Close,
+        MorphOperation::Gradient,
+        MorphOperation::TopHat,
+        MorphOperation::BlackHat,
+    ];
+
+    #[test]
+    fn morphology_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_rgba32f(41, 23, 31);
+        let texture = texture_from_rgba32f(&device, &queue, &input, wgpu::TextureFormat::Rgba32Float).unwrap();
+        let elements = [
+            StructuringElement::new(ElementShape::Rect, 3, 3).unwrap(),
+            StructuringElement::new(ElementShape::Cross, 5, 3).unwrap(),
+            StructuringElement::new(ElementShape::Ellipse, 7, 5).unwrap(),
+        ];
+        for element in &elements {
+            for operation in OPERATIONS {
+                for iterations in [1, 2, 3] {
+                    let output = morphology(&device, &queue, &texture, operation, element, iterations).unwrap();
+                    let expected = cpu_morphology(&input, operation, element, iterations);
+                    // 取最小值、最大值和相减都是精确的，结果应完全一致
+                    assert!(
+                        rgba32f_from_texture(&device, &queue, &output).unwrap() == expected,
+                        "{operation:?} {element:?} x{iterations}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn erode_binary_rgba8_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        let mut input = random_image(50, 40, 32);
+        for pixel in input.pixels_mut() {
+            let value = if pixel[0] >= 64 { 255 } else { 0 };
+            *pixel = image::Rgba([value, value, value, pixel[3]]);
+        }
+        let texture = texture_from_image(&device, &queue, &input);
+        let input_f32 = Rgba32FImage::from_fn(50, 40, |x, y| image::Rgba(input.get_pixel(x, y).0.map(|v| v as f32 / 255.0)));
+        let element = StructuringElement::new(ElementShape::Ellipse, 5, 5).unwrap();
+        for dilate in [false, true] {
+            let output = morphology(&device, &queue, &texture, if dilate { MorphOperation::Dilate } else { MorphOperation::Erode }, &element, 2).unwrap();
+            let expected = cpu_repeat(&input_f32, &element, dilate, 2);
+            let expected = image::RgbaImage::from_fn(50, 40, |x, y| image::Rgba(expected.get_pixel(x, y).0.map(|v| (v * 255.0).round() as u8)));
+            assert!(image_from_texture(&device, &queue, &output).unwrap() == expected);
+        }
+    }
+
+    #[test]
+    fn rejects_zero_iterations() {
+        let Some((device, queue)) = test_device() else { return };
+        let texture = texture_from_image(&device, &queue, &random_image(8, 8, 33));
+        let element = StructuringElement::new(ElementShape::Rect, 3, 3).unwrap();
+        assert!(morphology(&device, &queue, &texture, MorphOperation::Open, &element, 0).is_err());
+    }
+
+    #[test]
+    fn structuring_element_shapes() {
+        let rows = |element: &StructuringElement| {
+            let (width, height) = element.size();
+            (0..height)
+                .map(|y| (0..width).map(|x| if element.contains(x, y) { '#' } else { '.' }).collect::<String>())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(rows(&StructuringElement::new(ElementShape::Cross, 5, 3).unwrap()), ["..#..", "#####", "..#.."]);
+        assert_eq!(
+            rows(&StructuringElement::new(ElementShape::Ellipse, 5, 5).unwrap()),
+            [".###.", "#####", "#####", "#####", ".###."]
+        );
+        assert!(StructuringElement::new(ElementShape::Rect, 4, 3).is_err());
+    }
+}
@@ -0,0 +1,220 @@
+#![allow(dead_code)]
+
+use anyhow::Ok;
+use anyhow::Result;
+use image::load_from_memory;
+use image::Rgba32FImage;
+
+use crate::mipmap::mip_levels;
+use crate::mipmap::mipmapped;
+use crate::mipmap::MipFilter;
+use crate::mipmap::MipOptions;
+use crate::resize::resize_window;
+use crate::resize::ResizeFilter;
+use crate::sharpen::per_pixel;
+use crate::texture::image_from_texture;
+use crate::texture::mip_level_count;
+use crate::texture::rgba32f_from_texture;
+use crate::texture::texture_from_image;
+use crate::texture::texture_from_luma32f;
+use crate::utils::request_device;
+
+/// 拉普拉斯金字塔细节层和重建中间结果的格式，细节层有负值
+pub const DETAIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+
+/// 高斯金字塔，第 0 层为输入的副本，每层大小减半，格式与输入相同。
+/// levels 超过 mip 层数时只生成到 1x1
+pub fn gaussian_pyramid(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, levels: u32) -> Result<Vec<wgpu::Texture>> {
+    anyhow::ensure!(levels > 0, "at least one level is required");
+    let levels = levels.min(mip_level_count(input.width(), input.height()));
+    let options = MipOptions {
+        filter: MipFilter::Gaussian,
+        srgb: false,
+    };
+    let texture = mipmapped(device, queue, input, Some(levels), &options)?;
+    mip_levels(device, queue, &texture)
+}
+
+/// 双线性上采样到 width x height，结果为 DETAIL_FORMAT 格式。
+/// 分解和重建都用它，8 位输入的上采样结果也不会被量化，两边完全一致
+fn upsample(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, width: u32, height: u32) -> Result<wgpu::Texture> {
+    let window = (0.0, 0.0, input.width() as f32, input.height() as f32);
+    resize_window(device, queue, input, window, (width, height), ResizeFilter::Bilinear, DETAIL_FORMAT)
+}
+
+/// 拉普拉斯金字塔: 前面各层为细节 G_k - up(G_k+1)(DETAIL_FORMAT 格式)，最后一层为最粗的高斯层
+pub fn laplacian_pyramid(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, levels: u32) -> Result<Vec<wgpu::Texture>> {
+    let mut gaussian = gaussian_pyramid(device, queue, input, levels)?;
+    let residual = gaussian.pop().ok_or(anyhow::anyhow!("empty pyramid"))?;
+
+    let mut pyramid = vec![];
+    for (level, current) in gaussian.iter().enumerate() {
+        let next = gaussian.get(level + 1).unwrap_or(&residual);
+        let upsampled = upsample(device, queue, next, current.width(), current.height())?;
+        pyramid.push(per_pixel(device, queue, "difference", current, &upsampled, DETAIL_FORMAT, 1.0, 0.0)?);
+    }
+    pyramid.push(residual);
+    Ok(pyramid)
+}
+
+/// 由拉普拉斯金字塔重建图像: R_k = up(R_k+1) + gain_k L_k，gains 缺少的层增益为 1，结果为 format 格式。
+/// alpha 不参与增强，取自第 0 层细节(即输入的 alpha)
+pub fn collapse_laplacian(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    pyramid: &[wgpu::Texture],
+    gains: &[f32],
+    format: wgpu::TextureFormat,
+) -> Result<wgpu::Texture> {
+    anyhow::ensure!(pyramid.len() >= 2, "the pyramid needs at least one detail level");
+    let (residual, details) = pyramid.split_last().unwrap();
+
+    let mut result = None;
+    for (level, detail) in details.iter().enumerate().rev() {
+        let coarser = result.as_ref().unwrap_or(residual);
+        let base = upsample(device, queue, coarser, detail.width(), detail.height())?;
+        let level_format = if level == 0 { format } else { DETAIL_FORMAT };
+        let gain = gains.get(level).copied().unwrap_or(1.0);
+        result = Some(per_pixel(device, queue, "add_detail", &base, detail, level_format, gain, 0.0)?);
+    }
+    Ok(result.unwrap())
+}
+
+/// 读回金字塔的每一层
+pub fn pyramid_images(device: &wgpu::Device, queue: &wgpu::Queue, pyramid: &[wgpu::Texture]) -> Result<Vec<Rgba32FImage>> {
+    pyramid.iter().map(|level| rgba32f_from_texture(device, queue, level)).collect()
+}
+
+/// 高斯金字塔和拉普拉斯金字塔
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+
+    let input_image = load_from_memory(include_bytes!("../images/sushi.png"))?.to_rgba8();
+    let input_texture = texture_from_image(&device, &queue, &input_image);
+
+    let gaussian = gaussian_pyramid(&device, &queue, &input_texture, 5)?;
+    for (level, texture) in gaussian.iter().enumerate() {
+        image_from_texture(&device, &queue, texture)?.save(format!("./outputs/sushi_gaussian_{level}.png"))?;
+    }
+
+    // 细节层加 0.5 后保存，便于查看
+    let laplacian = laplacian_pyramid(&device, &queue, &input_texture, 5)?;
+    for (level, mut image) in pyramid_images(&device, &queue, &laplacian)?.into_iter().enumerate() {
+        if level + 1 < laplacian.len() {
+            for pixel in image.pixels_mut() {
+                *pixel = image::Rgba([pixel[0] + 0.5, pixel[1] + 0.5, pixel[2] + 0.5, 1.0]);
+            }
+        }
+        image::DynamicImage::ImageRgba32F(image).to_rgba8().save(format!("./outputs/sushi_laplacian_{level}.png"))?;
+    }
+
+    // 减弱最精细一层的细节
+    let collapsed = collapse_laplacian(&device, &queue, &laplacian, &[0.3], input_texture.format())?;
+    image_from_texture(&device, &queue, &collapsed)?.save("./outputs/sushi_laplacian_collapse.png")?;
+
+    // 单通道纹理
+    let gray = load_from_memory(include_bytes!("../images/sushi.png"))?.to_luma32f();
+    let gray_texture = texture_from_luma32f(&device, &queue, &gray);
+    let gray_pyramid = gaussian_pyramid(&device, &queue, &gray_texture, 3)?;
+    for (level, image) in pyramid_images(&device, &queue, &gray_pyramid)?.into_iter().enumerate() {
+        image::DynamicImage::ImageRgba32F(image).to_luma8().save(format!("./outputs/sushi_gray_gaussian_{level}.png"))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::texture_from_rgba32f;
+    use crate::utils::max_abs_diff;
+    use crate::utils::max_u8_diff;
+    use crate::utils::random_image;
+    use crate::utils::random_rgba32f;
+    use crate::utils::test_device;
+    use image::RgbaImage;
+
+    /// CPU 上的高斯降采样，与 mipmap.wgsl 中的 Gaussian 滤波相同: sigma 0.5、半径 1.5 个输出像素，边缘取最近的像素
+    fn cpu_gaussian_downsample(image: &RgbaImage) -> RgbaImage {
+        let (width, height) = image.dimensions();
+        let (out_width, out_height) = ((width / 2).max(1), (height / 2).max(1));
+        let (sx, sy) = (width as f64 / out_width as f64, height as f64 / out_height as f64);
+        let weight = |d: f64| (-d * d / (2.0 * 0.5 * 0.5)).exp();
+        RgbaImage::from_fn(out_width, out_height, |x, y| {
+            let (cx, cy) = ((x as f64 + 0.5) * sx, (y as f64 + 0.5) * sy);
+            let (rx, ry) = (1.5 * sx, 1.5 * sy);
+            let mut sum = [0.0f64; 4];
+            let mut weight_sum = 0.0;
+            for py in (cy - ry).floor() as i32..=(cy + ry).floor() as i32 {
+                let wy = weight((py as f64 + 0.5 - cy) / sy);
+                for px in (cx - rx).floor() as i32..=(cx + rx).floor() as i32 {
+                    let w = wy * weight((px as f64 + 0.5 - cx) / sx);
+                    let pixel = image.get_pixel(px.clamp(0, width as i32 - 1) as u32, py.clamp(0, height as i32 - 1) as u32);
+                    for (sum, &value) in sum.iter_mut().zip(pixel.0.iter()) {
+                        *sum += w * value as f64;
+                    }
+                    weight_sum += w;
+                }
+            }
+            image::Rgba(sum.map(|sum| (sum / weight_sum).round() as u8))
+        })
+    }
+
+    #[test]
+    fn gaussian_pyramid_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_image(53, 30, 8);
+        let pyramid = gaussian_pyramid(&device, &queue, &texture_from_image(&device, &queue, &input), 4).unwrap();
+        assert_eq!(pyramid.len(), 4);
+        assert_eq!(max_u8_diff(&image_from_texture(&device, &queue, &pyramid[0]).unwrap(), &input), 0);
+
+        let mut previous = input;
+        for (level, texture) in pyramid.iter().enumerate().skip(1) {
+            let image = image_from_texture(&device, &queue, texture).unwrap();
+            // 从 GPU 的上一层算起，只有写回 8 位时的舍入误差
+            assert!(max_u8_diff(&image, &cpu_gaussian_downsample(&previous)) <= 1, "level {level}");
+            previous = image;
+        }
+
+        // 层数超过 mip 层数时只生成到 1x1
+        let pyramid = gaussian_pyramid(&device, &queue, &texture_from_image(&device, &queue, &previous), 10).unwrap();
+        let last = pyramid.last().unwrap();
+        assert_eq!((last.width(), last.height()), (1, 1));
+    }
+
+    #[test]
+    fn laplacian_pyramid_round_trips() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_rgba32f(41, 26, 9);
+        let texture = texture_from_rgba32f(&device, &queue, &input, wgpu::TextureFormat::Rgba32Float).unwrap();
+        let pyramid = laplacian_pyramid(&device, &queue, &texture, 6).unwrap();
+        assert_eq!(pyramid.len(), 6);
+        assert!(pyramid[..5].iter().all(|level| level.format() == DETAIL_FORMAT));
+
+        // 细节层是 G_k - up(G_k+1)，重建时逐层加回，浮点输入只有舍入误差
+        let collapsed = collapse_laplacian(&device, &queue, &pyramid, &[], wgpu::TextureFormat::Rgba32Float).unwrap();
+        let output = rgba32f_from_texture(&device, &queue, &collapsed).unwrap();
+        let diff = max_abs_diff(output.as_raw(), input.as_raw());
+        assert!(diff < 1e-5, "{diff}");
+
+        // 分解和重建的上采样都在浮点中进行，8 位输入的浮点误差远小于半级，写回 8 位后不变
+        let input = random_image(97, 70, 10);
+        let texture = texture_from_image(&device, &queue, &input);
+        for levels in [6, 7] {
+            let pyramid = laplacian_pyramid(&device, &queue, &texture, levels).unwrap();
+            assert_eq!(pyramid.len(), levels as usize);
+            let collapsed = collapse_laplacian(&device, &queue, &pyramid, &[1.0; 6], texture.format()).unwrap();
+            assert_eq!(collapsed.format(), wgpu::TextureFormat::Rgba8Unorm);
+            let diff = max_u8_diff(&image_from_texture(&device, &queue, &collapsed).unwrap(), &input);
+            assert_eq!(diff, 0, "{levels} levels");
+
+            let collapsed = collapse_laplacian(&device, &queue, &pyramid, &[], DETAIL_FORMAT).unwrap();
+            let expected = image::DynamicImage::ImageRgba8(input.clone()).to_rgba32f();
+            let diff = max_abs_diff(rgba32f_from_texture(&device, &queue, &collapsed).unwrap().as_raw(), expected.as_raw());
+            assert!(diff < 1e-5, "{levels} levels: {diff}");
+        }
+
+        let pyramid = laplacian_pyramid(&device, &queue, &texture, 2).unwrap();
+        assert!(collapse_laplacian(&device, &queue, &pyramid[1..], &[], texture.format()).is_err());
+    }
+}
@@ -0,0 +1,383 @@
+#![allow(dead_code)]
+
+use std::borrow::Cow;
+use anyhow::Ok;
+use anyhow::Result;
+use wgpu::util::BufferInitDescriptor;
+use wgpu::util::DeviceExt;
+use wgpu::PipelineCompilationOptions;
+
+use crate::scan::ScanKind;
+use crate::scan::Scanner;
+use crate::utils::linear_workgroup_count;
+use crate::utils::read_buffer;
+use crate::utils::request_device;
+
+/// 与 radix_sort.wgsl 一致
+const WORKGROUP_SIZE: u32 = 256;
+const RADIX_BITS: u32 = 4;
+const RADIX: u32 = 1 << RADIX_BITS;
+
+/// 可以跟随键一起移动的值类型
+pub trait SortValue: bytemuck::Pod {}
+
+impl SortValue for u32 {}
+
+impl SortValue for f32 {}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct SortParams {
+    len: u32,
+    shift: u32,
+    num_blocks: u32,
+    has_values: u32,
+}
+
+/// 把 f32 转换成按无符号整数比较时保持 f32::total_cmp 顺序的键
+pub fn f32_to_sortable(value: f32) -> u32 {
+    let bits = value.to_bits();
+    if bits & 0x8000_0000 != 0 {
+        !bits
+    } else {
+        bits | 0x8000_0000
+    }
+}
+
+/// f32_to_sortable 的逆变换
+pub fn sortable_to_f32(key: u32) -> f32 {
+    if key & 0x8000_0000 != 0 {
+        f32::from_bits(key & 0x7fff_ffff)
+    } else {
+        f32::from_bits(!key)
+    }
+}
+
+/// 编译好的基数排序流水线
+pub struct RadixSorter {
+    count: wgpu::ComputePipeline,
+    scatter: wgpu::ComputePipeline,
+    scanner: Scanner,
+}
+
+impl RadixSorter {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("radix_sort_shader_module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("../shaders/radix_sort.wgsl"))),
+        });
+
+        let create_pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: None,
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: PipelineCompilationOptions::default(),
+                cache: None,
+            })
+        };
+
+        Self {
+            count: create_pipeline("count"),
+            scatter: create_pipeline("scatter"),
+            scanner: Scanner::new::<u32>(device),
+        }
+    }
+
+    /// 对 keys 的前 len 个 u32 升序排序，values 存在时跟随键一起移动(稳定排序)。
+    /// 结果写回原缓冲区，两个缓冲区都需要 STORAGE 和 COPY_SRC 用途。
+    pub fn encode(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        keys: &wgpu::Buffer,
+        values: Option<&wgpu::Buffer>,
+        len: u32,
+    ) {
+        if len <= 1 {
+            return;
+        }
+
+        let num_blocks = len.div_ceil(WORKGROUP_SIZE);
+        let (workgroup_count_x, workgroup_count_y) = linear_workgroup_count(&device.limits(), len, WORKGROUP_SIZE);
+        let storage_buffer = |size: u64| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            })
+        };
+
+        let temp_keys = storage_buffer(4 * len as u64);
+        // 没有值的时候绑定一个占位缓冲区
+        let has_values = values.is_some() as u32;
+        let temp_values = storage_buffer(if values.is_some() { 4 * len as u64 } else { 4 });
+        let dummy_values = storage_buffer(4);
+        let values = values.unwrap_or(&dummy_values);
+
+        let block_counts = storage_buffer(4 * (RADIX * num_blocks) as u64);
+        let block_offsets = storage_buffer(4 * (RADIX * num_blocks) as u64);
+
+        // 32 位分 8 趟，偶数趟后结果回到原缓冲区
+        for pass in 0..32 / RADIX_BITS {
+            let (keys_in, keys_out, values_in, values_out) = if pass % 2 == 0 {
+                (keys, &temp_keys, values, &temp_values)
+            } else {
+                (&temp_keys, keys, &temp_values, values)
+            };
+
+            let params_buffer = device.create_buffer_init(&BufferInitDescriptor {
+                label: None,
+                usage: wgpu::BufferUsages::UNIFORM,
+                contents: bytemuck::bytes_of(&SortParams {
+                    len,
+                    shift: pass * RADIX_BITS,
+                    num_blocks,
+                    has_values,
+                }),
+            });
+
+            let count_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.count.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: keys_in.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: block_counts.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 5,
+                        resource: params_buffer.as_entire_binding(),
+                    },
+                ],
+                label: Some("count_bind_group"),
+            });
+
+            let scatter_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.scatter.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: keys_in.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: keys_out.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: values_in.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: values_out.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 4,
+                        resource: block_offsets.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 5,
+                        resource: params_buffer.as_entire_binding(),
+                    },
+                ],
+                label: Some("scatter_bind_group"),
+            });
+
+            {
+                let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+                cpass.set_pipeline(&self.count);
+                cpass.set_bind_group(0, &count_bind_group, &[]);
+                cpass.dispatch_workgroups(workgroup_count_x, workgroup_count_y, 1);
+            }
+
+            self.scanner.encode(device, encoder, &block_counts, &block_offsets, RADIX * num_blocks, ScanKind::Exclusive);
+
+            {
+                let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+                cpass.set_pipeline(&self.scatter);
+                cpass.set_bind_group(0, &scatter_bind_group, &[]);
+                cpass.dispatch_workgroups(workgroup_count_x, workgroup_count_y, 1);
+            }
+        }
+    }
+}
+
+/// 上传、排序并读回
+fn sort_raw(device: &wgpu::Device, queue: &wgpu::Queue, keys: &mut [u32], values: Option<&mut [u32]>) -> Result<()> {
+    if let Some(values) = &values {
+        anyhow::ensure!(
+            keys.len() == values.len(),
+            "values length {} does not match keys length {}",
+            values.len(),
+            keys.len()
+        );
+    }
+    if keys.len() <= 1 {
+        return Ok(());
+    }
+
+    let upload = |data: &[u32]| {
+        device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            contents: bytemuck::cast_slice(data),
+        })
+    };
+    let keys_buffer = upload(keys);
+    let values_buffer = values.as_deref().map(upload);
+
+    let sorter = RadixSorter::new(device);
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    sorter.encode(device, &mut encoder, &keys_buffer, values_buffer.as_ref(), keys.len() as u32);
+    queue.submit(Some(encoder.finish()));
+
+    keys.copy_from_slice(&read_buffer::<u32>(device, queue, &keys_buffer, keys.len())?);
+    if let (Some(values), Some(values_buffer)) = (values, values_buffer) {
+        values.copy_from_slice(&read_buffer::<u32>(device, queue, &values_buffer, values.len())?);
+    }
+    Ok(())
+}
+
+/// u32 键升序排序
+pub fn sort_u32(device: &wgpu::Device, queue: &wgpu::Queue, keys: &mut [u32]) -> Result<()> {
+    sort_raw(device, queue, keys, None)
+}
+
+/// 按 u32 键对键值对稳定排序
+pub fn sort_u32_pairs<V: SortValue>(device: &wgpu::Device, queue: &wgpu::Queue, keys: &mut [u32], values: &mut [V]) -> Result<()> {
+    sort_raw(device, queue, keys, Some(bytemuck::cast_slice_mut(values)))
+}
+
+/// f32 键排序，顺序与 f32::total_cmp 一致(-NaN < -inf < ... < -0.0 < 0.0 < ... < inf < NaN)
+pub fn sort_f32(device: &wgpu::Device, queue: &wgpu::Queue, keys: &mut [f32]) -> Result<()> {
+    let mut sortable: Vec<u32> = keys.iter().map(|&x| f32_to_sortable(x)).collect();
+    sort_raw(device, queue, &mut sortable, None)?;
+    for (key, sorted) in keys.iter_mut().zip(sortable) {
+        *key = sortable_to_f32(sorted);
+    }
+    Ok(())
+}
+
+/// 按 f32 键对键值对稳定排序
+pub fn sort_f32_pairs<V: SortValue>(device: &wgpu::Device, queue: &wgpu::Queue, keys: &mut [f32], values: &mut [V]) -> Result<()> {
+    let mut sortable: Vec<u32> = keys.iter().map(|&x| f32_to_sortable(x)).collect();
+    sort_raw(device, queue, &mut sortable, Some(bytemuck::cast_slice_mut(values)))?;
+    for (key, sorted) in keys.iter_mut().zip(sortable) {
+        *key = sortable_to_f32(sorted);
+    }
+    Ok(())
+}
+
+/// 简单的伪随机数，避免引入额外依赖
+pub(crate) fn xorshift(state: &mut u32) -> u32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+    *state
+}
+
+/// 基数排序
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+    let mut seed = 0x1234_5678;
+
+    let mut keys: Vec<u32> = (0..16).map(|_| xorshift(&mut seed) % 100).collect();
+    println!("u32 键: {keys:?}");
+    sort_u32(&device, &queue, &mut keys)?;
+    println!("排序后: {keys:?}");
+
+    let mut keys = vec![2.5f32, -0.0, f32::NAN, 0.0, f32::NEG_INFINITY, -7.25, 1.0];
+    let mut values: Vec<u32> = (0..keys.len() as u32).collect();
+    sort_f32_pairs(&device, &queue, &mut keys, &mut values)?;
+    println!("f32 键: {keys:?} 原下标: {values:?}");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_device;
+
+    /// 包含不是 WORKGROUP_SIZE 倍数的长度
+    const LENGTHS: [usize; 5] = [1, 255, 257, 4099, 1_000_003];
+
+    #[test]
+    fn sort_u32_matches_slice_sort() {
+        let Some((device, queue)) = test_device() else { return };
+        let mut seed = 0x1234_5678;
+        for len in LENGTHS {
+            let mut keys: Vec<u32> = (0..len).map(|_| xorshift(&mut seed)).collect();
+            let mut expected = keys.clone();
+            expected.sort();
+            sort_u32(&device, &queue, &mut keys).unwrap();
+            assert_eq!(keys, expected, "length {len}");
+        }
+    }
+
+    #[test]
+    fn sort_u32_pairs_is_stable() {
+        let Some((device, queue)) = test_device() else { return };
+        let mut seed = 0x9e37_79b9;
+        for len in LENGTHS {
+            // 键有大量重复，值是原下标，稳定排序时相同键的值保持升序
+            let mut keys: Vec<u32> = (0..len).map(|_| xorshift(&mut seed) % 1000).collect();
+            let mut values: Vec<u32> = (0..len as u32).collect();
+            let mut expected: Vec<(u32, u32)> = keys.iter().copied().zip(values.iter().copied()).collect();
+            expected.sort_by_key(|&(key, _)| key);
+            sort_u32_pairs(&device, &queue, &mut keys, &mut values).unwrap();
+            let sorted: Vec<(u32, u32)> = keys.into_iter().zip(values).collect();
+            assert_eq!(sorted, expected, "length {len}");
+        }
+    }
+
+    #[test]
+    fn sort_f32_matches_total_cmp() {
+        let Some((device, queue)) = test_device() else { return };
+        let mut seed = 0x0bad_cafe;
+        let specials = [0.0, -0.0, f32::INFINITY, f32::NEG_INFINITY, f32::NAN, -f32::NAN, f32::MIN_POSITIVE, -f32::MIN_POSITIVE];
+        for len in LENGTHS {
+            let mut keys: Vec<f32> = (0..len)
+                .map(|_| (xorshift(&mut seed) as i32) as f32 / 1000.0)
+                .chain(specials)
+                .collect();
+            let mut expected = keys.clone();
+            expected.sort_by(f32::total_cmp);
+            sort_f32(&device, &queue, &mut keys).unwrap();
+            // 按位比较，区分 -0.0 和 0.0，NaN 也能比较
+            let bits = |keys: &[f32]| keys.iter().map(|x| x.to_bits()).collect::<Vec<_>>();
+            assert_eq!(bits(&keys), bits(&expected), "length {}", len + specials.len());
+        }
+    }
+
+    #[test]
+    fn sort_f32_pairs_is_stable() {
+        let Some((device, queue)) = test_device() else { return };
+        let mut seed = 0x5eed_1234;
+        let mut keys: Vec<f32> = (0..10_007)
+            .map(|_| (xorshift(&mut seed) % 50) as f32 - 25.0)
+            .chain([0.0, -0.0, f32::NAN, -0.0, 0.0, f32::NAN])
+            .collect();
+        let mut values: Vec<f32> = (0..keys.len()).map(|i| i as f32).collect();
+        let mut expected: Vec<(f32, f32)> = keys.iter().copied().zip(values.iter().copied()).collect();
+        expected.sort_by(|a, b| a.0.total_cmp(&b.0));
+        sort_f32_pairs(&device, &queue, &mut keys, &mut values).unwrap();
+        let sorted: Vec<(u32, u32)> = keys.iter().zip(&values).map(|(k, v)| (k.to_bits(), v.to_bits())).collect();
+        let expected: Vec<(u32, u32)> = expected.iter().map(|(k, v)| (k.to_bits(), v.to_bits())).collect();
+        assert_eq!(sorted, expected);
+    }
+
+    #[test]
+    fn sortable_round_trip() {
+        for x in [0.0f32, -0.0, 1.5, -1.5, f32::INFINITY, f32::NEG_INFINITY, f32::MAX, f32::MIN] {
+            assert_eq!(sortable_to_f32(f32_to_sortable(x)).to_bits(), x.to_bits());
+        }
+        assert!(f32_to_sortable(-0.0) < f32_to_sortable(0.0));
+    }
+}
@@ -0,0 +1,348 @@
+#![allow(dead_code)]
+
+use anyhow::Ok;
+use anyhow::Result;
+use image::load_from_memory;
+
+use crate::blur::EdgeMode;
+use crate::buffer::GpuBuffer;
+use crate::texture::create_output_texture;
+use crate::texture::create_texture;
+use crate::texture::image_from_texture;
+use crate::texture::texture_from_image;
+use crate::texture::with_storage_format;
+use crate::utils::compute_work_group_count;
+use crate::utils::create_bind_group;
+use crate::utils::create_pipeline;
+use crate::utils::create_shader;
+use crate::utils::dispatch;
+use crate::utils::request_device;
+use crate::warp::with_interpolation;
+use crate::warp::Border;
+use crate::warp::Interpolation;
+
+/// 相机内参，单位为像素
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraIntrinsics {
+    pub fx: f64,
+    pub fy: f64,
+    pub cx: f64,
+    pub cy: f64,
+}
+
+/// 镜头畸变模型
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DistortionModel {
+    /// Brown–Conrady 模型: 径向 k1、k2、k3，切向 p1、p2(与 OpenCV 的 distCoeffs 顺序相同)
+    BrownConrady { k1: f64, k2: f64, p1: f64, p2: f64, k3: f64 },
+    /// 等距鱼眼模型: θd = θ (1 + k1 θ² + k2 θ⁴ + k3 θ⁶ + k4 θ⁸)(与 OpenCV 的 fisheye 模块相同)
+    Fisheye { k1: f64, k2: f64, k3: f64, k4: f64 },
+}
+
+impl DistortionModel {
+    /// 把归一化平面上无畸变的点映射到有畸变的点
+    pub fn distort(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        match *self {
+            DistortionModel::BrownConrady { k1, k2, p1, p2, k3 } => {
+                let r2 = x * x + y * y;
+                let radial = 1.0 + r2 * (k1 + r2 * (k2 + r2 * k3));
+                (
+                    x * radial + 2.0 * p1 * x * y + p2 * (r2 + 2.0 * x * x),
+                    y * radial + p1 * (r2 + 2.0 * y * y) + 2.0 * p2 * x * y,
+                )
+            }
+            DistortionModel::Fisheye { k1, k2, k3, k4 } => {
+                let r = (x * x + y * y).sqrt();
+                if r < 1e-12 {
+                    return (x, y);
+                }
+                let theta = r.atan();
+                let theta2 = theta * theta;
+                let theta_d = theta * (1.0 + theta2 * (k1 + theta2 * (k2 + theta2 * (k3 + theta2 * k4))));
+                (x * theta_d / r, y * theta_d / r)
+            }
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct RemapParams {
+    interpolation: u32,
+    border: u32,
+    _padding: [u32; 2],
+    constant: [f32; 4],
+}
+
+/// 把按行存放的源坐标上传为 Rg32Float 纹理
+pub fn map_texture(device: &wgpu::Device, queue: &wgpu::Queue, map: &[[f32; 2]], width: u32, height: u32) -> Result<wgpu::Texture> {
+    anyhow::ensure!(
+        map.len() == (width * height) as usize,
+        "map has {} entries, expected {width}x{height}",
+        map.len()
+    );
+    let texture = create_texture(
+        device,
+        width,
+        height,
+        wgpu::TextureFormat::Rg32Float,
+        wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+    );
+    queue.write_texture(
+        texture.as_image_copy(),
+        bytemuck::cast_slice(map),
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(8 * width),
+            rows_per_image: None,
+        },
+        texture.size(),
+    );
+    Ok(texture)
+}
+
+/// 去畸变用的映射: 输出(无畸变)图像中每个像素在输入(有畸变)图像中的位置。
+/// 输出图像使用 new_intrinsics，为 None 时与 intrinsics 相同
+pub fn undistort_map(
+    intrinsics: CameraIntrinsics,
+    model: DistortionModel,
+    new_intrinsics: Option<CameraIntrinsics>,
+    (width, height): (u32, u32),
+) -> Vec<[f32; 2]> {
+    let new_intrinsics = new_intrinsics.unwrap_or(intrinsics);
+    let mut map = Vec::with_capacity((width * height) as usize);
+    for v in 0..height {
+        for u in 0..width {
+            let x = (u as f64 - new_intrinsics.cx) / new_intrinsics.fx;
+            let y = (v as f64 - new_intrinsics.cy) / new_intrinsics.fy;
+            let (xd, yd) = model.distort((x, y));
+            map.push([
+                (intrinsics.fx * xd + intrinsics.cx) as f32,
+                (intrinsics.fy * yd + intrinsics.cy) as f32,
+            ]);
+        }
+    }
+    map
+}
+
+/// 重映射: 输出像素 (x, y) 取输入图像中 map(x, y) 处的值，
+/// map 为 Rg32Float 纹理，大小即输出大小，坐标以像素下标为单位
+pub fn remap(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    input: &wgpu::Texture,
+    map: &wgpu::Texture,
+    interpolation: Interpolation,
+    border: Border,
+) -> Result<wgpu::Texture> {
+    anyhow::ensure!(
+        map.format() == wgpu::TextureFormat::Rg32Float,
+        "map must be Rg32Float, got {:?}",
+        map.format()
+    );
+    let (width, height) = (map.width(), map.height());
+    let output = create_output_texture(device, width, height, input.format());
+
+    let source = with_interpolation(include_str!("../shaders/remap.wgsl"));
+    let shader = create_shader(device, "remap_shader_module", &with_storage_format(&source, input.format())?);
+    let pipeline = create_pipeline(device, &shader, "remap");
+
+    let (border, constant) = border.to_params();
+    let params = GpuBuffer::uniform(
+        device,
+        &RemapParams {
+            interpolation: interpolation as u32,
+            border,
+            _padding: [0; 2],
+            constant,
+        },
+    );
+
+    let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+    let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+    let map_view = map.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = create_bind_group(
+        device,
+        &pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(&input_view)),
+            (1, wgpu::BindingResource::TextureView(&output_view)),
+            (2, params.as_entire_binding()),
+            (3, wgpu::BindingResource::TextureView(&map_view)),
+        ],
+    );
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let (x, y) = compute_work_group_count((width, height), (16, 16));
+    dispatch(&mut encoder, &pipeline, &bind_group, (x, y, 1));
+    queue.submit(Some(encoder.finish()));
+
+    Ok(output)
+}
+
+/// 缓存了映射纹理的去畸变，同一相机的每一帧都可以复用
+pub struct Undistorter {
+    map: wgpu::Texture,
+}
+
+impl Undistorter {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        intrinsics: CameraIntrinsics,
+        model: DistortionModel,
+        new_intrinsics: Option<CameraIntrinsics>,
+        size: (u32, u32),
+    ) -> Result<Self> {
+        let map = undistort_map(intrinsics, model, new_intrinsics, size);
+        Ok(Self {
+            map: map_texture(device, queue, &map, size.0, size.1)?,
+        })
+    }
+
+    pub fn map(&self) -> &wgpu::Texture {
+        &self.map
+    }
+
+    pub fn undistort(&self, device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, interpolation: Interpolation) -> Result<wgpu::Texture> {
+        remap(device, queue, input, &self.map, interpolation, Border::default())
+    }
+}
+
+/// 重映射和镜头去畸变
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+
+    let input_image = load_from_memory(include_bytes!("../images/capture.jpg"))?.to_rgba8();
+    let input_texture = texture_from_image(&device, &queue, &input_image);
+    let (width, height) = input_image.dimensions();
+
+    let intrinsics = CameraIntrinsics {
+        fx: width as f64 * 0.8,
+        fy: width as f64 * 0.8,
+        cx: width as f64 / 2.0,
+        cy: height as f64 / 2.0,
+    };
+    let models = [
+        (
+            DistortionModel::BrownConrady {
+                k1: -0.3,
+                k2: 0.1,
+                p1: 0.001,
+                p2: -0.001,
+                k3: 0.0,
+            },
+            "brown_conrady",
+        ),
+        (
+            DistortionModel::Fisheye {
+                k1: 0.05,
+                k2: 0.01,
+                k3: 0.0,
+                k4: 0.0,
+            },
+            "fisheye",
+        ),
+    ];
+    for (model, name) in models {
+        let undistorter = Undistorter::new(&device, &queue, intrinsics, model, None, (width, height))?;
+        let undistorted = undistorter.undistort(&device, &queue, &input_texture, Interpolation::Bilinear)?;
+        image_from_texture(&device, &queue, &undistorted)?.save(format!("./outputs/capture_undistort_{name}.png"))?;
+    }
+
+    // 用镜像边界的映射做一个波浪效果
+    let wave: Vec<[f32; 2]> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| [x as f32 + 10.0 * (y as f32 / 20.0).sin(), y as f32]))
+        .collect();
+    let wave_map = map_texture(&device, &queue, &wave, width, height)?;
+    let waved = remap(&device, &queue, &input_texture, &wave_map, Interpolation::Bicubic, Border::Edge(EdgeMode::Mirror))?;
+    image_from_texture(&device, &queue, &waved)?.save("./outputs/capture_wave.png")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::radix_sort::xorshift;
+    use crate::texture::rgba32f_from_texture;
+    use crate::texture::texture_from_rgba32f;
+    use crate::utils::random_image;
+    use crate::utils::random_rgba32f;
+    use crate::utils::test_device;
+    use crate::warp::cpu_sample;
+
+    #[test]
+    fn remap_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_rgba32f(29, 23, 41);
+        let texture = texture_from_rgba32f(&device, &queue, &input, wgpu::TextureFormat::Rgba32Float).unwrap();
+        // 源坐标随机分布在图像内外各 5 个像素的范围里
+        let mut state = 41u32;
+        let mut random = |range: f32| xorshift(&mut state) as f32 / u32::MAX as f32 * (range + 10.0) - 5.0;
+        let map: Vec<[f32; 2]> = (0..31 * 19).map(|_| [random(29.0), random(23.0)]).collect();
+        let map_texture = map_texture(&device, &queue, &map, 31, 19).unwrap();
+        let borders = [
+            Border::Constant([0.25, 0.5, 0.75, 1.0]),
+            Border::Edge(EdgeMode::Clamp),
+            Border::Edge(EdgeMode::Mirror),
+            Border::Edge(EdgeMode::Wrap),
+        ];
+        for interpolation in [Interpolation::Nearest, Interpolation::Bilinear, Interpolation::Bicubic] {
+            for border in borders {
+                let output = remap(&device, &queue, &texture, &map_texture, interpolation, border).unwrap();
+                let output = rgba32f_from_texture(&device, &queue, &output).unwrap();
+                assert_eq!(output.dimensions(), (31, 19));
+                // 坐标直接取自映射，与 CPU 完全相同，误差只来自插值的累加顺序
+                let diff = output
+                    .pixels()
+                    .zip(&map)
+                    .map(|(pixel, &[x, y])| {
+                        let expected = cpu_sample(&input, (x, y), interpolation, border);
+                        pixel.0.iter().zip(expected).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max)
+                    })
+                    .fold(0.0, f32::max);
+                assert!(diff <= 1e-5, "{interpolation:?} {border:?}: {diff}");
+            }
+        }
+    }
+
+    #[test]
+    fn identity_map_is_lossless() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_image(27, 21, 42);
+        let texture = texture_from_image(&device, &queue, &input);
+        let identity: Vec<[f32; 2]> = (0..21).flat_map(|y| (0..27).map(move |x| [x as f32, y as f32])).collect();
+        let identity_map = map_texture(&device, &queue, &identity, 27, 21).unwrap();
+        for interpolation in [Interpolation::Nearest, Interpolation::Bilinear, Interpolation::Bicubic] {
+            let output = remap(&device, &queue, &texture, &identity_map, interpolation, Border::default()).unwrap();
+            assert!(image_from_texture(&device, &queue, &output).unwrap() == input, "{interpolation:?}");
+        }
+
+        assert!(map_texture(&device, &queue, &identity, 27, 20).is_err());
+        assert!(remap(&device, &queue, &texture, &texture, Interpolation::Nearest, Border::default()).is_err());
+    }
+
+    #[test]
+    fn distortion_models() {
+        // 系数为 0 时没有畸变
+        let none = DistortionModel::BrownConrady { k1: 0.0, k2: 0.0, p1: 0.0, p2: 0.0, k3: 0.0 };
+        assert_eq!(none.distort((0.3, -0.2)), (0.3, -0.2));
+
+        // r² = 0.25: 径向系数 1 + 0.25 k1 + 0.0625 k2，切向项按 OpenCV 的公式
+        let model = DistortionModel::BrownConrady { k1: -0.4, k2: 0.16, p1: 0.01, p2: 0.02, k3: 0.0 };
+        let (x, y) = model.distort((0.5, 0.0));
+        assert!((x - (0.5 * 0.91 + 0.02 * (0.25 + 0.5))).abs() < 1e-12);
+        assert!((y - 0.01 * 0.25).abs() < 1e-12);
+
+        // 等距鱼眼在系数为 0 时 θd = θ，即半径变为 atan(r)
+        let fisheye = DistortionModel::Fisheye { k1: 0.0, k2: 0.0, k3: 0.0, k4: 0.0 };
+        let (x, y) = fisheye.distort((0.6, 0.8));
+        assert!((x - 0.6 * 1.0f64.atan()).abs() < 1e-12 && (y - 0.8 * 1.0f64.atan()).abs() < 1e-12);
+        assert_eq!(fisheye.distort((0.0, 0.0)), (0.0, 0.0));
+
+        let intrinsics = CameraIntrinsics { fx: 100.0, fy: 120.0, cx: 16.0, cy: 12.0 };
+        let map = undistort_map(intrinsics, none, None, (32, 24));
+        assert!(map.iter().enumerate().all(|(i, &[x, y])| x == (i % 32) as f32 && y == (i / 32) as f32));
+    }
+}
@@ -0,0 +1,324 @@
+#![allow(dead_code)]
+
+use anyhow::Ok;
+use anyhow::Result;
+use image::load_from_memory;
+
+use crate::buffer::GpuBuffer;
+use crate::texture::create_output_texture;
+use crate::texture::image_from_texture;
+use crate::texture::texture_from_image;
+use crate::texture::with_storage_format;
+use crate::utils::compute_work_group_count;
+use crate::utils::create_bind_group;
+use crate::utils::create_pipeline;
+use crate::utils::create_shader;
+use crate::utils::dispatch;
+use crate::utils::request_device;
+
+/// 两个方向之间的中间纹理格式
+const INTERMEDIATE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+
+/// 缩放使用的滤波器
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResizeFilter {
+    Nearest = 0,
+    #[default]
+    Bilinear = 1,
+    /// Catmull-Rom
+    Bicubic = 2,
+    /// 按覆盖面积加权平均，适合缩小
+    Area = 3,
+    Lanczos3 = 4,
+}
+
+/// 目标宽高与原图宽高比不同时的处理方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResizeMode {
+    /// 直接拉伸到目标大小
+    #[default]
+    Stretch,
+    /// 保持宽高比缩放到目标范围内，输出可能比目标小
+    Fit,
+    /// 保持宽高比缩放到覆盖目标范围，居中裁掉多余部分
+    Fill,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct ResizeParams {
+    direction: u32,
+    filter_type: u32,
+    src_offset: f32,
+    scale: f32,
+}
+
+/// Fit 模式下的输出大小
+pub fn fit_size((width, height): (u32, u32), (target_width, target_height): (u32, u32)) -> (u32, u32) {
+    let scale = (target_width as f64 / width as f64).min(target_height as f64 / height as f64);
+    (
+        ((width as f64 * scale).round() as u32).clamp(1, target_width),
+        ((height as f64 * scale).round() as u32).clamp(1, target_height),
+    )
+}
+
+/// Fill 模式下源图像中被使用的区域 (x, y, width, height)
+fn fill_window((width, height): (u32, u32), (target_width, target_height): (u32, u32)) -> (f32, f32, f32, f32) {
+    let scale = (target_width as f32 / width as f32).max(target_height as f32 / height as f32);
+    let (window_width, window_height) = (target_width as f32 / scale, target_height as f32 / scale);
+    (
+        (width as f32 - window_width) / 2.0,
+        (height as f32 - window_height) / 2.0,
+        window_width,
+        window_height,
+    )
+}
+
+/// 沿一个方向重采样，源区间为 [src_offset, src_offset + src_length)
+#[allow(clippy::too_many_arguments)]
+fn encode_resample(
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    input: &wgpu::Texture,
+    output: &wgpu::Texture,
+    vertical: bool,
+    filter: ResizeFilter,
+    src_offset: f32,
+    src_length: f32,
+) -> Result<()> {
+    let shader = create_shader(
+        device,
+        "resize_shader_module",
+        &with_storage_format(include_str!("../shaders/resize.wgsl"), output.format())?,
+    );
+    let pipeline = create_pipeline(device, &shader, "resample");
+
+    let dst_length = if vertical { output.height() } else { output.width() };
+    let params = GpuBuffer::uniform(
+        device,
+        &ResizeParams {
+            direction: vertical as u32,
+            filter_type: filter as u32,
+            src_offset,
+            scale: src_length / dst_length as f32,
+        },
+    );
+
+    let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+    let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = create_bind_group(
+        device,
+        &pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(&input_view)),
+            (1, wgpu::BindingResource::TextureView(&output_view)),
+            (2, params.as_entire_binding()),
+        ],
+    );
+
+    let (x, y) = compute_work_group_count((output.width(), output.height()), (16, 16));
+    dispatch(encoder, &pipeline, &bind_group, (x, y, 1));
+    Ok(())
+}
+
+/// 把源图像中 window = (x, y, width, height) 的区域缩放到 width x height，输出为 format 格式
+pub(crate) fn resize_window(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    input: &wgpu::Texture,
+    window: (f32, f32, f32, f32),
+    (width, height): (u32, u32),
+    filter: ResizeFilter,
+    format: wgpu::TextureFormat,
+) -> Result<wgpu::Texture> {
+    anyhow::ensure!(width > 0 && height > 0, "invalid target size {width}x{height}");
+    let (window_x, window_y, window_width, window_height) = window;
+
+    // 与 image crate 一样先垂直后水平
+    let intermediate = create_output_texture(device, input.width(), height, INTERMEDIATE_FORMAT);
+    let output = create_output_texture(device, width, height, format);
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encode_resample(device, &mut encoder, input, &intermediate, true, filter, window_y, window_height)?;
+    encode_resample(device, &mut encoder, &intermediate, &output, false, filter, window_x, window_width)?;
+    queue.submit(Some(encoder.finish()));
+
+    Ok(output)
+}
+
+/// 缩放到 width x height，支持 RGBA 和单通道(R32Float)纹理
+pub fn resize(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, width: u32, height: u32, filter: ResizeFilter) -> Result<wgpu::Texture> {
+    resize_with_mode(device, queue, input, width, height, filter, ResizeMode::Stretch)
+}
+
+/// 按 mode 缩放，Fit 模式下输出大小由 fit_size 决定
+pub fn resize_with_mode(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    input: &wgpu::Texture,
+    width: u32,
+    height: u32,
+    filter: ResizeFilter,
+    mode: ResizeMode,
+) -> Result<wgpu::Texture> {
+    let size = (input.width(), input.height());
+    let full = (0.0, 0.0, size.0 as f32, size.1 as f32);
+    match mode {
+        ResizeMode::Stretch => resize_window(device, queue, input, full, (width, height), filter, input.format()),
+        ResizeMode::Fit => resize_window(device, queue, input, full, fit_size(size, (width, height)), filter, input.format()),
+        ResizeMode::Fill => resize_window(device, queue, input, fill_window(size, (width, height)), (width, height), filter, input.format()),
+    }
+}
+
+/// 各种滤波器的缩放和保持宽高比的缩放
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+
+    let input_image = load_from_memory(include_bytes!("../images/sushi.png"))?.to_rgba8();
+    let input_texture = texture_from_image(&device, &queue, &input_image);
+    let (width, height) = input_image.dimensions();
+
+    let filters = [
+        (ResizeFilter::Nearest, "nearest"),
+        (ResizeFilter::Bilinear, "bilinear"),
+        (ResizeFilter::Bicubic, "bicubic"),
+        (ResizeFilter::Lanczos3, "lanczos3"),
+        (ResizeFilter::Area, "area"),
+    ];
+    for (target_width, target_height) in [(width / 3, height / 3), (width * 3 / 2, height * 3 / 2)] {
+        for (filter, name) in filters {
+            let output = resize(&device, &queue, &input_texture, target_width, target_height, filter)?;
+            image_from_texture(&device, &queue, &output)?.save(format!("./outputs/sushi_resize_{name}_{target_width}x{target_height}.png"))?;
+        }
+    }
+
+    let fit = resize_with_mode(&device, &queue, &input_texture, 200, 200, ResizeFilter::Bilinear, ResizeMode::Fit)?;
+    image_from_texture(&device, &queue, &fit)?.save("./outputs/sushi_resize_fit.png")?;
+    let fill = resize_with_mode(&device, &queue, &input_texture, 200, 200, ResizeFilter::Bilinear, ResizeMode::Fill)?;
+    image_from_texture(&device, &queue, &fill)?.save("./outputs/sushi_resize_fill.png")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::rgba32f_from_texture;
+    use crate::texture::texture_from_luma32f;
+    use crate::texture::texture_from_rgba32f;
+    use crate::utils::max_u8_diff;
+    use crate::utils::random_image;
+    use crate::utils::random_rgba32f;
+    use crate::utils::test_device;
+    use image::imageops::FilterType;
+    use image::RgbaImage;
+
+    /// 照片的一块(平滑区域和边缘)和随机噪声(最坏情况)
+    fn inputs() -> [RgbaImage; 2] {
+        let photo = load_from_memory(include_bytes!("../images/sushi.png")).unwrap().to_rgba8();
+        [image::imageops::crop_imm(&photo, 100, 100, 97, 83).to_image(), random_image(97, 83, 51)]
+    }
+
+    /// 缩小、放大和一个方向放大另一个方向缩小
+    const SIZES: [(u32, u32); 3] = [(31, 29), (250, 190), (194, 41)];
+
+    #[test]
+    fn filters_match_image_crate() {
+        let Some((device, queue)) = test_device() else { return };
+        // 与 image crate 的最大误差: 最近邻取同一个像素，应完全一致；
+        // 其它滤波器的权重计算顺序不同，8 位舍入时最多差 1 级，且这种像素很少(平均误差 <= 0.01)
+        let cases = [
+            (ResizeFilter::Nearest, FilterType::Nearest, 0),
+            (ResizeFilter::Bilinear, FilterType::Triangle, 1),
+            (ResizeFilter::Bicubic, FilterType::CatmullRom, 1),
+            (ResizeFilter::Lanczos3, FilterType::Lanczos3, 1),
+        ];
+        for input in inputs() {
+            let texture = texture_from_image(&device, &queue, &input);
+            for (width, height) in SIZES {
+                for (filter, filter_type, tolerance) in cases {
+                    let output = image_from_texture(&device, &queue, &resize(&device, &queue, &texture, width, height, filter).unwrap()).unwrap();
+                    let expected = image::imageops::resize(&input, width, height, filter_type);
+                    let diff = max_u8_diff(&output, &expected);
+                    assert!(diff <= tolerance, "{filter:?} {width}x{height}: max diff {diff}");
+                    let mean = output.as_raw().iter().zip(expected.as_raw()).map(|(&a, &b)| a.abs_diff(b) as f64).sum::<f64>()
+                        / output.as_raw().len() as f64;
+                    assert!(mean <= 0.01, "{filter:?} {width}x{height}: mean diff {mean}");
+                }
+            }
+        }
+    }
+
+    /// CPU 上按覆盖面积加权的平均
+    fn cpu_area(image: &RgbaImage, width: u32, height: u32) -> RgbaImage {
+        let (sx, sy) = (image.width() as f64 / width as f64, image.height() as f64 / height as f64);
+        RgbaImage::from_fn(width, height, |x, y| {
+            let (x0, x1) = (x as f64 * sx, (x + 1) as f64 * sx);
+            let (y0, y1) = (y as f64 * sy, (y + 1) as f64 * sy);
+            let mut sum = [0.0f64; 4];
+            let mut weight_sum = 0.0;
+            for py in y0.floor() as u32..(y1.ceil() as u32).min(image.height()) {
+                let wy = y1.min(py as f64 + 1.0) - y0.max(py as f64);
+                for px in x0.floor() as u32..(x1.ceil() as u32).min(image.width()) {
+                    let wx = x1.min(px as f64 + 1.0) - x0.max(px as f64);
+                    for (sum, &value) in sum.iter_mut().zip(image.get_pixel(px, py).0.iter()) {
+                        *sum += wx * wy * value as f64;
+                    }
+                    weight_sum += wx * wy;
+                }
+            }
+            image::Rgba(sum.map(|sum| (sum / weight_sum).round() as u8))
+        })
+    }
+
+    #[test]
+    fn area_matches_cpu_average() {
+        let Some((device, queue)) = test_device() else { return };
+        // image crate 没有区域平均滤波(thumbnail 的算法不同)，与 CPU 上的精确面积加权比较，误差不超过 1 级
+        for input in inputs() {
+            let texture = texture_from_image(&device, &queue, &input);
+            for (width, height) in [(31, 29), (97 / 3 + 1, 83 / 3), (7, 5)] {
+                let output = image_from_texture(&device, &queue, &resize(&device, &queue, &texture, width, height, ResizeFilter::Area).unwrap()).unwrap();
+                let diff = max_u8_diff(&output, &cpu_area(&input, width, height));
+                assert!(diff <= 1, "{width}x{height}: {diff}");
+            }
+        }
+    }
+
+    #[test]
+    fn single_channel_matches_red_channel() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_rgba32f(40, 30, 52);
+        let luma = image::ImageBuffer::from_fn(40, 30, |x, y| image::Luma([input.get_pixel(x, y)[0]]));
+        let rgba = texture_from_rgba32f(&device, &queue, &input, wgpu::TextureFormat::Rgba32Float).unwrap();
+        let gray = texture_from_luma32f(&device, &queue, &luma);
+        for filter in [ResizeFilter::Bilinear, ResizeFilter::Lanczos3, ResizeFilter::Area] {
+            let expected = rgba32f_from_texture(&device, &queue, &resize(&device, &queue, &rgba, 23, 47, filter).unwrap()).unwrap();
+            let output = resize(&device, &queue, &gray, 23, 47, filter).unwrap();
+            assert_eq!(output.format(), wgpu::TextureFormat::R32Float);
+            let output = rgba32f_from_texture(&device, &queue, &output).unwrap();
+            for (a, b) in output.pixels().zip(expected.pixels()) {
+                assert!((a[0] - b[0]).abs() <= 1e-6, "{filter:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn fit_and_fill() {
+        assert_eq!(fit_size((400, 200), (100, 100)), (100, 50));
+        assert_eq!(fit_size((200, 400), (100, 100)), (50, 100));
+        assert_eq!(fit_size((1000, 1), (10, 10)), (10, 1));
+
+        let Some((device, queue)) = test_device() else { return };
+        // 100x50 填满 50x50 时比例为 1，结果就是中间的 50x50
+        let input = random_image(100, 50, 53);
+        let texture = texture_from_image(&device, &queue, &input);
+        let fill = resize_with_mode(&device, &queue, &texture, 50, 50, ResizeFilter::Nearest, ResizeMode::Fill).unwrap();
+        let expected = image::imageops::crop_imm(&input, 25, 0, 50, 50).to_image();
+        assert!(image_from_texture(&device, &queue, &fill).unwrap() == expected);
+
+        let fit = resize_with_mode(&device, &queue, &texture, 60, 60, ResizeFilter::Bilinear, ResizeMode::Fit).unwrap();
+        assert_eq!((fit.width(), fit.height()), (60, 30));
+        assert!(resize(&device, &queue, &texture, 0, 10, ResizeFilter::Bilinear).is_err());
+    }
+}
@@ -0,0 +1,346 @@
+#![allow(dead_code)]
+
+use anyhow::Ok;
+use anyhow::Result;
+use image::load_from_memory;
+
+use crate::blur::gaussian_blur;
+use crate::blur::EdgeMode;
+use crate::buffer::GpuBuffer;
+use crate::texture::create_output_texture;
+use crate::texture::image_from_texture;
+use crate::texture::texture_from_image;
+use crate::texture::with_storage_format;
+use crate::utils::compute_work_group_count;
+use crate::utils::create_bind_group;
+use crate::utils::create_pipeline;
+use crate::utils::create_shader;
+use crate::utils::dispatch;
+use crate::utils::request_device;
+
+/// 图像中的矩形区域
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// 四周各扩展 margin 个像素，并限制在 width x height 的图像内
+    pub fn expand(&self, margin: u32, width: u32, height: u32) -> Self {
+        let x = self.x.saturating_sub(margin);
+        let y = self.y.saturating_sub(margin);
+        let right = (self.x + self.width + margin).min(width);
+        let bottom = (self.y + self.height + margin).min(height);
+        Self::new(x, y, right - x, bottom - y)
+    }
+
+    fn origin(&self) -> wgpu::Origin3d {
+        wgpu::Origin3d { x: self.x, y: self.y, z: 0 }
+    }
+
+    fn extent(&self) -> wgpu::Extent3d {
+        wgpu::Extent3d {
+            width: self.width,
+            height: self.height,
+            depth_or_array_layers: 1,
+        }
+    }
+}
+
+/// 扩展边界时外部像素的取值方式
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PadMode {
+    /// 固定颜色 (r, g, b, a)，范围 0~1
+    Constant([f32; 4]),
+    /// 重复边缘像素
+    Edge,
+    /// 镜像(不重复边缘像素)
+    Reflect,
+}
+
+/// 四个方向扩展的像素数
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Padding {
+    pub top: u32,
+    pub bottom: u32,
+    pub left: u32,
+    pub right: u32,
+}
+
+impl Padding {
+    pub fn uniform(size: u32) -> Self {
+        Self {
+            top: size,
+            bottom: size,
+            left: size,
+            right: size,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct PadParams {
+    offset: [i32; 2],
+    mode: u32,
+    _padding: u32,
+    constant: [f32; 4],
+}
+
+fn ensure_inside(texture: &wgpu::Texture, rect: Rect) -> Result<()> {
+    anyhow::ensure!(
+        rect.width > 0
+            && rect.height > 0
+            && rect.x + rect.width <= texture.width()
+            && rect.y + rect.height <= texture.height(),
+        "{rect:?} is outside of the {}x{} texture",
+        texture.width(),
+        texture.height()
+    );
+    Ok(())
+}
+
+/// 裁剪出 rect 区域，通过纹理拷贝的 origin 完成，输入需要 COPY_SRC 用途
+pub fn crop(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, rect: Rect) -> Result<wgpu::Texture> {
+    ensure_inside(input, rect)?;
+    let output = create_output_texture(device, rect.width, rect.height, input.format());
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.copy_texture_to_texture(
+        wgpu::TexelCopyTextureInfo {
+            texture: input,
+            mip_level: 0,
+            origin: rect.origin(),
+            aspect: wgpu::TextureAspect::All,
+        },
+        output.as_image_copy(),
+        rect.extent(),
+    );
+    queue.submit(Some(encoder.finish()));
+
+    Ok(output)
+}
+
+/// 在四周扩展边界
+pub fn pad(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, padding: Padding, mode: PadMode) -> Result<wgpu::Texture> {
+    let width = input.width() + padding.left + padding.right;
+    let height = input.height() + padding.top + padding.bottom;
+    let output = create_output_texture(device, width, height, input.format());
+
+    let source = format!("{}\n{}", include_str!("../shaders/border.wgsl"), include_str!("../shaders/pad.wgsl"));
+    let shader = create_shader(device, "pad_shader_module", &with_storage_format(&source, input.format())?);
+    let pipeline = create_pipeline(device, &shader, "pad");
+
+    let (mode, constant) = match mode {
+        PadMode::Constant(color) => (0, color),
+        PadMode::Edge => (1, [0.0; 4]),
+        PadMode::Reflect => (2, [0.0; 4]),
+    };
+    let params = GpuBuffer::uniform(
+        device,
+        &PadParams {
+            offset: [padding.left as i32, padding.top as i32],
+            mode,
+            _padding: 0,
+            constant,
+        },
+    );
+
+    let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+    let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = create_bind_group(
+        device,
+        &pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(&input_view)),
+            (1, wgpu::BindingResource::TextureView(&output_view)),
+            (2, params.as_entire_binding()),
+        ],
+    );
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let (x, y) = compute_work_group_count((width, height), (16, 16));
+    dispatch(&mut encoder, &pipeline, &bind_group, (x, y, 1));
+    queue.submit(Some(encoder.finish()));
+
+    Ok(output)
+}
+
+/// 只在 rect 区域内执行 filter，其余像素保持不变。
+/// filter 的输入是向外扩展了 margin 个像素的子图(邻域滤波需要周围的像素)，
+/// 输出必须与输入大小和格式相同，结果中 rect 对应的部分会被拷贝回原图的副本
+pub fn apply_to_roi<F>(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, rect: Rect, margin: u32, filter: F) -> Result<wgpu::Texture>
+where
+    F: FnOnce(&wgpu::Device, &wgpu::Queue, &wgpu::Texture) -> Result<wgpu::Texture>,
+{
+    ensure_inside(input, rect)?;
+    let context = rect.expand(margin, input.width(), input.height());
+    let region = crop(device, queue, input, context)?;
+    let filtered = filter(device, queue, &region)?;
+    anyhow::ensure!(
+        filtered.size() == region.size() && filtered.format() == region.format(),
+        "filter changed the region from {:?} {:?} to {:?} {:?}",
+        region.size(),
+        region.format(),
+        filtered.size(),
+        filtered.format()
+    );
+
+    let output = create_output_texture(device, input.width(), input.height(), input.format());
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.copy_texture_to_texture(input.as_image_copy(), output.as_image_copy(), input.size());
+    encoder.copy_texture_to_texture(
+        wgpu::TexelCopyTextureInfo {
+            texture: &filtered,
+            mip_level: 0,
+            origin: wgpu::Origin3d {
+                x: rect.x - context.x,
+                y: rect.y - context.y,
+                z: 0,
+            },
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::TexelCopyTextureInfo {
+            texture: &output,
+            mip_level: 0,
+            origin: rect.origin(),
+            aspect: wgpu::TextureAspect::All,
+        },
+        rect.extent(),
+    );
+    queue.submit(Some(encoder.finish()));
+
+    Ok(output)
+}
+
+/// 裁剪、扩展边界和局部处理
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+
+    let input_image = load_from_memory(include_bytes!("../images/capture.jpg"))?.to_rgba8();
+    let input_texture = texture_from_image(&device, &queue, &input_image);
+    let (width, height) = input_image.dimensions();
+    let rect = Rect::new(width / 4, height / 4, width / 2, height / 2);
+
+    let cropped = crop(&device, &queue, &input_texture, rect)?;
+    image_from_texture(&device, &queue, &cropped)?.save("./outputs/capture_crop.png")?;
+
+    let modes = [
+        (PadMode::Constant([1.0, 0.0, 0.0, 1.0]), "constant"),
+        (PadMode::Edge, "edge"),
+        (PadMode::Reflect, "reflect"),
+    ];
+    for (mode, name) in modes {
+        let padded = pad(&device, &queue, &input_texture, Padding::uniform(64), mode)?;
+        image_from_texture(&device, &queue, &padded)?.save(format!("./outputs/capture_pad_{name}.png"))?;
+    }
+
+    // 只模糊中间的区域
+    let sigma = 8.0;
+    let margin = (3.0 * sigma) as u32;
+    let blurred = apply_to_roi(&device, &queue, &input_texture, rect, margin, |device, queue, region| {
+        gaussian_blur(device, queue, region, sigma, None, EdgeMode::Clamp)
+    })?;
+    image_from_texture(&device, &queue, &blurred)?.save("./outputs/capture_roi_blur.png")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::rgba32f_from_texture;
+    use crate::texture::texture_from_rgba32f;
+    use crate::utils::random_image;
+    use crate::utils::random_rgba32f;
+    use crate::utils::test_device;
+    use image::RgbaImage;
+
+    fn contains(rect: Rect, x: u32, y: u32) -> bool {
+        x >= rect.x && x < rect.x + rect.width && y >= rect.y && y < rect.y + rect.height
+    }
+
+    #[test]
+    fn crop_matches_image_crate() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_image(41, 33, 21);
+        let texture = texture_from_image(&device, &queue, &input);
+        // 纹理拷贝不做任何计算，结果应完全一致
+        for rect in [Rect::new(0, 0, 41, 33), Rect::new(5, 7, 20, 11), Rect::new(40, 32, 1, 1)] {
+            let output = image_from_texture(&device, &queue, &crop(&device, &queue, &texture, rect).unwrap()).unwrap();
+            let expected = image::imageops::crop_imm(&input, rect.x, rect.y, rect.width, rect.height).to_image();
+            assert!(output == expected, "{rect:?}");
+        }
+        assert!(crop(&device, &queue, &texture, Rect::new(30, 0, 12, 10)).is_err());
+        assert!(crop(&device, &queue, &texture, Rect::new(0, 0, 0, 10)).is_err());
+    }
+
+    #[test]
+    fn pad_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_image(7, 5, 22);
+        let texture = texture_from_image(&device, &queue, &input);
+        // 扩展的宽度大于图像本身时镜像要多次折返
+        let padding = Padding {
+            top: 3,
+            bottom: 9,
+            left: 12,
+            right: 1,
+        };
+        let modes = [
+            (PadMode::Constant([1.0, 0.0, 0.2, 0.6]), None),
+            (PadMode::Edge, Some(EdgeMode::Clamp)),
+            (PadMode::Reflect, Some(EdgeMode::Mirror)),
+        ];
+        for (mode, edge) in modes {
+            let output = image_from_texture(&device, &queue, &pad(&device, &queue, &texture, padding, mode).unwrap()).unwrap();
+            let expected = RgbaImage::from_fn(7 + 13, 5 + 12, |x, y| {
+                let (sx, sy) = (x as i32 - 12, y as i32 - 3);
+                match edge {
+                    _ if (0..7).contains(&sx) && (0..5).contains(&sy) => *input.get_pixel(sx as u32, sy as u32),
+                    Some(edge) => *input.get_pixel(edge.border_index(sx, 7) as u32, edge.border_index(sy, 5) as u32),
+                    None => image::Rgba([255, 0, 51, 153]),
+                }
+            });
+            // 只是取像素，应完全一致
+            assert!(output == expected, "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn roi_matches_full_image_filter() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_rgba32f(48, 40, 23);
+        let texture = texture_from_rgba32f(&device, &queue, &input, wgpu::TextureFormat::Rgba32Float).unwrap();
+        let blur = |device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture| gaussian_blur(device, queue, texture, 2.0, Some(6), EdgeMode::Clamp);
+        let full = rgba32f_from_texture(&device, &queue, &blur(&device, &queue, &texture).unwrap()).unwrap();
+        // 一个区域在图像内部，一个贴着图像边缘
+        for rect in [Rect::new(10, 12, 20, 15), Rect::new(0, 30, 17, 10)] {
+            let output = apply_to_roi(&device, &queue, &texture, rect, 6, blur).unwrap();
+            let output = rgba32f_from_texture(&device, &queue, &output).unwrap();
+            for (x, y, pixel) in output.enumerate_pixels() {
+                // margin 不小于卷积核半径时，区域内与整图滤波的计算完全相同；区域外保持原样
+                let expected = if contains(rect, x, y) { full.get_pixel(x, y) } else { input.get_pixel(x, y) };
+                assert_eq!(pixel, expected, "{rect:?} ({x}, {y})");
+            }
+        }
+
+        let resized = apply_to_roi(&device, &queue, &texture, Rect::new(0, 0, 8, 8), 0, |device, queue, region| {
+            crop(device, queue, region, Rect::new(0, 0, 4, 4))
+        });
+        assert!(resized.is_err());
+    }
+
+    #[test]
+    fn expand_clamps_to_image() {
+        assert_eq!(Rect::new(2, 3, 4, 5).expand(3, 100, 100), Rect::new(0, 0, 9, 11));
+        assert_eq!(Rect::new(90, 90, 10, 10).expand(5, 100, 100), Rect::new(85, 85, 15, 15));
+    }
+}
@@ -0,0 +1,605 @@
+#![allow(dead_code)]
+
+use std::borrow::Cow;
+use anyhow::Ok;
+use anyhow::Result;
+use wgpu::util::BufferInitDescriptor;
+use wgpu::util::DeviceExt;
+use wgpu::PipelineCompilationOptions;
+
+use crate::utils::linear_workgroup_count;
+use crate::utils::read_buffer;
+use crate::utils::request_device;
+
+/// 每个工作组处理的元素个数，与 scan.wgsl 中的 BLOCK_SIZE 一致
+const BLOCK_SIZE: u32 = 512;
+/// 逐元素计算的工作组大小，与 compact.wgsl 一致
+const WORKGROUP_SIZE: u32 = 256;
+
+/// 可以在 GPU 上扫描的元素类型
+pub trait ScanElement: bytemuck::Pod {
+    /// WGSL 中对应的类型名
+    const WGSL_TYPE: &'static str;
+    /// WGSL 中的 0
+    const WGSL_ZERO: &'static str;
+}
+
+impl ScanElement for u32 {
+    const WGSL_TYPE: &'static str = "u32";
+    const WGSL_ZERO: &'static str = "0u";
+}
+
+impl ScanElement for i32 {
+    const WGSL_TYPE: &'static str = "i32";
+    const WGSL_ZERO: &'static str = "0i";
+}
+
+impl ScanElement for f32 {
+    const WGSL_TYPE: &'static str = "f32";
+    const WGSL_ZERO: &'static str = "0.0";
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScanKind {
+    /// out[i] = in[0] + ... + in[i-1]
+    Exclusive,
+    /// out[i] = in[0] + ... + in[i]
+    Inclusive,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct ScanParams {
+    len: u32,
+    inclusive: u32,
+}
+
+/// 求和扫描的 WGSL 前缀
+fn add_prelude<T: ScanElement>() -> String {
+    format!(
+        "alias Elem = {ty};\n\
+         const IDENTITY: Elem = {zero};\n\
+         fn combine(a: Elem, b: Elem) -> Elem {{ return a + b; }}\n",
+        ty = T::WGSL_TYPE,
+        zero = T::WGSL_ZERO,
+    )
+}
+
+/// 分段求和扫描的 WGSL 前缀，元素是 (值, 段首标记)
+/// (a, fa) + (b, fb) = (fb ? b : a + b, fa | fb)
+fn segmented_prelude<T: ScanElement>() -> String {
+    format!(
+        "struct Elem {{ value: {ty}, head: u32, }}\n\
+         const IDENTITY: Elem = Elem({zero}, 0u);\n\
+         fn combine(a: Elem, b: Elem) -> Elem {{\n\
+             if b.head != 0u {{ return b; }}\n\
+             return Elem(a.value + b.value, a.head);\n\
+         }}\n",
+        ty = T::WGSL_TYPE,
+        zero = T::WGSL_ZERO,
+    )
+}
+
+/// 编译好的扫描流水线，可以对 GPU 上的缓冲区重复使用
+pub struct Scanner {
+    scan_blocks: wgpu::ComputePipeline,
+    add_block_offsets: wgpu::ComputePipeline,
+    elem_size: u64,
+}
+
+impl Scanner {
+    /// 对 T 求和的扫描
+    pub fn new<T: ScanElement>(device: &wgpu::Device) -> Self {
+        Self::with_prelude(device, &add_prelude::<T>(), std::mem::size_of::<T>())
+    }
+
+    fn with_prelude(device: &wgpu::Device, prelude: &str, elem_size: usize) -> Self {
+        let source = format!("{prelude}\n{}", include_str!("../shaders/scan.wgsl"));
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("scan_shader_module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(source)),
+        });
+
+        let create_pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: None,
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: PipelineCompilationOptions::default(),
+                cache: None,
+            })
+        };
+
+        Self {
+            scan_blocks: create_pipeline("scan_blocks"),
+            add_block_offsets: create_pipeline("add_block_offsets"),
+            elem_size: elem_size as u64,
+        }
+    }
+
+    /// 把 input 前 len 个元素的扫描结果写入 output，命令记录在 encoder 中。
+    /// 两个缓冲区都需要 STORAGE 用途，且不能是同一个缓冲区。
+    pub fn encode(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        input: &wgpu::Buffer,
+        output: &wgpu::Buffer,
+        len: u32,
+        kind: ScanKind,
+    ) {
+        if len == 0 {
+            return;
+        }
+
+        let blocks = len.div_ceil(BLOCK_SIZE);
+        let (workgroup_count_x, workgroup_count_y) = linear_workgroup_count(&device.limits(), len, BLOCK_SIZE);
+        let block_sums = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("block_sums"),
+            size: self.elem_size * blocks as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let params_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            usage: wgpu::BufferUsages::UNIFORM,
+            contents: bytemuck::bytes_of(&ScanParams {
+                len,
+                inclusive: (kind == ScanKind::Inclusive) as u32,
+            }),
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.scan_blocks.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: input.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: output.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: block_sums.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: params_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("scan_bind_group"),
+        });
+
+        {
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            cpass.set_pipeline(&self.scan_blocks);
+            cpass.set_bind_group(0, &bind_group, &[]);
+            cpass.dispatch_workgroups(workgroup_count_x, workgroup_count_y, 1);
+        }
+
+        if blocks == 1 {
+            return;
+        }
+
+        // 递归扫描各块的总和，得到每个块的偏移
+        let block_offsets = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("block_offsets"),
+            size: self.elem_size * blocks as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        self.encode(device, encoder, &block_sums, &block_offsets, blocks, ScanKind::Exclusive);
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.add_block_offsets.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: output.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: block_offsets.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: params_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("add_block_offsets_bind_group"),
+        });
+
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+        cpass.set_pipeline(&self.add_block_offsets);
+        cpass.set_bind_group(0, &bind_group, &[]);
+        cpass.dispatch_workgroups(workgroup_count_x, workgroup_count_y, 1);
+    }
+}
+
+/// 上传 input，扫描后读回结果
+fn run_scan<E: bytemuck::Pod>(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    scanner: &Scanner,
+    input: &[E],
+    kind: ScanKind,
+) -> Result<Vec<E>> {
+    if input.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let input_buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: None,
+        usage: wgpu::BufferUsages::STORAGE,
+        contents: bytemuck::cast_slice(input),
+    });
+
+    let output_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: std::mem::size_of_val(input) as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    scanner.encode(device, &mut encoder, &input_buffer, &output_buffer, input.len() as u32, kind);
+    queue.submit(Some(encoder.finish()));
+
+    read_buffer(device, queue, &output_buffer, input.len())
+}
+
+/// 前缀和
+pub fn scan<T: ScanElement>(device: &wgpu::Device, queue: &wgpu::Queue, input: &[T], kind: ScanKind) -> Result<Vec<T>> {
+    let scanner = Scanner::new::<T>(device);
+    run_scan(device, queue, &scanner, input, kind)
+}
+
+pub fn exclusive_scan<T: ScanElement>(device: &wgpu::Device, queue: &wgpu::Queue, input: &[T]) -> Result<Vec<T>> {
+    scan(device, queue, input, ScanKind::Exclusive)
+}
+
+pub fn inclusive_scan<T: ScanElement>(device: &wgpu::Device, queue: &wgpu::Queue, input: &[T]) -> Result<Vec<T>> {
+    scan(device, queue, input, ScanKind::Inclusive)
+}
+
+/// 分段前缀和，heads[i] 为 true 表示第 i 个元素开始一个新段(第 0 个元素总是段首)
+pub fn segmented_scan<T: ScanElement>(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    input: &[T],
+    heads: &[bool],
+    kind: ScanKind,
+) -> Result<Vec<T>> {
+    anyhow::ensure!(
+        std::mem::size_of::<T>() == 4,
+        "segmented scan only supports 32-bit elements"
+    );
+    anyhow::ensure!(
+        input.len() == heads.len(),
+        "heads length {} does not match input length {}",
+        heads.len(),
+        input.len()
+    );
+
+    let pairs: Vec<[u32; 2]> = input
+        .iter()
+        .zip(heads)
+        .map(|(x, &head)| [bytemuck::cast(*x), head as u32])
+        .collect();
+
+    let scanner = Scanner::with_prelude(device, &segmented_prelude::<T>(), std::mem::size_of::<[u32; 2]>());
+    let result = run_scan(device, queue, &scanner, &pairs, kind)?;
+
+    // exclusive 时段首的值来自上一段，需要置 0
+    Ok(result
+        .iter()
+        .zip(heads)
+        .map(|(pair, &head)| {
+            if kind == ScanKind::Exclusive && head {
+                T::zeroed()
+            } else {
+                bytemuck::cast(pair[0])
+            }
+        })
+        .collect())
+}
+
+/// 流压缩的保留条件，x 为元素，与 compact.wgsl 中的 keep 一致
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Predicate<T> {
+    Equal(T),
+    NotEqual(T),
+    Less(T),
+    LessEqual(T),
+    Greater(T),
+    GreaterEqual(T),
+}
+
+impl<T: ScanElement> Predicate<T> {
+    /// (比较方式, 操作数)，操作数按位传给 uniform
+    fn to_params(self) -> (u32, u32) {
+        let (comparison, operand) = match self {
+            Predicate::Equal(v) => (0, v),
+            Predicate::NotEqual(v) => (1, v),
+            Predicate::Less(v) => (2, v),
+            Predicate::LessEqual(v) => (3, v),
+            Predicate::Greater(v) => (4, v),
+            Predicate::GreaterEqual(v) => (5, v),
+        };
+        (comparison, bytemuck::cast_slice::<T, u32>(&[operand])[0])
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct CompactParams {
+    len: u32,
+    comparison: u32,
+    operand: u32,
+    _padding: u32,
+}
+
+/// 流压缩: 按顺序保留满足 predicate 的元素
+pub fn compact<T: ScanElement>(device: &wgpu::Device, queue: &wgpu::Queue, input: &[T], predicate: Predicate<T>) -> Result<Vec<T>> {
+    if input.is_empty() {
+        return Ok(vec![]);
+    }
+    let len = input.len() as u32;
+
+    let source = format!(
+        "alias Elem = {ty};\n{}",
+        include_str!("../shaders/compact.wgsl"),
+        ty = T::WGSL_TYPE,
+    );
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("compact_shader_module"),
+        source: wgpu::ShaderSource::Wgsl(Cow::Owned(source)),
+    });
+
+    let create_pipeline = |entry_point| {
+        device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(entry_point),
+            layout: None,
+            module: &shader,
+            entry_point: Some(entry_point),
+            compilation_options: PipelineCompilationOptions::default(),
+            cache: None,
+        })
+    };
+    let mark_pipeline = create_pipeline("mark");
+    let scatter_pipeline = create_pipeline("scatter");
+
+    let storage_buffer = |size: u64| {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        })
+    };
+
+    let input_buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: None,
+        usage: wgpu::BufferUsages::STORAGE,
+        contents: bytemuck::cast_slice(input),
+    });
+    let flags_buffer = storage_buffer(4 * len as u64);
+    let positions_buffer = storage_buffer(4 * len as u64);
+    let output_buffer = storage_buffer(std::mem::size_of_val(input) as u64);
+    let count_buffer = storage_buffer(4);
+    let (comparison, operand) = predicate.to_params();
+    let params_buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: None,
+        usage: wgpu::BufferUsages::UNIFORM,
+        contents: bytemuck::bytes_of(&CompactParams {
+            len,
+            comparison,
+            operand,
+            _padding: 0,
+        }),
+    });
+
+    let mark_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: &mark_pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: input_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: flags_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: params_buffer.as_entire_binding(),
+            },
+        ],
+        label: Some("mark_bind_group"),
+    });
+
+    let scatter_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: &scatter_pipeline.get_bind_group_layout(0),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: input_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: flags_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: positions_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: output_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: count_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: params_buffer.as_entire_binding(),
+            },
+        ],
+        label: Some("scatter_bind_group"),
+    });
+
+    let (workgroup_count_x, workgroup_count_y) = linear_workgroup_count(&device.limits(), len, WORKGROUP_SIZE);
+    let scanner = Scanner::new::<u32>(device);
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+        cpass.set_pipeline(&mark_pipeline);
+        cpass.set_bind_group(0, &mark_bind_group, &[]);
+        cpass.dispatch_workgroups(workgroup_count_x, workgroup_count_y, 1);
+    }
+    scanner.encode(device, &mut encoder, &flags_buffer, &positions_buffer, len, ScanKind::Exclusive);
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+        cpass.set_pipeline(&scatter_pipeline);
+        cpass.set_bind_group(0, &scatter_bind_group, &[]);
+        cpass.dispatch_workgroups(workgroup_count_x, workgroup_count_y, 1);
+    }
+    queue.submit(Some(encoder.finish()));
+
+    let count = read_buffer::<u32>(device, queue, &count_buffer, 1)?[0];
+    read_buffer(device, queue, &output_buffer, count as usize)
+}
+
+/// 前缀和(exclusive/inclusive)，以及基于它的流压缩和分段扫描
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+
+    let input: Vec<u32> = (0..20u32).map(|i| i % 7).collect();
+    println!("输入: {input:?}");
+    println!("exclusive scan: {:?}", exclusive_scan(&device, &queue, &input)?);
+    println!("inclusive scan: {:?}", inclusive_scan(&device, &queue, &input)?);
+    println!("保留小于 3 的元素: {:?}", compact(&device, &queue, &input, Predicate::Less(3))?);
+
+    // 每 5 个元素一段
+    let heads: Vec<bool> = (0..input.len()).map(|i| i % 5 == 0).collect();
+    println!("分段扫描: {:?}", segmented_scan(&device, &queue, &input, &heads, ScanKind::Inclusive)?);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_device;
+
+    /// 长度不是 BLOCK_SIZE 的倍数，并且超过一层块
+    fn input() -> Vec<u32> {
+        (0..300_001u32).map(|i| i % 7).collect()
+    }
+
+    #[test]
+    fn exclusive_scan_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = input();
+        let expected: Vec<u32> = input
+            .iter()
+            .scan(0, |sum, x| {
+                let value = *sum;
+                *sum += x;
+                Some(value)
+            })
+            .collect();
+        assert_eq!(exclusive_scan(&device, &queue, &input).unwrap(), expected);
+    }
+
+    #[test]
+    fn inclusive_scan_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = input();
+        let expected: Vec<u32> = input
+            .iter()
+            .scan(0, |sum, x| {
+                *sum += x;
+                Some(*sum)
+            })
+            .collect();
+        assert_eq!(inclusive_scan(&device, &queue, &input).unwrap(), expected);
+
+        // 0.5 的倍数在 f32 中精确表示，和不超过 2^24 时结果精确
+        let floats: Vec<f32> = (0..1000).map(|i| (i % 10) as f32 * 0.5).collect();
+        let expected: Vec<f32> = floats
+            .iter()
+            .scan(0.0, |sum, x| {
+                *sum += x;
+                Some(*sum)
+            })
+            .collect();
+        assert_eq!(inclusive_scan(&device, &queue, &floats).unwrap(), expected);
+    }
+
+    #[test]
+    fn compact_matches_filter() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = input();
+        let predicates = [
+            Predicate::Equal(3),
+            Predicate::NotEqual(3),
+            Predicate::Less(3),
+            Predicate::LessEqual(3),
+            Predicate::Greater(3),
+            Predicate::GreaterEqual(3),
+        ];
+        for predicate in predicates {
+            let expected: Vec<u32> = input
+                .iter()
+                .copied()
+                .filter(|&x| match predicate {
+                    Predicate::Equal(v) => x == v,
+                    Predicate::NotEqual(v) => x != v,
+                    Predicate::Less(v) => x < v,
+                    Predicate::LessEqual(v) => x <= v,
+                    Predicate::Greater(v) => x > v,
+                    Predicate::GreaterEqual(v) => x >= v,
+                })
+                .collect();
+            assert_eq!(compact(&device, &queue, &input, predicate).unwrap(), expected, "{predicate:?}");
+        }
+
+        // 有符号数和浮点数的操作数按位传递
+        let signed: Vec<i32> = (-500..500).collect();
+        let expected: Vec<i32> = signed.iter().copied().filter(|&x| x < -7).collect();
+        assert_eq!(compact(&device, &queue, &signed, Predicate::Less(-7)).unwrap(), expected);
+        let floats = [1.5f32, -0.5, f32::NAN, 2.0, 0.25];
+        assert_eq!(compact(&device, &queue, &floats, Predicate::GreaterEqual(0.25)).unwrap(), vec![1.5, 2.0, 0.25]);
+    }
+
+    #[test]
+    fn segmented_scan_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        let values: Vec<i32> = (0..5000).map(|i| i % 13 - 6).collect();
+        let heads: Vec<bool> = (0..values.len()).map(|i| i % 100 == 0 || i % 777 == 0).collect();
+        for kind in [ScanKind::Inclusive, ScanKind::Exclusive] {
+            let mut expected = Vec::with_capacity(values.len());
+            let mut sum = 0;
+            for (x, &head) in values.iter().zip(&heads) {
+                if head {
+                    sum = 0;
+                }
+                if kind == ScanKind::Exclusive {
+                    expected.push(sum);
+                }
+                sum += x;
+                if kind == ScanKind::Inclusive {
+                    expected.push(sum);
+                }
+            }
+            assert_eq!(segmented_scan(&device, &queue, &values, &heads, kind).unwrap(), expected, "{kind:?}");
+        }
+    }
+}
@@ -0,0 +1,220 @@
+#![allow(dead_code)]
+
+use anyhow::Ok;
+use anyhow::Result;
+use image::load_from_memory;
+
+use crate::blur::gaussian_blur;
+use crate::blur::EdgeMode;
+use crate::buffer::GpuBuffer;
+use crate::pyramid::collapse_laplacian;
+use crate::pyramid::laplacian_pyramid;
+use crate::texture::create_output_texture;
+use crate::texture::image_from_texture;
+use crate::texture::texture_from_image;
+use crate::texture::with_storage_format;
+use crate::utils::compute_work_group_count;
+use crate::utils::create_bind_group;
+use crate::utils::create_pipeline;
+use crate::utils::create_shader;
+use crate::utils::dispatch;
+use crate::utils::request_device;
+
+/// USM 锐化参数
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UnsharpMask {
+    /// 细节放大的倍数，0 为不变
+    pub amount: f32,
+    /// 高斯模糊的 sigma
+    pub radius: f32,
+    /// 与模糊结果的差值不超过 threshold(范围 0~1)的通道不锐化，避免放大噪声
+    pub threshold: f32,
+}
+
+impl Default for UnsharpMask {
+    fn default() -> Self {
+        Self {
+            amount: 1.0,
+            radius: 1.0,
+            threshold: 0.0,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct SharpenParams {
+    amount: f32,
+    threshold: f32,
+    _padding: [f32; 2],
+}
+
+/// 对 input 和 second 做逐像素运算，结果写入 format 格式的新纹理
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn per_pixel(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    entry_point: &str,
+    input: &wgpu::Texture,
+    second: &wgpu::Texture,
+    format: wgpu::TextureFormat,
+    amount: f32,
+    threshold: f32,
+) -> Result<wgpu::Texture> {
+    let (width, height) = (input.width(), input.height());
+    let output = create_output_texture(device, width, height, format);
+
+    let shader = create_shader(device, "sharpen_shader_module", &with_storage_format(include_str!("../shaders/sharpen.wgsl"), format)?);
+    let pipeline = create_pipeline(device, &shader, entry_point);
+    let params = GpuBuffer::uniform(
+        device,
+        &SharpenParams {
+            amount,
+            threshold,
+            _padding: [0.0; 2],
+        },
+    );
+
+    let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+    let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+    let second_view = second.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = create_bind_group(
+        device,
+        &pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(&input_view)),
+            (1, wgpu::BindingResource::TextureView(&output_view)),
+            (2, params.as_entire_binding()),
+            (3, wgpu::BindingResource::TextureView(&second_view)),
+        ],
+    );
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let (x, y) = compute_work_group_count((width, height), (16, 16));
+    dispatch(&mut encoder, &pipeline, &bind_group, (x, y, 1));
+    queue.submit(Some(encoder.finish()));
+
+    Ok(output)
+}
+
+/// USM 锐化: input + amount (input - blur(input))，输出格式与输入相同
+pub fn unsharp_mask(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, mask: &UnsharpMask) -> Result<wgpu::Texture> {
+    let blurred = gaussian_blur(device, queue, input, mask.radius, None, EdgeMode::Clamp)?;
+    per_pixel(device, queue, "unsharp", input, &blurred, input.format(), mask.amount, mask.threshold)
+}
+
+/// 多尺度细节增强: 把图像分解为拉普拉斯金字塔，第 k 层细节乘以 gains[k](0 为最精细的一层)后重建。
+/// gains 全为 1 时得到原图，输出格式与输入相同
+pub fn enhance_details(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, gains: &[f32]) -> Result<wgpu::Texture> {
+    anyhow::ensure!(!gains.is_empty(), "at least one level is required");
+    let pyramid = laplacian_pyramid(device, queue, input, gains.len() as u32 + 1)?;
+    collapse_laplacian(device, queue, &pyramid, gains, input.format())
+}
+
+/// USM 锐化和多尺度细节增强
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+
+    let input_image = load_from_memory(include_bytes!("../images/sushi.png"))?.to_rgba8();
+    let input_texture = texture_from_image(&device, &queue, &input_image);
+
+    let sharpened = unsharp_mask(&device, &queue, &input_texture, &UnsharpMask::default())?;
+    image_from_texture(&device, &queue, &sharpened)?.save("./outputs/sushi_unsharp.png")?;
+
+    let strong = UnsharpMask {
+        amount: 2.0,
+        radius: 2.0,
+        threshold: 0.02,
+    };
+    let sharpened = unsharp_mask(&device, &queue, &input_texture, &strong)?;
+    image_from_texture(&device, &queue, &sharpened)?.save("./outputs/sushi_unsharp_strong.png")?;
+
+    // 主要放大中间尺度的细节，最精细一层增益较小，避免放大噪声
+    let enhanced = enhance_details(&device, &queue, &input_texture, &[1.2, 1.8, 1.5, 1.2])?;
+    image_from_texture(&device, &queue, &enhanced)?.save("./outputs/sushi_detail_enhance.png")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::rgba32f_from_texture;
+    use crate::texture::texture_from_rgba32f;
+    use crate::utils::max_abs_diff;
+    use crate::utils::max_u8_diff;
+    use crate::utils::random_rgba32f;
+    use crate::utils::test_device;
+
+    /// 平滑的渐变加上几条硬边，接近照片而不是纯噪声
+    fn synthetic(width: u32, height: u32) -> image::RgbaImage {
+        image::RgbaImage::from_fn(width, height, |x, y| {
+            let stripe = if (x / 7 + y / 11) % 2 == 0 { 60 } else { 0 };
+            image::Rgba([(x * 3 + stripe) as u8, (y * 4) as u8, (150 + stripe) as u8, 255])
+        })
+    }
+
+    #[test]
+    fn unsharp_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_rgba32f(37, 23, 3);
+        let texture = texture_from_rgba32f(&device, &queue, &input, wgpu::TextureFormat::Rgba32Float).unwrap();
+        for mask in [
+            UnsharpMask::default(),
+            UnsharpMask {
+                amount: 2.5,
+                radius: 2.0,
+                threshold: 0.1,
+            },
+        ] {
+            // 模糊本身在 blur 的测试中和 CPU 比较过，这里只检查逐像素的锐化公式和阈值
+            let blurred = gaussian_blur(&device, &queue, &texture, mask.radius, None, EdgeMode::Clamp).unwrap();
+            let blurred = rgba32f_from_texture(&device, &queue, &blurred).unwrap();
+            let expected: Vec<f32> = input
+                .pixels()
+                .zip(blurred.pixels())
+                .flat_map(|(color, blurred)| {
+                    let mut result = color.0;
+                    for c in 0..3 {
+                        let detail = color[c] - blurred[c];
+                        if detail.abs() > mask.threshold {
+                            result[c] += mask.amount * detail;
+                        }
+                    }
+                    result
+                })
+                .collect();
+
+            let output = rgba32f_from_texture(&device, &queue, &unsharp_mask(&device, &queue, &texture, &mask).unwrap()).unwrap();
+            // 同样的浮点运算，只有舍入顺序的差别
+            assert!(max_abs_diff(output.as_raw(), &expected) < 1e-5, "{mask:?}");
+        }
+    }
+
+    #[test]
+    fn unsharp_matches_image_crate() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = synthetic(61, 47);
+        let texture = texture_from_image(&device, &queue, &input);
+        let output = image_from_texture(&device, &queue, &unsharp_mask(&device, &queue, &texture, &UnsharpMask::default()).unwrap()).unwrap();
+        let expected = image::imageops::unsharpen(&input, 1.0, 0);
+
+        let diffs: Vec<u8> = output.as_raw().iter().zip(expected.as_raw()).map(|(&a, &b)| a.abs_diff(b)).collect();
+        let max = diffs.iter().copied().max().unwrap_or(0);
+        let mean = diffs.iter().map(|&d| d as f64).sum::<f64>() / diffs.len() as f64;
+        // image 先把模糊结果量化到 8 位再求差值，差值又被放大一倍，模糊核的截断半径也不同，
+        // 所以硬边附近允许几级的误差
+        assert!(max <= 4 && mean <= 0.5, "max {max} mean {mean:.3}");
+    }
+
+    #[test]
+    fn enhance_details_with_unit_gains_is_lossless() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = synthetic(45, 38);
+        let texture = texture_from_image(&device, &queue, &input);
+        // 6 层金字塔，分解和重建的上采样都在浮点中进行，结果与输入完全相同
+        let output = image_from_texture(&device, &queue, &enhance_details(&device, &queue, &texture, &[1.0; 5]).unwrap()).unwrap();
+        assert_eq!(max_u8_diff(&output, &input), 0);
+        assert!(enhance_details(&device, &queue, &texture, &[]).is_err());
+    }
+}
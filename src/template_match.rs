@@ -0,0 +1,580 @@
+#![allow(dead_code)]
+
+use anyhow::Ok;
+use anyhow::Result;
+use image::load_from_memory;
+use image::GrayImage;
+
+use crate::buffer::GpuBuffer;
+use crate::grayscale::grayscale_texture;
+use crate::grayscale::GrayscaleMode;
+use crate::pyramid::gaussian_pyramid;
+use crate::roi::crop;
+use crate::roi::Rect;
+use crate::texture::create_output_texture;
+use crate::texture::rgba32f_from_texture;
+use crate::texture::texture_from_image;
+use crate::utils::compute_work_group_count;
+use crate::utils::create_bind_group;
+use crate::utils::create_pipeline;
+use crate::utils::create_shader;
+use crate::utils::dispatch;
+use crate::utils::request_device;
+
+/// 由粗到细搜索时，最粗一层的模板边长不小于该值
+const MIN_PYRAMID_TEMPLATE_SIZE: u32 = 8;
+
+/// 匹配得分的计算方式，与 template_match.wgsl 一致
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MatchMethod {
+    /// 差的平方和，越小越好
+    Ssd = 0,
+    /// 差的绝对值之和，越小越好
+    Sad = 1,
+    /// 零均值归一化互相关，范围 -1~1，越大越好，不受亮度和对比度变化影响
+    #[default]
+    Zncc = 2,
+}
+
+impl MatchMethod {
+    pub fn higher_is_better(self) -> bool {
+        self == MatchMethod::Zncc
+    }
+
+    /// a 是否比 b 好(或相同)
+    pub fn is_better_or_equal(self, a: f32, b: f32) -> bool {
+        if self.higher_is_better() {
+            a >= b
+        } else {
+            a <= b
+        }
+    }
+}
+
+/// 匹配结果，(x, y) 为模板左上角在图像中的位置
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Match {
+    pub x: u32,
+    pub y: u32,
+    pub score: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MatchOptions {
+    pub method: MatchMethod,
+    /// 最多返回的匹配数
+    pub max_matches: u32,
+    /// 两个匹配的最小距离，None 为模板较短边的一半
+    pub min_distance: Option<f32>,
+    /// 只保留不差于该得分的匹配，例如 ZNCC 取 0.9
+    pub threshold: Option<f32>,
+    /// 大于 1 时先在高斯金字塔的粗层上搜索，再在原图的候选位置附近细化
+    pub pyramid_levels: u32,
+}
+
+impl Default for MatchOptions {
+    fn default() -> Self {
+        Self {
+            method: MatchMethod::default(),
+            max_matches: 1,
+            min_distance: None,
+            threshold: None,
+            pyramid_levels: 1,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct MatchParams {
+    method: u32,
+    count: u32,
+    min_distance: f32,
+    width: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Candidate {
+    x: u32,
+    y: u32,
+    score: f32,
+    key: f32,
+}
+
+impl Candidate {
+    /// 与 template_match.wgsl 的 SUPPRESSED 相同，表示没有结果
+    const NONE: Self = Self {
+        x: 0,
+        y: 0,
+        score: 0.0,
+        key: f32::MIN,
+    };
+}
+
+fn create_match_shader(device: &wgpu::Device) -> wgpu::ShaderModule {
+    create_shader(device, "template_match_shader_module", include_str!("../shaders/template_match.wgsl"))
+}
+
+/// 匹配在灰度上进行: R32Float 纹理直接使用，其它格式先按 BT.601 转为灰度(与 grayscale.wgsl 相同)
+fn to_gray(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture) -> Result<wgpu::Texture> {
+    if input.format() == wgpu::TextureFormat::R32Float {
+        return Ok(input.clone());
+    }
+    grayscale_texture(device, queue, input, GrayscaleMode::Bt601)
+}
+
+/// 计算得分图(R32Float)，大小为 (图像宽 - 模板宽 + 1) x (图像高 - 模板高 + 1)
+pub fn match_template(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    image: &wgpu::Texture,
+    template: &wgpu::Texture,
+    method: MatchMethod,
+) -> Result<wgpu::Texture> {
+    anyhow::ensure!(
+        template.width() <= image.width() && template.height() <= image.height(),
+        "the {}x{} template is larger than the {}x{} image",
+        template.width(),
+        template.height(),
+        image.width(),
+        image.height()
+    );
+    let image = to_gray(device, queue, image)?;
+    let template = to_gray(device, queue, template)?;
+    match_gray(device, queue, &image, &template, method)
+}
+
+/// 在已经转为灰度的纹理上计算得分图
+fn match_gray(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    image: &wgpu::Texture,
+    template: &wgpu::Texture,
+    method: MatchMethod,
+) -> Result<wgpu::Texture> {
+    let (width, height) = (image.width() - template.width() + 1, image.height() - template.height() + 1);
+    let scores = create_output_texture(device, width, height, wgpu::TextureFormat::R32Float);
+
+    let shader = create_match_shader(device);
+    let stats_pipeline = create_pipeline(device, &shader, "template_statistics");
+    let match_pipeline = create_pipeline(device, &shader, "match_template");
+    let params = GpuBuffer::uniform(
+        device,
+        &MatchParams {
+            method: method as u32,
+            count: 0,
+            min_distance: 0.0,
+            width,
+        },
+    );
+    let template_stats = GpuBuffer::<f32>::storage_zeroed(device, 2);
+
+    let image_view = image.create_view(&wgpu::TextureViewDescriptor::default());
+    let scores_view = scores.create_view(&wgpu::TextureViewDescriptor::default());
+    let template_view = template.create_view(&wgpu::TextureViewDescriptor::default());
+    let stats_bind_group = create_bind_group(
+        device,
+        &stats_pipeline,
+        &[
+            (3, wgpu::BindingResource::TextureView(&template_view)),
+            (4, template_stats.as_entire_binding()),
+        ],
+    );
+    let match_bind_group = create_bind_group(
+        device,
+        &match_pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(&image_view)),
+            (1, wgpu::BindingResource::TextureView(&scores_view)),
+            (2, params.as_entire_binding()),
+            (3, wgpu::BindingResource::TextureView(&template_view)),
+            (4, template_stats.as_entire_binding()),
+        ],
+    );
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    dispatch(&mut encoder, &stats_pipeline, &stats_bind_group, (1, 1, 1));
+    let (x, y) = compute_work_group_count((width, height), (16, 16));
+    dispatch(&mut encoder, &match_pipeline, &match_bind_group, (x, y, 1));
+    queue.submit(Some(encoder.finish()));
+
+    Ok(scores)
+}
+
+/// 在得分图上选出最好的 count 个位置，彼此距离不小于 min_distance，按得分从好到差排列。
+/// 候选是比 3x3 邻域内其它位置都好的局部最大值，得分相同时像素下标(y * 宽 + x)小的优先
+pub fn best_matches(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    scores: &wgpu::Texture,
+    method: MatchMethod,
+    count: u32,
+    min_distance: f32,
+) -> Result<Vec<Match>> {
+    anyhow::ensure!(scores.format() == wgpu::TextureFormat::R32Float, "the score map must be R32Float");
+    if count == 0 {
+        return Ok(vec![]);
+    }
+
+    let shader = create_match_shader(device);
+    let maxima_pipeline = create_pipeline(device, &shader, "local_maxima");
+    let select_pipeline = create_pipeline(device, &shader, "select_best");
+    let (width, height) = (scores.width(), scores.height());
+    let params = GpuBuffer::uniform(
+        device,
+        &MatchParams {
+            method: method as u32,
+            count,
+            min_distance,
+            width,
+        },
+    );
+    // 相邻的位置不会同时成为候选，每个 2x2 块中最多一个
+    let capacity = width.div_ceil(2) * height.div_ceil(2);
+    let candidates = GpuBuffer::<Candidate>::storage_zeroed(device, capacity as usize);
+    let candidate_total = GpuBuffer::<u32>::storage_zeroed(device, 1);
+    let results = GpuBuffer::storage(device, &vec![Candidate::NONE; count as usize]);
+
+    let scores_view = scores.create_view(&wgpu::TextureViewDescriptor::default());
+    let maxima_bind_group = create_bind_group(
+        device,
+        &maxima_pipeline,
+        &[
+            (2, params.as_entire_binding()),
+            (5, wgpu::BindingResource::TextureView(&scores_view)),
+            (6, candidates.as_entire_binding()),
+            (8, candidate_total.as_entire_binding()),
+        ],
+    );
+    let select_bind_group = create_bind_group(
+        device,
+        &select_pipeline,
+        &[
+            (2, params.as_entire_binding()),
+            (6, candidates.as_entire_binding()),
+            (7, results.as_entire_binding()),
+            (8, candidate_total.as_entire_binding()),
+        ],
+    );
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let (x, y) = compute_work_group_count((width, height), (16, 16));
+    dispatch(&mut encoder, &maxima_pipeline, &maxima_bind_group, (x, y, 1));
+    dispatch(&mut encoder, &select_pipeline, &select_bind_group, (1, 1, 1));
+    queue.submit(Some(encoder.finish()));
+
+    Ok(results
+        .read_blocking(device, queue)?
+        .into_iter()
+        .take_while(|candidate| candidate.key > f32::MIN)
+        .map(|candidate| Match {
+            x: candidate.x,
+            y: candidate.y,
+            score: candidate.score,
+        })
+        .collect())
+}
+
+/// 在图像中查找模板，返回按得分从好到差排列的匹配
+pub fn find_template(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    image: &wgpu::Texture,
+    template: &wgpu::Texture,
+    options: &MatchOptions,
+) -> Result<Vec<Match>> {
+    anyhow::ensure!(
+        template.width() <= image.width() && template.height() <= image.height(),
+        "the {}x{} template is larger than the {}x{} image",
+        template.width(),
+        template.height(),
+        image.width(),
+        image.height()
+    );
+    let min_distance = options
+        .min_distance
+        .unwrap_or(template.width().min(template.height()) as f32 / 2.0);
+    let image = to_gray(device, queue, image)?;
+    let template = to_gray(device, queue, template)?;
+
+    // 模板缩小后不能太小，否则粗层上的得分没有区分度
+    let mut level = options.pyramid_levels.max(1) - 1;
+    while level > 0 && template.width().min(template.height()) >> level < MIN_PYRAMID_TEMPLATE_SIZE {
+        level -= 1;
+    }
+
+    let mut matches = if level == 0 {
+        let scores = match_gray(device, queue, &image, &template, options.method)?;
+        best_matches(device, queue, &scores, options.method, options.max_matches, min_distance)?
+    } else {
+        let image_pyramid = gaussian_pyramid(device, queue, &image, level + 1)?;
+        let template_pyramid = gaussian_pyramid(device, queue, &template, level + 1)?;
+        let scores = match_gray(device, queue, &image_pyramid[level as usize], &template_pyramid[level as usize], options.method)?;
+        // 多取一些候选，粗层上的排序不一定准确
+        let coarse = best_matches(
+            device,
+            queue,
+            &scores,
+            options.method,
+            options.max_matches * 2,
+            min_distance / (1 << level) as f32,
+        )?;
+
+        // 在原图上，以候选位置为中心、2^(level+1) 为半径的范围内重新匹配
+        let radius = 2 << level;
+        let mut refined = vec![];
+        for candidate in coarse {
+            let x = (candidate.x << level).saturating_sub(radius);
+            let y = (candidate.y << level).saturating_sub(radius);
+            let right = ((candidate.x << level) + radius + template.width()).min(image.width());
+            let bottom = ((candidate.y << level) + radius + template.height()).min(image.height());
+            let region = crop(device, queue, &image, Rect::new(x, y, right - x, bottom - y))?;
+            let scores = match_gray(device, queue, &region, &template, options.method)?;
+            if let Some(best) = best_matches(device, queue, &scores, options.method, 1, 0.0)?.first() {
+                refined.push(Match {
+                    x: x + best.x,
+                    y: y + best.y,
+                    score: best.score,
+                });
+            }
+        }
+
+        // 不同的候选可能细化到同一位置，按得分排序后去掉距离过近的
+        refined.sort_by(|a, b| {
+            let order = a.score.total_cmp(&b.score);
+            if options.method.higher_is_better() {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+        let mut matches: Vec<Match> = vec![];
+        for candidate in refined {
+            let close = matches.iter().any(|m| {
+                let (dx, dy) = (m.x as f32 - candidate.x as f32, m.y as f32 - candidate.y as f32);
+                (dx * dx + dy * dy).sqrt() < min_distance.max(0.5)
+            });
+            if !close {
+                matches.push(candidate);
+            }
+        }
+        matches.truncate(options.max_matches as usize);
+        matches
+    };
+
+    if let Some(threshold) = options.threshold {
+        matches.retain(|m| options.method.is_better_or_equal(m.score, threshold));
+    }
+    Ok(matches)
+}
+
+/// 模板匹配
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+
+    // 从截图中取出一块作为模板，再把它贴到另外两处，应找到三个位置
+    let mut screenshot = load_from_memory(include_bytes!("../images/capture.jpg"))?.to_rgba8();
+    let template_image = image::imageops::crop_imm(&screenshot, 412, 236, 48, 40).to_image();
+    let expected = [(412, 236), (100, 600), (900, 150)];
+    for &(x, y) in &expected[1..] {
+        image::imageops::replace(&mut screenshot, &template_image, x, y);
+    }
+    let image_texture = texture_from_image(&device, &queue, &screenshot);
+    let template_texture = texture_from_image(&device, &queue, &template_image);
+
+    // 三个位置各保存一张匹配到的区域
+    let options = MatchOptions {
+        max_matches: 3,
+        ..Default::default()
+    };
+    for (index, m) in find_template(&device, &queue, &image_texture, &template_texture, &options)?.iter().enumerate() {
+        let (width, height) = template_image.dimensions();
+        image::imageops::crop_imm(&screenshot, m.x, m.y, width, height)
+            .to_image()
+            .save(format!("./outputs/capture_match_{index}.png"))?;
+    }
+
+    // ZNCC 得分图映射到 0~255 保存
+    let scores = match_template(&device, &queue, &image_texture, &template_texture, MatchMethod::Zncc)?;
+    let score_image = rgba32f_from_texture(&device, &queue, &scores)?;
+    let score_map = GrayImage::from_fn(score_image.width(), score_image.height(), |x, y| {
+        image::Luma([((score_image.get_pixel(x, y)[0] + 1.0) / 2.0 * 255.0).round() as u8])
+    });
+    score_map.save("./outputs/capture_zncc_scores.png")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::texture_from_luma32f;
+    use crate::utils::random_image;
+    use crate::utils::test_device;
+    use image::ImageBuffer;
+    use image::Luma;
+
+    type GrayF32 = ImageBuffer<Luma<f32>, Vec<f32>>;
+
+    /// 0~1 之间的随机灰度图
+    fn random_gray(width: u32, height: u32, seed: u32) -> GrayF32 {
+        let image = random_image(width, height, seed);
+        GrayF32::from_fn(width, height, |x, y| Luma([image.get_pixel(x, y)[0] as f32 / 255.0]))
+    }
+
+    /// 把 template 贴到 image 的 (x, y) 处
+    fn paste(image: &mut GrayF32, template: &GrayF32, x: u32, y: u32) {
+        for (tx, ty, pixel) in template.enumerate_pixels() {
+            image.put_pixel(x + tx, y + ty, *pixel);
+        }
+    }
+
+    /// CPU 上计算模板左上角在 (x, y) 处的得分
+    fn cpu_score(image: &GrayF32, template: &GrayF32, x: u32, y: u32, method: MatchMethod) -> f64 {
+        let pairs = template
+            .enumerate_pixels()
+            .map(|(tx, ty, t)| (image.get_pixel(x + tx, y + ty)[0] as f64, t[0] as f64));
+        match method {
+            MatchMethod::Ssd => pairs.map(|(i, t)| (i - t) * (i - t)).sum(),
+            MatchMethod::Sad => pairs.map(|(i, t)| (i - t).abs()).sum(),
+            MatchMethod::Zncc => {
+                let n = (template.width() * template.height()) as f64;
+                let (image_mean, template_mean) = pairs.clone().fold((0.0, 0.0), |(a, b), (i, t)| (a + i / n, b + t / n));
+                let (mut covariance, mut image_variance, mut template_variance) = (0.0, 0.0, 0.0);
+                for (i, t) in pairs {
+                    covariance += (i - image_mean) * (t - template_mean);
+                    image_variance += (i - image_mean) * (i - image_mean);
+                    template_variance += (t - template_mean) * (t - template_mean);
+                }
+                let denominator = (image_variance * template_variance).sqrt();
+                if denominator > 1e-6 {
+                    covariance / denominator
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    const METHODS: [MatchMethod; 3] = [MatchMethod::Ssd, MatchMethod::Sad, MatchMethod::Zncc];
+
+    #[test]
+    fn scores_match_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        let image = random_gray(40, 27, 11);
+        let template = random_gray(9, 6, 12);
+        let image_texture = texture_from_luma32f(&device, &queue, &image);
+        let template_texture = texture_from_luma32f(&device, &queue, &template);
+        for method in METHODS {
+            let scores = match_template(&device, &queue, &image_texture, &template_texture, method).unwrap();
+            assert_eq!((scores.width(), scores.height()), (32, 22));
+            let scores = rgba32f_from_texture(&device, &queue, &scores).unwrap();
+            for (x, y, score) in scores.enumerate_pixels() {
+                let expected = cpu_score(&image, &template, x, y, method);
+                // f32 累加 54 项，SSD、SAD 按相对误差比较; ZNCC 由 ΣI² - (ΣI)²/n 求方差，有一定的抵消误差
+                let tolerance = if method == MatchMethod::Zncc { 1e-4 } else { 1e-5 * expected.max(1.0) };
+                assert!((score[0] as f64 - expected).abs() < tolerance, "{method:?} ({x}, {y}): {} {expected}", score[0]);
+            }
+        }
+    }
+
+    #[test]
+    fn finds_matches_closer_than_a_tile() {
+        let Some((device, queue)) = test_device() else { return };
+        // 三处相隔 9~13 个像素，都在同一个 16x16 块中
+        let template = random_gray(8, 8, 21);
+        let mut image = random_gray(48, 40, 22);
+        let expected = [(17, 18), (27, 18), (18, 27)];
+        for &(x, y) in &expected {
+            paste(&mut image, &template, x, y);
+        }
+        let image_texture = texture_from_luma32f(&device, &queue, &image);
+        let template_texture = texture_from_luma32f(&device, &queue, &template);
+
+        for method in METHODS {
+            let options = MatchOptions {
+                method,
+                max_matches: 3,
+                ..Default::default()
+            };
+            let matches = find_template(&device, &queue, &image_texture, &template_texture, &options).unwrap();
+            let mut positions: Vec<(u32, u32)> = matches.iter().map(|m| (m.x, m.y)).collect();
+            positions.sort();
+            let mut sorted = expected.to_vec();
+            sorted.sort();
+            assert_eq!(positions, sorted, "{method:?}");
+            let perfect = if method == MatchMethod::Zncc { 1.0 } else { 0.0 };
+            assert!(matches.iter().all(|m| (m.score - perfect).abs() < 1e-4), "{method:?} {matches:?}");
+
+            // 最小距离大于间隔时只剩一个，三处的得分完全相同，取像素下标最小的位置
+            let options = MatchOptions {
+                min_distance: Some(20.0),
+                threshold: Some(if method == MatchMethod::Zncc { 0.99 } else { 0.01 }),
+                ..options
+            };
+            let matches = find_template(&device, &queue, &image_texture, &template_texture, &options).unwrap();
+            assert_eq!(matches.iter().map(|m| (m.x, m.y)).collect::<Vec<_>>(), [(17, 18)], "{method:?}");
+        }
+    }
+
+    #[test]
+    fn best_matches_keeps_adjacent_peaks_and_breaks_ties_by_index() {
+        let Some((device, queue)) = test_device() else { return };
+        // 直接构造得分图: (5, 5) 和 (7, 5) 只隔一个像素，另外三个峰的得分相同
+        let mut scores = GrayF32::new(24, 12);
+        for (x, y, score) in [(5, 5, 0.9), (7, 5, 0.8), (20, 3, 0.7), (12, 3, 0.7), (3, 9, 0.7)] {
+            scores.put_pixel(x, y, Luma([score]));
+        }
+        let texture = texture_from_luma32f(&device, &queue, &scores);
+        // 得分为 0 的背景也是一片平坦区域，其中只有 (0, 0) 是候选
+        let matches = best_matches(&device, &queue, &texture, MatchMethod::Zncc, 10, 1.0).unwrap();
+        let positions: Vec<(u32, u32)> = matches.iter().map(|m| (m.x, m.y)).collect();
+        assert_eq!(positions, [(5, 5), (7, 5), (12, 3), (20, 3), (3, 9), (0, 0)]);
+        assert_eq!(matches[1].score, 0.8);
+
+        // 距离 2 以内的被抑制
+        let matches = best_matches(&device, &queue, &texture, MatchMethod::Zncc, 10, 2.5).unwrap();
+        assert_eq!(matches.len(), 5);
+        assert!(!matches.iter().any(|m| (m.x, m.y) == (7, 5)));
+
+        // 得分全部相同时整张图只有一个候选，即下标最小的 (0, 0)
+        let flat = texture_from_luma32f(&device, &queue, &GrayF32::from_pixel(19, 7, Luma([0.25])));
+        for method in METHODS {
+            let matches = best_matches(&device, &queue, &flat, method, 3, 0.0).unwrap();
+            assert_eq!(matches, [Match { x: 0, y: 0, score: 0.25 }], "{method:?}");
+        }
+    }
+
+    #[test]
+    fn pyramid_search_matches_full_search() {
+        let Some((device, queue)) = test_device() else { return };
+        let template = random_gray(32, 24, 31);
+        let mut image = random_gray(160, 120, 32);
+        let expected = [(13, 70), (101, 9)];
+        for &(x, y) in &expected {
+            paste(&mut image, &template, x, y);
+        }
+        let image_texture = texture_from_luma32f(&device, &queue, &image);
+        let template_texture = texture_from_luma32f(&device, &queue, &template);
+        let options = MatchOptions {
+            max_matches: 2,
+            threshold: Some(0.9),
+            pyramid_levels: 3,
+            ..Default::default()
+        };
+        let matches = find_template(&device, &queue, &image_texture, &template_texture, &options).unwrap();
+        let mut positions: Vec<(u32, u32)> = matches.iter().map(|m| (m.x, m.y)).collect();
+        positions.sort();
+        assert_eq!(positions, expected);
+    }
+
+    #[test]
+    fn template_larger_than_image_is_rejected() {
+        let Some((device, queue)) = test_device() else { return };
+        let image = texture_from_luma32f(&device, &queue, &random_gray(8, 8, 1));
+        let template = texture_from_luma32f(&device, &queue, &random_gray(9, 4, 2));
+        assert!(match_template(&device, &queue, &image, &template, MatchMethod::Zncc).is_err());
+        assert!(find_template(&device, &queue, &image, &template, &MatchOptions::default()).is_err());
+    }
+}
@@ -0,0 +1,273 @@
+#![allow(dead_code)]
+
+use anyhow::Result;
+use half::f16;
+use image::DynamicImage;
+use image::ImageBuffer;
+use image::Luma;
+use image::Rgba;
+use image::Rgba32FImage;
+use image::RgbaImage;
+
+use crate::buffer::read_range_blocking;
+
+/// 16 位 RGBA 图像
+pub type Rgba16Image = ImageBuffer<Rgba<u16>, Vec<u16>>;
+
+/// 计算结果纹理的用途: 可以作为 storage 写入、作为下一步的输入，也可以读回
+pub const OUTPUT_USAGE: wgpu::TextureUsages = wgpu::TextureUsages::STORAGE_BINDING
+    .union(wgpu::TextureUsages::TEXTURE_BINDING)
+    .union(wgpu::TextureUsages::COPY_SRC)
+    .union(wgpu::TextureUsages::COPY_DST);
+
+pub fn create_texture(device: &wgpu::Device, width: u32, height: u32, format: wgpu::TextureFormat, usage: wgpu::TextureUsages) -> wgpu::Texture {
+    create_texture_with_mips(device, width, height, format, usage, 1)
+}
+
+/// 完整 mip 链的层数，最后一层为 1x1
+pub fn mip_level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+/// 创建有 mip_level_count 层 mip 的纹理
+pub fn create_texture_with_mips(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+    usage: wgpu::TextureUsages,
+    mip_level_count: u32,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage,
+        view_formats: &[],
+    })
+}
+
+/// 创建计算结果纹理
+pub fn create_output_texture(device: &wgpu::Device, width: u32, height: u32, format: wgpu::TextureFormat) -> wgpu::Texture {
+    create_texture(device, width, height, format, OUTPUT_USAGE)
+}
+
+/// 把 RGBA8 图像上传为 Rgba8Unorm 纹理
+pub fn texture_from_image(device: &wgpu::Device, queue: &wgpu::Queue, image: &RgbaImage) -> wgpu::Texture {
+    let (width, height) = image.dimensions();
+    let texture = create_texture(
+        device,
+        width,
+        height,
+        wgpu::TextureFormat::Rgba8Unorm,
+        wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::COPY_SRC,
+    );
+
+    queue.write_texture(
+        texture.as_image_copy(),
+        image.as_raw(),
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * width),
+            rows_per_image: None, // Doesn't need to be specified as we are writing a single image.
+        },
+        texture.size(),
+    );
+    texture
+}
+
+/// 把浮点像素上传为 Rgba16Float 或 Rgba32Float 纹理
+fn texture_from_f32(device: &wgpu::Device, queue: &wgpu::Queue, (width, height): (u32, u32), pixels: &[f32], format: wgpu::TextureFormat) -> Result<wgpu::Texture> {
+    let data: Vec<u8> = match format {
+        wgpu::TextureFormat::Rgba32Float => bytemuck::cast_slice(pixels).to_vec(),
+        wgpu::TextureFormat::Rgba16Float => {
+            let halves: Vec<u16> = pixels.iter().map(|&value| f16::from_f32(value).to_bits()).collect();
+            bytemuck::cast_slice(&halves).to_vec()
+        }
+        _ => anyhow::bail!("expected Rgba16Float or Rgba32Float, got {format:?}"),
+    };
+    let texture = create_texture(
+        device,
+        width,
+        height,
+        format,
+        wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::COPY_SRC,
+    );
+    let bytes_per_pixel = format.block_copy_size(None).unwrap_or(16);
+    queue.write_texture(
+        texture.as_image_copy(),
+        &data,
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(bytes_per_pixel * width),
+            rows_per_image: None,
+        },
+        texture.size(),
+    );
+    Ok(texture)
+}
+
+/// 把浮点图像上传为 Rgba16Float 或 Rgba32Float 纹理，数值原样保留(可以超出 0~1)
+pub fn texture_from_rgba32f(device: &wgpu::Device, queue: &wgpu::Queue, image: &Rgba32FImage, format: wgpu::TextureFormat) -> Result<wgpu::Texture> {
+    texture_from_f32(device, queue, image.dimensions(), image.as_raw(), format)
+}
+
+/// 把 16 位图像归一化到 0~1 后上传。Rgba32Float 可以无损保存全部 65536 级，Rgba16Float 只有 11 位有效精度
+pub fn texture_from_rgba16(device: &wgpu::Device, queue: &wgpu::Queue, image: &Rgba16Image, format: wgpu::TextureFormat) -> Result<wgpu::Texture> {
+    let pixels: Vec<f32> = image.as_raw().iter().map(|&value| value as f32 / 65535.0).collect();
+    texture_from_f32(device, queue, image.dimensions(), &pixels, format)
+}
+
+/// 把单通道浮点图像上传为 R32Float 纹理
+pub fn texture_from_luma32f(device: &wgpu::Device, queue: &wgpu::Queue, image: &ImageBuffer<Luma<f32>, Vec<f32>>) -> wgpu::Texture {
+    let (width, height) = image.dimensions();
+    let texture = create_texture(
+        device,
+        width,
+        height,
+        wgpu::TextureFormat::R32Float,
+        wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::COPY_SRC,
+    );
+    queue.write_texture(
+        texture.as_image_copy(),
+        bytemuck::cast_slice(image.as_raw()),
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * width),
+            rows_per_image: None,
+        },
+        texture.size(),
+    );
+    texture
+}
+
+/// 按位深选择纹理格式: 8 位图像为 Rgba8Unorm，16 位和浮点图像为 high_depth_format(Rgba16Float 或 Rgba32Float)
+pub fn texture_from_dynamic(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    image: &DynamicImage,
+    high_depth_format: wgpu::TextureFormat,
+) -> Result<wgpu::Texture> {
+    let color = image.color();
+    match color.bytes_per_pixel() / color.channel_count() {
+        1 => Ok(texture_from_image(device, queue, &image.to_rgba8())),
+        2 => texture_from_rgba16(device, queue, &image.to_rgba16(), high_depth_format),
+        _ => texture_from_rgba32f(device, queue, &image.to_rgba32f(), high_depth_format),
+    }
+}
+
+/// 读回纹理的原始数据(去掉每行的 256 字节对齐填充)
+pub fn read_texture(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) -> Result<Vec<u8>> {
+    let (width, height) = (texture.width(), texture.height());
+    let bytes_per_pixel = texture
+        .format()
+        .block_copy_size(None)
+        .ok_or(anyhow::anyhow!("unsupported texture format {:?}", texture.format()))?;
+    let unpadded_bytes_per_row = width * bytes_per_pixel;
+    let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+    let output_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: padded_bytes_per_row as u64 * height as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::TexelCopyBufferInfo {
+            buffer: &output_buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let padded_data: Vec<u8> = read_range_blocking(device, queue, &output_buffer, 0, output_buffer.size() as usize)?;
+
+    let mut pixels: Vec<u8> = vec![0; (unpadded_bytes_per_row * height) as usize];
+    for (padded, pixels) in padded_data
+        .chunks_exact(padded_bytes_per_row as usize)
+        .zip(pixels.chunks_exact_mut(unpadded_bytes_per_row as usize))
+    {
+        pixels.copy_from_slice(&padded[..unpadded_bytes_per_row as usize]);
+    }
+    Ok(pixels)
+}
+
+/// 读回 Rgba8Unorm 纹理
+pub fn image_from_texture(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) -> Result<RgbaImage> {
+    let pixels = read_texture(device, queue, texture)?;
+    RgbaImage::from_raw(texture.width(), texture.height(), pixels).ok_or(anyhow::anyhow!("texture is not rgba8"))
+}
+
+/// 读回为浮点图像，支持 Rgba8Unorm、Rgba16Float、Rgba32Float 和 R32Float(展开为灰度)
+pub fn rgba32f_from_texture(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) -> Result<Rgba32FImage> {
+    let data = read_texture(device, queue, texture)?;
+    let pixels: Vec<f32> = match texture.format() {
+        wgpu::TextureFormat::Rgba8Unorm => data.iter().map(|&value| value as f32 / 255.0).collect(),
+        wgpu::TextureFormat::Rgba16Float => data
+            .chunks_exact(2)
+            .map(|bytes| f16::from_le_bytes([bytes[0], bytes[1]]).to_f32())
+            .collect(),
+        wgpu::TextureFormat::Rgba32Float => bytemuck::pod_collect_to_vec(&data),
+        wgpu::TextureFormat::R32Float => bytemuck::pod_collect_to_vec::<u8, f32>(&data)
+            .into_iter()
+            .flat_map(|value| [value, value, value, 1.0])
+            .collect(),
+        format => anyhow::bail!("unsupported texture format {format:?}"),
+    };
+    Rgba32FImage::from_raw(texture.width(), texture.height(), pixels).ok_or(anyhow::anyhow!("texture size does not match"))
+}
+
+/// 读回为 16 位图像，超出 0~1 的值会被截断
+pub fn rgba16_from_texture(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) -> Result<Rgba16Image> {
+    let image = rgba32f_from_texture(device, queue, texture)?;
+    let pixels: Vec<u16> = image.as_raw().iter().map(|&value| (value.clamp(0.0, 1.0) * 65535.0).round() as u16).collect();
+    Rgba16Image::from_raw(image.width(), image.height(), pixels).ok_or(anyhow::anyhow!("texture size does not match"))
+}
+
+/// 中间结果的纹理格式: 32 位浮点输入保持 32 位，其它格式用 Rgba16Float 避免 8 位量化误差
+pub fn intermediate_format(format: wgpu::TextureFormat) -> wgpu::TextureFormat {
+    match format {
+        wgpu::TextureFormat::Rgba32Float | wgpu::TextureFormat::R32Float => wgpu::TextureFormat::Rgba32Float,
+        _ => wgpu::TextureFormat::Rgba16Float,
+    }
+}
+
+/// storage 纹理格式在 WGSL 中的名字
+pub fn wgsl_storage_format(format: wgpu::TextureFormat) -> Result<&'static str> {
+    Ok(match format {
+        wgpu::TextureFormat::Rgba8Unorm => "rgba8unorm",
+        wgpu::TextureFormat::Rgba16Float => "rgba16float",
+        wgpu::TextureFormat::Rgba32Float => "rgba32float",
+        wgpu::TextureFormat::R32Float => "r32float",
+        _ => anyhow::bail!("{format:?} is not supported as a storage texture"),
+    })
+}
+
+/// 着色器里的输出纹理格式写作占位符 STORAGE_FORMAT，这里替换成实际的输出格式
+pub fn with_storage_format(source: &str, format: wgpu::TextureFormat) -> Result<String> {
+    Ok(source.replace("STORAGE_FORMAT", wgsl_storage_format(format)?))
+}
+
+/// 只包含第 level 层 mip 的视图
+pub fn mip_view(texture: &wgpu::Texture, level: u32) -> wgpu::TextureView {
+    texture.create_view(&wgpu::TextureViewDescriptor {
+        base_mip_level: level,
+        mip_level_count: Some(1),
+        ..Default::default()
+    })
+}
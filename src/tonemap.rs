@@ -0,0 +1,394 @@
+#![allow(dead_code)]
+
+use std::path::Path;
+
+use anyhow::Ok;
+use anyhow::Result;
+use image::load_from_memory;
+use image::DynamicImage;
+use image::Rgba32FImage;
+
+use crate::blur::fast_blur;
+use crate::blur::EdgeMode;
+use crate::buffer::GpuBuffer;
+use crate::texture::create_output_texture;
+use crate::texture::image_from_texture;
+use crate::texture::texture_from_rgba32f;
+use crate::utils::compute_work_group_count;
+use crate::utils::create_bind_group;
+use crate::utils::create_pipeline;
+use crate::utils::create_shader;
+use crate::utils::dispatch;
+use crate::utils::request_device;
+
+/// 色调映射曲线，与 tonemap.wgsl 中的 curve 对应
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ToneMapOperator {
+    /// 只做曝光和 gamma，超过 1 的部分直接截断
+    Clamp = 0,
+    /// 全局 Reinhard，亮度达到 white 时映射为纯白
+    Reinhard = 1,
+    /// 局部 Reinhard，用高斯模糊后的邻域亮度做分母
+    ReinhardLocal = 2,
+    /// ACES filmic 拟合曲线
+    #[default]
+    Aces = 3,
+    /// Uncharted 2 的 Hable 曲线
+    Hable = 4,
+}
+
+/// 曝光方式
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Exposure {
+    /// 固定曝光补偿，单位 EV
+    Manual(f32),
+    /// 把对数平均亮度映射到 key(中灰通常取 0.18)，在 GPU 上统计，不需要读回
+    Auto { key: f32 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ToneMapConfig {
+    pub operator: ToneMapOperator,
+    pub exposure: Exposure,
+    /// 输出 = 映射结果^(1/gamma)
+    pub gamma: f32,
+    /// Reinhard 和 Hable 中映射为纯白的亮度(曝光之后)
+    pub white: f32,
+    /// 局部 Reinhard 的邻域大小
+    pub local_sigma: f32,
+}
+
+impl Default for ToneMapConfig {
+    fn default() -> Self {
+        Self {
+            operator: ToneMapOperator::default(),
+            exposure: Exposure::Auto { key: 0.18 },
+            gamma: 2.2,
+            white: 11.2,
+            local_sigma: 16.0,
+        }
+    }
+}
+
+/// 亮度统计
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LuminanceStats {
+    /// exp(mean(ln(δ + L)))
+    pub log_average: f32,
+    pub max: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct ToneMapParams {
+    curve: u32,
+    auto_exposure: u32,
+    exposure_scale: f32,
+    key: f32,
+    inverse_gamma: f32,
+    white: f32,
+    _padding: [f32; 2],
+}
+
+/// 读取 Radiance HDR(.hdr) 或 OpenEXR(.exr) 图像，上传为 Rgba32Float 纹理
+pub fn load_hdr(device: &wgpu::Device, queue: &wgpu::Queue, path: impl AsRef<Path>) -> Result<wgpu::Texture> {
+    let image = image::open(path)?.into_rgba32f();
+    texture_from_rgba32f(device, queue, &image, wgpu::TextureFormat::Rgba32Float)
+}
+
+/// 两次归约统计亮度，结果写入 stats: [对数平均亮度, 最大亮度]
+fn encode_luminance_stats(device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, shader: &wgpu::ShaderModule, input: &wgpu::Texture, stats: &GpuBuffer<f32>) {
+    let (x, y) = compute_work_group_count((input.width(), input.height()), (16, 16));
+    let partials = GpuBuffer::<f32>::storage_zeroed(device, 2 * (x * y) as usize);
+
+    let log_pipeline = create_pipeline(device, shader, "log_luminance");
+    let reduce_pipeline = create_pipeline(device, shader, "reduce_luminance");
+    let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+    let log_bind_group = create_bind_group(
+        device,
+        &log_pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(&input_view)),
+            (4, partials.as_entire_binding()),
+        ],
+    );
+    let reduce_bind_group = create_bind_group(
+        device,
+        &reduce_pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(&input_view)),
+            (3, stats.as_entire_binding()),
+            (4, partials.as_entire_binding()),
+        ],
+    );
+    dispatch(encoder, &log_pipeline, &log_bind_group, (x, y, 1));
+    dispatch(encoder, &reduce_pipeline, &reduce_bind_group, (1, 1, 1));
+}
+
+/// 统计线性 HDR 纹理的对数平均亮度和最大亮度
+pub fn luminance_stats(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture) -> Result<LuminanceStats> {
+    let shader = create_shader(device, "tonemap_shader_module", include_str!("../shaders/tonemap.wgsl"));
+    let stats = GpuBuffer::<f32>::storage_zeroed(device, 2);
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encode_luminance_stats(device, &mut encoder, &shader, input, &stats);
+    queue.submit(Some(encoder.finish()));
+
+    let stats = stats.read_blocking(device, queue)?;
+    Ok(LuminanceStats {
+        log_average: stats[0],
+        max: stats[1],
+    })
+}
+
+/// 把线性的 HDR 纹理映射为 Rgba8Unorm，可以直接用 image_from_texture 读回保存
+pub fn tone_map(device: &wgpu::Device, queue: &wgpu::Queue, input: &wgpu::Texture, config: &ToneMapConfig) -> Result<wgpu::Texture> {
+    let (width, height) = (input.width(), input.height());
+    let output = create_output_texture(device, width, height, wgpu::TextureFormat::Rgba8Unorm);
+
+    let shader = create_shader(device, "tonemap_shader_module", include_str!("../shaders/tonemap.wgsl"));
+    let pipeline = create_pipeline(device, &shader, "tonemap");
+
+    let (auto_exposure, exposure_scale, key) = match config.exposure {
+        Exposure::Manual(ev) => (0, ev.exp2(), 0.0),
+        Exposure::Auto { key } => (1, 1.0, key),
+    };
+    let params = GpuBuffer::uniform(
+        device,
+        &ToneMapParams {
+            curve: config.operator as u32,
+            auto_exposure,
+            exposure_scale,
+            key,
+            inverse_gamma: 1.0 / config.gamma.max(1e-3),
+            white: config.white.max(1e-3),
+            _padding: [0.0; 2],
+        },
+    );
+    let stats = GpuBuffer::<f32>::storage_zeroed(device, 2);
+
+    // 局部算子需要邻域平均颜色，其它算子不会读取，绑定输入本身即可
+    let adaptation = if config.operator == ToneMapOperator::ReinhardLocal {
+        Some(fast_blur(device, queue, input, config.local_sigma, EdgeMode::Mirror)?)
+    } else {
+        None
+    };
+
+    let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+    let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+    let adaptation_view = adaptation.as_ref().unwrap_or(input).create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = create_bind_group(
+        device,
+        &pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(&input_view)),
+            (1, wgpu::BindingResource::TextureView(&output_view)),
+            (2, params.as_entire_binding()),
+            (3, stats.as_entire_binding()),
+            (5, wgpu::BindingResource::TextureView(&adaptation_view)),
+        ],
+    );
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    if auto_exposure == 1 {
+        encode_luminance_stats(device, &mut encoder, &shader, input, &stats);
+    }
+    let (x, y) = compute_work_group_count((width, height), (16, 16));
+    dispatch(&mut encoder, &pipeline, &bind_group, (x, y, 1));
+    queue.submit(Some(encoder.finish()));
+
+    Ok(output)
+}
+
+/// 用 8 位图像合成一张 HDR 图像: 解码 sRGB 后乘以从左到右增强的光照，右上角加一个很亮的光源
+fn synthesize_hdr(image: &image::RgbaImage) -> Rgba32FImage {
+    let (width, height) = image.dimensions();
+    let decode = |value: u8| {
+        let c = value as f32 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    Rgba32FImage::from_fn(width, height, |x, y| {
+        let pixel = image.get_pixel(x, y);
+        let light = 0.05 * 1000f32.powf(x as f32 / width as f32);
+        let (dx, dy) = (x as f32 - width as f32 * 0.85, y as f32 - height as f32 * 0.15);
+        let sun = 200.0 * (-(dx * dx + dy * dy) / (2.0 * 20.0 * 20.0)).exp();
+        image::Rgba([
+            decode(pixel[0]) * light + sun,
+            decode(pixel[1]) * light + sun,
+            decode(pixel[2]) * light + sun * 0.9,
+            pixel[3] as f32 / 255.0,
+        ])
+    })
+}
+
+/// HDR 读取和色调映射
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+
+    let hdr = synthesize_hdr(&load_from_memory(include_bytes!("../images/sushi.png"))?.to_rgba8());
+    DynamicImage::ImageRgba32F(hdr.clone()).to_rgb32f().save("./outputs/sushi.hdr")?;
+    hdr.save("./outputs/sushi.exr")?;
+
+    let input_texture = load_hdr(&device, &queue, "./outputs/sushi.exr")?;
+
+    let operators = [
+        (ToneMapOperator::Clamp, "clamp"),
+        (ToneMapOperator::Reinhard, "reinhard"),
+        (ToneMapOperator::ReinhardLocal, "reinhard_local"),
+        (ToneMapOperator::Aces, "aces"),
+        (ToneMapOperator::Hable, "hable"),
+    ];
+    for (operator, name) in operators {
+        let config = ToneMapConfig { operator, ..Default::default() };
+        let output = tone_map(&device, &queue, &input_texture, &config)?;
+        image_from_texture(&device, &queue, &output)?.save(format!("./outputs/sushi_tonemap_{name}.png"))?;
+    }
+
+    // 手动曝光: 每档 EV 亮度翻倍
+    for ev in [-2.0, 0.0, 2.0] {
+        let config = ToneMapConfig {
+            operator: ToneMapOperator::Clamp,
+            exposure: Exposure::Manual(ev),
+            ..Default::default()
+        };
+        let output = tone_map(&device, &queue, &input_texture, &config)?;
+        image_from_texture(&device, &queue, &output)?.save(format!("./outputs/sushi_exposure_{ev}.png"))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::rgba32f_from_texture;
+    use crate::utils::max_u8_diff;
+    use crate::utils::random_rgba32f;
+    use crate::utils::test_device;
+
+    /// 随机的 HDR 图像，亮度跨越约 5 个数量级，并有一个很亮的区域
+    fn random_hdr(width: u32, height: u32, seed: u32) -> Rgba32FImage {
+        let noise = random_rgba32f(width, height, seed);
+        Rgba32FImage::from_fn(width, height, |x, y| {
+            let pixel = noise.get_pixel(x, y).0;
+            let light = 0.01 * 10000f32.powf(x as f32 / width as f32);
+            let sun = if x > width * 3 / 4 && y < height / 4 { 500.0 } else { 0.0 };
+            image::Rgba([pixel[0] * light + sun, pixel[1] * light + sun, pixel[2] * light, pixel[3]])
+        })
+    }
+
+    fn cpu_luminance(c: [f32; 3]) -> f32 {
+        0.2126 * c[0] + 0.7152 * c[1] + 0.0722 * c[2]
+    }
+
+    fn cpu_log_average(image: &Rgba32FImage) -> f64 {
+        let sum: f64 = image.pixels().map(|pixel| (1e-4 + cpu_luminance([pixel[0], pixel[1], pixel[2]]).max(0.0) as f64).ln()).sum();
+        (sum / image.pixels().len() as f64).exp()
+    }
+
+    /// CPU 上的色调映射，与 tonemap.wgsl 相同，adaptation 为局部算子的邻域平均颜色
+    fn cpu_tone_map(image: &Rgba32FImage, adaptation: Option<&Rgba32FImage>, config: &ToneMapConfig) -> image::RgbaImage {
+        let scale = match config.exposure {
+            Exposure::Manual(ev) => ev.exp2(),
+            Exposure::Auto { key } => key / (cpu_log_average(image) as f32).max(1e-4),
+        };
+        let white = config.white.max(1e-3);
+        let scale_luminance = |c: [f32; 3], l: f32, mapped: f32| if l <= 0.0 { [0.0; 3] } else { c.map(|v| v * mapped / l) };
+        let hable_curve = |x: f32| (x * (0.15 * x + 0.05) + 0.004) / (x * (0.15 * x + 0.5) + 0.06) - 0.02 / 0.3;
+        image::RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+            let pixel = image.get_pixel(x, y).0;
+            let hdr = [pixel[0], pixel[1], pixel[2]].map(|v| (v * scale).max(0.0));
+            let l = cpu_luminance(hdr);
+            let mapped = match config.operator {
+                ToneMapOperator::Clamp => hdr,
+                ToneMapOperator::Reinhard => scale_luminance(hdr, l, l * (1.0 + l / (white * white)) / (1.0 + l)),
+                ToneMapOperator::ReinhardLocal => {
+                    let a = adaptation.unwrap().get_pixel(x, y).0;
+                    let adaptation = (cpu_luminance([a[0], a[1], a[2]]) * scale).max(0.0);
+                    scale_luminance(hdr, l, l / (1.0 + adaptation))
+                }
+                ToneMapOperator::Aces => hdr.map(|c| (c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14)),
+                ToneMapOperator::Hable => hdr.map(|c| hable_curve(2.0 * c) / hable_curve(white)),
+            };
+            let [r, g, b] = mapped.map(|v| (v.clamp(0.0, 1.0).powf(1.0 / config.gamma.max(1e-3)) * 255.0).round() as u8);
+            image::Rgba([r, g, b, (pixel[3].clamp(0.0, 1.0) * 255.0).round() as u8])
+        })
+    }
+
+    #[test]
+    fn luminance_stats_match_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        // 不是 16 的倍数，覆盖不完整的工作组
+        let hdr = random_hdr(123, 77, 91);
+        let texture = texture_from_rgba32f(&device, &queue, &hdr, wgpu::TextureFormat::Rgba32Float).unwrap();
+        let stats = luminance_stats(&device, &queue, &texture).unwrap();
+        // 对数之和在 GPU 上用 f32 树形归约，相对误差远小于 1e-4
+        let expected = cpu_log_average(&hdr);
+        assert!((stats.log_average as f64 - expected).abs() <= 1e-4 * expected, "{} {expected}", stats.log_average);
+        let max = hdr.pixels().map(|pixel| cpu_luminance([pixel[0], pixel[1], pixel[2]])).fold(0.0, f32::max);
+        assert!((stats.max - max).abs() <= 1e-6 * max, "{} {max}", stats.max);
+    }
+
+    #[test]
+    fn tone_map_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        let hdr = random_hdr(64, 48, 92);
+        let texture = texture_from_rgba32f(&device, &queue, &hdr, wgpu::TextureFormat::Rgba32Float).unwrap();
+        // 局部算子的邻域平均直接用 GPU 的 fast_blur 结果(blur 模块另有测试)
+        let adaptation = rgba32f_from_texture(&device, &queue, &fast_blur(&device, &queue, &texture, 4.0, EdgeMode::Mirror).unwrap()).unwrap();
+        let operators = [
+            ToneMapOperator::Clamp,
+            ToneMapOperator::Reinhard,
+            ToneMapOperator::ReinhardLocal,
+            ToneMapOperator::Aces,
+            ToneMapOperator::Hable,
+        ];
+        for operator in operators {
+            for exposure in [Exposure::Manual(-3.0), Exposure::Auto { key: 0.18 }] {
+                let config = ToneMapConfig {
+                    operator,
+                    exposure,
+                    gamma: 2.2,
+                    white: 4.0,
+                    local_sigma: 4.0,
+                };
+                let output = image_from_texture(&device, &queue, &tone_map(&device, &queue, &texture, &config).unwrap()).unwrap();
+                let expected = cpu_tone_map(&hdr, Some(&adaptation), &config);
+                // 输出为 8 位，只差在最后的量化上
+                let diff = max_u8_diff(&output, &expected);
+                assert!(diff <= 1, "{operator:?} {exposure:?}: {diff}");
+            }
+        }
+    }
+
+    #[test]
+    fn hdr_files_round_trip() {
+        let Some((device, queue)) = test_device() else { return };
+        let hdr = random_hdr(40, 30, 93);
+        let directory = std::env::temp_dir().join(format!("tonemap_test_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        // EXR 保存 32 位浮点，往返无损
+        let exr = directory.join("test.exr");
+        hdr.save(&exr).unwrap();
+        let loaded = rgba32f_from_texture(&device, &queue, &load_hdr(&device, &queue, &exr).unwrap()).unwrap();
+        assert!(loaded == hdr);
+
+        // Radiance HDR 是 RGBE 编码: 三个通道共用向上取整的 2 的幂指数，各有 8 位截断的尾数，误差不超过最大通道的 2^-7
+        let radiance = directory.join("test.hdr");
+        DynamicImage::ImageRgba32F(hdr.clone()).to_rgb32f().save(&radiance).unwrap();
+        let loaded = rgba32f_from_texture(&device, &queue, &load_hdr(&device, &queue, &radiance).unwrap()).unwrap();
+        for (a, b) in loaded.pixels().zip(hdr.pixels()) {
+            let max = b[0].max(b[1]).max(b[2]);
+            for channel in 0..3 {
+                assert!((a[channel] - b[channel]).abs() <= max / 128.0, "{a:?} {b:?}");
+            }
+            assert_eq!(a[3], 1.0);
+        }
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
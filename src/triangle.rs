@@ -45,7 +45,7 @@ pub fn triangle() -> Result<()>{
         dimension: wgpu::TextureDimension::D2,
         format: wgpu::TextureFormat::Rgba8Unorm,
         usage: wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::RENDER_ATTACHMENT,
-        view_formats: &vec![],
+        view_formats: &[],
     });
 
     println!("output_texture 创建完成. {}x{}", output_texture.width(), output_texture.height());
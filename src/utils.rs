@@ -1,7 +1,192 @@
+use anyhow::Result;
+use pollster::FutureExt;
+
+use crate::buffer::read_range_blocking;
 
 /// Compute the next multiple of 256 for texture retrieval padding.
 pub fn padded_bytes_per_row(width: u32) -> usize {
     let bytes_per_row = width as usize * 4;
     let padding = (256 - bytes_per_row % 256) % 256;
     bytes_per_row + padding
-}
\ No newline at end of file
+}
+
+/// 创建默认的 device 和 queue
+pub fn request_device() -> Result<(wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::default();
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptionsBase::default())
+        .block_on()
+        .ok_or(anyhow::anyhow!("Couldn't create the adapter"))?;
+
+    let (device, queue) = adapter
+        .request_device(&Default::default(), None)
+        .block_on()?;
+    Ok((device, queue))
+}
+
+/// 测试用的 device 和 queue，没有可用的适配器时返回 None，调用方应跳过测试
+#[cfg(test)]
+pub fn test_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    match request_device() {
+        anyhow::Result::Ok(device) => Some(device),
+        Err(error) => {
+            eprintln!("跳过 GPU 测试: {error}");
+            None
+        }
+    }
+}
+
+/// 测试用的随机 RGBA8 图像，种子固定所以结果可以复现
+#[cfg(test)]
+pub fn random_image(width: u32, height: u32, mut seed: u32) -> image::RgbaImage {
+    image::RgbaImage::from_fn(width, height, |_, _| {
+        image::Rgba(crate::radix_sort::xorshift(&mut seed).to_le_bytes())
+    })
+}
+
+/// 测试用的随机浮点图像，每个分量在 0~1 之间
+#[cfg(test)]
+pub fn random_rgba32f(width: u32, height: u32, seed: u32) -> image::Rgba32FImage {
+    let image = random_image(width, height, seed);
+    image::Rgba32FImage::from_fn(width, height, |x, y| image::Rgba(image.get_pixel(x, y).0.map(|v| v as f32 / 255.0)))
+}
+
+/// 两组数据逐个比较的最大绝对误差
+#[cfg(test)]
+pub fn max_abs_diff(a: &[f32], b: &[f32]) -> f32 {
+    assert_eq!(a.len(), b.len());
+    a.iter().zip(b).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max)
+}
+
+/// 两张 8 位图像逐个分量比较的最大误差
+#[cfg(test)]
+pub fn max_u8_diff(a: &image::RgbaImage, b: &image::RgbaImage) -> u8 {
+    assert_eq!(a.dimensions(), b.dimensions());
+    a.as_raw().iter().zip(b.as_raw()).map(|(&a, &b)| a.abs_diff(b)).max().unwrap_or(0)
+}
+
+/// 把 storage 缓冲区的前 len 个元素读回内存
+pub fn read_buffer<T: bytemuck::Pod>(device: &wgpu::Device, queue: &wgpu::Queue, buffer: &wgpu::Buffer, len: usize) -> Result<Vec<T>> {
+    read_range_blocking(device, queue, buffer, 0, len)
+}
+
+/// 一维任务使用的工作组大小，不超过设备限制
+pub fn linear_workgroup_size(limits: &wgpu::Limits, preferred: u32) -> u32 {
+    preferred
+        .min(limits.max_compute_workgroup_size_x)
+        .min(limits.max_compute_invocations_per_workgroup)
+        .max(1)
+}
+
+/// 一维任务的工作组数量。
+/// 超过每一维的上限(通常是 65535)时分摊到 y 维，着色器中用
+/// `global_id.x + global_id.y * num_workgroups.x * WORKGROUP_SIZE` 还原下标，并和真实长度比较。
+pub fn linear_workgroup_count(limits: &wgpu::Limits, len: u32, workgroup_size: u32) -> (u32, u32) {
+    let groups = len.div_ceil(workgroup_size);
+    let max_groups = limits.max_compute_workgroups_per_dimension;
+    if groups <= max_groups {
+        return (groups, 1);
+    }
+    let y = groups.div_ceil(max_groups);
+    (groups.div_ceil(y), y)
+}
+
+/// Compute the amount of work groups to be dispatched for an image, based on the work group size.
+/// Chances are, the group will not match perfectly, like an image of width 100, for a workgroup size of 32.
+/// To make sure the that the whole 100 pixels are visited, then we would need a count of 4, as 4 * 32 = 128,
+/// which is bigger than 100. A count of 3 would be too little, as it means 96, so four columns (or, 100 - 96) would be ignored.
+///
+/// # Arguments
+///
+/// * `(width, height)` - The dimension of the image we are working on.
+/// * `(workgroup_width, workgroup_height)` - The width and height dimensions of the compute workgroup.
+pub fn compute_work_group_count(
+    (width, height): (u32, u32),
+    (workgroup_width, workgroup_height): (u32, u32),
+) -> (u32, u32) {
+    let x = width.div_ceil(workgroup_width);
+    let y = height.div_ceil(workgroup_height);
+
+    (x, y)
+}
+
+/// 从 WGSL 源码创建着色器模块
+pub fn create_shader(device: &wgpu::Device, label: &str, source: &str) -> wgpu::ShaderModule {
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    })
+}
+
+/// 创建计算流水线，布局由着色器自动推导
+pub fn create_pipeline(device: &wgpu::Device, shader: &wgpu::ShaderModule, entry_point: &str) -> wgpu::ComputePipeline {
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some(entry_point),
+        layout: None,
+        module: shader,
+        entry_point: Some(entry_point),
+        compilation_options: wgpu::PipelineCompilationOptions::default(),
+        cache: None,
+    })
+}
+
+/// 按 (binding, 资源) 创建流水线 group 0 的 bind group。
+/// 自动推导的布局只包含入口函数用到的 binding，这里也只需要传这些。
+pub fn create_bind_group(device: &wgpu::Device, pipeline: &wgpu::ComputePipeline, entries: &[(u32, wgpu::BindingResource)]) -> wgpu::BindGroup {
+    let entries: Vec<wgpu::BindGroupEntry> = entries
+        .iter()
+        .map(|(binding, resource)| wgpu::BindGroupEntry {
+            binding: *binding,
+            resource: resource.clone(),
+        })
+        .collect();
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &entries,
+    })
+}
+
+/// 在 encoder 中记录一次计算调度
+pub fn dispatch(encoder: &mut wgpu::CommandEncoder, pipeline: &wgpu::ComputePipeline, bind_group: &wgpu::BindGroup, (x, y, z): (u32, u32, u32)) {
+    let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+    cpass.set_pipeline(pipeline);
+    cpass.set_bind_group(0, bind_group, &[]);
+    cpass.dispatch_workgroups(x, y, z);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_workgroup_count_splits_over_y() {
+        let limits = wgpu::Limits {
+            max_compute_workgroups_per_dimension: 1000,
+            ..Default::default()
+        };
+        for (len, workgroup_size) in [(1, 256), (1000 * 256, 256), (1000 * 256 + 1, 256), (65535 * 256 + 1, 256), (u32::MAX, 64)] {
+            let (x, y) = linear_workgroup_count(&limits, len, workgroup_size);
+            let groups = len.div_ceil(workgroup_size) as u64;
+            assert!(x <= 1000, "len {len}: x = {x}");
+            assert!(x as u64 * y as u64 >= groups, "len {len}: {x}x{y} groups");
+            // y 取最小值: 少一行就放不下
+            assert!((y as u64 - 1) * 1000 < groups, "len {len}: y = {y}");
+        }
+        assert_eq!(linear_workgroup_count(&limits, 1000 * 256 + 1, 256), (501, 2));
+
+        // 默认上限 65535 时刚好超出一个工作组
+        assert_eq!(linear_workgroup_count(&wgpu::Limits::default(), 65535 * 256, 256), (65535, 1));
+        assert_eq!(linear_workgroup_count(&wgpu::Limits::default(), 65535 * 256 + 1, 256), (32768, 2));
+    }
+
+    #[test]
+    fn linear_workgroup_size_respects_limits() {
+        let limits = wgpu::Limits {
+            max_compute_workgroup_size_x: 128,
+            ..Default::default()
+        };
+        assert_eq!(linear_workgroup_size(&limits, 256), 128);
+        assert_eq!(linear_workgroup_size(&wgpu::Limits::default(), 256), 256);
+    }
+}
@@ -0,0 +1,431 @@
+#![allow(dead_code)]
+
+use anyhow::Ok;
+use anyhow::Result;
+use image::load_from_memory;
+
+use crate::blur::EdgeMode;
+use crate::buffer::GpuBuffer;
+use crate::texture::create_output_texture;
+use crate::texture::image_from_texture;
+use crate::texture::texture_from_image;
+use crate::texture::with_storage_format;
+use crate::utils::compute_work_group_count;
+use crate::utils::create_bind_group;
+use crate::utils::create_pipeline;
+use crate::utils::create_shader;
+use crate::utils::dispatch;
+use crate::utils::request_device;
+
+/// 2×3 仿射矩阵，把输入坐标映射到输出坐标
+pub type Affine = [[f64; 3]; 2];
+/// 3×3 单应矩阵，把输入坐标映射到输出坐标
+pub type Homography = [[f64; 3]; 3];
+
+/// 插值方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Interpolation {
+    Nearest = 0,
+    #[default]
+    Bilinear = 1,
+    /// Catmull-Rom
+    Bicubic = 2,
+}
+
+/// 映射到图像外时的取值方式
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Border {
+    /// 固定颜色 (r, g, b, a)，范围 0~1
+    Constant([f32; 4]),
+    Edge(EdgeMode),
+}
+
+impl Default for Border {
+    fn default() -> Self {
+        Border::Constant([0.0, 0.0, 0.0, 0.0])
+    }
+}
+
+impl Border {
+    /// 与 interpolate.wgsl 中 border 和 constant 的取值对应
+    pub(crate) fn to_params(self) -> (u32, [f32; 4]) {
+        match self {
+            Border::Constant(color) => (3, color),
+            Border::Edge(mode) => (mode as u32, [0.0; 4]),
+        }
+    }
+}
+
+/// 在着色器前拼接 border.wgsl 和 interpolate.wgsl
+pub(crate) fn with_interpolation(source: &str) -> String {
+    format!(
+        "{}\n{}\n{}",
+        include_str!("../shaders/border.wgsl"),
+        include_str!("../shaders/interpolate.wgsl"),
+        source
+    )
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct WarpParams {
+    rows: [[f32; 4]; 3],
+    interpolation: u32,
+    border: u32,
+    _padding: [u32; 2],
+    constant: [f32; 4],
+}
+
+/// 仿射矩阵扩展为 3×3
+pub fn affine_to_homography(matrix: &Affine) -> Homography {
+    [matrix[0], matrix[1], [0.0, 0.0, 1.0]]
+}
+
+/// 绕 center 逆时针旋转 angle 度并缩放 scale 倍(与 OpenCV 的 getRotationMatrix2D 相同)
+pub fn rotation_matrix(center: (f64, f64), angle: f64, scale: f64) -> Affine {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let (alpha, beta) = (scale * cos, scale * sin);
+    [
+        [alpha, beta, (1.0 - alpha) * center.0 - beta * center.1],
+        [-beta, alpha, beta * center.0 + (1.0 - alpha) * center.1],
+    ]
+}
+
+/// 3×3 矩阵求逆
+pub fn invert(m: &Homography) -> Result<Homography> {
+    let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0];
+    let adjugate = [
+        [cofactor(1, 2, 1, 2), -cofactor(0, 2, 1, 2), cofactor(0, 1, 1, 2)],
+        [-cofactor(1, 2, 0, 2), cofactor(0, 2, 0, 2), -cofactor(0, 1, 0, 2)],
+        [cofactor(1, 2, 0, 1), -cofactor(0, 2, 0, 1), cofactor(0, 1, 0, 1)],
+    ];
+    let determinant = m[0][0] * adjugate[0][0] + m[0][1] * adjugate[1][0] + m[0][2] * adjugate[2][0];
+    anyhow::ensure!(determinant.abs() > 1e-12, "matrix is singular");
+    Ok(adjugate.map(|row| row.map(|value| value / determinant)))
+}
+
+/// 用 src 到 dst 的 4 组对应点求单应矩阵(h33 = 1)，任意 3 点不能共线
+pub fn homography_from_points(src: [(f64, f64); 4], dst: [(f64, f64); 4]) -> Result<Homography> {
+    // 每组点给出两个方程:
+    // h11 x + h12 y + h13 - h31 x u - h32 y u = u
+    // h21 x + h22 y + h23 - h31 x v - h32 y v = v
+    let mut system = [[0.0f64; 9]; 8];
+    for (i, (&(x, y), &(u, v))) in src.iter().zip(&dst).enumerate() {
+        system[2 * i] = [x, y, 1.0, 0.0, 0.0, 0.0, -x * u, -y * u, u];
+        system[2 * i + 1] = [0.0, 0.0, 0.0, x, y, 1.0, -x * v, -y * v, v];
+    }
+
+    // 列主元高斯消元
+    for column in 0..8 {
+        let pivot = (column..8)
+            .max_by(|&a, &b| system[a][column].abs().total_cmp(&system[b][column].abs()))
+            .unwrap();
+        anyhow::ensure!(system[pivot][column].abs() > 1e-12, "degenerate point configuration");
+        system.swap(column, pivot);
+        let pivot_row = system[column];
+        for (row, equation) in system.iter_mut().enumerate() {
+            if row != column {
+                let factor = equation[column] / pivot_row[column];
+                for (value, pivot_value) in equation.iter_mut().zip(pivot_row).skip(column) {
+                    *value -= factor * pivot_value;
+                }
+            }
+        }
+    }
+    let h: Vec<f64> = (0..8).map(|i| system[i][8] / system[i][i]).collect();
+    Ok([[h[0], h[1], h[2]], [h[3], h[4], h[5]], [h[6], h[7], 1.0]])
+}
+
+/// 用单应矩阵变换一个点
+pub fn transform_point(matrix: &Homography, (x, y): (f64, f64)) -> (f64, f64) {
+    let w = matrix[2][0] * x + matrix[2][1] * y + matrix[2][2];
+    (
+        (matrix[0][0] * x + matrix[0][1] * y + matrix[0][2]) / w,
+        (matrix[1][0] * x + matrix[1][1] * y + matrix[1][2]) / w,
+    )
+}
+
+/// 透视变换，matrix 把输入坐标映射到输出坐标，输出大小为 width x height，格式与输入相同
+pub fn warp_perspective(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    input: &wgpu::Texture,
+    matrix: &Homography,
+    (width, height): (u32, u32),
+    interpolation: Interpolation,
+    border: Border,
+) -> Result<wgpu::Texture> {
+    let inverse = invert(matrix)?;
+    let output = create_output_texture(device, width, height, input.format());
+
+    let source = with_interpolation(include_str!("../shaders/warp.wgsl"));
+    let shader = create_shader(device, "warp_shader_module", &with_storage_format(&source, input.format())?);
+    let pipeline = create_pipeline(device, &shader, "warp");
+
+    let (border, constant) = border.to_params();
+    let params = GpuBuffer::uniform(
+        device,
+        &WarpParams {
+            rows: inverse.map(|row| [row[0] as f32, row[1] as f32, row[2] as f32, 0.0]),
+            interpolation: interpolation as u32,
+            border,
+            _padding: [0; 2],
+            constant,
+        },
+    );
+
+    let input_view = input.create_view(&wgpu::TextureViewDescriptor::default());
+    let output_view = output.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = create_bind_group(
+        device,
+        &pipeline,
+        &[
+            (0, wgpu::BindingResource::TextureView(&input_view)),
+            (1, wgpu::BindingResource::TextureView(&output_view)),
+            (2, params.as_entire_binding()),
+        ],
+    );
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let (x, y) = compute_work_group_count((width, height), (16, 16));
+    dispatch(&mut encoder, &pipeline, &bind_group, (x, y, 1));
+    queue.submit(Some(encoder.finish()));
+
+    Ok(output)
+}
+
+/// 仿射变换，matrix 把输入坐标映射到输出坐标
+pub fn warp_affine(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    input: &wgpu::Texture,
+    matrix: &Affine,
+    size: (u32, u32),
+    interpolation: Interpolation,
+    border: Border,
+) -> Result<wgpu::Texture> {
+    warp_perspective(device, queue, input, &affine_to_homography(matrix), size, interpolation, border)
+}
+
+/// CPU 上的插值，与 interpolate.wgsl 的 sample_at 相同，用于测试
+#[cfg(test)]
+pub(crate) fn cpu_sample(image: &image::Rgba32FImage, (x, y): (f32, f32), interpolation: Interpolation, border: Border) -> [f32; 4] {
+    let (width, height) = (image.width() as i32, image.height() as i32);
+    let fetch = |x: i32, y: i32| match border {
+        Border::Constant(color) if x < 0 || y < 0 || x >= width || y >= height => color,
+        Border::Constant(_) => image.get_pixel(x as u32, y as u32).0,
+        Border::Edge(mode) => image.get_pixel(mode.border_index(x, width) as u32, mode.border_index(y, height) as u32).0,
+    };
+    let mix = |a: [f32; 4], b: [f32; 4], t: f32| std::array::from_fn(|i| a[i] * (1.0 - t) + b[i] * t);
+    let cubic_weights = |t: f32| {
+        let (t2, t3) = (t * t, t * t * t);
+        [
+            -0.5 * t3 + t2 - 0.5 * t,
+            1.5 * t3 - 2.5 * t2 + 1.0,
+            -1.5 * t3 + 2.0 * t2 + 0.5 * t,
+            0.5 * t3 - 0.5 * t2,
+        ]
+    };
+
+    if interpolation == Interpolation::Nearest {
+        return fetch((x + 0.5).floor() as i32, (y + 0.5).floor() as i32);
+    }
+    let (bx, by) = (x.floor(), y.floor());
+    let (tx, ty) = (x - bx, y - by);
+    let (ox, oy) = (bx as i32, by as i32);
+    if interpolation == Interpolation::Bilinear {
+        let top = mix(fetch(ox, oy), fetch(ox + 1, oy), tx);
+        let bottom = mix(fetch(ox, oy + 1), fetch(ox + 1, oy + 1), tx);
+        return mix(top, bottom, ty);
+    }
+    let (wx, wy) = (cubic_weights(tx), cubic_weights(ty));
+    let mut sum = [0.0f32; 4];
+    for (j, wy) in wy.iter().enumerate() {
+        let mut row = [0.0f32; 4];
+        for (i, wx) in wx.iter().enumerate() {
+            let value = fetch(ox + i as i32 - 1, oy + j as i32 - 1);
+            row = std::array::from_fn(|c| row[c] + wx * value[c]);
+        }
+        sum = std::array::from_fn(|c| sum[c] + wy * row[c]);
+    }
+    sum
+}
+
+/// 仿射变换和透视变换
+pub fn main() -> Result<()> {
+    let (device, queue) = request_device()?;
+
+    let input_image = load_from_memory(include_bytes!("../images/capture.jpg"))?.to_rgba8();
+    let input_texture = texture_from_image(&device, &queue, &input_image);
+    let (width, height) = input_image.dimensions();
+
+    // 绕中心旋转 30 度
+    let rotation = rotation_matrix((width as f64 / 2.0, height as f64 / 2.0), 30.0, 0.8);
+    let rotated = warp_affine(&device, &queue, &input_texture, &rotation, (width, height), Interpolation::Nearest, Border::default())?;
+    image_from_texture(&device, &queue, &rotated)?.save("./outputs/capture_rotate30.png")?;
+
+    let bicubic = warp_affine(
+        &device,
+        &queue,
+        &input_texture,
+        &rotation,
+        (width, height),
+        Interpolation::Bicubic,
+        Border::Edge(EdgeMode::Mirror),
+    )?;
+    image_from_texture(&device, &queue, &bicubic)?.save("./outputs/capture_rotate30_bicubic.png")?;
+
+    // 把图中的一个四边形拉成矩形，模拟文档矫正
+    let (w, h) = (width as f64, height as f64);
+    let corners = [(w * 0.2, h * 0.1), (w * 0.85, h * 0.2), (w * 0.9, h * 0.9), (w * 0.1, h * 0.8)];
+    let (page_width, page_height) = (600, 800);
+    let target = [
+        (0.0, 0.0),
+        (page_width as f64 - 1.0, 0.0),
+        (page_width as f64 - 1.0, page_height as f64 - 1.0),
+        (0.0, page_height as f64 - 1.0),
+    ];
+    let homography = homography_from_points(corners, target)?;
+
+    let rectified = warp_perspective(
+        &device,
+        &queue,
+        &input_texture,
+        &homography,
+        (page_width, page_height),
+        Interpolation::Bilinear,
+        Border::Edge(EdgeMode::Clamp),
+    )?;
+    image_from_texture(&device, &queue, &rectified)?.save("./outputs/capture_rectified.png")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::rgba32f_from_texture;
+    use crate::texture::texture_from_rgba32f;
+    use crate::utils::max_u8_diff;
+    use crate::utils::random_image;
+    use crate::utils::random_rgba32f;
+    use crate::utils::test_device;
+    use image::Rgba32FImage;
+
+    /// CPU 上的透视变换，与 warp.wgsl 一样用 f32 的逆矩阵计算坐标。
+    /// 最近邻插值时坐标离 .5 太近的像素在两边取整都合理，返回 None
+    fn cpu_warp(image: &Rgba32FImage, matrix: &Homography, (width, height): (u32, u32), interpolation: Interpolation, border: Border) -> Vec<Option<[f32; 4]>> {
+        let inverse = invert(matrix).unwrap().map(|row| row.map(|v| v as f32));
+        let (_, constant) = border.to_params();
+        let mut output = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let p = [x as f32, y as f32, 1.0];
+                let dot = |row: [f32; 3]| row[0] * p[0] + row[1] * p[1] + row[2] * p[2];
+                let w = dot(inverse[2]);
+                if w.abs() < 1e-8 {
+                    output.push(Some(constant));
+                    continue;
+                }
+                let (sx, sy) = (dot(inverse[0]) / w, dot(inverse[1]) / w);
+                let ambiguous = |v: f32| (v - v.floor() - 0.5).abs() < 1e-3;
+                if interpolation == Interpolation::Nearest && (ambiguous(sx) || ambiguous(sy)) {
+                    output.push(None);
+                } else {
+                    output.push(Some(cpu_sample(image, (sx, sy), interpolation, border)));
+                }
+            }
+        }
+        output
+    }
+
+    fn max_diff(output: &Rgba32FImage, expected: &[Option<[f32; 4]>]) -> f32 {
+        output
+            .pixels()
+            .zip(expected)
+            .filter_map(|(a, b)| b.map(|b| a.0.iter().zip(b).map(|(a, b)| (a - b).abs()).fold(0.0, f32::max)))
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn warp_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_rgba32f(37, 29, 31);
+        let texture = texture_from_rgba32f(&device, &queue, &input, wgpu::TextureFormat::Rgba32Float).unwrap();
+        let rotation = affine_to_homography(&rotation_matrix((18.0, 14.0), 30.0, 0.8));
+        let perspective = homography_from_points(
+            [(3.0, 2.0), (33.0, 5.0), (35.0, 27.0), (1.0, 24.0)],
+            [(0.0, 0.0), (44.0, 0.0), (44.0, 34.0), (0.0, 34.0)],
+        )
+        .unwrap();
+        let borders = [
+            Border::Constant([0.25, 0.5, 0.75, 1.0]),
+            Border::Edge(EdgeMode::Clamp),
+            Border::Edge(EdgeMode::Mirror),
+            Border::Edge(EdgeMode::Wrap),
+        ];
+        for matrix in [rotation, perspective] {
+            for interpolation in [Interpolation::Nearest, Interpolation::Bilinear, Interpolation::Bicubic] {
+                for border in borders {
+                    let output = warp_perspective(&device, &queue, &texture, &matrix, (45, 35), interpolation, border).unwrap();
+                    let output = rgba32f_from_texture(&device, &queue, &output).unwrap();
+                    let expected = cpu_warp(&input, &matrix, (45, 35), interpolation, border);
+                    // 坐标的计算顺序不同会带来约 1e-6 的偏差，乘上插值权重对坐标的导数(双三次最大约 3)后不超过 1e-4
+                    let diff = max_diff(&output, &expected);
+                    assert!(diff <= 1e-4, "{interpolation:?} {border:?}: {diff}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn warp_rgba8_matches_cpu() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_image(40, 30, 32);
+        let texture = texture_from_image(&device, &queue, &input);
+        let input_f32 = Rgba32FImage::from_fn(40, 30, |x, y| image::Rgba(input.get_pixel(x, y).0.map(|v| v as f32 / 255.0)));
+        let matrix = affine_to_homography(&rotation_matrix((20.0, 15.0), -20.0, 1.3));
+        let output = warp_perspective(&device, &queue, &texture, &matrix, (40, 30), Interpolation::Bicubic, Border::Edge(EdgeMode::Mirror)).unwrap();
+        let expected = cpu_warp(&input_f32, &matrix, (40, 30), Interpolation::Bicubic, Border::Edge(EdgeMode::Mirror));
+        let expected = image::RgbaImage::from_fn(40, 30, |x, y| {
+            image::Rgba(expected[(y * 40 + x) as usize].unwrap().map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8))
+        });
+        // 8 位输出只差在最后的量化上
+        let diff = max_u8_diff(&image_from_texture(&device, &queue, &output).unwrap(), &expected);
+        assert!(diff <= 1, "{diff}");
+    }
+
+    #[test]
+    fn identity_warp_is_lossless() {
+        let Some((device, queue)) = test_device() else { return };
+        let input = random_image(23, 17, 33);
+        let texture = texture_from_image(&device, &queue, &input);
+        let identity = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+        for interpolation in [Interpolation::Nearest, Interpolation::Bilinear, Interpolation::Bicubic] {
+            let output = warp_perspective(&device, &queue, &texture, &identity, (23, 17), interpolation, Border::default()).unwrap();
+            assert!(image_from_texture(&device, &queue, &output).unwrap() == input, "{interpolation:?}");
+        }
+    }
+
+    #[test]
+    fn matrix_helpers() {
+        let src = [(10.0, 20.0), (300.0, 15.0), (320.0, 240.0), (5.0, 200.0)];
+        let dst = [(0.0, 0.0), (599.0, 0.0), (599.0, 799.0), (0.0, 799.0)];
+        let homography = homography_from_points(src, dst).unwrap();
+        let inverse = invert(&homography).unwrap();
+        for (&p, &q) in src.iter().zip(&dst) {
+            let (x, y) = transform_point(&homography, p);
+            assert!((x - q.0).abs() < 1e-9 && (y - q.1).abs() < 1e-9);
+            let (x, y) = transform_point(&inverse, q);
+            assert!((x - p.0).abs() < 1e-9 && (y - p.1).abs() < 1e-9);
+        }
+
+        // 逆时针旋转 90 度: 中心右边的点转到中心上边
+        let rotation = affine_to_homography(&rotation_matrix((5.0, 5.0), 90.0, 1.0));
+        let (x, y) = transform_point(&rotation, (6.0, 5.0));
+        assert!((x - 5.0).abs() < 1e-12 && (y - 4.0).abs() < 1e-12);
+
+        assert!(invert(&[[1.0, 2.0, 0.0], [2.0, 4.0, 0.0], [0.0, 0.0, 1.0]]).is_err());
+        assert!(homography_from_points([(0.0, 0.0), (1.0, 1.0), (2.0, 2.0), (0.0, 1.0)], dst).is_err());
+    }
+}
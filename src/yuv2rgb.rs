@@ -254,11 +254,11 @@ pub fn yuv2rgb() -> Result<()> {
                 origin: wgpu::Origin3d::ZERO,
                 aspect: wgpu::TextureAspect::All,
             },
-            &y_data,
+            y_data,
             wgpu::TexelCopyBufferLayout {
                 offset: 0,
-                bytes_per_row: Some(width as u32),
-                rows_per_image: Some(height as u32),
+                bytes_per_row: Some(width),
+                rows_per_image: Some(height),
             },
             texture_size,
         );
@@ -270,11 +270,11 @@ pub fn yuv2rgb() -> Result<()> {
                 origin: wgpu::Origin3d::ZERO,
                 aspect: wgpu::TextureAspect::All,
             },
-            &uv_data,
+            uv_data,
             wgpu::TexelCopyBufferLayout {
                 offset: 0,
-                bytes_per_row: Some(width as u32),
-                rows_per_image: Some(height as u32),
+                bytes_per_row: Some(width),
+                rows_per_image: Some(height),
             },
             u_size,
         );
@@ -376,9 +376,9 @@ pub fn yuv_to_rgba_cpu(data:&[u8], width:i32, height:i32) -> Vec<u8>{
             let mut g = y1192 - 833 * v - 400 * u;
             let mut b = y1192 + 2066 * u;
 
-            if r < 0 { r = 0; } else if r > 262143 { r = 262143; };
-            if g < 0 { g = 0; } else if g > 262143 { g = 262143; }
-            if b < 0 { b = 0;} else if b > 262143 { b = 262143; }
+            r = r.clamp(0, 262143);
+            g = g.clamp(0, 262143);
+            b = b.clamp(0, 262143);
 
             let r = (r>>10) & 0xff;
             let g = (g>>10) & 0xff;